@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! SSH Agent Signing
+//! A `Signer` backed by an SSH agent (draft-miller-ssh-agent), such as
+//! `ssh-agent` or a hardware token exposing the same protocol, so that the
+//! enclave signing key never leaves the agent.
+//!
+//! SIGSTRUCT requires an RSA-3072 key with public exponent 3, which has to
+//! be imported into the agent (e.g. with `ssh-add key.pem`).
+
+use crate::{RsaNumber, Signer};
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH2_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH2_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH2_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH2_AGENT_SIGN_RESPONSE: u8 = 14;
+const SSH_AGENT_RSA_SHA2_256: u32 = 2;
+
+/// The longest reply accepted from the agent, as in OpenSSH
+const MAX_REPLY: usize = 256 * 1024;
+
+fn invalid() -> Error {
+    Error::new(ErrorKind::InvalidData, "malformed ssh-agent message")
+}
+
+/// Appends an SSH `string`
+fn put(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// Takes an SSH `uint32` from the front of `buf`
+fn take_u32(buf: &mut &[u8]) -> Result<u32> {
+    if buf.len() < 4 {
+        return Err(invalid());
+    }
+
+    let (head, tail) = buf.split_at(4);
+    *buf = tail;
+    Ok(u32::from_be_bytes([head[0], head[1], head[2], head[3]]))
+}
+
+/// Takes an SSH `string` from the front of `buf`
+fn take<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = take_u32(buf)? as usize;
+    if buf.len() < len {
+        return Err(invalid());
+    }
+
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+    Ok(head)
+}
+
+/// Takes a positive SSH `mpint` from the front of `buf`, without leading zeros
+fn take_mpint<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let mut int = take(buf)?;
+    while let [0, rest @ ..] = int {
+        int = rest;
+    }
+
+    Ok(int)
+}
+
+/// A connection to an SSH agent, signing with one of its RSA keys
+#[derive(Clone, Debug)]
+pub struct Agent {
+    path: PathBuf,
+    blob: Vec<u8>,
+    modulus: Vec<u8>,
+}
+
+impl Agent {
+    /// Uses the first key of the agent listening on `path` that is usable
+    /// for SIGSTRUCT: an RSA-3072 key with public exponent 3.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let reply = Self::call(&path, SSH2_AGENTC_REQUEST_IDENTITIES, &[])?;
+        let mut reply = match reply.split_first() {
+            Some((&SSH2_AGENT_IDENTITIES_ANSWER, rest)) => rest,
+            _ => return Err(invalid()),
+        };
+
+        for _ in 0..take_u32(&mut reply)? {
+            let blob = take(&mut reply)?;
+            let _comment = take(&mut reply)?;
+
+            let mut key = blob;
+            if take(&mut key)? != b"ssh-rsa" {
+                continue;
+            }
+
+            let e = take_mpint(&mut key)?;
+            let n = take_mpint(&mut key)?;
+            if e == [3] && n.len() == RsaNumber::SIZE {
+                return Ok(Self {
+                    path,
+                    blob: blob.to_vec(),
+                    modulus: n.to_vec(),
+                });
+            }
+        }
+
+        Err(Error::new(ErrorKind::NotFound, "no SIGSTRUCT key in agent"))
+    }
+
+    /// Like `open()`, using the agent of the `SSH_AUTH_SOCK` environment
+    /// variable.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var_os("SSH_AUTH_SOCK") {
+            Some(path) => Self::open(path),
+            None => Err(Error::new(ErrorKind::NotFound, "SSH_AUTH_SOCK is not set")),
+        }
+    }
+
+    fn call(path: &Path, kind: u8, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut stream = UnixStream::connect(path)?;
+
+        let mut request = Vec::with_capacity(payload.len() + 5);
+        request.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+        request.push(kind);
+        request.extend_from_slice(payload);
+        stream.write_all(&request)?;
+
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_REPLY {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "ssh-agent reply is too long",
+            ));
+        }
+
+        let mut reply = vec![0u8; len];
+        stream.read_exact(&mut reply)?;
+
+        if reply.first() == Some(&SSH_AGENT_FAILURE) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "ssh-agent refused the request",
+            ));
+        }
+
+        Ok(reply)
+    }
+}
+
+impl Signer for Agent {
+    fn modulus(&self) -> Result<Vec<u8>> {
+        Ok(self.modulus.clone())
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let mut request = Vec::new();
+        put(&mut request, &self.blob);
+        put(&mut request, message);
+        request.extend_from_slice(&SSH_AGENT_RSA_SHA2_256.to_be_bytes());
+
+        let reply = Self::call(&self.path, SSH2_AGENTC_SIGN_REQUEST, &request)?;
+        let mut reply = match reply.split_first() {
+            Some((&SSH2_AGENT_SIGN_RESPONSE, rest)) => rest,
+            _ => return Err(invalid()),
+        };
+
+        let mut signature = take(&mut reply)?;
+        if take(&mut signature)? != b"rsa-sha2-256" {
+            return Err(invalid());
+        }
+
+        Ok(take(&mut signature)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Signature;
+
+    use openssl::{pkey, rsa};
+    use std::fs::File;
+    use std::os::unix::net::UnixListener;
+
+    fn mpint(buf: &mut Vec<u8>, int: &[u8]) {
+        let mut data = vec![0u8];
+        data.extend_from_slice(int);
+        put(buf, &data);
+    }
+
+    /// Serves `requests` requests like an SSH agent holding `key`
+    fn serve(listener: UnixListener, key: rsa::Rsa<pkey::Private>, requests: usize) {
+        let mut blob = Vec::new();
+        put(&mut blob, b"ssh-rsa");
+        mpint(&mut blob, &key.e().to_vec());
+        mpint(&mut blob, &key.n().to_vec());
+
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).unwrap();
+            let mut request = vec![0u8; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut request).unwrap();
+
+            let mut reply = Vec::new();
+            match request[0] {
+                SSH2_AGENTC_REQUEST_IDENTITIES => {
+                    reply.push(SSH2_AGENT_IDENTITIES_ANSWER);
+                    reply.extend_from_slice(&1u32.to_be_bytes());
+                    put(&mut reply, &blob);
+                    put(&mut reply, b"encl.pem");
+                }
+
+                SSH2_AGENTC_SIGN_REQUEST => {
+                    let mut payload = &request[1..];
+                    assert_eq!(take(&mut payload).unwrap(), &blob[..]);
+                    let message = take(&mut payload).unwrap();
+                    assert_eq!(take_u32(&mut payload).unwrap(), SSH_AGENT_RSA_SHA2_256);
+
+                    let mut signature = Vec::new();
+                    put(&mut signature, b"rsa-sha2-256");
+                    put(&mut signature, &key.sign(message).unwrap());
+                    reply.push(SSH2_AGENT_SIGN_RESPONSE);
+                    put(&mut reply, &signature);
+                }
+
+                _ => reply.push(SSH_AGENT_FAILURE),
+            }
+
+            let mut message = Vec::new();
+            put(&mut message, &reply);
+            stream.write_all(&message).unwrap();
+        }
+    }
+
+    #[test]
+    fn sign() {
+        let mut pem = Vec::new();
+        File::open("tests/encl.pem")
+            .unwrap()
+            .read_to_end(&mut pem)
+            .unwrap();
+        let key = rsa::Rsa::private_key_from_pem(&pem).unwrap();
+        let sig = Signature::read_from(File::open("tests/encl.ss").unwrap()).unwrap();
+
+        let path = std::env::temp_dir().join(format!("sgx-agent-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || serve(listener, key, 2));
+
+        let agent = Agent::open(&path).unwrap();
+        let signed = sig.measurement().sign_with(sig.author(), &agent).unwrap();
+        assert_eq!(sig, signed);
+
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn long_reply() {
+        let path = std::env::temp_dir().join(format!("sgx-agent-long-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            let mut stream = listener.incoming().next().unwrap().unwrap();
+            let mut request = [0u8; 5];
+            stream.read_exact(&mut request).unwrap();
+            stream.write_all(&u32::MAX.to_be_bytes()).unwrap();
+        });
+
+        let err = Agent::open(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,476 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Measurement Artifacts
+//! A `Recorder` measures an enclave like the `Hasher`, but also records every
+//! page it loads. The resulting `Artifact` serializes to canonical JSON (keys
+//! sorted, no whitespace), so it can be committed alongside a release and
+//! third parties can independently reproduce the MRENCLAVE page by page.
+
+use crate::{
+    Attributes, Class, Digest, Features, Hasher, InvalidLoad, MiscSelect, Parameters, Permissions,
+    SecInfo, Xfrm,
+};
+
+use core::convert::TryFrom;
+use core::num::NonZeroU32;
+use serde_json::{json, Value};
+
+const PAGE: usize = 4096;
+
+/// The JSON is not a valid measurement artifact
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidArtifact;
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(value: &Value) -> Option<[u8; 32]> {
+    let s = value.as_str()?;
+    if s.len() != 64 {
+        return None;
+    }
+
+    let mut out = [0u8; 32];
+    for (o, i) in out.iter_mut().zip((0..s.len()).step_by(2)) {
+        *o = u8::from_str_radix(s.get(i..i + 2)?, 16).ok()?;
+    }
+
+    Some(out)
+}
+
+fn class(value: u8) -> Option<Class> {
+    Some(match value {
+        0 => Class::Secs,
+        1 => Class::Tcs,
+        2 => Class::Reg,
+        3 => Class::Va,
+        4 => Class::Trim,
+        _ => return None,
+    })
+}
+
+/// One page loaded via EADD (and EEXTEND if measured)
+#[derive(Copy, Clone, Debug)]
+pub struct LoadedPage {
+    /// The offset of the page from the enclave base
+    pub offset: u64,
+
+    /// The security information of the page
+    pub secinfo: SecInfo,
+
+    /// Whether the contents were measured (EEXTEND)
+    pub measured: bool,
+
+    /// The SHA-256 of the page contents
+    pub sha256: [u8; 32],
+}
+
+impl PartialEq for LoadedPage {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+            && self.secinfo.class == other.secinfo.class
+            && self.secinfo.perms == other.secinfo.perms
+            && self.measured == other.measured
+            && self.sha256 == other.sha256
+    }
+}
+
+/// The first difference between two artifacts, in measurement order; see
+/// `Artifact::diff()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    /// The enclave sizes (hashed by ECREATE) differ
+    Size(u64, u64),
+
+    /// The pages per SSA frame (hashed by ECREATE) differ
+    SsaFramePages(u32, u32),
+
+    /// Only one artifact has a page at `index`
+    Missing {
+        /// The position of the page in load order
+        index: usize,
+    },
+
+    /// The pages at `index` are loaded at different offsets
+    Offset {
+        /// The position of the page in load order
+        index: usize,
+
+        /// The offsets of both pages
+        offsets: (u64, u64),
+    },
+
+    /// The pages at `index` have different SecInfo
+    SecInfo {
+        /// The position of the page in load order
+        index: usize,
+
+        /// The offset of both pages
+        offset: u64,
+    },
+
+    /// Only one of the pages at `index` is measured
+    Measured {
+        /// The position of the page in load order
+        index: usize,
+
+        /// The offset of both pages
+        offset: u64,
+    },
+
+    /// The pages at `index` have different contents
+    Content {
+        /// The position of the page in load order
+        index: usize,
+
+        /// The offset of both pages
+        offset: u64,
+    },
+
+    /// The MRENCLAVE values match, but the enclave parameters differ
+    Parameters,
+}
+
+impl core::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Divergence::Size(l, r) => write!(f, "enclave size {:#x} != {:#x}", l, r),
+            Divergence::SsaFramePages(l, r) => write!(f, "SSA frame pages {} != {}", l, r),
+            Divergence::Missing { index } => write!(f, "page #{} is only loaded once", index),
+            Divergence::Offset { index, offsets } => write!(
+                f,
+                "page #{} is loaded at {:#x} != {:#x}",
+                index, offsets.0, offsets.1
+            ),
+            Divergence::SecInfo { index, offset } => {
+                write!(
+                    f,
+                    "page #{} at {:#x} has a different SecInfo",
+                    index, offset
+                )
+            }
+            Divergence::Measured { index, offset } => {
+                write!(f, "page #{} at {:#x} is only measured once", index, offset)
+            }
+            Divergence::Content { index, offset } => {
+                write!(f, "page #{} at {:#x} has different contents", index, offset)
+            }
+            Divergence::Parameters => write!(f, "the enclave parameters differ"),
+        }
+    }
+}
+
+/// A reproducible description of how an MRENCLAVE was computed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Artifact {
+    /// The name and version of the tool that produced the artifact
+    pub tool: String,
+
+    /// The enclave size (SECS.SIZE)
+    pub size: u64,
+
+    /// The pages per SSA frame (SECS.SSAFRAMESIZE)
+    pub ssa_frame_pages: NonZeroU32,
+
+    /// The enclave parameters
+    pub parameters: Parameters,
+
+    /// Every loaded page, in load order
+    pub pages: Vec<LoadedPage>,
+
+    /// The resulting MRENCLAVE
+    pub mrenclave: [u8; 32],
+}
+
+impl Artifact {
+    /// Serializes the artifact to canonical JSON.
+    pub fn to_json(&self) -> String {
+        let pages: Vec<Value> = self
+            .pages
+            .iter()
+            .map(|page| {
+                json!({
+                    "offset": page.offset,
+                    "class": page.secinfo.class as u8,
+                    "perms": page.secinfo.perms.bits(),
+                    "measured": page.measured,
+                    "sha256": hex(&page.sha256),
+                })
+            })
+            .collect();
+
+        let parameters = &self.parameters;
+        json!({
+            "tool": self.tool,
+            "size": self.size,
+            "ssa_frame_pages": self.ssa_frame_pages.get(),
+            "parameters": {
+                "miscselect": [parameters.misc.data.bits(), parameters.misc.mask.bits()],
+                "features": [
+                    parameters.attr.data.features().bits(),
+                    parameters.attr.mask.features().bits(),
+                ],
+                "xfrm": [
+                    parameters.attr.data.xfrm().bits(),
+                    parameters.attr.mask.xfrm().bits(),
+                ],
+                "isv_prod_id": parameters.isv_prod_id.inner(),
+                "isv_svn": parameters.isv_svn.inner(),
+            },
+            "pages": pages,
+            "mrenclave": hex(&self.mrenclave),
+        })
+        .to_string()
+    }
+
+    /// Finds the first operation at which `self` and `other` diverge, in the
+    /// order they are measured, or `None` if they are equivalent. The tool
+    /// versions are not compared.
+    pub fn diff(&self, other: &Self) -> Option<Divergence> {
+        if self.size != other.size {
+            return Some(Divergence::Size(self.size, other.size));
+        }
+
+        if self.ssa_frame_pages != other.ssa_frame_pages {
+            let (l, r) = (self.ssa_frame_pages.get(), other.ssa_frame_pages.get());
+            return Some(Divergence::SsaFramePages(l, r));
+        }
+
+        let pages = self.pages.iter().zip(other.pages.iter());
+        for (index, (l, r)) in pages.enumerate() {
+            let offset = l.offset;
+            if l.offset != r.offset {
+                let offsets = (l.offset, r.offset);
+                return Some(Divergence::Offset { index, offsets });
+            }
+
+            if l.secinfo.class != r.secinfo.class || l.secinfo.perms != r.secinfo.perms {
+                return Some(Divergence::SecInfo { index, offset });
+            }
+
+            if l.measured != r.measured {
+                return Some(Divergence::Measured { index, offset });
+            }
+
+            // Unmeasured contents do not affect MRENCLAVE.
+            if l.measured && l.sha256 != r.sha256 {
+                return Some(Divergence::Content { index, offset });
+            }
+        }
+
+        if self.pages.len() != other.pages.len() {
+            let index = self.pages.len().min(other.pages.len());
+            return Some(Divergence::Missing { index });
+        }
+
+        if self.parameters != other.parameters {
+            return Some(Divergence::Parameters);
+        }
+
+        None
+    }
+
+    /// Parses an artifact produced by `to_json()`.
+    pub fn from_json(json: &str) -> Result<Self, InvalidArtifact> {
+        let value: Value = serde_json::from_str(json).map_err(|_| InvalidArtifact)?;
+        Self::parse(&value).ok_or(InvalidArtifact)
+    }
+
+    fn parse(value: &Value) -> Option<Self> {
+        let pair = |value: &Value| -> Option<(u64, u64)> {
+            let pair = value.as_array()?;
+            match pair.as_slice() {
+                [data, mask] => Some((data.as_u64()?, mask.as_u64()?)),
+                _ => None,
+            }
+        };
+
+        let params = &value["parameters"];
+        let miscselect = pair(&params["miscselect"])?;
+        let features = pair(&params["features"])?;
+        let xfrm = pair(&params["xfrm"])?;
+
+        let misc = |bits: u64| MiscSelect::from_bits(u32::try_from(bits).ok()?);
+        let attr = |features: u64, xfrm: u64| {
+            Some(Attributes::new(
+                Features::from_bits(features)?,
+                Xfrm::from_bits(xfrm)?,
+            ))
+        };
+
+        let mut parameters = Parameters::default();
+        parameters.misc.data = misc(miscselect.0)?;
+        parameters.misc.mask = misc(miscselect.1)?;
+        parameters.attr.data = attr(features.0, xfrm.0)?;
+        parameters.attr.mask = attr(features.1, xfrm.1)?;
+        parameters.isv_prod_id = u16::try_from(params["isv_prod_id"].as_u64()?).ok()?.into();
+        parameters.isv_svn = u16::try_from(params["isv_svn"].as_u64()?).ok()?.into();
+
+        let mut pages = Vec::new();
+        for page in value["pages"].as_array()? {
+            let perms = u8::try_from(page["perms"].as_u64()?).ok()?;
+            let mut secinfo = SecInfo::reg(Permissions::from_bits(perms)?);
+            secinfo.class = class(u8::try_from(page["class"].as_u64()?).ok()?)?;
+
+            pages.push(LoadedPage {
+                offset: page["offset"].as_u64()?,
+                secinfo,
+                measured: page["measured"].as_bool()?,
+                sha256: unhex(&page["sha256"])?,
+            });
+        }
+
+        Some(Self {
+            tool: value["tool"].as_str()?.to_string(),
+            size: value["size"].as_u64()?,
+            ssa_frame_pages: NonZeroU32::new(
+                u32::try_from(value["ssa_frame_pages"].as_u64()?).ok()?,
+            )?,
+            parameters,
+            pages,
+            mrenclave: unhex(&value["mrenclave"])?,
+        })
+    }
+}
+
+/// Measures an enclave with a `Hasher` while recording an `Artifact`
+pub struct Recorder<D: Digest + Default> {
+    hasher: Hasher<D>,
+    artifact: Artifact,
+}
+
+impl<D: Digest + Default> Recorder<D> {
+    /// Mimics ECREATE like `Hasher::with_digest()`, using fresh `D` digests.
+    pub fn new(size: u64, ssa_frame_pages: NonZeroU32, parameters: Parameters) -> Self {
+        Self {
+            hasher: Hasher::with_digest(D::default(), size, ssa_frame_pages, parameters),
+            artifact: Artifact {
+                tool: concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")).to_string(),
+                size,
+                ssa_frame_pages,
+                parameters,
+                pages: Vec::new(),
+                mrenclave: [0; 32],
+            },
+        }
+    }
+
+    /// Hashes and records pages like `Hasher::load()`.
+    pub fn load(
+        &mut self,
+        pages: &[u8],
+        offset: u64,
+        secinfo: SecInfo,
+        measure: bool,
+    ) -> Result<(), InvalidLoad> {
+        self.hasher.load(pages, offset, secinfo, measure)?;
+
+        for (i, page) in pages.chunks(PAGE).enumerate() {
+            let mut digest = D::default();
+            digest.update(page);
+
+            self.artifact.pages.push(LoadedPage {
+                offset: offset + (i * PAGE) as u64,
+                secinfo,
+                measured: measure,
+                sha256: digest.finish(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Produces the artifact, including the MRENCLAVE.
+    pub fn finish(self) -> Artifact {
+        let mut artifact = self.artifact;
+        artifact.mrenclave = self.hasher.finish().mrenclave();
+        artifact
+    }
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod test {
+    use super::*;
+    use openssl::sha::Sha256;
+
+    #[test]
+    fn roundtrip() {
+        let one = NonZeroU32::new(1).unwrap();
+        let rx = SecInfo::reg(Permissions::READ | Permissions::EXECUTE);
+        let data = [0x90u8; PAGE * 2];
+
+        let mut recorder = Recorder::<Sha256>::new(PAGE as u64 * 4, one, Parameters::default());
+        recorder
+            .load(&data[..PAGE], 0, SecInfo::tcs(), true)
+            .unwrap();
+        recorder.load(&data, PAGE as u64, rx, true).unwrap();
+        let artifact = recorder.finish();
+
+        let mut hasher = Hasher::new(PAGE as u64 * 4, one, Parameters::default());
+        hasher.load(&data[..PAGE], 0, SecInfo::tcs(), true).unwrap();
+        hasher.load(&data, PAGE as u64, rx, true).unwrap();
+        assert_eq!(artifact.mrenclave, hasher.finish().mrenclave());
+
+        assert_eq!(artifact.pages.len(), 3);
+        assert_eq!(artifact.pages[2].offset, PAGE as u64 * 2);
+        assert_eq!(
+            artifact.pages[2].sha256,
+            openssl::sha::sha256(&data[..PAGE])
+        );
+
+        let json = artifact.to_json();
+        assert!(json.starts_with("{\"mrenclave\":"));
+        assert_eq!(Artifact::from_json(&json), Ok(artifact));
+        assert_eq!(Artifact::from_json("{}"), Err(InvalidArtifact));
+    }
+
+    #[test]
+    fn diff() {
+        let one = NonZeroU32::new(1).unwrap();
+        let rw = SecInfo::reg(Permissions::READ | Permissions::WRITE);
+        let record = |data: &[u8], secinfo: SecInfo, measure: bool| {
+            let mut recorder = Recorder::<Sha256>::new(PAGE as u64 * 4, one, Parameters::default());
+            recorder
+                .load(&[0u8; PAGE], 0, SecInfo::tcs(), true)
+                .unwrap();
+            recorder.load(data, PAGE as u64, secinfo, measure).unwrap();
+            recorder.finish()
+        };
+
+        let left = record(&[1u8; PAGE * 2], rw, true);
+        assert_eq!(left.diff(&left.clone()), None);
+
+        let right = record(&[1u8; PAGE * 2], SecInfo::reg(Permissions::READ), true);
+        let divergence = right.diff(&left).unwrap();
+        let offset = PAGE as u64;
+        assert_eq!(divergence, Divergence::SecInfo { index: 1, offset });
+        assert_eq!(
+            divergence.to_string(),
+            "page #1 at 0x1000 has a different SecInfo"
+        );
+
+        let mut data = [1u8; PAGE * 2];
+        data[PAGE] = 0;
+        let offset = PAGE as u64 * 2;
+        let right = record(&data, rw, true);
+        assert_eq!(
+            left.diff(&right),
+            Some(Divergence::Content { index: 2, offset })
+        );
+
+        let right = record(&[1u8; PAGE], rw, true);
+        assert_eq!(left.diff(&right), Some(Divergence::Missing { index: 2 }));
+
+        let mut right = left.clone();
+        right.parameters.isv_svn = 1.into();
+        assert_eq!(left.diff(&right), Some(Divergence::Parameters));
+
+        let mut right = left.clone();
+        right.size *= 2;
+        assert_eq!(
+            left.diff(&right),
+            Some(Divergence::Size(left.size, right.size))
+        );
+    }
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Evidence of an enclave's identity, produced either locally by EREPORT or
+//! remotely by a Quoting Enclave. Verifiers that support both local and remote
+//! attestation can check either against the same policy.
+
+#[cfg(feature = "crypto")]
+use super::quote::Quote;
+use super::report::{Body, Report};
+use crate::{Mismatch, Parameters};
+
+use core::fmt;
+
+/// Evidence that does not satisfy a policy, see `Evidence::verify()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidEvidence {
+    /// The evidence does not describe an SGX enclave, e.g. a TDX quote
+    NotSgx,
+
+    /// These fields of the enclave's Report do not match the policy
+    Mismatches(Vec<Mismatch>),
+}
+
+impl fmt::Display for InvalidEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidEvidence::NotSgx => write!(f, "evidence is not of an SGX enclave"),
+            InvalidEvidence::Mismatches(mismatches) => {
+                write!(f, "{} field(s) do not match the policy", mismatches.len())
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidEvidence {}
+
+/// A local `Report` or a remote `Quote`
+///
+/// Note that `verify()` only checks the enclave's Report against the policy.
+/// The Report's MAC or the Quote's signatures must be verified separately,
+/// e.g. with `EGETKEY` or `verify::verify()` respectively.
+#[derive(Clone, Copy)]
+pub enum Evidence<'a> {
+    /// A Report produced by EREPORT for local attestation
+    Report(&'a Report),
+
+    /// A Quote produced by a Quoting Enclave for remote attestation
+    #[cfg(feature = "crypto")]
+    Quote(&'a Quote),
+}
+
+impl<'a> From<&'a Report> for Evidence<'a> {
+    fn from(report: &'a Report) -> Self {
+        Evidence::Report(report)
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl<'a> From<&'a Quote> for Evidence<'a> {
+    fn from(quote: &'a Quote) -> Self {
+        Evidence::Quote(quote)
+    }
+}
+
+impl<'a> Evidence<'a> {
+    /// Returns the Report body of the SGX enclave, if present
+    pub fn body(&self) -> Option<&'a Body> {
+        match *self {
+            Evidence::Report(report) => Some(&report.reportbody),
+            #[cfg(feature = "crypto")]
+            Evidence::Quote(quote) => quote.body().sgx(),
+        }
+    }
+
+    /// Checks the enclave's Report body against the `policy`, as
+    /// `Parameters::check()` does.
+    pub fn verify(&self, policy: &Parameters) -> Result<(), InvalidEvidence> {
+        let body = self.body().ok_or(InvalidEvidence::NotSgx)?;
+        policy.check(body).map_err(InvalidEvidence::Mismatches)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ProductId, SecurityVersion};
+
+    #[test]
+    fn report() {
+        let mut report = Report::default();
+        report.reportbody.isv_prod_id = ProductId::new(1);
+        report.reportbody.isv_svn = SecurityVersion::new(2);
+
+        let evidence = Evidence::from(&report);
+        assert_eq!(evidence.body().unwrap().isv_prod_id, ProductId::new(1));
+
+        let mut policy = Parameters {
+            isv_prod_id: ProductId::new(1),
+            isv_svn: SecurityVersion::new(2),
+            ..Default::default()
+        };
+        assert_eq!(evidence.verify(&policy), Ok(()));
+
+        policy.isv_svn = SecurityVersion::new(3);
+        assert_eq!(
+            evidence.verify(&policy),
+            Err(InvalidEvidence::Mismatches(vec![
+                Mismatch::SecurityVersion {
+                    expected: SecurityVersion::new(3),
+                    actual: SecurityVersion::new(2),
+                }
+            ]))
+        );
+    }
+}
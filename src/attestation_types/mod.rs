@@ -4,8 +4,12 @@
 //! Section references in further documentation refer to this document.
 //! https://www.intel.com/content/dam/www/public/emea/xe/en/documents/manuals/64-ia-32-architectures-software-developer-vol-3d-part-4-manual.pdf
 
+#[cfg(feature = "std")]
+pub mod evidence;
+pub mod policy;
 #[cfg(feature = "crypto")]
 pub mod quote;
+pub mod quoteref;
 pub mod report;
 pub mod ti;
 #[cfg(feature = "crypto")]
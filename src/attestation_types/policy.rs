@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Security version policies
+//! Minimum security versions an attested enclave must have, checked against
+//! the Report body in one place.
+
+use super::report::Body;
+use crate::{CpuSvn, SecurityVersion};
+
+use core::fmt;
+
+/// A security version of the Report below the policy's minimum
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SvnFailure {
+    /// The ISV security version is below the minimum
+    IsvSvn {
+        /// The minimum security version
+        expected: SecurityVersion,
+        /// The security version of the Report
+        actual: SecurityVersion,
+    },
+
+    /// The configuration security version is below the minimum
+    ConfigSvn {
+        /// The minimum security version
+        expected: u16,
+        /// The security version of the Report
+        actual: u16,
+    },
+
+    /// Some component of the CPUSVN is below the floor
+    CpuSvn {
+        /// The CPUSVN floor
+        expected: CpuSvn,
+        /// The CPUSVN of the Report
+        actual: CpuSvn,
+    },
+}
+
+impl fmt::Display for SvnFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvnFailure::IsvSvn { expected, actual } => {
+                write!(f, "ISVSVN {} is below the minimum {}", actual, expected)
+            }
+            SvnFailure::ConfigSvn { expected, actual } => {
+                write!(f, "CONFIGSVN {} is below the minimum {}", actual, expected)
+            }
+            SvnFailure::CpuSvn { expected, actual } => write!(
+                f,
+                "CPUSVN {:02x?} is below the floor {:02x?}",
+                actual.inner(),
+                expected.inner()
+            ),
+        }
+    }
+}
+
+/// The minimum security versions of an attested enclave
+///
+/// The default policy accepts any security versions.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SvnPolicy {
+    /// The minimum ISV security version
+    pub isv_svn: SecurityVersion,
+
+    /// The minimum configuration security version
+    pub config_svn: u16,
+
+    /// The CPUSVN floor; every component of the Report's CPUSVN must be at
+    /// least the corresponding component of the floor
+    pub cpusvn: CpuSvn,
+}
+
+impl SvnPolicy {
+    /// Checks the Report `body` against the policy, returning the first
+    /// security version below its minimum.
+    pub fn evaluate(&self, body: &Body) -> Result<(), SvnFailure> {
+        self.failures(body).next().map_or(Ok(()), Err)
+    }
+
+    /// Returns every security version of the Report `body` below its minimum.
+    pub fn failures<'a>(&'a self, body: &'a Body) -> impl Iterator<Item = SvnFailure> + 'a {
+        let isv_svn = if body.isv_svn < self.isv_svn {
+            Some(SvnFailure::IsvSvn {
+                expected: self.isv_svn,
+                actual: body.isv_svn,
+            })
+        } else {
+            None
+        };
+
+        let config_svn = if body.config_svn < self.config_svn {
+            Some(SvnFailure::ConfigSvn {
+                expected: self.config_svn,
+                actual: body.config_svn,
+            })
+        } else {
+            None
+        };
+
+        let cpusvn = if !body.cpusvn.is_at_least(&self.cpusvn) {
+            Some(SvnFailure::CpuSvn {
+                expected: self.cpusvn,
+                actual: body.cpusvn,
+            })
+        } else {
+            None
+        };
+
+        isv_svn.into_iter().chain(config_svn).chain(cpusvn)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluate() {
+        let policy = SvnPolicy {
+            isv_svn: SecurityVersion::new(2),
+            config_svn: 1,
+            cpusvn: CpuSvn::new([1; 16]),
+        };
+
+        let mut body = Body::default();
+        body.isv_svn = SecurityVersion::new(2);
+        body.config_svn = 1;
+        body.cpusvn = CpuSvn::new([2; 16]);
+        assert_eq!(policy.evaluate(&body), Ok(()));
+        assert_eq!(SvnPolicy::default().evaluate(&Body::default()), Ok(()));
+
+        body.isv_svn = SecurityVersion::new(1);
+        body.cpusvn = CpuSvn::new([0; 16]);
+        assert_eq!(
+            policy.evaluate(&body),
+            Err(SvnFailure::IsvSvn {
+                expected: SecurityVersion::new(2),
+                actual: SecurityVersion::new(1),
+            })
+        );
+        assert_eq!(policy.failures(&body).count(), 2);
+
+        body.isv_svn = SecurityVersion::new(3);
+        body.config_svn = 0;
+        body.cpusvn = CpuSvn::new([2; 16]);
+        assert_eq!(
+            policy.evaluate(&body),
+            Err(SvnFailure::ConfigSvn {
+                expected: 1,
+                actual: 0
+            })
+        );
+    }
+}
@@ -5,12 +5,17 @@
 //! See Section A.4 in the following link for all types in this module:
 //! https://download.01.org/intel-sgx/dcap-1.0/docs/SGX_ECDSA_QuoteGenReference_DCAP_API_Linux_1.0.pdf
 
+pub mod pckext;
 pub mod quoteheader;
 pub mod sigdata;
+pub mod tdreport;
+
+pub use super::quoteref;
 
 use super::report::{Body, ReportError};
-use quoteheader::QuoteHeader;
-use sigdata::SigData;
+use quoteheader::{QuoteHeader, TeeType, VERSION};
+use sigdata::{PckCertChain, SigData};
+use tdreport::{TdReportBody, TD_REPORT_BODY_LEN};
 
 use core::{convert::TryFrom, fmt};
 
@@ -25,9 +30,9 @@ const PCK_HASH_LEN: usize = 32;
 // in the Quote in several fields (in ISV Enclave Report for the attesting enclave,
 // as well as in the Quote Signature for the QE verifying the ISV Enclave Report).
 // The REPORTDATA_START refers to the starting index of a ReportData field from
-// the beginning of any Report, whereas QE_REPORTDATA_START refers to the starting
-// index of the ReportData in the QE Report embedded in the Quote Signature and the
-// offset is therefore from the beginning of the Quote.
+// the beginning of any Report, whereas the starting index of the QE Report embedded
+// in the Quote Signature depends on the Quote's version and TEE type and is
+// therefore computed from the header (see Layout).
 //
 // Quote
 // |-----------
@@ -49,6 +54,10 @@ const PCK_HASH_LEN: usize = 32;
 // |    | -- ...
 // |____________
 //
+// Version 4 Quotes differ in two ways. For a TDX TEE, the ISV Enclave Report is
+// replaced by a TD Report Body (584 bytes), moving everything after it. And the
+// QE Report is preceded by a Cert Data type (2 bytes) and length (4 bytes), since
+// it is wrapped in QE Report Certification Data.
 
 // Report Layout consts
 const REPORTDATA_START: usize = 320;
@@ -57,13 +66,11 @@ const REPORTDATA_START: usize = 320;
 const QUOTE_HEADER_LEN: usize = 48;
 const ISV_ENCLAVE_REPORT_LEN: usize = 384;
 const QUOTE_SIG_DATA_LEN_LEN: usize = 4;
-const QUOTE_SIG_START: usize = 436;
 
 // Quote Signature Layout consts
 const ISV_ENCLAVE_REPORT_SIG_LEN: usize = 64;
 const ATT_KEY_PUB_LEN: usize = 64;
-const QE_REPORT_START: usize = QUOTE_SIG_START + ISV_ENCLAVE_REPORT_SIG_LEN + ATT_KEY_PUB_LEN;
-const QE_REPORTDATA_START: usize = QE_REPORT_START + REPORTDATA_START;
+const QE_REPORT_CERT_DATA_HEADER_LEN: usize = 6;
 
 #[derive(Clone, Debug)]
 /// Error type for Quote module
@@ -77,6 +84,12 @@ impl fmt::Display for QuoteError {
 
 impl std::error::Error for QuoteError {}
 
+impl From<quoteref::InvalidQuote> for QuoteError {
+    fn from(e: quoteref::InvalidQuote) -> Self {
+        QuoteError(e.to_string())
+    }
+}
+
 impl From<ReportError> for QuoteError {
     fn from(_: ReportError) -> Self {
         QuoteError("Report error".to_string())
@@ -110,6 +123,93 @@ impl From<&[u8; 4]> for SigDataLen {
     }
 }
 
+/// The body of a Quote: the report of the attested SGX enclave or TDX TD.
+#[derive(Clone, Copy)]
+pub enum QuoteBody {
+    /// ISV Enclave Report of an SGX enclave
+    Sgx(Body),
+
+    /// TD Report Body of a TDX Trust Domain (version 4 only)
+    Tdx(TdReportBody),
+}
+
+impl Default for QuoteBody {
+    fn default() -> Self {
+        QuoteBody::Sgx(Body::default())
+    }
+}
+
+impl QuoteBody {
+    /// Returns the SGX ISV Enclave Report, if present
+    pub fn sgx(&self) -> Option<&Body> {
+        match self {
+            QuoteBody::Sgx(body) => Some(body),
+            QuoteBody::Tdx(_) => None,
+        }
+    }
+
+    /// Returns the TDX TD Report Body, if present
+    pub fn tdx(&self) -> Option<&TdReportBody> {
+        match self {
+            QuoteBody::Sgx(_) => None,
+            QuoteBody::Tdx(body) => Some(body),
+        }
+    }
+
+    /// Returns the ReportData bound into the body
+    pub fn reportdata(&self) -> &[u8; 64] {
+        match self {
+            QuoteBody::Sgx(body) => &body.reportdata,
+            QuoteBody::Tdx(body) => &body.report_data,
+        }
+    }
+}
+
+// The offsets of the variable parts of a Quote, determined from its header.
+struct Layout {
+    body_len: usize,
+    qe_report_start: usize,
+}
+
+impl Layout {
+    fn new(header: &QuoteHeader) -> Self {
+        let body_len = match header.tee_type {
+            TeeType::Sgx => ISV_ENCLAVE_REPORT_LEN,
+            TeeType::Tdx => TD_REPORT_BODY_LEN,
+        };
+
+        let mut qe_report_start = QUOTE_HEADER_LEN
+            + body_len
+            + QUOTE_SIG_DATA_LEN_LEN
+            + ISV_ENCLAVE_REPORT_SIG_LEN
+            + ATT_KEY_PUB_LEN;
+        if header.version != VERSION {
+            qe_report_start += QE_REPORT_CERT_DATA_HEADER_LEN;
+        }
+
+        Self {
+            body_len,
+            qe_report_start,
+        }
+    }
+
+    fn parse(quote: &[u8]) -> Result<Self, QuoteError> {
+        if quote.len() < QUOTE_HEADER_LEN {
+            return Err(QuoteError(
+                "Insufficient Quote length; no header specified".to_string(),
+            ));
+        }
+
+        let mut header = [0u8; QUOTE_HEADER_LEN];
+        header.copy_from_slice(&quote[0..QUOTE_HEADER_LEN]);
+        Ok(Self::new(&QuoteHeader::try_from(&header)?))
+    }
+
+    fn sig_start(&self) -> usize {
+        QUOTE_HEADER_LEN + self.body_len + QUOTE_SIG_DATA_LEN_LEN
+    }
+}
+
 /// Section A.4
 /// All integer fields are in little endian.
 #[derive(Default)]
@@ -118,8 +218,8 @@ pub struct Quote {
     /// Header for Quote structure; transparent to the user.
     pub header: QuoteHeader,
 
-    /// Report of the atteste enclave.
-    isv_enclave_report: Body,
+    /// Report of the attested enclave or TD.
+    body: QuoteBody,
 
     /// Size of the Signature Data field.
     sig_data_len: SigDataLen,
@@ -134,17 +234,31 @@ impl TryFrom<&[u8]> for Quote {
     type Error = QuoteError;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        // The header determines the layout of the rest of the Quote
+        if bytes.len() < QUOTE_HEADER_LEN {
+            return Err(QuoteError(
+                "Insufficient Quote length; no header specified".to_string(),
+            ));
+        }
+
+        let mut header = [0u8; QUOTE_HEADER_LEN];
+        header.copy_from_slice(&bytes[0..QUOTE_HEADER_LEN]);
+        let header = QuoteHeader::try_from(&header)?;
+        trace_event!(version = header.version, tee_type = ?header.tee_type, "parsing quote");
+        let layout = Layout::new(&header);
+        let sig_start = layout.sig_start();
+
         // Check validity of Quote length
         let mut sig_data_len_bytes = [0u8; QUOTE_SIG_DATA_LEN_LEN];
-        if bytes.len() < 436 {
+        if bytes.len() < sig_start {
             return Err(QuoteError(
                 "Insufficient Quote length; no sig data len specified".to_string(),
             ));
         }
-        sig_data_len_bytes.copy_from_slice(&bytes[432..436]);
+        sig_data_len_bytes.copy_from_slice(&bytes[(sig_start - QUOTE_SIG_DATA_LEN_LEN)..sig_start]);
         let sd_len = u32::from_le_bytes(sig_data_len_bytes);
 
-        let expected_quote_len = QUOTE_SIG_START + sd_len as usize;
+        let expected_quote_len = sig_start + sd_len as usize;
 
         if bytes.len() < expected_quote_len {
             return Err(QuoteError(
@@ -153,35 +267,52 @@ impl TryFrom<&[u8]> for Quote {
         }
 
         // Convert the Quote from byte slice
-        let mut header = [0u8; QUOTE_HEADER_LEN];
-        header.copy_from_slice(&bytes[0..QUOTE_HEADER_LEN]);
-
-        let mut body = [0u8; ISV_ENCLAVE_REPORT_LEN];
-        body.copy_from_slice(&bytes[QUOTE_HEADER_LEN..(QUOTE_HEADER_LEN + ISV_ENCLAVE_REPORT_LEN)]);
+        let body_bytes = &bytes[QUOTE_HEADER_LEN..(QUOTE_HEADER_LEN + layout.body_len)];
+        let body = match header.tee_type {
+            TeeType::Sgx => {
+                let mut body = [0u8; ISV_ENCLAVE_REPORT_LEN];
+                body.copy_from_slice(body_bytes);
+                QuoteBody::Sgx(Body::try_from(&body)?)
+            }
+
+            TeeType::Tdx => {
+                let mut body = [0u8; TD_REPORT_BODY_LEN];
+                body.copy_from_slice(body_bytes);
+                QuoteBody::Tdx(TdReportBody::try_from(&body)?)
+            }
+        };
+
+        let sig_data_bytes = &bytes[sig_start..expected_quote_len];
+        let sig_data = if header.version == VERSION {
+            SigData::try_from(sig_data_bytes)?
+        } else {
+            SigData::try_from_v4(sig_data_bytes)?
+        };
 
         Ok(Self {
-            header: QuoteHeader::try_from(&header)?,
-            isv_enclave_report: Body::try_from(&body)?,
+            header,
+            body,
             sig_data_len: SigDataLen::from(sd_len),
-            sig_data: SigData::try_from(&bytes[QUOTE_SIG_START..expected_quote_len])?,
+            sig_data,
         })
     }
 }
 
 impl Quote {
-    /// This vector of the Quote Header and ISV Enclave Report is the material signed
-    /// by the Quoting Enclave's Attestation Key and should be returned in raw form to
-    /// verify the Attestation Key's signature. Specifically, the header's version
+    /// This vector of the Quote Header and ISV Enclave Report (or TD Report Body) is the
+    /// material signed by the Quoting Enclave's Attestation Key and should be returned in
+    /// raw form to verify the Attestation Key's signature. Specifically, the header's version
     /// number should also be kept intact in the vector, rather than being abstracted
     /// into the Header enum.
     pub fn raw_header_and_body(quote: &[u8]) -> Result<&[u8], QuoteError> {
-        if quote.len() < QUOTE_HEADER_LEN + ISV_ENCLAVE_REPORT_LEN {
+        let layout = Layout::parse(quote)?;
+        if quote.len() < QUOTE_HEADER_LEN + layout.body_len {
             return Err(QuoteError(
                 "Insufficient Quote length; cannot return raw header and body".to_string(),
             ));
         }
 
-        Ok(&quote[0..QUOTE_HEADER_LEN + ISV_ENCLAVE_REPORT_LEN])
+        Ok(&quote[0..QUOTE_HEADER_LEN + layout.body_len])
     }
 
     /// The Report Data of the QE Report holds a SHA256 hash of (ECDSA Attestation Key || QE
@@ -190,13 +321,14 @@ impl Quote {
     /// Quote Signature. The structure of the QE Report in the Quote Signature is identical
     /// to the structure of any enclave's Report, so the Report Data begins at byte 320 of the Report.
     pub fn raw_pck_hash(quote: &[u8]) -> Result<&[u8], QuoteError> {
-        if quote.len() < QE_REPORTDATA_START + PCK_HASH_LEN {
+        let qe_reportdata_start = Layout::parse(quote)?.qe_report_start + REPORTDATA_START;
+        if quote.len() < qe_reportdata_start + PCK_HASH_LEN {
             return Err(QuoteError(
                 "Insufficient Quote length; cannot return raw PCK hash".to_string(),
             ));
         }
 
-        Ok(&quote[QE_REPORTDATA_START..QE_REPORTDATA_START + PCK_HASH_LEN])
+        Ok(&quote[qe_reportdata_start..qe_reportdata_start + PCK_HASH_LEN])
     }
 
     /// Retrieves Quote Header
@@ -205,8 +337,8 @@ impl Quote {
     }
 
     /// Retrieves Quote Body
-    pub fn body(&self) -> &Body {
-        &self.isv_enclave_report
+    pub fn body(&self) -> &QuoteBody {
+        &self.body
     }
 
     /// Retrieves Quote's sig length
@@ -218,4 +350,184 @@ impl Quote {
     pub fn sigdata(&self) -> &SigData {
         &self.sig_data
     }
+
+    /// Retrieves the PCK certificate chain of Cert Data type 5, with the SGX
+    /// extension of the leaf certificate parsed. The chain is not validated.
+    pub fn pck_chain(&self) -> Result<PckCertChain, QuoteError> {
+        self.sig_data.qe_cert_data_pckchain()
+    }
+}
+
+// Arbitrary Quotes are structurally valid version 3 SGX Quotes, parsed from
+// generated bytes: the Cert Data has one of the types 1 to 5 and the length
+// of its type, but the signatures, keys and certificates are random.
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Quote {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut bytes = VERSION.to_le_bytes().to_vec();
+        bytes.extend_from_slice(
+            &(quoteheader::AttestationKeyType::ECDSA256P256 as u16).to_le_bytes(),
+        );
+        bytes.extend_from_slice(&(TeeType::Sgx as u32).to_le_bytes());
+        bytes.extend_from_slice(u.bytes(QUOTE_HEADER_LEN - 8)?);
+        bytes.extend(u.arbitrary::<Body>()?.to_vec());
+
+        let mut sig_data = u
+            .bytes(ISV_ENCLAVE_REPORT_SIG_LEN + ATT_KEY_PUB_LEN)?
+            .to_vec();
+        sig_data.extend(u.arbitrary::<Body>()?.to_vec());
+        sig_data.extend_from_slice(u.bytes(ECDSASIGLEN as usize)?);
+
+        let qe_auth_len = u.int_in_range(0..=64u16)?;
+        sig_data.extend_from_slice(&qe_auth_len.to_le_bytes());
+        sig_data.extend_from_slice(u.bytes(qe_auth_len.into())?);
+
+        let cert_data_type = u.int_in_range(1..=5u16)?;
+        let cert_data_len = match cert_data_type {
+            1 => 16 + 20,
+            2 => 256 + 20,
+            3 => 384 + 20,
+            _ => u.int_in_range(0..=2048u32)?,
+        };
+        sig_data.extend_from_slice(&cert_data_type.to_le_bytes());
+        sig_data.extend_from_slice(&cert_data_len.to_le_bytes());
+        sig_data.extend_from_slice(u.bytes(cert_data_len as usize)?);
+
+        bytes.extend_from_slice(&(sig_data.len() as u32).to_le_bytes());
+        bytes.extend(sig_data);
+        Quote::try_from(&bytes[..]).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use quoteheader::{AttestationKeyType, INTELVID, VERSION_4};
+    use sigdata::{CertData, CertDataType};
+
+    fn sgx_body() -> Vec<u8> {
+        let mut body = Body::default();
+        body.mrenclave = [0x33; 32];
+        body.reportdata = [0x34; 64];
+        body.to_vec()
+    }
+
+    fn tdx_body() -> Vec<u8> {
+        let mut body = vec![0u8; TD_REPORT_BODY_LEN];
+        body[136..184].copy_from_slice(&[0x44; 48]);
+        body[472..520].copy_from_slice(&[0x45; 48]);
+        body[520..584].copy_from_slice(&[0x46; 64]);
+        body
+    }
+
+    // A version 4 Quote of `tee_type` with `body`, whose QE Report Cert Data
+    // wraps the QE Report, QE Auth Data and Cert Data of type 1.
+    fn quote_v4(tee_type: TeeType, body: &[u8]) -> Vec<u8> {
+        let mut quote = VERSION_4.to_le_bytes().to_vec();
+        quote.extend(&(AttestationKeyType::ECDSA256P256 as u16).to_le_bytes());
+        quote.extend(&(tee_type as u32).to_le_bytes());
+        quote.extend(&[0u8; 4]);
+        quote.extend(&INTELVID);
+        quote.extend(&[0u8; 20]);
+        quote.extend(body);
+
+        let mut qe_report = Body::default();
+        qe_report.reportdata = [0x55; 64];
+        let mut wrapped = qe_report.to_vec();
+        wrapped.extend(&[0x66; 64]);
+        wrapped.extend(&3u16.to_le_bytes());
+        wrapped.extend(&[0x77; 3]);
+        wrapped.extend(&(CertDataType::PpidPlaintext as u16).to_le_bytes());
+        wrapped.extend(&36u32.to_le_bytes());
+        wrapped.extend(&[0x88; 36]);
+
+        let mut sig_data = vec![0x11; 64];
+        sig_data.extend(&[0x22; 64]);
+        sig_data.extend(&(CertDataType::QEReportCertData as u16).to_le_bytes());
+        sig_data.extend(&(wrapped.len() as u32).to_le_bytes());
+        sig_data.extend(wrapped);
+
+        quote.extend(&(sig_data.len() as u32).to_le_bytes());
+        quote.extend(sig_data);
+        quote
+    }
+
+    fn check_sig_data(quote: &Quote) {
+        let sig_data = quote.sigdata();
+        assert_eq!(sig_data.report_sig().to_vec(), vec![0x11; 64]);
+        assert_eq!(sig_data.attkey().to_vec(), vec![0x22; 64]);
+        assert_eq!(sig_data.qe_report().reportdata, [0x55; 64]);
+        assert_eq!(sig_data.qe_report_sig().to_vec(), vec![0x66; 64]);
+        assert_eq!(sig_data.qe_auth(), vec![0x77; 3]);
+        assert_eq!(*sig_data.qe_cert_data_type(), CertDataType::PpidPlaintext);
+        match sig_data.qe_cert_data() {
+            CertData::PpidPlaintext(data) => assert_eq!(data.ppid, vec![0x88; 16]),
+            cert_data => panic!("parsed as {}", cert_data.cert_data_type()),
+        }
+    }
+
+    #[test]
+    fn v4_sgx() {
+        let bytes = quote_v4(TeeType::Sgx, &sgx_body());
+        let quote = Quote::try_from(&bytes[..]).unwrap();
+        assert_eq!(quote.header().version, VERSION_4);
+        assert_eq!(quote.header().tee_type, TeeType::Sgx);
+
+        let body = quote.body().sgx().unwrap();
+        assert_eq!(body.mrenclave, [0x33; 32]);
+        assert_eq!(quote.body().reportdata(), &[0x34; 64]);
+        assert!(quote.body().tdx().is_none());
+        check_sig_data(&quote);
+
+        assert_eq!(
+            Quote::raw_header_and_body(&bytes).unwrap(),
+            &bytes[..QUOTE_HEADER_LEN + ISV_ENCLAVE_REPORT_LEN]
+        );
+        assert_eq!(Quote::raw_pck_hash(&bytes).unwrap(), &[0x55; 32]);
+    }
+
+    #[test]
+    fn v4_tdx() {
+        let bytes = quote_v4(TeeType::Tdx, &tdx_body());
+        let quote = Quote::try_from(&bytes[..]).unwrap();
+        assert_eq!(quote.header().tee_type, TeeType::Tdx);
+
+        let body = quote.body().tdx().unwrap();
+        assert_eq!(body.mrtd, [0x44; 48]);
+        assert_eq!(body.rtmr[3], [0x45; 48]);
+        assert_eq!(quote.body().reportdata(), &[0x46; 64]);
+        assert!(quote.body().sgx().is_none());
+        check_sig_data(&quote);
+
+        assert_eq!(
+            Quote::raw_header_and_body(&bytes).unwrap(),
+            &bytes[..QUOTE_HEADER_LEN + TD_REPORT_BODY_LEN]
+        );
+        assert_eq!(Quote::raw_pck_hash(&bytes).unwrap(), &[0x55; 32]);
+    }
+
+    #[test]
+    fn v4_invalid() {
+        let bytes = quote_v4(TeeType::Tdx, &tdx_body());
+        assert!(Quote::try_from(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Quote::try_from(&bytes[..QUOTE_HEADER_LEN + 100]).is_err());
+
+        // Version 3 Quotes only carry SGX bodies.
+        let mut v3 = bytes.clone();
+        v3[0..2].copy_from_slice(&VERSION.to_le_bytes());
+        assert!(Quote::try_from(&v3[..]).is_err());
+
+        // The QE Report must be wrapped in QE Report Cert Data...
+        let wrapper = QUOTE_HEADER_LEN + TD_REPORT_BODY_LEN + QUOTE_SIG_DATA_LEN_LEN + 128;
+        let mut unwrapped = bytes.clone();
+        unwrapped[wrapper..wrapper + 2]
+            .copy_from_slice(&(CertDataType::PCKCertChain as u16).to_le_bytes());
+        assert!(Quote::try_from(&unwrapped[..]).is_err());
+
+        // ...that fits in the signature data.
+        let mut overlong = bytes;
+        overlong[wrapper + 2..wrapper + 6].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Quote::try_from(&overlong[..]).is_err());
+    }
 }
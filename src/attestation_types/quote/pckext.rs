@@ -0,0 +1,257 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The SGX extension of PCK Certificates, see the Intel SGX PCK Certificate
+//! and CRL Profile Specification, Section 1.3.5.
+//!
+//! SGXExtensions ::= SEQUENCE {
+//!     ppid            [16 byte OCTET STRING],
+//!     tcb             SEQUENCE { 16 component SVNs, PCESVN, CPUSVN },
+//!     pceId           [2 byte OCTET STRING],
+//!     fmspc           [6 byte OCTET STRING],
+//!     sgxType         ENUMERATED,
+//!     ...
+//! }
+//!
+//! Every field is wrapped in a SEQUENCE with its OID, under 1.2.840.113741.1.13.1.
+
+use super::QuoteError;
+use crate::{CpuSvn, Fmspc, PceId, PceSvn};
+
+// DER tags
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const ENUMERATED: u8 = 0x0a;
+const SEQUENCE: u8 = 0x30;
+const EXTENSIONS: u8 = 0xa3;
+
+// 1.2.840.113741.1.13.1
+const SGX_EXTENSION: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01];
+
+// The last arcs of the fields under SGX_EXTENSION
+const PPID: u8 = 1;
+const TCB: u8 = 2;
+const PCEID: u8 = 3;
+const FMSPC: u8 = 4;
+const SGX_TYPE: u8 = 5;
+const PCESVN: u8 = 17;
+const CPUSVN: u8 = 18;
+
+fn invalid(what: &str) -> QuoteError {
+    QuoteError(format!("invalid SGX extension: {}", what))
+}
+
+// A reader of consecutive DER elements
+struct Der<'a>(&'a [u8]);
+
+impl<'a> Der<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Takes the next element, returning its tag and contents.
+    fn take(&mut self) -> Result<(u8, &'a [u8]), QuoteError> {
+        let (tag, len, rest) = match self.0 {
+            [tag, len, rest @ ..] if *len < 0x80 => (*tag, *len as usize, rest),
+            [tag, n @ 0x81..=0x84, rest @ ..] if rest.len() >= (*n & 0x7f) as usize => {
+                let (len, rest) = rest.split_at((*n & 0x7f) as usize);
+                let len = len.iter().fold(0usize, |l, b| l << 8 | *b as usize);
+                (*tag, len, rest)
+            }
+            _ => return Err(invalid("truncated DER")),
+        };
+
+        if rest.len() < len {
+            return Err(invalid("truncated DER"));
+        }
+
+        let (contents, rest) = rest.split_at(len);
+        self.0 = rest;
+        Ok((tag, contents))
+    }
+
+    // Takes the next element, which must have the given tag.
+    fn expect(&mut self, tag: u8) -> Result<&'a [u8], QuoteError> {
+        match self.take()? {
+            (t, contents) if t == tag => Ok(contents),
+            _ => Err(invalid("unexpected DER tag")),
+        }
+    }
+
+    // Takes an INTEGER of up to 16 bits.
+    fn integer(&mut self) -> Result<u16, QuoteError> {
+        match self.expect(INTEGER)? {
+            [b] if *b < 0x80 => Ok(*b as u16),
+            [0, b] if *b >= 0x80 => Ok(*b as u16),
+            [hi, lo] if *hi != 0 && *hi < 0x80 => Ok(u16::from_be_bytes([*hi, *lo])),
+            [0, hi, lo] if *hi >= 0x80 => Ok(u16::from_be_bytes([*hi, *lo])),
+            _ => Err(invalid("INTEGER out of range")),
+        }
+    }
+
+    // Takes an OCTET STRING of exactly `out.len()` bytes into `out`.
+    fn octets(&mut self, out: &mut [u8]) -> Result<(), QuoteError> {
+        let bytes = self.expect(OCTET_STRING)?;
+        if bytes.len() != out.len() {
+            return Err(invalid("OCTET STRING of the wrong length"));
+        }
+
+        out.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    // Takes a SEQUENCE of an OID under SGX_EXTENSION and a value, returning
+    // the arcs of the OID following SGX_EXTENSION and a reader of the value.
+    fn field(&mut self) -> Result<(&'a [u8], Der<'a>), QuoteError> {
+        let mut field = Der(self.expect(SEQUENCE)?);
+        match field.expect(OID)? {
+            oid if oid.starts_with(&SGX_EXTENSION) => Ok((&oid[SGX_EXTENSION.len()..], field)),
+            _ => Err(invalid("unexpected OID")),
+        }
+    }
+}
+
+/// The platform and TCB identifiers in the SGX extension of a PCK Certificate
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SgxExtension {
+    /// The Platform Provisioning ID
+    pub ppid: [u8; 16],
+
+    /// The SVNs of the 16 SGX TCB components
+    pub tcb_components: [u8; 16],
+
+    /// The PCESVN of the TCB
+    pub pcesvn: PceSvn,
+
+    /// The CPUSVN of the TCB
+    pub cpusvn: CpuSvn,
+
+    /// The ID of the Provisioning Certification Enclave
+    pub pceid: PceId,
+
+    /// The FMSPC of the platform
+    pub fmspc: Fmspc,
+
+    /// The SGX type: 0 (Standard), 1 (Scalable) or 2 (Scalable with Integrity)
+    pub sgx_type: u8,
+}
+
+impl SgxExtension {
+    /// Parses the SGX extension of the DER-encoded PCK certificate `der`.
+    pub fn from_der(der: &[u8]) -> Result<Self, QuoteError> {
+        let mut cert = Der(Der(der).expect(SEQUENCE)?);
+        let mut tbs = Der(cert.expect(SEQUENCE)?);
+
+        while !tbs.is_empty() {
+            let (tag, contents) = tbs.take()?;
+            if tag != EXTENSIONS {
+                continue;
+            }
+
+            let mut extensions = Der(Der(contents).expect(SEQUENCE)?);
+            while !extensions.is_empty() {
+                let mut extension = Der(extensions.expect(SEQUENCE)?);
+                if extension.expect(OID)? != SGX_EXTENSION {
+                    continue;
+                }
+
+                let mut value = extension.take()?;
+                if value.0 == BOOLEAN {
+                    value = extension.take()?;
+                }
+
+                match value {
+                    (OCTET_STRING, value) => return Self::parse(value),
+                    _ => return Err(invalid("unexpected DER tag")),
+                }
+            }
+        }
+
+        Err(invalid("missing"))
+    }
+
+    // Parses the value of the SGX extension.
+    fn parse(value: &[u8]) -> Result<Self, QuoteError> {
+        let mut ppid = None;
+        let mut tcb = None;
+        let mut pceid = None;
+        let mut fmspc = None;
+        let mut sgx_type = None;
+
+        let mut fields = Der(Der(value).expect(SEQUENCE)?);
+        while !fields.is_empty() {
+            match fields.field()? {
+                ([PPID], mut v) => {
+                    let mut bytes = [0u8; 16];
+                    v.octets(&mut bytes)?;
+                    ppid = Some(bytes);
+                }
+                ([TCB], mut v) => tcb = Some(Self::tcb(v.expect(SEQUENCE)?)?),
+                ([PCEID], mut v) => {
+                    let mut le = [0u8; 2];
+                    v.octets(&mut le)?;
+                    pceid = Some(PceId::new(u16::from_le_bytes(le)));
+                }
+                ([FMSPC], mut v) => {
+                    let mut bytes = [0u8; 6];
+                    v.octets(&mut bytes)?;
+                    fmspc = Some(Fmspc::new(bytes));
+                }
+                ([SGX_TYPE], mut v) => match v.expect(ENUMERATED)? {
+                    [t] => sgx_type = Some(*t),
+                    _ => return Err(invalid("SGX type out of range")),
+                },
+
+                // PlatformInstanceID and Configuration of multi-package platforms
+                _ => (),
+            }
+        }
+
+        let (tcb_components, pcesvn, cpusvn) = tcb.ok_or_else(|| invalid("missing TCB"))?;
+        Ok(Self {
+            ppid: ppid.ok_or_else(|| invalid("missing PPID"))?,
+            tcb_components,
+            pcesvn,
+            cpusvn,
+            pceid: pceid.ok_or_else(|| invalid("missing PCEID"))?,
+            fmspc: fmspc.ok_or_else(|| invalid("missing FMSPC"))?,
+            sgx_type: sgx_type.ok_or_else(|| invalid("missing SGX type"))?,
+        })
+    }
+
+    // Parses the TCB field.
+    fn tcb(value: &[u8]) -> Result<([u8; 16], PceSvn, CpuSvn), QuoteError> {
+        let mut components = [None; 16];
+        let mut pcesvn = None;
+        let mut cpusvn = None;
+
+        let mut fields = Der(value);
+        while !fields.is_empty() {
+            match fields.field()? {
+                ([TCB, n @ 1..=16], mut v) => match v.integer()? {
+                    svn if svn <= 0xff => components[*n as usize - 1] = Some(svn as u8),
+                    _ => return Err(invalid("TCB component SVN out of range")),
+                },
+                ([TCB, PCESVN], mut v) => pcesvn = Some(PceSvn::new(v.integer()?)),
+                ([TCB, CPUSVN], mut v) => {
+                    let mut bytes = [0u8; 16];
+                    v.octets(&mut bytes)?;
+                    cpusvn = Some(CpuSvn::new(bytes));
+                }
+                _ => return Err(invalid("unexpected TCB field")),
+            }
+        }
+
+        let mut svns = [0u8; 16];
+        for (svn, component) in svns.iter_mut().zip(components.iter()) {
+            *svn = component.ok_or_else(|| invalid("missing TCB component"))?;
+        }
+
+        Ok((
+            svns,
+            pcesvn.ok_or_else(|| invalid("missing PCESVN"))?,
+            cpusvn.ok_or_else(|| invalid("missing CPUSVN"))?,
+        ))
+    }
+}
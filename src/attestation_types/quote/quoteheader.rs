@@ -3,11 +3,15 @@
 //! The QuoteHeader is part of the Quote structure. See the Quote module for more.
 
 use super::QuoteError;
+use crate::PceSvn;
 use core::convert::TryFrom;
 
 /// The Quote version for DCAP is 3. Must be 2 bytes.
 pub const VERSION: u16 = 3;
 
+/// Version 4 Quotes may carry either an SGX or a TDX report body.
+pub const VERSION_4: u16 = 4;
+
 /// Intel's Vendor ID, as specified in A.4, Table 3. Must be 16 bytes.
 pub const INTELVID: [u8; 16] = [
     0x93, 0x9A, 0x72, 0x33, 0xF7, 0x9C, 0x4C, 0xA9, 0x94, 0x0A, 0x0D, 0xB3, 0x95, 0x7F, 0x06, 0x07,
@@ -45,27 +49,58 @@ impl TryFrom<u16> for AttestationKeyType {
     }
 }
 
+/// The type of TEE that generated the quoted report.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u32)]
+pub enum TeeType {
+    /// Intel SGX; the Quote body is an ISV Enclave Report
+    Sgx = 0x00000000,
+
+    /// Intel TDX; the Quote body is a TD Report Body (version 4 only)
+    Tdx = 0x00000081,
+}
+
+impl Default for TeeType {
+    fn default() -> Self {
+        TeeType::Sgx
+    }
+}
+
+impl TryFrom<u32> for TeeType {
+    type Error = QuoteError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x00000000 => Ok(TeeType::Sgx),
+            0x00000081 => Ok(TeeType::Tdx),
+            _ => Err(QuoteError(format!("Unknown TeeType value: {:#x}", value))),
+        }
+    }
+}
+
 /// Unlike the other parts of the Quote, this structure
 /// is transparent to the user.
 /// Section A.4, Table 3
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct QuoteHeader {
-    /// Version of Quote structure, 3 in the ECDSA case.
+    /// Version of Quote structure, 3 in the ECDSA case or 4 if it may carry
+    /// a TDX report.
     pub version: u16,
 
     /// Type of attestation key used. Only one type is currently supported:
     /// 2 (ECDSA-256-with-P-256-curve).
     pub att_key_type: AttestationKeyType,
 
-    /// Reserved.
-    reserved: u32,
+    /// Type of the TEE whose report is quoted; reserved (SGX) in version 3.
+    pub tee_type: TeeType,
 
-    /// Security version of the QE.
+    /// Security version of the QE; reserved in version 4.
     pub qe_svn: u16,
 
-    /// Security version of the Provisioning Cerfitication Enclave.
-    pub pce_svn: u16,
+    /// Security version of the Provisioning Cerfitication Enclave; reserved
+    /// in version 4.
+    pub pce_svn: PceSvn,
 
     /// ID of the QE vendor.
     pub qe_vendor_id: [u8; 16],
@@ -82,7 +117,7 @@ impl Default for QuoteHeader {
         Self {
             version: VERSION,
             att_key_type: Default::default(),
-            reserved: Default::default(),
+            tee_type: Default::default(),
             qe_svn: Default::default(),
             pce_svn: Default::default(),
             qe_vendor_id: INTELVID,
@@ -99,10 +134,10 @@ impl TryFrom<&[u8; 48]> for QuoteHeader {
 
         tmp.copy_from_slice(&bytes[0..2]);
         let version = u16::from_le_bytes(tmp);
-        if version != VERSION {
+        if version != VERSION && version != VERSION_4 {
             return Err(QuoteError(
-                    format!("Incorrect Quote version, expected: {}, actual: {}; cannot convert bytes to QuoteHeader", 
-                VERSION, version)));
+                    format!("Incorrect Quote version, expected: {} or {}, actual: {}; cannot convert bytes to QuoteHeader",
+                VERSION, VERSION_4, version)));
         }
 
         tmp.copy_from_slice(&bytes[2..4]);
@@ -113,11 +148,21 @@ impl TryFrom<&[u8; 48]> for QuoteHeader {
                 AttestationKeyType::default() as u16, att_key_type as u16)));
         }
 
+        let mut tee = [0u8; 4];
+        tee.copy_from_slice(&bytes[4..8]);
+        let tee_type = TeeType::try_from(u32::from_le_bytes(tee))?;
+        if version == VERSION && tee_type != TeeType::Sgx {
+            return Err(QuoteError(format!(
+                "Quote version {} only supports SGX; cannot convert bytes to QuoteHeader",
+                VERSION
+            )));
+        }
+
         tmp.copy_from_slice(&bytes[8..10]);
         let qe_svn = u16::from_le_bytes(tmp);
 
         tmp.copy_from_slice(&bytes[10..12]);
-        let pce_svn = u16::from_le_bytes(tmp);
+        let pce_svn = PceSvn::new(u16::from_le_bytes(tmp));
 
         let mut qe_vendor_id = [0u8; 16];
         qe_vendor_id.copy_from_slice(&bytes[12..28]);
@@ -128,11 +173,11 @@ impl TryFrom<&[u8; 48]> for QuoteHeader {
         Ok(Self {
             version,
             att_key_type,
+            tee_type,
             qe_svn,
             pce_svn,
             qe_vendor_id,
             user_data,
-            ..Default::default()
         })
     }
 }
@@ -142,7 +187,7 @@ testaso! {
     struct QuoteHeader: 4, 48 => {
         version: 0,
         att_key_type: 2,
-        reserved: 4,
+        tee_type: 4,
         qe_svn: 8,
         pce_svn: 10,
         qe_vendor_id: 12,
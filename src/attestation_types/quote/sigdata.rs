@@ -2,8 +2,10 @@
 
 //! The SigData structure is part of the Quote structure. For more, see the Quote module.
 
+use super::pckext::SgxExtension;
 use super::QuoteError;
 use crate::attestation_types::report::Body;
+use crate::{CpuSvn, PceId, PceSvn};
 use openssl::x509::X509;
 use std::{convert::TryFrom, fmt, vec::Vec};
 
@@ -89,6 +91,9 @@ pub struct PckCertChain {
 
     /// Root certificate
     pub root_cert: X509,
+
+    /// SGX extension of the leaf certificate
+    pub sgx_extension: SgxExtension,
 }
 
 /// Section A.4, Table 9
@@ -114,8 +119,9 @@ pub enum CertDataType {
     /// PCK Leaf Cert||Intermediate CA Cert||Root CA Cert
     PCKCertChain = 5,
 
-    /// Intel SGX Quote (not supported).
-    Quote = 6,
+    /// QE Report Certification Data, wrapping the QE Report and
+    /// its own Cert Data in version 4 Quotes.
+    QEReportCertData = 6,
 
     /// Platform Manifest (not supported).
     Manifest = 7,
@@ -137,7 +143,7 @@ impl TryFrom<u16> for CertDataType {
             3 => Ok(CertDataType::PpidRSA3072OAEP),
             4 => Ok(CertDataType::PCKLeafCert),
             5 => Ok(CertDataType::PCKCertChain),
-            6 => Ok(CertDataType::Quote),
+            6 => Ok(CertDataType::QEReportCertData),
             7 => Ok(CertDataType::Manifest),
             _ => Err(QuoteError(format!("Unknown Cert Data type: {}", value))),
         }
@@ -152,12 +158,113 @@ impl fmt::Display for CertDataType {
             CertDataType::PpidRSA3072OAEP => write!(f, "PpidRSA3072)AEP"),
             CertDataType::PCKLeafCert => write!(f, "PCKLeafCert"),
             CertDataType::PCKCertChain => write!(f, "PCKCertChain"),
-            CertDataType::Quote => write!(f, "Quote"),
+            CertDataType::QEReportCertData => write!(f, "QEReportCertData"),
             CertDataType::Manifest => write!(f, "Manifest"),
         }
     }
 }
 
+/// Platform identification carried in the PPID types of CertData:
+/// PPID (in the clear or encrypted) || CPUSVN || PCESVN (LE) || PCEID (LE)
+#[derive(Clone, Debug)]
+pub struct PpidData {
+    /// The Platform Provisioning ID, in the clear or encrypted
+    pub ppid: Vec<u8>,
+
+    /// The security version number of the processor
+    pub cpusvn: CpuSvn,
+
+    /// The security version number of the PCE
+    pub pce_svn: PceSvn,
+
+    /// The ID of the PCE
+    pub pce_id: PceId,
+}
+
+impl PpidData {
+    fn parse(bytes: &[u8], ppid_len: usize) -> Result<Self, QuoteError> {
+        if bytes.len() != ppid_len + 20 {
+            return Err(QuoteError(format!(
+                "Invalid PPID Cert Data length, expected: {}, actual: {}",
+                ppid_len + 20,
+                bytes.len()
+            )));
+        }
+
+        let mut cpusvn = [0u8; 16];
+        cpusvn.copy_from_slice(&bytes[ppid_len..ppid_len + 16]);
+
+        let mut tmp = [0u8; 2];
+        tmp.copy_from_slice(&bytes[ppid_len + 16..ppid_len + 18]);
+        let pce_svn = PceSvn::new(u16::from_le_bytes(tmp));
+
+        tmp.copy_from_slice(&bytes[ppid_len + 18..ppid_len + 20]);
+        let pce_id = PceId::new(u16::from_le_bytes(tmp));
+
+        Ok(Self {
+            ppid: bytes[..ppid_len].to_vec(),
+            cpusvn: CpuSvn::new(cpusvn),
+            pce_svn,
+            pce_id,
+        })
+    }
+}
+
+/// The QE Certification Data, typed according to its CertDataType.
+/// Section A.4, Table 9
+#[derive(Clone, Debug)]
+pub enum CertData {
+    /// Cleartext PPID (16 bytes) and platform identification
+    PpidPlaintext(PpidData),
+
+    /// PPID encrypted using RSA-2048-OAEP (256 bytes) and platform identification
+    PpidRSA2048OAEP(PpidData),
+
+    /// PPID encrypted using RSA-3072-OAEP (384 bytes) and platform identification
+    PpidRSA3072OAEP(PpidData),
+
+    /// PCK Leaf Certificate
+    PCKLeafCert(Vec<u8>),
+
+    /// Concatenated PCK Cert Chain (PEM formatted).
+    PCKCertChain(Vec<u8>),
+}
+
+impl Default for CertData {
+    fn default() -> Self {
+        Self::PCKLeafCert(Vec::new())
+    }
+}
+
+impl CertData {
+    /// Parses Cert Data of the given type.
+    pub fn parse(cert_data_type: CertDataType, bytes: &[u8]) -> Result<Self, QuoteError> {
+        match cert_data_type {
+            CertDataType::PpidPlaintext => Ok(Self::PpidPlaintext(PpidData::parse(bytes, 16)?)),
+            CertDataType::PpidRSA2048OAEP => {
+                Ok(Self::PpidRSA2048OAEP(PpidData::parse(bytes, 256)?))
+            }
+            CertDataType::PpidRSA3072OAEP => {
+                Ok(Self::PpidRSA3072OAEP(PpidData::parse(bytes, 384)?))
+            }
+            CertDataType::PCKLeafCert => Ok(Self::PCKLeafCert(bytes.to_vec())),
+            CertDataType::PCKCertChain => Ok(Self::PCKCertChain(bytes.to_vec())),
+            t => Err(QuoteError(format!("Unsupported Cert Data type: {}", t))),
+        }
+    }
+
+    /// Returns the type of this Cert Data
+    pub fn cert_data_type(&self) -> CertDataType {
+        match self {
+            Self::PpidPlaintext(_) => CertDataType::PpidPlaintext,
+            Self::PpidRSA2048OAEP(_) => CertDataType::PpidRSA2048OAEP,
+            Self::PpidRSA3072OAEP(_) => CertDataType::PpidRSA3072OAEP,
+            Self::PCKLeafCert(_) => CertDataType::PCKLeafCert,
+            Self::PCKCertChain(_) => CertDataType::PCKCertChain,
+        }
+    }
+}
+
 /// A.4, Table 4
 #[derive(Default)]
 #[repr(C)]
@@ -170,6 +277,7 @@ pub struct SigData {
     qe_cert_data_type: CertDataType,
     qe_cert_data_len: u32,
     qe_cert_data: Vec<u8>,
+    cert_data: CertData,
 }
 
 // The size of SigData is not known at compile time. It is specified in the data itself.
@@ -177,27 +285,84 @@ impl TryFrom<&[u8]> for SigData {
     type Error = QuoteError;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() < 128 {
+            return Err(QuoteError(
+                "Insufficient SigData length; no attestation key specified".to_string(),
+            ));
+        }
+
+        Self::parse(&bytes[0..128], &bytes[128..])
+    }
+}
+
+impl SigData {
+    /// Converts the signature data of a version 4 Quote. Unlike in version 3,
+    /// the QE Report and everything following it are wrapped in Cert Data of
+    /// type 6 (QE Report Certification Data).
+    pub fn try_from_v4(bytes: &[u8]) -> Result<Self, QuoteError> {
+        if bytes.len() < 134 {
+            return Err(QuoteError(
+                "Insufficient SigData length; no QE Report Cert Data specified".to_string(),
+            ));
+        }
+
+        let mut wrapper_type_bytes = [0u8; 2];
+        wrapper_type_bytes.copy_from_slice(&bytes[128..130]);
+        let wrapper_type = CertDataType::try_from(u16::from_le_bytes(wrapper_type_bytes))?;
+        if wrapper_type != CertDataType::QEReportCertData {
+            return Err(QuoteError(format!(
+                "Expected CertDataType::QEReportCertData, got: {}",
+                wrapper_type
+            )));
+        }
+
+        let mut wrapper_len_bytes = [0u8; 4];
+        wrapper_len_bytes.copy_from_slice(&bytes[130..134]);
+        let wrapper_end = 134 + u32::from_le_bytes(wrapper_len_bytes) as usize;
+        if bytes.len() < wrapper_end {
+            return Err(QuoteError(
+                "Insufficient SigData length; QE Report Cert Data truncated".to_string(),
+            ));
+        }
+
+        Self::parse(&bytes[0..128], &bytes[134..wrapper_end])
+    }
+
+    // `keys` holds the ISV Enclave Report Signature and the Attestation Key;
+    // `bytes` begins with the QE Report.
+    fn parse(keys: &[u8], bytes: &[u8]) -> Result<Self, QuoteError> {
         let mut tmp = [0u8; 64];
-        tmp.copy_from_slice(&bytes[0..64]);
+        tmp.copy_from_slice(&keys[0..64]);
         let isv_enclave_report_sig = ECDSAP256Sig::from(&tmp);
 
-        tmp.copy_from_slice(&bytes[64..128]);
+        tmp.copy_from_slice(&keys[64..128]);
         let ecdsa_attestation_key = ECDSAPubKey::from(&tmp);
 
+        if bytes.len() < 450 {
+            return Err(QuoteError(
+                "Insufficient SigData length; no QE Report specified".to_string(),
+            ));
+        }
+
         let mut body = [0u8; 384];
-        body.copy_from_slice(&bytes[128..512]);
+        body.copy_from_slice(&bytes[0..384]);
         let qe_report = Body::try_from(&body)?;
 
-        tmp.copy_from_slice(&bytes[512..576]);
+        tmp.copy_from_slice(&bytes[384..448]);
         let qe_report_sig = ECDSAP256Sig::from(&tmp);
 
-        // QE Auth Data length is variable, specified in &bytes[576..578]
+        // QE Auth Data length is variable, specified in &bytes[448..450]
         let mut qe_auth_len_bytes = [0u8; 2];
-        qe_auth_len_bytes.copy_from_slice(&bytes[576..578]);
+        qe_auth_len_bytes.copy_from_slice(&bytes[448..450]);
         let qe_auth_len: usize = u16::from_le_bytes(qe_auth_len_bytes).into();
+        let qe_auth_end = 450usize + qe_auth_len;
+        if bytes.len() < qe_auth_end + 6 {
+            return Err(QuoteError(
+                "Insufficient SigData length; no Cert Data specified".to_string(),
+            ));
+        }
         let mut qe_auth = vec![0u8; qe_auth_len];
-        let qe_auth_end = 578usize + qe_auth_len;
-        qe_auth.copy_from_slice(&bytes[578..qe_auth_end]);
+        qe_auth.copy_from_slice(&bytes[450..qe_auth_end]);
 
         // Cert Data beginning and length is variable
         let mut qe_cert_data_type_bytes = [0u8; 2];
@@ -205,22 +370,21 @@ impl TryFrom<&[u8]> for SigData {
         let qe_cert_data_type =
             CertDataType::try_from(u16::from_le_bytes(qe_cert_data_type_bytes))?;
 
-        if qe_cert_data_type != CertDataType::PCKCertChain {
-            return Err(QuoteError(format!(
-                "Expected CertDataType::PCKCertChain, got: {}",
-                qe_cert_data_type
-            )));
-        }
-
         let cert_data_len_start = qe_auth_end + 2;
         let mut cert_data_len_bytes = [0u8; 4];
         cert_data_len_bytes.copy_from_slice(&bytes[cert_data_len_start..(cert_data_len_start + 4)]);
         let qe_cert_data_len = u32::from_le_bytes(cert_data_len_bytes);
         let cert_data_start = cert_data_len_start + 4;
+        if bytes.len() < cert_data_start + qe_cert_data_len as usize {
+            return Err(QuoteError(
+                "Insufficient SigData length; Cert Data truncated".to_string(),
+            ));
+        }
         let mut qe_cert_data = vec![0u8; qe_cert_data_len as usize];
         qe_cert_data.copy_from_slice(
             &bytes[cert_data_start..(cert_data_start + qe_cert_data_len as usize)],
         );
+        let cert_data = CertData::parse(qe_cert_data_type, &qe_cert_data)?;
 
         Ok(Self {
             isv_enclave_report_sig,
@@ -231,6 +395,7 @@ impl TryFrom<&[u8]> for SigData {
             qe_cert_data_type,
             qe_cert_data_len,
             qe_cert_data,
+            cert_data,
         })
     }
 }
@@ -278,6 +443,11 @@ impl SigData {
         self.qe_cert_data.clone()
     }
 
+    /// Retrieve the QE Cert Data, parsed according to its type
+    pub fn qe_cert_data(&self) -> &CertData {
+        &self.cert_data
+    }
+
     /// Returns QE Cert Data as a PCK certificate chain, if QE Cert Data is of the appropriate type
     pub fn qe_cert_data_pckchain(&self) -> Result<PckCertChain, QuoteError> {
         if self.qe_cert_data_type != CertDataType::PCKCertChain {
@@ -294,14 +464,106 @@ impl SigData {
         let pck_cert_chain =
             X509::stack_from_pem(chain.as_bytes()).map_err(|e| QuoteError(e.to_string()))?;
 
-        let leaf_cert = pck_cert_chain[0].clone();
-        let intermed_cert = pck_cert_chain[1].clone();
-        let root_cert = pck_cert_chain[2].clone();
+        let (leaf_cert, intermed_cert, root_cert) = match &pck_cert_chain[..] {
+            [leaf, intermed, root] => (leaf.clone(), intermed.clone(), root.clone()),
+            certs => {
+                return Err(QuoteError(format!(
+                    "PCK cert chain has {} certificates instead of 3",
+                    certs.len()
+                )))
+            }
+        };
+
+        let der = leaf_cert.to_der().map_err(|e| QuoteError(e.to_string()))?;
+        let sgx_extension = SgxExtension::from_der(&der)?;
 
         Ok(PckCertChain {
             leaf_cert,
             intermed_cert,
             root_cert,
+            sgx_extension,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // PPID || CPUSVN || PCESVN (LE) || PCEID (LE)
+    fn ppid_data(ppid_len: usize) -> Vec<u8> {
+        let mut bytes = vec![0xaa; ppid_len];
+        bytes.extend(1..=16u8);
+        bytes.extend(&0x0102u16.to_le_bytes());
+        bytes.extend(&0x0304u16.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn ppid() {
+        let types = [
+            (CertDataType::PpidPlaintext, 16),
+            (CertDataType::PpidRSA2048OAEP, 256),
+            (CertDataType::PpidRSA3072OAEP, 384),
+        ];
+
+        for (cert_data_type, ppid_len) in types.iter().copied() {
+            let bytes = ppid_data(ppid_len);
+            let cert_data = CertData::parse(cert_data_type, &bytes).unwrap();
+            assert_eq!(cert_data.cert_data_type(), cert_data_type);
+
+            let data = match cert_data {
+                CertData::PpidPlaintext(data)
+                | CertData::PpidRSA2048OAEP(data)
+                | CertData::PpidRSA3072OAEP(data) => data,
+                _ => panic!(
+                    "{} parsed as {}",
+                    cert_data_type,
+                    cert_data.cert_data_type()
+                ),
+            };
+            assert_eq!(data.ppid, vec![0xaa; ppid_len]);
+            assert_eq!(
+                data.cpusvn,
+                CpuSvn::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16])
+            );
+            assert_eq!(data.pce_svn, PceSvn::new(0x0102));
+            assert_eq!(data.pce_id, PceId::new(0x0304));
+
+            // Truncated or overlong data is rejected.
+            assert!(CertData::parse(cert_data_type, &bytes[..bytes.len() - 1]).is_err());
+            assert!(CertData::parse(cert_data_type, &bytes[..ppid_len]).is_err());
+            assert!(CertData::parse(cert_data_type, &[]).is_err());
+
+            let mut long = bytes.clone();
+            long.push(0);
+            assert!(CertData::parse(cert_data_type, &long).is_err());
+        }
+
+        // Each type has its own PPID length.
+        let bytes = ppid_data(16);
+        assert!(CertData::parse(CertDataType::PpidRSA2048OAEP, &bytes).is_err());
+        assert!(CertData::parse(CertDataType::PpidRSA3072OAEP, &bytes).is_err());
+    }
+
+    #[test]
+    fn leaf_cert() {
+        let bytes = [0x30, 0x82, 0x01, 0x00];
+        match CertData::parse(CertDataType::PCKLeafCert, &bytes).unwrap() {
+            CertData::PCKLeafCert(der) => assert_eq!(der, bytes),
+            cert_data => panic!("parsed as {}", cert_data.cert_data_type()),
+        }
+    }
+
+    #[test]
+    fn cert_data_type() {
+        for value in 1..=7 {
+            assert_eq!(CertDataType::try_from(value).unwrap() as u16, value);
+        }
+
+        assert!(CertDataType::try_from(0).is_err());
+        assert!(CertDataType::try_from(8).is_err());
+        assert!(CertData::parse(CertDataType::QEReportCertData, &[]).is_err());
+        assert!(CertData::parse(CertDataType::Manifest, &[]).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The TD Report Body is the body of a version 4 Quote generated for an Intel TDX
+//! Trust Domain, in place of the SGX ISV Enclave Report. See the Quote module for more.
+//! See Appendix A of the Intel TDX DCAP Quoting Library API for the layout.
+
+use super::QuoteError;
+use core::convert::TryFrom;
+
+/// The length in bytes of the TD Report Body.
+pub const TD_REPORT_BODY_LEN: usize = 584;
+
+/// TD Report Body of a version 4 Quote
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct TdReportBody {
+    /// Describes the TCB of the TDX module (SEAM) and the TDX platform
+    pub tee_tcb_svn: [u8; 16],
+
+    /// Measurement of the TDX module
+    pub mrseam: [u8; 48],
+
+    /// Measurement of the signer of the TDX module; zero for Intel-signed modules
+    pub mrsignerseam: [u8; 48],
+
+    /// Attributes of the TDX module
+    pub seam_attributes: u64,
+
+    /// Attributes of the TD
+    pub td_attributes: u64,
+
+    /// XSAVE features enabled for the TD (XFAM)
+    pub xfam: u64,
+
+    /// Measurement of the initial contents of the TD
+    pub mrtd: [u8; 48],
+
+    /// Software-defined ID for non-owner-defined configuration of the TD
+    pub mrconfigid: [u8; 48],
+
+    /// Software-defined ID for the owner of the TD
+    pub mrowner: [u8; 48],
+
+    /// Software-defined ID for owner-defined configuration of the TD
+    pub mrownerconfig: [u8; 48],
+
+    /// Runtime extendable measurement registers
+    pub rtmr: [[u8; 48]; 4],
+
+    /// Data provided by the user and protected by the Quote signature
+    pub report_data: [u8; 64],
+}
+
+impl Default for TdReportBody {
+    fn default() -> Self {
+        Self {
+            tee_tcb_svn: [0u8; 16],
+            mrseam: [0u8; 48],
+            mrsignerseam: [0u8; 48],
+            seam_attributes: 0,
+            td_attributes: 0,
+            xfam: 0,
+            mrtd: [0u8; 48],
+            mrconfigid: [0u8; 48],
+            mrowner: [0u8; 48],
+            mrownerconfig: [0u8; 48],
+            rtmr: [[0u8; 48]; 4],
+            report_data: [0u8; 64],
+        }
+    }
+}
+
+impl TryFrom<&[u8; TD_REPORT_BODY_LEN]> for TdReportBody {
+    type Error = QuoteError;
+
+    fn try_from(bytes: &[u8; TD_REPORT_BODY_LEN]) -> Result<Self, Self::Error> {
+        fn u64_at(bytes: &[u8], start: usize) -> u64 {
+            let mut tmp = [0u8; 8];
+            tmp.copy_from_slice(&bytes[start..start + 8]);
+            u64::from_le_bytes(tmp)
+        }
+
+        fn mr_at(bytes: &[u8], start: usize) -> [u8; 48] {
+            let mut tmp = [0u8; 48];
+            tmp.copy_from_slice(&bytes[start..start + 48]);
+            tmp
+        }
+
+        let mut tee_tcb_svn = [0u8; 16];
+        tee_tcb_svn.copy_from_slice(&bytes[0..16]);
+
+        let mut report_data = [0u8; 64];
+        report_data.copy_from_slice(&bytes[520..584]);
+
+        Ok(Self {
+            tee_tcb_svn,
+            mrseam: mr_at(bytes, 16),
+            mrsignerseam: mr_at(bytes, 64),
+            seam_attributes: u64_at(bytes, 112),
+            td_attributes: u64_at(bytes, 120),
+            xfam: u64_at(bytes, 128),
+            mrtd: mr_at(bytes, 136),
+            mrconfigid: mr_at(bytes, 184),
+            mrowner: mr_at(bytes, 232),
+            mrownerconfig: mr_at(bytes, 280),
+            rtmr: [
+                mr_at(bytes, 328),
+                mr_at(bytes, 376),
+                mr_at(bytes, 424),
+                mr_at(bytes, 472),
+            ],
+            report_data,
+        })
+    }
+}
+
+#[cfg(test)]
+testaso! {
+    struct TdReportBody: 8, 584 => {
+        tee_tcb_svn: 0,
+        mrseam: 16,
+        mrsignerseam: 64,
+        seam_attributes: 112,
+        td_attributes: 120,
+        xfam: 128,
+        mrtd: 136,
+        mrconfigid: 184,
+        mrowner: 232,
+        mrownerconfig: 280,
+        rtmr: 328,
+        report_data: 520
+    }
+}
@@ -0,0 +1,479 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Borrowed views of a Quote and its Reports. Unlike `Quote`, which copies every
+//! field out of the input, these only check lengths up front and then borrow
+//! from the input buffer, so inspecting a Quote neither allocates nor needs the
+//! `crypto` feature; they can be used inside an enclave or on `no_std` targets.
+//! See the Quote module for the layout.
+
+use crate::{Attributes, CpuSvn, Features, MiscSelect, PceSvn, ProductId, SecurityVersion, Xfrm};
+use core::convert::TryFrom;
+use core::fmt;
+
+const VERSION_3: u16 = 3;
+const VERSION_4: u16 = 4;
+const ECDSA256P256: u16 = 2;
+const TEE_SGX: u32 = 0x00000000;
+const TEE_TDX: u32 = 0x00000081;
+const QE_REPORT_CERT_DATA: u16 = 6;
+const LAST_CERT_DATA: u16 = 7;
+
+const QUOTE_HEADER_LEN: usize = 48;
+const REPORT_LEN: usize = 384;
+const TD_REPORT_BODY_LEN: usize = 584;
+const REPORTDATA_START: usize = 320;
+const SIG_DATA_LEN_LEN: usize = 4;
+const REPORT_SIG_LEN: usize = 64;
+const ATT_KEY_LEN: usize = 64;
+const QE_REPORT_SIG_LEN: usize = 64;
+const QE_AUTH_LEN_LEN: usize = 2;
+const CERT_DATA_HEADER_LEN: usize = 6;
+
+/// The bytes are not a Quote or Report these views can read
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidQuote {
+    /// The input ends before a structure it must contain
+    Length,
+
+    /// The Quote version is neither 3 nor 4
+    Version(u16),
+
+    /// The Attestation Key type is not ECDSA-256-with-P-256
+    KeyType(u16),
+
+    /// The TEE type is unknown, or TDX in a version 3 Quote
+    TeeType(u32),
+
+    /// A Report has undefined MISCSELECT or ATTRIBUTES bits
+    Attributes,
+
+    /// A Cert Data type is unknown, or the QE Report of a version 4 Quote is
+    /// not wrapped in QE Report Cert Data
+    CertDataType(u16),
+}
+
+impl fmt::Display for InvalidQuote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidQuote::Length => write!(f, "Quote truncated"),
+            InvalidQuote::Version(v) => write!(f, "unsupported Quote version {}", v),
+            InvalidQuote::KeyType(t) => write!(f, "unsupported Attestation Key type {}", t),
+            InvalidQuote::TeeType(t) => write!(f, "unsupported TEE type {:#x}", t),
+            InvalidQuote::Attributes => write!(f, "invalid Report attributes"),
+            InvalidQuote::CertDataType(t) => write!(f, "unexpected Cert Data type {}", t),
+        }
+    }
+}
+
+fn u16_at(bytes: &[u8], start: usize) -> u16 {
+    let mut tmp = [0u8; 2];
+    tmp.copy_from_slice(&bytes[start..start + 2]);
+    u16::from_le_bytes(tmp)
+}
+
+fn u32_at(bytes: &[u8], start: usize) -> u32 {
+    let mut tmp = [0u8; 4];
+    tmp.copy_from_slice(&bytes[start..start + 4]);
+    u32::from_le_bytes(tmp)
+}
+
+fn u64_at(bytes: &[u8], start: usize) -> u64 {
+    let mut tmp = [0u8; 8];
+    tmp.copy_from_slice(&bytes[start..start + 8]);
+    u64::from_le_bytes(tmp)
+}
+
+/// A borrowed ISV Enclave Report or QE Report (Table 38-21).
+#[derive(Clone, Copy)]
+pub struct ReportBodyRef<'a>(&'a [u8; REPORT_LEN]);
+
+impl<'a> TryFrom<&'a [u8]> for ReportBodyRef<'a> {
+    type Error = InvalidQuote;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        let bytes = <&[u8; REPORT_LEN]>::try_from(bytes).map_err(|_| InvalidQuote::Length)?;
+
+        if MiscSelect::from_bits(u32_at(bytes, 16)).is_none()
+            || Features::from_bits(u64_at(bytes, 48)).is_none()
+            || Xfrm::from_bits(u64_at(bytes, 56)).is_none()
+        {
+            return Err(InvalidQuote::Attributes);
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl<'a> ReportBodyRef<'a> {
+    /// The raw bytes of the Report
+    pub fn as_bytes(&self) -> &'a [u8; REPORT_LEN] {
+        self.0
+    }
+
+    /// The security version number of the processor
+    pub fn cpusvn(&self) -> CpuSvn {
+        let mut cpusvn = [0u8; 16];
+        cpusvn.copy_from_slice(&self.0[0..16]);
+        CpuSvn::new(cpusvn)
+    }
+
+    /// The extended features saved to the MISC region of the SSA frame
+    pub fn miscselect(&self) -> MiscSelect {
+        MiscSelect::from_bits_truncate(u32_at(self.0, 16))
+    }
+
+    /// The attributes of the enclave
+    pub fn attributes(&self) -> Attributes {
+        Attributes::new(
+            Features::from_bits_truncate(u64_at(self.0, 48)),
+            Xfrm::from_bits_truncate(u64_at(self.0, 56)),
+        )
+    }
+
+    /// The value of SECS.MRENCLAVE
+    pub fn mrenclave(&self) -> &'a [u8] {
+        &self.0[64..96]
+    }
+
+    /// The value of SECS.MRSIGNER
+    pub fn mrsigner(&self) -> &'a [u8] {
+        &self.0[128..160]
+    }
+
+    /// The product ID of the enclave
+    pub fn isv_prod_id(&self) -> ProductId {
+        ProductId::new(u16_at(self.0, 256))
+    }
+
+    /// The security version number of the enclave
+    pub fn isv_svn(&self) -> SecurityVersion {
+        SecurityVersion::new(u16_at(self.0, 258))
+    }
+
+    /// The data provided by the user and protected by the Report
+    pub fn reportdata(&self) -> &'a [u8] {
+        &self.0[REPORTDATA_START..REPORT_LEN]
+    }
+}
+
+/// A borrowed Quote Header (Section A.4, Table 3), validated as by
+/// `QuoteHeader`
+#[derive(Clone, Copy)]
+pub struct HeaderRef<'a>(&'a [u8; QUOTE_HEADER_LEN]);
+
+impl<'a> HeaderRef<'a> {
+    /// The version of the Quote: 3, or 4 if it may carry a TDX report
+    pub fn version(&self) -> u16 {
+        u16_at(self.0, 0)
+    }
+
+    /// Returns true if the Quote carries a TD Report Body rather than an ISV
+    /// Enclave Report
+    pub fn is_tdx(&self) -> bool {
+        u32_at(self.0, 4) == TEE_TDX
+    }
+
+    /// The security version of the QE; reserved in version 4
+    pub fn qe_svn(&self) -> u16 {
+        u16_at(self.0, 8)
+    }
+
+    /// The security version of the PCE; reserved in version 4
+    pub fn pce_svn(&self) -> PceSvn {
+        PceSvn::new(u16_at(self.0, 10))
+    }
+
+    /// The ID of the QE vendor
+    pub fn qe_vendor_id(&self) -> &'a [u8] {
+        &self.0[12..28]
+    }
+
+    /// Custom user-defined data
+    pub fn user_data(&self) -> &'a [u8] {
+        &self.0[28..48]
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for HeaderRef<'a> {
+    type Error = InvalidQuote;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        let bytes = bytes
+            .get(..QUOTE_HEADER_LEN)
+            .and_then(|b| <&[u8; QUOTE_HEADER_LEN]>::try_from(b).ok())
+            .ok_or(InvalidQuote::Length)?;
+
+        let version = u16_at(bytes, 0);
+        if version != VERSION_3 && version != VERSION_4 {
+            return Err(InvalidQuote::Version(version));
+        }
+
+        let key_type = u16_at(bytes, 2);
+        if key_type != ECDSA256P256 {
+            return Err(InvalidQuote::KeyType(key_type));
+        }
+
+        match u32_at(bytes, 4) {
+            TEE_SGX => (),
+            TEE_TDX if version == VERSION_4 => (),
+            tee => return Err(InvalidQuote::TeeType(tee)),
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+/// A borrowed Quote. All lengths are checked when the view is created, so
+/// the accessors cannot fail.
+#[derive(Clone, Copy)]
+pub struct QuoteRef<'a> {
+    bytes: &'a [u8],
+    header: HeaderRef<'a>,
+    body_len: usize,
+    qe_report_start: usize,
+    cert_data_type: u16,
+    cert_data_start: usize,
+}
+
+impl<'a> TryFrom<&'a [u8]> for QuoteRef<'a> {
+    type Error = InvalidQuote;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        let header = HeaderRef::try_from(bytes)?;
+        let body_len = if header.is_tdx() {
+            TD_REPORT_BODY_LEN
+        } else {
+            REPORT_LEN
+        };
+
+        let sig_start = QUOTE_HEADER_LEN + body_len + SIG_DATA_LEN_LEN;
+        let mut qe_report_start = sig_start + REPORT_SIG_LEN + ATT_KEY_LEN;
+        if header.version() != VERSION_3 {
+            qe_report_start += CERT_DATA_HEADER_LEN;
+        }
+
+        if bytes.len() < sig_start {
+            return Err(InvalidQuote::Length);
+        }
+
+        let end = (u32_at(bytes, sig_start - SIG_DATA_LEN_LEN) as usize)
+            .checked_add(sig_start)
+            .ok_or(InvalidQuote::Length)?;
+        let bytes = bytes.get(..end).ok_or(InvalidQuote::Length)?;
+
+        let qe_auth_start = qe_report_start + REPORT_LEN + QE_REPORT_SIG_LEN + QE_AUTH_LEN_LEN;
+        if bytes.len() < qe_auth_start {
+            return Err(InvalidQuote::Length);
+        }
+
+        // In version 4, the QE Report is wrapped in QE Report Cert Data.
+        if header.version() != VERSION_3 {
+            let wrapper_type = u16_at(bytes, qe_report_start - CERT_DATA_HEADER_LEN);
+            if wrapper_type != QE_REPORT_CERT_DATA {
+                return Err(InvalidQuote::CertDataType(wrapper_type));
+            }
+        }
+
+        ReportBodyRef::try_from(&bytes[qe_report_start..qe_report_start + REPORT_LEN])?;
+        if !header.is_tdx() {
+            ReportBodyRef::try_from(&bytes[QUOTE_HEADER_LEN..QUOTE_HEADER_LEN + body_len])?;
+        }
+
+        let qe_auth_len = u16_at(bytes, qe_auth_start - QE_AUTH_LEN_LEN) as usize;
+        let cert_data_header_start = qe_auth_start + qe_auth_len;
+        let cert_data_start = cert_data_header_start + CERT_DATA_HEADER_LEN;
+        if bytes.len() < cert_data_start {
+            return Err(InvalidQuote::Length);
+        }
+
+        let cert_data_type = u16_at(bytes, cert_data_header_start);
+        if cert_data_type == 0 || cert_data_type > LAST_CERT_DATA {
+            return Err(InvalidQuote::CertDataType(cert_data_type));
+        }
+
+        let cert_data_len = u32_at(bytes, cert_data_header_start + 2) as usize;
+        let cert_data_end = cert_data_start
+            .checked_add(cert_data_len)
+            .ok_or(InvalidQuote::Length)?;
+        let bytes = bytes.get(..cert_data_end).ok_or(InvalidQuote::Length)?;
+
+        Ok(Self {
+            bytes,
+            header,
+            body_len,
+            qe_report_start,
+            cert_data_type,
+            cert_data_start,
+        })
+    }
+}
+
+impl<'a> QuoteRef<'a> {
+    // Reports are validated when the view is created.
+    fn report_at(&self, start: usize) -> ReportBodyRef<'a> {
+        let bytes = &self.bytes[start..start + REPORT_LEN];
+        ReportBodyRef(<&[u8; REPORT_LEN]>::try_from(bytes).unwrap())
+    }
+
+    /// The Quote Header
+    pub fn header(&self) -> HeaderRef<'a> {
+        self.header
+    }
+
+    /// The Quote Header and body; the material signed by the Attestation Key
+    pub fn raw_header_and_body(&self) -> &'a [u8] {
+        &self.bytes[..QUOTE_HEADER_LEN + self.body_len]
+    }
+
+    /// The ISV Enclave Report, if this is an SGX Quote
+    pub fn sgx_body(&self) -> Option<ReportBodyRef<'a>> {
+        if self.header.is_tdx() {
+            return None;
+        }
+
+        Some(self.report_at(QUOTE_HEADER_LEN))
+    }
+
+    /// The raw TD Report Body, if this is a TDX Quote
+    pub fn tdx_body(&self) -> Option<&'a [u8]> {
+        if !self.header.is_tdx() {
+            return None;
+        }
+
+        Some(&self.bytes[QUOTE_HEADER_LEN..QUOTE_HEADER_LEN + self.body_len])
+    }
+
+    /// The ReportData of the SGX or TDX body
+    pub fn reportdata(&self) -> &'a [u8] {
+        let end = QUOTE_HEADER_LEN + self.body_len;
+        &self.bytes[end - 64..end]
+    }
+
+    /// The ISV Enclave Report Signature
+    pub fn report_sig(&self) -> &'a [u8] {
+        let start = QUOTE_HEADER_LEN + self.body_len + SIG_DATA_LEN_LEN;
+        &self.bytes[start..start + REPORT_SIG_LEN]
+    }
+
+    /// The Attestation Key
+    pub fn attkey(&self) -> &'a [u8] {
+        let start = QUOTE_HEADER_LEN + self.body_len + SIG_DATA_LEN_LEN + REPORT_SIG_LEN;
+        &self.bytes[start..start + ATT_KEY_LEN]
+    }
+
+    /// The QE Report
+    pub fn qe_report(&self) -> ReportBodyRef<'a> {
+        self.report_at(self.qe_report_start)
+    }
+
+    /// The QE Report Signature
+    pub fn qe_report_sig(&self) -> &'a [u8] {
+        let start = self.qe_report_start + REPORT_LEN;
+        &self.bytes[start..start + QE_REPORT_SIG_LEN]
+    }
+
+    /// The QE Authentication Data
+    pub fn qe_auth(&self) -> &'a [u8] {
+        let start = self.qe_report_start + REPORT_LEN + QE_REPORT_SIG_LEN + QE_AUTH_LEN_LEN;
+        &self.bytes[start..self.cert_data_start - CERT_DATA_HEADER_LEN]
+    }
+
+    /// The QE Cert Data type (Section A.4, Table 9)
+    pub fn qe_cert_data_type(&self) -> u16 {
+        self.cert_data_type
+    }
+
+    /// The raw QE Cert Data
+    pub fn qe_cert_data(&self) -> &'a [u8] {
+        &self.bytes[self.cert_data_start..]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Builds a version 3 SGX Quote with `auth` QE Authentication Data and
+    // `cert` PCK Cert Chain Cert Data.
+    fn quote(auth: &[u8], cert: &[u8]) -> [u8; 2048] {
+        let mut quote = [0u8; 2048];
+        quote[0..2].copy_from_slice(&VERSION_3.to_le_bytes());
+        quote[2..4].copy_from_slice(&ECDSA256P256.to_le_bytes());
+        quote[48 + 320..48 + 384].copy_from_slice(&[7u8; 64]);
+
+        let sig_len = 64 + 64 + 384 + 64 + 2 + auth.len() + 6 + cert.len();
+        quote[432..436].copy_from_slice(&(sig_len as u32).to_le_bytes());
+
+        let auth_start = 436 + 64 + 64 + 384 + 64;
+        quote[auth_start..auth_start + 2].copy_from_slice(&(auth.len() as u16).to_le_bytes());
+        quote[auth_start + 2..][..auth.len()].copy_from_slice(auth);
+
+        let cert_start = auth_start + 2 + auth.len();
+        quote[cert_start..cert_start + 2].copy_from_slice(&5u16.to_le_bytes());
+        quote[cert_start + 2..cert_start + 6].copy_from_slice(&(cert.len() as u32).to_le_bytes());
+        quote[cert_start + 6..][..cert.len()].copy_from_slice(cert);
+        quote
+    }
+
+    #[test]
+    fn constructed() {
+        let bytes = quote(&[1; 32], b"chain");
+        let view = QuoteRef::try_from(&bytes[..]).unwrap();
+
+        assert_eq!(view.header().version(), 3);
+        assert!(!view.header().is_tdx());
+        assert_eq!(view.reportdata(), &[7u8; 64][..]);
+        assert_eq!(view.sgx_body().unwrap().reportdata(), view.reportdata());
+        assert!(view.tdx_body().is_none());
+        assert_eq!(view.qe_auth(), &[1u8; 32][..]);
+        assert_eq!(view.qe_cert_data_type(), 5);
+        assert_eq!(view.qe_cert_data(), b"chain");
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = quote(&[], b"chain");
+        let len = QuoteRef::try_from(&bytes[..]).unwrap().qe_cert_data().len();
+        assert_eq!(len, 5);
+
+        let end = 436 + 64 + 64 + 384 + 64 + 2 + 6 + 5;
+        assert_eq!(
+            QuoteRef::try_from(&bytes[..end - 1]).err(),
+            Some(InvalidQuote::Length)
+        );
+        assert_eq!(
+            QuoteRef::try_from(&bytes[..40]).err(),
+            Some(InvalidQuote::Length)
+        );
+
+        let mut bad = bytes;
+        bad[0] = 5;
+        assert_eq!(
+            QuoteRef::try_from(&bad[..]).err(),
+            Some(InvalidQuote::Version(5))
+        );
+
+        let mut bad = bytes;
+        bad[4] = 0x81;
+        assert_eq!(
+            QuoteRef::try_from(&bad[..]).err(),
+            Some(InvalidQuote::TeeType(0x81))
+        );
+
+        let mut bad = bytes;
+        bad[48 + 48] = 0xff;
+        bad[48 + 55] = 0xff;
+        assert_eq!(
+            QuoteRef::try_from(&bad[..]).err(),
+            Some(InvalidQuote::Attributes)
+        );
+
+        // The signature data length cannot overflow the end of the Quote.
+        let mut bad = bytes;
+        bad[432..436].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            QuoteRef::try_from(&bad[..]).err(),
+            Some(InvalidQuote::Length)
+        );
+    }
+}
@@ -5,7 +5,7 @@
 
 #![allow(missing_docs)]
 
-use crate::{Attributes, Features, MiscSelect, ProductId, SecurityVersion, Xfrm};
+use crate::{Attributes, CpuSvn, Features, MiscSelect, ProductId, SecurityVersion, Xfrm};
 
 use core::{convert::TryFrom, default::Default};
 
@@ -19,7 +19,7 @@ pub struct ReportError;
 #[repr(C)]
 pub struct Body {
     /// The security version number of the processor
-    pub cpusvn: [u8; 16],
+    pub cpusvn: CpuSvn,
 
     /// Bit vector specifying which extended features are saved to the
     /// MISC region of the SSA frame when an AEX occurs
@@ -49,8 +49,11 @@ pub struct Body {
     /// Security version number of the enclave
     pub isv_svn: SecurityVersion,
 
+    /// Security version number of the enclave's configuration (CONFIGSVN)
+    pub config_svn: u16,
+
     /// Reserved
-    reserved3: [u32; 15],
+    reserved3: [u16; 29],
 
     /// Data provided by the user and protected by the Report's MAC (Section 38.15.1)
     pub reportdata: [u8; 64],
@@ -59,7 +62,7 @@ pub struct Body {
 impl Default for Body {
     fn default() -> Self {
         Body {
-            cpusvn: <[u8; 16]>::default(),
+            cpusvn: CpuSvn::default(),
             miscselect: MiscSelect::default(),
             reserved0: <[u32; 7]>::default(),
             attributes: Attributes::default(),
@@ -69,7 +72,8 @@ impl Default for Body {
             reserved2: <[u32; 24]>::default(),
             isv_prod_id: ProductId::default(),
             isv_svn: SecurityVersion::default(),
-            reserved3: <[u32; 15]>::default(),
+            config_svn: 0,
+            reserved3: [0; 29],
             reportdata: [0u8; 64],
         }
     }
@@ -81,6 +85,7 @@ impl TryFrom<&[u8; 384]> for Body {
     fn try_from(bytes: &[u8; 384]) -> Result<Self, Self::Error> {
         let mut cpusvn = [0u8; 16];
         cpusvn.copy_from_slice(&bytes[0..16]);
+        let cpusvn = CpuSvn::new(cpusvn);
 
         let mut misc = [0u8; 4];
         misc.copy_from_slice(&bytes[16..20]);
@@ -121,6 +126,10 @@ impl TryFrom<&[u8; 384]> for Body {
         svn.copy_from_slice(&bytes[258..260]);
         let isv_svn = SecurityVersion::new(u16::from_le_bytes(svn));
 
+        let mut config_svn = [0u8; 2];
+        config_svn.copy_from_slice(&bytes[260..262]);
+        let config_svn = u16::from_le_bytes(config_svn);
+
         let mut reportdata = [0u8; 64];
         reportdata.copy_from_slice(&bytes[320..384]);
 
@@ -132,6 +141,7 @@ impl TryFrom<&[u8; 384]> for Body {
             mrsigner,
             isv_prod_id,
             isv_svn,
+            config_svn,
             reportdata,
             ..Default::default()
         })
@@ -139,29 +149,42 @@ impl TryFrom<&[u8; 384]> for Body {
 }
 
 impl Body {
+    // Feeds the canonical little-endian bytes of the body to `out`, in order.
+    #[cfg(any(feature = "std", feature = "soft-cmac"))]
+    fn write(&self, mut out: impl FnMut(&[u8])) {
+        fn reserved(out: &mut impl FnMut(&[u8]), words: &[u32]) {
+            for word in words {
+                out(&word.to_le_bytes());
+            }
+        }
+
+        fn reserved16(out: &mut impl FnMut(&[u8]), words: &[u16]) {
+            for word in words {
+                out(&word.to_le_bytes());
+            }
+        }
+
+        out(&self.cpusvn.inner());
+        out(&self.miscselect.bits().to_le_bytes());
+        reserved(&mut out, &self.reserved0);
+        out(&self.attributes.features().bits().to_le_bytes());
+        out(&self.attributes.xfrm().bits().to_le_bytes());
+        out(&self.mrenclave);
+        reserved(&mut out, &self.reserved1);
+        out(&self.mrsigner);
+        reserved(&mut out, &self.reserved2);
+        out(&self.isv_prod_id.inner().to_le_bytes());
+        out(&self.isv_svn.inner().to_le_bytes());
+        out(&self.config_svn.to_le_bytes());
+        reserved16(&mut out, &self.reserved3);
+        out(&self.reportdata);
+    }
+
     /// Returns a Vec<u8> representation of Body
     #[cfg(feature = "std")]
     pub fn to_vec(&self) -> Vec<u8> {
         let mut vec: Vec<u8> = Vec::new();
-
-        let (_, res0, _) = unsafe { &self.reserved0[..].align_to::<u8>() };
-        let (_, res1, _) = unsafe { &self.reserved1[..].align_to::<u8>() };
-        let (_, res2, _) = unsafe { &self.reserved2[..].align_to::<u8>() };
-        let (_, res3, _) = unsafe { &self.reserved3[..].align_to::<u8>() };
-        let (_, reportdata, _) = unsafe { &self.reportdata.align_to::<u8>() };
-
-        vec.extend(&self.cpusvn);
-        vec.extend(&self.miscselect.bits().to_le_bytes());
-        vec.extend_from_slice(res0);
-        vec.extend(&self.attributes.to_vec());
-        vec.extend(&self.mrenclave);
-        vec.extend_from_slice(res1);
-        vec.extend(&self.mrsigner);
-        vec.extend_from_slice(res2);
-        vec.extend(&self.isv_prod_id.inner().to_le_bytes());
-        vec.extend(&self.isv_svn.inner().to_le_bytes());
-        vec.extend_from_slice(res3);
-        vec.extend_from_slice(reportdata);
+        self.write(|bytes| vec.extend_from_slice(bytes));
         vec
     }
 }
@@ -183,6 +206,72 @@ pub struct Report {
     padding: [u128; 5],
 }
 
+// Arbitrary values are structurally valid: the reserved fields and padding
+// are zero, but the MAC is not expected to verify.
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Body {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut body = Self {
+            cpusvn: u.arbitrary()?,
+            miscselect: u.arbitrary()?,
+            attributes: u.arbitrary()?,
+            isv_prod_id: u.arbitrary()?,
+            isv_svn: u.arbitrary()?,
+            config_svn: u.arbitrary()?,
+            ..Default::default()
+        };
+
+        u.fill_buffer(&mut body.mrenclave)?;
+        u.fill_buffer(&mut body.mrsigner)?;
+        u.fill_buffer(&mut body.reportdata)?;
+        Ok(body)
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Report {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut report = Self {
+            reportbody: u.arbitrary()?,
+            mac: u.arbitrary()?,
+            ..Default::default()
+        };
+
+        u.fill_buffer(&mut report.keyid)?;
+        Ok(report)
+    }
+}
+
+#[cfg(feature = "soft-cmac")]
+impl Report {
+    // Returns the CMAC state under `key` after absorbing the body.
+    fn cmac(&self, key: &[u8; 16]) -> cmac::Cmac<aes::Aes128> {
+        use cmac::{Mac, NewMac};
+
+        let mut cmac = cmac::Cmac::<aes::Aes128>::new(key.into());
+        self.reportbody.write(|bytes| cmac.update(bytes));
+        cmac
+    }
+
+    /// Computes the MAC of the body under the Report Key `key`, as EREPORT
+    /// does: the AES-128-CMAC of the body's bytes.
+    pub fn compute_mac(&self, key: &[u8; 16]) -> u128 {
+        use cmac::Mac;
+
+        let mut mac = [0u8; 16];
+        mac.copy_from_slice(&self.cmac(key).finalize().into_bytes());
+        u128::from_le_bytes(mac)
+    }
+
+    /// Checks the MAC of the Report under the Report Key `key`, comparing
+    /// it in constant time.
+    pub fn verify_mac(&self, key: &[u8; 16]) -> bool {
+        use cmac::Mac;
+
+        self.cmac(key).verify(&self.mac.to_le_bytes()).is_ok()
+    }
+}
+
 #[cfg(test)]
 testaso! {
     struct Body: 4, 384 => {
@@ -196,7 +285,8 @@ testaso! {
         reserved2: 160,
         isv_prod_id: 256,
         isv_svn: 258,
-        reserved3: 260,
+        config_svn: 260,
+        reserved3: 262,
         reportdata: 320
     }
 
@@ -207,3 +297,22 @@ testaso! {
         padding: 432
     }
 }
+
+#[cfg(all(test, feature = "soft-cmac"))]
+mod mac {
+    use super::*;
+
+    const KEY: [u8; 16] = [0x42; 16];
+
+    #[test]
+    fn mac() {
+        let mut report = Report::default();
+        report.reportbody.reportdata = [7; 64];
+        report.mac = report.compute_mac(&KEY);
+        assert!(report.verify_mac(&KEY));
+        assert!(!report.verify_mac(&[0; 16]));
+
+        report.reportbody.reportdata = [8; 64];
+        assert!(!report.verify_mac(&KEY));
+    }
+}
@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::claims::VerifiedReport;
+use super::handshake::QuoteVerifier;
+use super::time::TimeSource;
+
+use openssl::sha::Sha256;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+struct Entry {
+    report: VerifiedReport,
+    expires: i64,
+}
+
+/// Memoizes successful Quote verifications for a limited time
+///
+/// Entries are keyed by the SHA-256 hash of the Quote and the version of the
+/// collateral it was verified against, so that new collateral (e.g. a TCB
+/// recovery) invalidates them. Failed verifications are not cached.
+///
+/// The key does not identify the verifier, so a cache must only be used with
+/// a single verifier: verdicts of a verifier with other trusted roots or
+/// reference values would otherwise be returned for it.
+///
+/// The cache holds at most `capacity` entries. When it is full, the entries
+/// closest to expiring, expired ones first, are dropped.
+pub struct Cache {
+    ttl: i64,
+    capacity: usize,
+    entries: HashMap<[u8; 32], Entry>,
+    expiry: BTreeSet<(i64, [u8; 32])>,
+}
+
+impl Cache {
+    /// Creates an empty cache keeping at most `capacity` verdicts for `ttl`
+    /// seconds each.
+    pub fn new(ttl: i64, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: HashMap::new(),
+            expiry: BTreeSet::new(),
+        }
+    }
+
+    fn remove(&mut self, key: &[u8; 32]) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.expiry.remove(&(entry.expires, *key));
+        }
+    }
+
+    // Makes room for one more entry.
+    fn evict(&mut self) {
+        while self.entries.len() >= self.capacity {
+            match self.expiry.iter().next().copied() {
+                Some((_, key)) => self.remove(&key),
+                None => break,
+            }
+        }
+    }
+
+    fn key(quote: &[u8], collateral_version: u32) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(quote);
+        hasher.update(&collateral_version.to_le_bytes());
+        hasher.finish()
+    }
+
+    /// Returns the cached verdict for `quote`, or verifies it with `verifier`
+    /// and caches the result if it verifies.
+    pub fn verify(
+        &mut self,
+        quote: &[u8],
+        collateral_version: u32,
+        verifier: &dyn QuoteVerifier,
+        time: &dyn TimeSource,
+    ) -> Result<VerifiedReport, Box<dyn Error>> {
+        let now = time.now()?;
+        let key = Self::key(quote, collateral_version);
+
+        if let Some(entry) = self.entries.get(&key) {
+            if now < entry.expires {
+                trace_event!("quote verdict cached");
+                return Ok(entry.report.clone());
+            }
+        }
+
+        let report = verifier.verify(quote)?;
+        if self.capacity == 0 {
+            return Ok(report);
+        }
+
+        self.remove(&key);
+        self.evict();
+        let expires = now.saturating_add(self.ttl);
+        self.expiry.insert((expires, key));
+        self.entries.insert(
+            key,
+            Entry {
+                report: report.clone(),
+                expires,
+            },
+        );
+        Ok(report)
+    }
+
+    /// Removes the expired entries.
+    pub fn purge(&mut self, time: &dyn TimeSource) -> Result<(), Box<dyn Error>> {
+        let now = time.now()?;
+        while let Some(&(expires, key)) = self.expiry.iter().next() {
+            if now < expires {
+                break;
+            }
+
+            self.remove(&key);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of entries, including expired ones not yet purged.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestation_types::report::Body;
+    use crate::attestation_types::verify::FixedTime;
+    use std::cell::Cell;
+
+    struct Counting(Cell<usize>);
+
+    impl QuoteVerifier for Counting {
+        fn verify(&self, quote: &[u8]) -> Result<VerifiedReport, Box<dyn Error>> {
+            self.0.set(self.0.get() + 1);
+            if quote.is_empty() {
+                return Err("empty quote".into());
+            }
+
+            Ok(VerifiedReport::new(Body::default()))
+        }
+    }
+
+    #[test]
+    fn cache() {
+        let verifier = Counting(Cell::new(0));
+        let mut cache = Cache::new(10, 8);
+
+        cache.verify(b"quote", 1, &verifier, &FixedTime(0)).unwrap();
+        cache.verify(b"quote", 1, &verifier, &FixedTime(9)).unwrap();
+        assert_eq!(verifier.0.get(), 1);
+
+        cache.verify(b"quote", 2, &verifier, &FixedTime(9)).unwrap();
+        assert_eq!(verifier.0.get(), 2);
+
+        cache
+            .verify(b"quote", 1, &verifier, &FixedTime(10))
+            .unwrap();
+        assert_eq!(verifier.0.get(), 3);
+
+        assert!(cache.verify(b"", 1, &verifier, &FixedTime(10)).is_err());
+        assert!(cache.verify(b"", 1, &verifier, &FixedTime(10)).is_err());
+        assert_eq!(verifier.0.get(), 5);
+
+        assert_eq!(cache.len(), 2);
+        cache.purge(&FixedTime(19)).unwrap();
+        assert_eq!(cache.len(), 1);
+        cache.purge(&FixedTime(20)).unwrap();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn capacity() {
+        let verifier = Counting(Cell::new(0));
+        let mut cache = Cache::new(10, 2);
+
+        cache.verify(b"a", 1, &verifier, &FixedTime(0)).unwrap();
+        cache.verify(b"b", 1, &verifier, &FixedTime(1)).unwrap();
+        cache.verify(b"c", 1, &verifier, &FixedTime(2)).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(verifier.0.get(), 3);
+
+        // The oldest entry was evicted; the newer ones are still cached.
+        cache.verify(b"c", 1, &verifier, &FixedTime(3)).unwrap();
+        cache.verify(b"b", 1, &verifier, &FixedTime(3)).unwrap();
+        assert_eq!(verifier.0.get(), 3);
+        cache.verify(b"a", 1, &verifier, &FixedTime(3)).unwrap();
+        assert_eq!(verifier.0.get(), 4);
+        assert_eq!(cache.len(), 2);
+
+        let mut cache = Cache::new(10, 0);
+        cache.verify(b"a", 1, &verifier, &FixedTime(0)).unwrap();
+        assert!(cache.is_empty());
+    }
+}
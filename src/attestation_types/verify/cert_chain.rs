@@ -1,7 +1,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use super::error::VerifyError;
-use openssl::{stack::Stack, x509::*};
+use super::{error::VerifyError, roots::TrustedRoots, time::TimeSource};
+use openssl::{
+    stack::Stack,
+    x509::{verify::X509VerifyParam, *},
+};
+use std::convert::TryInto;
 
 /// This constructs a complete certificate chain by uniting the tenant's chain (from Intel)
 /// with the leaf cert embedded in the platform's Quote.
@@ -68,8 +72,14 @@ impl CertChain {
     }
 
     /// This verifies that the signatures on the certificate chain are correct by
-    /// checking the context of the leaf certificate.
-    pub fn verify_sigs(mut self) -> Result<(), VerifyError> {
+    /// checking the context of the leaf certificate. The chain must terminate
+    /// at one of the pinned `roots`, and every certificate must be valid at the
+    /// time given by `time`.
+    pub fn verify_sigs(
+        mut self,
+        roots: &TrustedRoots,
+        time: &dyn TimeSource,
+    ) -> Result<(), VerifyError> {
         // Parse out root cert, which will be at end of chain.
         // The rest of the chain holds intermediate certs.
         let root_cert = match self.chain.pop() {
@@ -79,9 +89,29 @@ impl CertChain {
             }
         };
 
-        // Only the root certificate is added to the trusted store.
+        // The chain must not be allowed to supply its own root.
+        if !roots.contains(&root_cert)? {
+            return Err(VerifyError(
+                "certificate chain terminates at an untrusted root".to_string(),
+            ));
+        }
+
+        // Only the pinned root certificates are added to the trusted store.
         let mut store_bldr = store::X509StoreBuilder::new()?;
-        store_bldr.add_cert(root_cert)?;
+        for root in roots.roots() {
+            store_bldr.add_cert(root.clone())?;
+        }
+
+        // Validity periods are checked against the injected time rather than
+        // the system clock. `time_t` is only 32 bits on some targets.
+        let now = time.now()?;
+        #[allow(clippy::useless_conversion)]
+        let now = now
+            .try_into()
+            .map_err(|_| VerifyError(format!("time {} is out of range", now)))?;
+        let mut param = X509VerifyParam::new()?;
+        param.set_time(now);
+        store_bldr.set_param(&param)?;
         let store = store_bldr.build();
 
         // Creates the chain of untrusted certificates.
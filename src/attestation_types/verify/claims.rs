@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::supplemental::Supplemental;
+use super::tcbeval::TcbLevel;
+use crate::attestation_types::report::Body;
+
+/// The claims of an enclave whose Report has been verified, either locally
+/// with `verify_report()` or remotely with `verify_claims_at()`
+#[derive(Clone)]
+pub struct VerifiedReport {
+    body: Body,
+    qe_auth: Vec<u8>,
+    tcb_level: Option<TcbLevel>,
+    supplemental: Option<Supplemental>,
+}
+
+impl VerifiedReport {
+    pub(super) fn new(body: Body) -> Self {
+        Self::with_qe_auth(body, Vec::new())
+    }
+
+    pub(super) fn with_qe_auth(body: Body, qe_auth: Vec<u8>) -> Self {
+        Self {
+            body,
+            qe_auth,
+            tcb_level: None,
+            supplemental: None,
+        }
+    }
+
+    pub(super) fn with_collateral(self, tcb_level: TcbLevel, supplemental: Supplemental) -> Self {
+        Self {
+            tcb_level: Some(tcb_level),
+            supplemental: Some(supplemental),
+            ..self
+        }
+    }
+
+    /// Returns the verified Report body
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Returns the verified ReportData
+    pub fn reportdata(&self) -> [u8; 64] {
+        self.body.reportdata
+    }
+
+    /// Returns the QE Authentication Data of the Quote, which the Quoting
+    /// Enclave bound into its Report, or nothing for a local Report
+    pub fn qe_auth(&self) -> &[u8] {
+        &self.qe_auth
+    }
+
+    /// Returns the TCB level of the attesting platform, with its status,
+    /// `tcbDate` and advisory IDs, if the Quote was verified against the TCB
+    /// Info of the platform (see `verify_all()`)
+    pub fn tcb_level(&self) -> Option<&TcbLevel> {
+        self.tcb_level.as_ref()
+    }
+
+    /// Returns the supplemental data of the collateral the Quote was
+    /// verified against, if any (see `verify_all()`)
+    pub fn supplemental(&self) -> Option<&Supplemental> {
+        self.supplemental.as_ref()
+    }
+}
@@ -0,0 +1,547 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::cert_chain::CertChain;
+use super::error::VerifyError;
+use super::key::Key;
+use super::roots::TrustedRoots;
+use super::sig::Signature;
+use super::time::TimeSource;
+
+use openssl::nid::Nid;
+use openssl::x509::X509;
+use std::convert::TryFrom;
+
+/// The subject common name of the Intel SGX TCB Signing certificate, the only
+/// certificate allowed to sign collateral
+pub const TCB_SIGNING_CN: &str = "Intel SGX TCB Signing";
+
+fn malformed(what: &str) -> VerifyError {
+    VerifyError(format!("malformed collateral: {}", what))
+}
+
+fn skip_ws(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+
+    i
+}
+
+// Returns the index after the JSON string starting at `i`.
+fn string_end(bytes: &[u8], mut i: usize) -> Result<usize, VerifyError> {
+    i += 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Ok(i + 1),
+            _ => i += 1,
+        }
+    }
+
+    Err(malformed("unterminated string"))
+}
+
+// Returns the index after the JSON value starting at `i`.
+fn value_end(bytes: &[u8], mut i: usize) -> Result<usize, VerifyError> {
+    match bytes.get(i) {
+        Some(b'"') => string_end(bytes, i),
+
+        Some(b'{') | Some(b'[') => {
+            let mut depth = 0usize;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'"' => {
+                        i = string_end(bytes, i)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => depth -= 1,
+                    _ => (),
+                }
+
+                i += 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+
+            Err(malformed("unterminated object"))
+        }
+
+        Some(_) => {
+            let start = i;
+            while i < bytes.len() && !b",}] \t\r\n".contains(&bytes[i]) {
+                i += 1;
+            }
+
+            if i == start {
+                return Err(malformed("missing value"));
+            }
+
+            Ok(i)
+        }
+
+        None => Err(malformed("missing value")),
+    }
+}
+
+// Splits the top-level JSON object `body` into its keys and values, without
+// unescaping or reformatting them, since signatures cover the exact bytes of
+// the signed value. Duplicate keys are rejected, so that every consumer of
+// the body agrees on which value was signed, and so are escaped keys, which
+// could otherwise spell a duplicate differently (e.g. `"tcb\u0049nfo"`).
+pub(super) fn members(body: &str) -> Result<Vec<(&str, &str)>, VerifyError> {
+    let bytes = body.as_bytes();
+    let mut members: Vec<(&str, &str)> = Vec::new();
+
+    let mut i = skip_ws(bytes, 0);
+    if bytes.get(i) != Some(&b'{') {
+        return Err(malformed("not a JSON object"));
+    }
+
+    i = skip_ws(bytes, i + 1);
+    if bytes.get(i) == Some(&b'}') {
+        i += 1;
+    } else {
+        loop {
+            if bytes.get(i) != Some(&b'"') {
+                return Err(malformed("missing key"));
+            }
+
+            let end = string_end(bytes, i)?;
+            let key = &body[i + 1..end - 1];
+            if key.contains('\\') {
+                return Err(malformed("escaped key"));
+            }
+
+            if members.iter().any(|(k, _)| *k == key) {
+                return Err(malformed("duplicate key"));
+            }
+
+            i = skip_ws(bytes, end);
+            if bytes.get(i) != Some(&b':') {
+                return Err(malformed("missing colon"));
+            }
+
+            let start = skip_ws(bytes, i + 1);
+            let end = value_end(bytes, start)?;
+            members.push((key, &body[start..end]));
+
+            i = skip_ws(bytes, end);
+            match bytes.get(i) {
+                Some(b',') => i = skip_ws(bytes, i + 1),
+                Some(b'}') => {
+                    i += 1;
+                    break;
+                }
+                _ => return Err(malformed("missing comma")),
+            }
+        }
+    }
+
+    if skip_ws(bytes, i) != bytes.len() {
+        return Err(malformed("trailing data"));
+    }
+
+    Ok(members)
+}
+
+// Splits the JSON array `array` into its elements, like `members()`.
+pub(super) fn elements(array: &str) -> Result<Vec<&str>, VerifyError> {
+    let bytes = array.as_bytes();
+    let mut elements = Vec::new();
+
+    let mut i = skip_ws(bytes, 0);
+    if bytes.get(i) != Some(&b'[') {
+        return Err(malformed("not a JSON array"));
+    }
+
+    i = skip_ws(bytes, i + 1);
+    if bytes.get(i) == Some(&b']') {
+        i += 1;
+    } else {
+        loop {
+            let end = value_end(bytes, i)?;
+            elements.push(&array[i..end]);
+
+            i = skip_ws(bytes, end);
+            match bytes.get(i) {
+                Some(b',') => i = skip_ws(bytes, i + 1),
+                Some(b']') => {
+                    i += 1;
+                    break;
+                }
+                _ => return Err(malformed("missing comma")),
+            }
+        }
+    }
+
+    if skip_ws(bytes, i) != bytes.len() {
+        return Err(malformed("trailing data"));
+    }
+
+    Ok(elements)
+}
+
+// Returns the timestamp `key` of a signed collateral value. The timestamps of
+// the Provisioning Certification Service all have the `YYYY-MM-DDThh:mm:ssZ`
+// format, so they are ordered as strings.
+pub(super) fn timestamp<'a>(signed: &'a str, key: &str) -> Result<&'a str, VerifyError> {
+    let value = members(signed)?
+        .into_iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+        .ok_or_else(|| malformed(&format!("missing {}", key)))?;
+
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .filter(|v| {
+            v.len() == 20
+                && v.bytes().enumerate().all(|(i, b)| match i {
+                    4 | 7 => b == b'-',
+                    10 => b == b'T',
+                    13 | 16 => b == b':',
+                    19 => b == b'Z',
+                    _ => b.is_ascii_digit(),
+                })
+        })
+        .ok_or_else(|| malformed(&format!("invalid {}: {}", key, value)))
+}
+
+// Returns the seconds since the Unix epoch of a timestamp returned by
+// `timestamp()`, whose fields are all digits.
+fn seconds(timestamp: &str) -> i64 {
+    let field = |start: usize, end: usize| timestamp[start..end].parse::<i64>().unwrap_or(0);
+    let (year, month, day) = (field(0, 4), field(5, 7), field(8, 10));
+
+    // Count the days since 0000-03-01, in eras of 400 years.
+    let year = if month <= 2 { year - 1 } else { year };
+    let (era, year) = (year / 400, year % 400);
+    let day = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let days = era * 146_097 + year * 365 + year / 4 - year / 100 + day - 719_468;
+
+    days * 86400 + field(11, 13) * 3600 + field(14, 16) * 60 + field(17, 19)
+}
+
+// Returns the `issueDate` and `nextUpdate` of a signed collateral value, in
+// seconds since the Unix epoch.
+pub(super) fn validity(signed: &str) -> Result<(i64, i64), VerifyError> {
+    Ok((
+        seconds(timestamp(signed, "issueDate")?),
+        seconds(timestamp(signed, "nextUpdate")?),
+    ))
+}
+
+pub(super) fn unhex(hex: &str) -> Result<Vec<u8>, VerifyError> {
+    if hex.len() % 2 != 0 {
+        return Err(malformed("odd length hex"));
+    }
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16);
+            let lo = (pair[1] as char).to_digit(16);
+            match (hi, lo) {
+                (Some(hi), Some(lo)) => Ok((hi << 4 | lo) as u8),
+                _ => Err(malformed("invalid hex")),
+            }
+        })
+        .collect()
+}
+
+/// Verifies signed collateral from the Intel Provisioning Certification
+/// Service, such as TCB Info or QE Identity, and returns the signed JSON
+/// value of `field`, exactly as it appears in `body`.
+///
+/// `body` is a JSON object holding `field` and a hex-encoded ECDSA P-256
+/// `signature` of it. `issuer_chain` holds the PEM certificates of the
+/// signer, leaf first (e.g. the URL-decoded `TCB-Info-Issuer-Chain` header).
+/// The signer must be the Intel SGX TCB Signing certificate, issued directly
+/// by one of the pinned `roots`: other leaves anchored at the roots, such as
+/// PCK certificates whose keys live on platforms, are rejected. Validity
+/// periods are checked against `time`. The returned value must not be
+/// trusted unless this function succeeds.
+///
+/// The `issueDate` and `nextUpdate` of the value are not checked here, since
+/// they apply to each Quote verified against it; `verify_all()` checks them.
+pub fn verify_collateral<'a>(
+    body: &'a str,
+    field: &str,
+    issuer_chain: &str,
+    roots: &TrustedRoots,
+    time: &dyn TimeSource,
+) -> Result<&'a str, VerifyError> {
+    let members = members(body)?;
+    let value = |key: &str| {
+        members
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| malformed(&format!("missing {}", key)))
+    };
+
+    let signed = value(field)?;
+    if !signed.starts_with('{') {
+        return Err(malformed(&format!("{} is not an object", field)));
+    }
+
+    let signature = match value("signature")? {
+        s if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') => unhex(&s[1..s.len() - 1])?,
+        _ => return Err(malformed("signature is not a string")),
+    };
+    if signature.len() != 64 {
+        return Err(malformed("signature is not 64 bytes"));
+    }
+
+    // The signing certificate chain is validated up to a pinned root.
+    let mut certs = X509::stack_from_pem(issuer_chain.as_bytes())?;
+    if certs.is_empty() {
+        return Err(VerifyError("empty collateral issuer chain".to_string()));
+    }
+
+    // Only the TCB Signing certificate, directly under the root, may sign.
+    if certs.len() != 2 {
+        return Err(VerifyError(
+            "collateral must be signed by a certificate issued by the root".to_string(),
+        ));
+    }
+
+    let leaf = certs.remove(0);
+    let cn = leaf
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .map(|e| e.data().as_slice());
+    if cn != Some(TCB_SIGNING_CN.as_bytes()) {
+        return Err(VerifyError(
+            "collateral is not signed by the TCB Signing certificate".to_string(),
+        ));
+    }
+
+    let chain = CertChain::new_from_chain(certs, &leaf);
+    chain.len_ok()?;
+    chain.verify_issuers()?;
+    chain.verify_sigs(roots, time)?;
+
+    // The signing certificate's key signed the value.
+    let key = Key::new_from_pubkey(leaf.public_key()?)?;
+    let signature = Signature::try_from(&signature[..])?.to_der_vec()?;
+    key.verify_sig(signed.as_bytes(), &signature)?;
+
+    trace_event!(field, "collateral signature verified");
+    Ok(signed)
+}
+
+/// Verifies a TCB Info response body, see `verify_collateral()`, and
+/// returns the signed `tcbInfo` JSON.
+pub fn verify_tcb_info<'a>(
+    body: &'a str,
+    issuer_chain: &str,
+    roots: &TrustedRoots,
+    time: &dyn TimeSource,
+) -> Result<&'a str, VerifyError> {
+    verify_collateral(body, "tcbInfo", issuer_chain, roots, time)
+}
+
+/// Verifies a QE Identity response body, see `verify_collateral()`, and
+/// returns the signed `enclaveIdentity` JSON (or `qeIdentity` for version 1
+/// of the API).
+pub fn verify_qe_identity<'a>(
+    body: &'a str,
+    issuer_chain: &str,
+    roots: &TrustedRoots,
+    time: &dyn TimeSource,
+) -> Result<&'a str, VerifyError> {
+    let mut field = "enclaveIdentity";
+    if members(body)?.iter().any(|(k, _)| *k == "qeIdentity") {
+        field = "qeIdentity";
+    }
+
+    verify_collateral(body, field, issuer_chain, roots, time)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::samples::{cert, key, pki, sign};
+    use super::*;
+    use crate::attestation_types::verify::FixedTime;
+
+    const NOW: FixedTime = FixedTime(1_500_000_000);
+
+    #[test]
+    fn tcb_info() {
+        let pki = pki();
+        let signed =
+            r#"{"version":2,"fmspc":"00906ea10000","tcbLevels":[{"tcbStatus":"UpToDate"}]}"#;
+        let body = format!(
+            "{{\"tcbInfo\":{},\"signature\":\"{}\"}}",
+            signed,
+            sign(&pki.key, signed)
+        );
+
+        let verified = verify_tcb_info(&body, &pki.chain, &pki.roots, &NOW).unwrap();
+        assert_eq!(verified, signed);
+
+        // A single changed byte invalidates the signature.
+        let tampered = body.replace("UpToDate", "UpToDatf");
+        assert!(verify_tcb_info(&tampered, &pki.chain, &pki.roots, &NOW).is_err());
+
+        // The chain must be anchored at a pinned root.
+        let other = pki();
+        assert!(verify_tcb_info(&body, &pki.chain, &other.roots, &NOW).is_err());
+
+        // A chain signed by another key does not verify the signature.
+        assert!(verify_tcb_info(&body, &other.chain, &other.roots, &NOW).is_err());
+
+        // The certificates are only valid until 2033.
+        let later = FixedTime(2_100_000_000);
+        assert!(verify_tcb_info(&body, &pki.chain, &pki.roots, &later).is_err());
+    }
+
+    #[test]
+    fn pck_signer() {
+        let root_key = key();
+        let root = cert("Root", &root_key, None);
+        let roots = TrustedRoots::new(vec![root.clone()]);
+        let ca_key = key();
+        let ca = cert(
+            "Intel SGX PCK Platform CA",
+            &ca_key,
+            Some((&root, &root_key)),
+        );
+        let pck_key = key();
+        let pck = cert("Intel SGX PCK Certificate", &pck_key, Some((&ca, &ca_key)));
+
+        let signed = r#"{"version":2,"tcbLevels":[{"tcbStatus":"UpToDate"}]}"#;
+        let body = format!(
+            "{{\"tcbInfo\":{},\"signature\":\"{}\"}}",
+            signed,
+            sign(&pck_key, signed)
+        );
+
+        // A PCK leaf chains to the root, but must not sign collateral.
+        let chain: String = [&pck, &ca, &root]
+            .iter()
+            .map(|c| String::from_utf8(c.to_pem().unwrap()).unwrap())
+            .collect();
+        assert!(verify_tcb_info(&body, &chain, &roots, &NOW).is_err());
+
+        // Neither may any other leaf issued directly by the root.
+        let other = cert(
+            "Intel SGX PCK Certificate",
+            &pck_key,
+            Some((&root, &root_key)),
+        );
+        let chain: String = [&other, &root]
+            .iter()
+            .map(|c| String::from_utf8(c.to_pem().unwrap()).unwrap())
+            .collect();
+        assert!(verify_tcb_info(&body, &chain, &roots, &NOW).is_err());
+
+        // Nor a TCB Signing certificate below an intermediate CA.
+        let tcb = cert(TCB_SIGNING_CN, &pck_key, Some((&ca, &ca_key)));
+        let chain: String = [&tcb, &ca, &root]
+            .iter()
+            .map(|c| String::from_utf8(c.to_pem().unwrap()).unwrap())
+            .collect();
+        assert!(verify_tcb_info(&body, &chain, &roots, &NOW).is_err());
+    }
+
+    #[test]
+    fn qe_identity() {
+        let pki = pki();
+        let signed = r#"{"id":"QE","version":2,"isvprodid":1}"#;
+        let signature = sign(&pki.key, signed);
+
+        let body = format!(
+            "{{ \"enclaveIdentity\" : {} , \"signature\" : \"{}\" }}",
+            signed, signature
+        );
+        let verified = verify_qe_identity(&body, &pki.chain, &pki.roots, &NOW).unwrap();
+        assert_eq!(verified, signed);
+
+        let body = format!(
+            "{{\"qeIdentity\":{},\"signature\":\"{}\"}}",
+            signed, signature
+        );
+        let verified = verify_qe_identity(&body, &pki.chain, &pki.roots, &NOW).unwrap();
+        assert_eq!(verified, signed);
+    }
+
+    #[test]
+    fn validity() {
+        let signed = r#"{"issueDate":"2022-08-10T12:00:00Z","nextUpdate":"2024-02-29T23:59:59Z"}"#;
+        assert_eq!(
+            super::validity(signed).unwrap(),
+            (1_660_132_800, 1_709_251_199)
+        );
+
+        assert!(super::validity(r#"{"issueDate":"2022-08-10T12:00:00Z"}"#).is_err());
+        let local = signed.replace("2022-08-10T12:00:00Z", "2022-08-10 12:00:00");
+        assert!(super::validity(&local).is_err());
+        let signed = signed.replace("2022-08-10T12:00:00Z", "2022-08-1OT12:00:00Z");
+        assert!(super::validity(&signed).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(members(r#"{"a":{"b":"}"},"c":[1,2],"d":"\"x"}"#).is_ok());
+        assert!(members(r#"{"a":1,"a":2}"#).is_err());
+        assert!(members(r#"{"a":1"#).is_err());
+        assert!(members(r#"{"a":1} x"#).is_err());
+        assert!(members(r#"["a"]"#).is_err());
+
+        let pki = pki();
+        let body = r#"{"tcbInfo":{},"signature":"00"}"#;
+        assert!(verify_tcb_info(body, &pki.chain, &pki.roots, &NOW).is_err());
+        let body = r#"{"tcbInfo":{}}"#;
+        assert!(verify_tcb_info(body, &pki.chain, &pki.roots, &NOW).is_err());
+    }
+
+    #[test]
+    fn escaped() {
+        // Escaped quotes and backslashes do not end strings early.
+        let body = r#"{"a":"x"}","b":"\","c":{"d":""]"}}"#;
+        assert_eq!(
+            members(body).unwrap(),
+            [("a", r#""x"}""#), ("b", r#""\""#), ("c", r#"{"d":""]"}"#)]
+        );
+        assert_eq!(
+            elements(r#"[""", "\", ["]"]]"#).unwrap(),
+            [r#"""""#, r#""\""#, r#"["]"]"#]
+        );
+        assert!(members(r#"{"a":""}"#).is_err());
+
+        // Keys are compared as written, so escapes could hide duplicates.
+        assert!(members(r#"{"tcbInfo":{},"tcbInfo":{}}"#).is_err());
+        assert!(members(r#"{"a"":1}"#).is_err());
+    }
+
+    #[test]
+    fn duplicate() {
+        let pki = pki();
+        let value = r#"{"version":2}"#;
+        let signature = sign(&pki.key, value);
+        let body = format!(r#"{{"tcbInfo":{},"signature":"{}"}}"#, value, signature);
+        assert!(verify_tcb_info(&body, &pki.chain, &pki.roots, &NOW).is_ok());
+
+        // A second signed value or signature could be the one another parser
+        // picks.
+        let other = r#"{"version":3}"#;
+        let body = format!(
+            r#"{{"tcbInfo":{},"signature":"{}","tcbInfo":{}}}"#,
+            value, signature, other
+        );
+        assert!(verify_tcb_info(&body, &pki.chain, &pki.roots, &NOW).is_err());
+        let body = format!(
+            r#"{{"tcbInfo":{},"signature":"{}","signature":"{}"}}"#,
+            value, signature, signature
+        );
+        assert!(verify_tcb_info(&body, &pki.chain, &pki.roots, &NOW).is_err());
+
+        // So could a nested key, once the signed value is parsed.
+        assert!(members(r#"{"version":2,"version":3}"#).is_err());
+    }
+}
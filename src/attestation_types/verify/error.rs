@@ -20,3 +20,55 @@ impl From<ErrorStack> for VerifyError {
         VerifyError(format!("ErrorStack: {:?}", e.errors()))
     }
 }
+
+/// A signature of a Quote, or of the Report of its Quoting Enclave, does not
+/// verify
+#[derive(Clone, Debug)]
+pub struct InvalidSignature(pub VerifyError);
+
+impl fmt::Display for InvalidSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid signature: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidSignature {}
+
+/// Why a Quote is rejected by its collateral, although its signatures and
+/// certificates verified
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CollateralError {
+    /// The collateral was issued after the time of verification
+    NotYetValid,
+
+    /// The collateral is past its `nextUpdate` and must be fetched again
+    Expired,
+
+    /// The TCB of the platform is below all TCB levels of its TCB Info
+    TcbLevelUnsupported,
+
+    /// The Quoting Enclave does not match its QE Identity, or its ISVSVN is
+    /// below all of its TCB levels
+    QeIdentityMismatch,
+
+    /// The TCB level of the platform or of its Quoting Enclave is revoked
+    Revoked,
+}
+
+impl fmt::Display for CollateralError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollateralError::NotYetValid => write!(f, "collateral is not valid yet"),
+            CollateralError::Expired => write!(f, "collateral has expired"),
+            CollateralError::TcbLevelUnsupported => {
+                write!(f, "TCB of the platform is below all TCB levels")
+            }
+            CollateralError::QeIdentityMismatch => {
+                write!(f, "Quoting Enclave does not match its QE Identity")
+            }
+            CollateralError::Revoked => write!(f, "TCB level is revoked"),
+        }
+    }
+}
+
+impl std::error::Error for CollateralError {}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::claims::VerifiedReport;
+use super::error::VerifyError;
+use super::handshake::report_data;
+use super::time::TimeSource;
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// A Quote failed the freshness check, see `Nonce::check()`
+#[derive(Debug)]
+pub enum FreshnessError {
+    /// The nonce was issued longer ago than the freshness window allows
+    Stale,
+
+    /// The verified ReportData does not bind the nonce and transcript
+    Binding,
+
+    /// The current time could not be determined
+    Time(VerifyError),
+}
+
+impl fmt::Display for FreshnessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FreshnessError::Stale => write!(f, "nonce is outside of the freshness window"),
+            FreshnessError::Binding => write!(f, "ReportData does not bind the nonce"),
+            FreshnessError::Time(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for FreshnessError {}
+
+/// A challenge nonce, remembered by the verifier along with the time it was
+/// issued so that Quotes answering it are only accepted for a limited time
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Nonce {
+    value: [u8; 32],
+    issued: i64,
+}
+
+impl Nonce {
+    /// Generates a random nonce, issued at the current time of `time`.
+    pub fn generate(time: &dyn TimeSource) -> Result<Self, Box<dyn Error>> {
+        let mut value = [0u8; 32];
+        openssl::rand::rand_bytes(&mut value)?;
+        Ok(Self::new(value, time.now()?))
+    }
+
+    /// Creates a nonce with a given `value`, issued at `issued` seconds since
+    /// the Unix epoch.
+    pub fn new(value: [u8; 32], issued: i64) -> Self {
+        Self { value, issued }
+    }
+
+    /// Returns the value of the nonce, to send to the attester
+    pub fn value(&self) -> &[u8; 32] {
+        &self.value
+    }
+
+    /// Returns the time the nonce was issued, in seconds since the Unix epoch
+    pub fn issued(&self) -> i64 {
+        self.issued
+    }
+
+    /// Returns the ReportData binding this nonce and, optionally, the hash of
+    /// the `transcript` of the session, as laid out by `report_data()`.
+    pub fn report_data(&self, transcript: Option<&[u8; 32]>) -> [u8; 64] {
+        report_data(&self.value, transcript.unwrap_or(&[0u8; 32]))
+    }
+
+    /// Checks that a verified `report` answers this nonce: its ReportData
+    /// must bind the nonce and `transcript` hash, and the nonce must have
+    /// been issued no longer than `window` before the current time of
+    /// `time`.
+    ///
+    /// A nonce issued in the future is rejected as well, so that a clock
+    /// going backwards does not extend the window.
+    pub fn check(
+        &self,
+        report: &VerifiedReport,
+        transcript: Option<&[u8; 32]>,
+        window: Duration,
+        time: &dyn TimeSource,
+    ) -> Result<(), FreshnessError> {
+        if report.reportdata()[..] != self.report_data(transcript)[..] {
+            return Err(FreshnessError::Binding);
+        }
+
+        let now = time.now().map_err(FreshnessError::Time)?;
+        let age = now.checked_sub(self.issued).ok_or(FreshnessError::Stale)?;
+        if age < 0 || age as u64 > window.as_secs() {
+            return Err(FreshnessError::Stale);
+        }
+
+        trace_event!(age, "quote is fresh");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestation_types::report::Body;
+    use crate::attestation_types::verify::FixedTime;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    fn report(reportdata: [u8; 64]) -> VerifiedReport {
+        let mut body = Body::default();
+        body.reportdata = reportdata;
+        VerifiedReport::new(body)
+    }
+
+    #[test]
+    fn fresh() {
+        let nonce = Nonce::generate(&FixedTime(1000)).unwrap();
+        assert_ne!(nonce, Nonce::generate(&FixedTime(1000)).unwrap());
+        assert_eq!(nonce.issued(), 1000);
+
+        let report = report(nonce.report_data(None));
+        assert!(nonce.check(&report, None, WINDOW, &FixedTime(1000)).is_ok());
+        assert!(nonce.check(&report, None, WINDOW, &FixedTime(1060)).is_ok());
+    }
+
+    #[test]
+    fn stale() {
+        let nonce = Nonce::new([1; 32], 1000);
+        let report = report(nonce.report_data(None));
+
+        assert!(matches!(
+            nonce.check(&report, None, WINDOW, &FixedTime(1061)),
+            Err(FreshnessError::Stale)
+        ));
+        assert!(matches!(
+            nonce.check(&report, None, WINDOW, &FixedTime(999)),
+            Err(FreshnessError::Stale)
+        ));
+    }
+
+    #[test]
+    fn transcript() {
+        let nonce = Nonce::new([1; 32], 1000);
+        let report = report(nonce.report_data(Some(&[2; 32])));
+        assert_eq!(report.reportdata()[32..], [2; 32]);
+
+        assert!(nonce
+            .check(&report, Some(&[2; 32]), WINDOW, &FixedTime(1000))
+            .is_ok());
+        assert!(matches!(
+            nonce.check(&report, Some(&[3; 32]), WINDOW, &FixedTime(1000)),
+            Err(FreshnessError::Binding)
+        ));
+        assert!(matches!(
+            nonce.check(&report, None, WINDOW, &FixedTime(1000)),
+            Err(FreshnessError::Binding)
+        ));
+
+        let other = Nonce::new([4; 32], 1000);
+        assert!(matches!(
+            other.check(&report, Some(&[2; 32]), WINDOW, &FixedTime(1000)),
+            Err(FreshnessError::Binding)
+        ));
+    }
+}
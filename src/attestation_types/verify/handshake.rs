@@ -0,0 +1,297 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::claims::VerifiedReport;
+use super::freshness::{FreshnessError, Nonce};
+use super::time::TimeSource;
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// The ReportData of the attester's Quote: the challenger's nonce followed
+/// by the attester's `binding`, e.g. the hash of its public key.
+pub fn report_data(nonce: &[u8; 32], binding: &[u8; 32]) -> [u8; 64] {
+    let mut reportdata = [0u8; 64];
+    reportdata[..32].copy_from_slice(nonce);
+    reportdata[32..].copy_from_slice(binding);
+    reportdata
+}
+
+/// A failed attestation handshake
+#[derive(Debug)]
+pub enum HandshakeError {
+    /// A message could not be parsed
+    Malformed,
+
+    /// The attester could not produce a Quote
+    Attester(Box<dyn Error>),
+
+    /// The Quote did not verify
+    Verifier(Box<dyn Error>),
+
+    /// The verified ReportData is not bound to the challenge
+    Binding,
+
+    /// The challenge is no longer fresh, see `Nonce::check()`
+    Freshness(FreshnessError),
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandshakeError::Malformed => write!(f, "malformed attestation message"),
+            HandshakeError::Attester(e) => write!(f, "quote production failed: {}", e),
+            HandshakeError::Verifier(e) => write!(f, "quote verification failed: {}", e),
+            HandshakeError::Binding => write!(f, "ReportData does not match the challenge"),
+            HandshakeError::Freshness(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for HandshakeError {}
+
+/// The first message of the handshake, from the challenger to the attester
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    /// The nonce the attester must bind into its Quote
+    pub nonce: [u8; 32],
+}
+
+impl Challenge {
+    /// Returns the bytes of the message
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.nonce
+    }
+
+    /// Parses the bytes of the message
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HandshakeError> {
+        let mut nonce = [0u8; 32];
+        if bytes.len() != nonce.len() {
+            return Err(HandshakeError::Malformed);
+        }
+
+        nonce.copy_from_slice(bytes);
+        Ok(Self { nonce })
+    }
+}
+
+/// The second message of the handshake, from the attester to the challenger
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    /// The data the attester bound into its Quote along with the nonce
+    pub binding: [u8; 32],
+
+    /// The attester's Quote
+    pub quote: Vec<u8>,
+}
+
+impl Response {
+    /// Returns the bytes of the message: the binding followed by the Quote
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut vec = self.binding.to_vec();
+        vec.extend_from_slice(&self.quote);
+        vec
+    }
+
+    /// Parses the bytes of the message
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HandshakeError> {
+        if bytes.len() < 32 {
+            return Err(HandshakeError::Malformed);
+        }
+
+        let mut binding = [0u8; 32];
+        binding.copy_from_slice(&bytes[..32]);
+        Ok(Self {
+            binding,
+            quote: bytes[32..].to_vec(),
+        })
+    }
+}
+
+/// Produces a Quote with the given ReportData, e.g. by EREPORT targeting the
+/// Quoting Enclave followed by a request to the quoting service.
+pub trait QuoteProvider {
+    /// Returns a Quote of the attesting enclave with `reportdata`.
+    fn quote(&mut self, reportdata: &[u8; 64]) -> Result<Vec<u8>, Box<dyn Error>>;
+}
+
+impl<F: FnMut(&[u8; 64]) -> Result<Vec<u8>, Box<dyn Error>>> QuoteProvider for F {
+    fn quote(&mut self, reportdata: &[u8; 64]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self(reportdata)
+    }
+}
+
+/// Verifies a Quote, e.g. with `verify_claims_at()`.
+pub trait QuoteVerifier {
+    /// Returns the claims of the verified Quote.
+    fn verify(&self, quote: &[u8]) -> Result<VerifiedReport, Box<dyn Error>>;
+}
+
+impl<F: Fn(&[u8]) -> Result<VerifiedReport, Box<dyn Error>>> QuoteVerifier for F {
+    fn verify(&self, quote: &[u8]) -> Result<VerifiedReport, Box<dyn Error>> {
+        self(quote)
+    }
+}
+
+/// The outcome of a successful handshake
+#[derive(Clone)]
+pub struct Verdict {
+    /// The claims of the attesting enclave
+    pub report: VerifiedReport,
+
+    /// The data the attester bound to this handshake
+    pub binding: [u8; 32],
+}
+
+/// The relying party of the handshake
+///
+/// A `Challenger` is consumed by `verify()`, so each nonce is accepted at
+/// most once, and only within the freshness window of the handshake.
+#[derive(Debug)]
+pub struct Challenger {
+    nonce: Nonce,
+    window: Duration,
+}
+
+impl Challenger {
+    /// Starts a handshake with a random nonce issued at the current time of
+    /// `time`, which the attester must answer within `window`.
+    pub fn new(window: Duration, time: &dyn TimeSource) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            nonce: Nonce::generate(time)?,
+            window,
+        })
+    }
+
+    /// Returns the challenge to send to the attester.
+    pub fn challenge(&self) -> Challenge {
+        Challenge {
+            nonce: *self.nonce.value(),
+        }
+    }
+
+    /// Verifies the attester's `response` at the current time of `time`: its
+    /// Quote must verify, its ReportData must bind the nonce of this
+    /// handshake and the response's binding, and the nonce must still be
+    /// within the window of the handshake.
+    pub fn verify(
+        self,
+        response: &Response,
+        verifier: &dyn QuoteVerifier,
+        time: &dyn TimeSource,
+    ) -> Result<Verdict, HandshakeError> {
+        let report = verifier
+            .verify(&response.quote)
+            .map_err(HandshakeError::Verifier)?;
+
+        self.nonce
+            .check(&report, Some(&response.binding), self.window, time)
+            .map_err(|e| match e {
+                FreshnessError::Binding => HandshakeError::Binding,
+                e => HandshakeError::Freshness(e),
+            })?;
+
+        trace_event!("attestation handshake verified");
+        Ok(Verdict {
+            report,
+            binding: response.binding,
+        })
+    }
+}
+
+/// The attesting party of the handshake
+#[derive(Debug)]
+pub struct Attester<P: QuoteProvider> {
+    provider: P,
+}
+
+impl<P: QuoteProvider> Attester<P> {
+    /// Creates an attester producing Quotes with `provider`.
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+
+    /// Answers `challenge` with a Quote binding its nonce and `binding`.
+    pub fn respond(
+        &mut self,
+        challenge: &Challenge,
+        binding: [u8; 32],
+    ) -> Result<Response, HandshakeError> {
+        let reportdata = report_data(&challenge.nonce, &binding);
+        let quote = self
+            .provider
+            .quote(&reportdata)
+            .map_err(HandshakeError::Attester)?;
+
+        Ok(Response { binding, quote })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestation_types::report::Body;
+    use crate::attestation_types::verify::FixedTime;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    // The "Quote" is just the ReportData, trusted as is.
+    fn provider(reportdata: &[u8; 64]) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(reportdata.to_vec())
+    }
+
+    fn verifier(quote: &[u8]) -> Result<VerifiedReport, Box<dyn Error>> {
+        let mut body = Body::default();
+        body.reportdata.copy_from_slice(quote);
+        Ok(VerifiedReport::new(body))
+    }
+
+    #[test]
+    fn handshake() {
+        let challenger = Challenger::new(WINDOW, &FixedTime(1000)).unwrap();
+        let nonce = challenger.challenge().nonce;
+        let challenge = Challenge::from_bytes(&challenger.challenge().to_bytes()).unwrap();
+
+        let mut attester = Attester::new(provider);
+        let response = attester.respond(&challenge, [2; 32]).unwrap();
+        let response = Response::from_bytes(&response.to_vec()).unwrap();
+
+        let verdict = challenger
+            .verify(&response, &verifier, &FixedTime(1060))
+            .unwrap();
+        assert_eq!(verdict.binding, [2; 32]);
+        assert_eq!(verdict.report.reportdata()[..32], nonce);
+    }
+
+    #[test]
+    fn replay() {
+        let mut attester = Attester::new(provider);
+        let old = Challenger::new(WINDOW, &FixedTime(1000)).unwrap();
+        let response = attester.respond(&old.challenge(), [2; 32]).unwrap();
+
+        let challenger = Challenger::new(WINDOW, &FixedTime(1000)).unwrap();
+        assert_ne!(challenger.challenge(), old.challenge());
+        assert!(matches!(
+            challenger.verify(&response, &verifier, &FixedTime(1000)),
+            Err(HandshakeError::Binding)
+        ));
+    }
+
+    #[test]
+    fn stale() {
+        let mut attester = Attester::new(provider);
+        let challenger = Challenger::new(WINDOW, &FixedTime(1000)).unwrap();
+        let response = attester.respond(&challenger.challenge(), [2; 32]).unwrap();
+
+        assert!(matches!(
+            challenger.verify(&response, &verifier, &FixedTime(1061)),
+            Err(HandshakeError::Freshness(FreshnessError::Stale))
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Challenge::from_bytes(&[0; 31]).is_err());
+        assert!(Response::from_bytes(&[0; 31]).is_err());
+    }
+}
@@ -0,0 +1,452 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::claims::VerifiedReport;
+use super::error::VerifyError;
+use super::sig::Signature;
+use super::supplemental::Supplemental;
+use super::tcbeval::TcbLevel;
+use super::time::TimeSource;
+use crate::attestation_types::report::Body;
+use crate::{Attributes, CpuSvn, Features, MiscSelect, PceSvn, ProductId, SecurityVersion, Xfrm};
+
+use openssl::{
+    bn::BigNum,
+    ec::EcKey,
+    ecdsa::EcdsaSig,
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private, Public},
+    sign::{Signer, Verifier},
+};
+use serde_json::{json, Value};
+use std::convert::TryFrom;
+
+fn encode(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode(data: &str) -> Result<Vec<u8>, VerifyError> {
+    base64::decode_config(data, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| VerifyError("token is not base64url".to_string()))
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(value: &Value, out: &mut [u8]) -> Option<()> {
+    let s = value.as_str()?;
+    if s.len() != out.len() * 2 {
+        return None;
+    }
+
+    for (o, i) in out.iter_mut().zip((0..s.len()).step_by(2)) {
+        *o = u8::from_str_radix(s.get(i..i + 2)?, 16).ok()?;
+    }
+
+    Some(())
+}
+
+fn invalid() -> VerifyError {
+    VerifyError("token claims are invalid".to_string())
+}
+
+fn check_curve<T>(key: &EcKey<T>) -> Result<(), VerifyError> {
+    match key.group().curve_name() {
+        Some(Nid::X9_62_PRIME256V1) => Ok(()),
+        _ => Err(VerifyError("ES256 requires a P-256 key".to_string())),
+    }
+}
+
+/// Issues signed JWTs (ES256) attesting to the claims of verified Reports, so
+/// that relying parties need not verify Quotes themselves
+///
+/// Besides the Report, a token carries the verdict on the Quote: the TCB
+/// level of the platform and the supplemental data of the collateral, if the
+/// Quote was verified against collateral (see `verify_all()`).
+pub struct Issuer {
+    key: PKey<Private>,
+    kid: String,
+    iss: String,
+}
+
+impl Issuer {
+    /// Creates an issuer named `iss` signing with the P-256 `key`, identified
+    /// by `kid` in the relying parties' `KeySet`.
+    pub fn new(
+        key: EcKey<Private>,
+        kid: impl Into<String>,
+        iss: impl Into<String>,
+    ) -> Result<Self, VerifyError> {
+        check_curve(&key)?;
+        Ok(Self {
+            key: PKey::from_ec_key(key)?,
+            kid: kid.into(),
+            iss: iss.into(),
+        })
+    }
+
+    /// Issues a token with the claims of `report` for the relying party
+    /// `aud`, if given, valid from `iat` until `exp` (in seconds since the
+    /// Unix epoch).
+    pub fn issue(
+        &self,
+        report: &VerifiedReport,
+        aud: Option<&str>,
+        iat: i64,
+        exp: i64,
+    ) -> Result<String, VerifyError> {
+        let body = report.body();
+        let header = json!({ "alg": "ES256", "typ": "JWT", "kid": self.kid });
+        let mut claims = json!({
+            "iss": self.iss,
+            "iat": iat,
+            "exp": exp,
+            "sgx_cpusvn": hex(&body.cpusvn.inner()),
+            "sgx_miscselect": body.miscselect.bits(),
+            "sgx_attributes_flags": body.attributes.features().bits(),
+            "sgx_attributes_xfrm": body.attributes.xfrm().bits(),
+            "sgx_mrenclave": hex(&body.mrenclave),
+            "sgx_mrsigner": hex(&body.mrsigner),
+            "sgx_isvprodid": body.isv_prod_id.inner(),
+            "sgx_isvsvn": body.isv_svn.inner(),
+            "sgx_config_svn": body.config_svn,
+            "sgx_reportdata": hex(&body.reportdata),
+        });
+
+        if let Some(aud) = aud {
+            claims["aud"] = json!(aud);
+        }
+
+        if let (Some(level), Some(data)) = (report.tcb_level(), report.supplemental()) {
+            claims["sgx_tcb_status"] = json!(level.status);
+            claims["sgx_tcb_date"] = json!(level.date);
+            claims["sgx_advisory_ids"] = json!(level.advisory_ids);
+            claims["sgx_supplemental"] = json!({
+                "earliest_issue_date": data.earliest_issue_date,
+                "latest_issue_date": data.latest_issue_date,
+                "earliest_expiration_date": data.earliest_expiration_date,
+                "tcb_level_date_tag": data.tcb_level_date_tag,
+                "tcb_eval_ref_num": data.tcb_eval_ref_num,
+                "fmspc": data.fmspc.to_string(),
+                "pce_id": data.pce_id.to_string(),
+                "tcb_cpusvn": hex(&data.tcb_cpusvn.inner()),
+                "tcb_pce_isvsvn": data.tcb_pce_isvsvn.inner(),
+                "sgx_type": data.sgx_type,
+                "root_key_id": hex(&data.root_key_id),
+                "pck_crl_num": data.pck_crl_num,
+            });
+        }
+
+        let mut token = encode(header.to_string().as_bytes());
+        token.push('.');
+        token.push_str(&encode(claims.to_string().as_bytes()));
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(token.as_bytes())?;
+        let der = EcdsaSig::from_der(&signer.sign_to_vec()?)?;
+
+        // JWS uses the raw concatenation of r and s rather than DER.
+        let mut sig = der.r().to_vec_padded(32)?;
+        sig.extend(der.s().to_vec_padded(32)?);
+
+        token.push('.');
+        token.push_str(&encode(&sig));
+        Ok(token)
+    }
+}
+
+/// The public keys of trusted token `Issuer`s, by key ID
+#[derive(Default)]
+pub struct KeySet(Vec<(String, String, PKey<Public>)>);
+
+impl KeySet {
+    /// Creates an empty key set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts tokens issued by `iss` and signed by its P-256 `key`
+    /// identified by `kid`.
+    pub fn insert(
+        &mut self,
+        kid: impl Into<String>,
+        iss: impl Into<String>,
+        key: EcKey<Public>,
+    ) -> Result<(), VerifyError> {
+        check_curve(&key)?;
+        self.0
+            .push((kid.into(), iss.into(), PKey::from_ec_key(key)?));
+        Ok(())
+    }
+
+    /// Validates `token`: it must be signed by a key of this set, have been
+    /// issued by the issuer of that key and be valid at the time given by
+    /// `time`. A relying party that identifies itself as `aud` only accepts
+    /// tokens for it or for anyone; one that does not only accepts tokens
+    /// for anyone. Returns the attested claims.
+    pub fn validate(
+        &self,
+        token: &str,
+        aud: Option<&str>,
+        time: &dyn TimeSource,
+    ) -> Result<VerifiedReport, VerifyError> {
+        let parts: Vec<&str> = token.split('.').collect();
+        if parts.len() != 3 {
+            return Err(VerifyError("token is not a JWS".to_string()));
+        }
+
+        let header: Value = serde_json::from_slice(&decode(parts[0])?).map_err(|_| invalid())?;
+        if header["alg"] != "ES256" {
+            return Err(VerifyError("token is not signed with ES256".to_string()));
+        }
+
+        let kid = header["kid"].as_str().ok_or_else(invalid)?;
+        let (iss, key) = self
+            .0
+            .iter()
+            .find(|(k, _, _)| k == kid)
+            .map(|(_, iss, key)| (iss, key))
+            .ok_or_else(|| VerifyError(format!("unknown token key ID {}", kid)))?;
+
+        let sig = decode(parts[2])?;
+        if sig.len() != 64 {
+            return Err(VerifyError("token signature is malformed".to_string()));
+        }
+
+        let mut verifier = Verifier::new(MessageDigest::sha256(), key)?;
+        verifier.update(parts[0].as_bytes())?;
+        verifier.update(b".")?;
+        verifier.update(parts[1].as_bytes())?;
+        if !verifier.verify(&Signature::try_from(&sig[..])?.to_der_vec()?)? {
+            return Err(VerifyError("token signature does not verify".to_string()));
+        }
+
+        let claims: Value = serde_json::from_slice(&decode(parts[1])?).map_err(|_| invalid())?;
+        let now = time.now()?;
+        let iat = claims["iat"].as_i64().ok_or_else(invalid)?;
+        let exp = claims["exp"].as_i64().ok_or_else(invalid)?;
+        if now < iat || now >= exp {
+            return Err(VerifyError("token is expired or not yet valid".to_string()));
+        }
+
+        if claims["iss"] != iss.as_str() {
+            return Err(VerifyError(format!("token is not issued by {}", iss)));
+        }
+
+        match (&claims["aud"], aud) {
+            (Value::Null, _) => (),
+            (Value::String(a), Some(aud)) if a == aud => (),
+            _ => return Err(VerifyError("token is for another audience".to_string())),
+        }
+
+        claims_report(&claims).ok_or_else(invalid)
+    }
+}
+
+fn claims_report(claims: &Value) -> Option<VerifiedReport> {
+    let report = VerifiedReport::new(claims_body(claims)?);
+    if claims.get("sgx_tcb_status").is_none() && claims.get("sgx_supplemental").is_none() {
+        return Some(report);
+    }
+
+    let string = |value: &Value| value.as_str().map(str::to_string);
+    let level = TcbLevel {
+        status: string(&claims["sgx_tcb_status"])?,
+        date: string(&claims["sgx_tcb_date"])?,
+        advisory_ids: claims["sgx_advisory_ids"]
+            .as_array()?
+            .iter()
+            .map(string)
+            .collect::<Option<_>>()?,
+    };
+
+    let data = &claims["sgx_supplemental"];
+    let mut tcb_cpusvn = [0u8; 16];
+    unhex(&data["tcb_cpusvn"], &mut tcb_cpusvn)?;
+    let mut root_key_id = [0u8; 48];
+    unhex(&data["root_key_id"], &mut root_key_id)?;
+    let pck_crl_num = match &data["pck_crl_num"] {
+        Value::Null => None,
+        n => Some(u32::try_from(n.as_u64()?).ok()?),
+    };
+
+    let supplemental = Supplemental {
+        earliest_issue_date: string(&data["earliest_issue_date"])?,
+        latest_issue_date: string(&data["latest_issue_date"])?,
+        earliest_expiration_date: string(&data["earliest_expiration_date"])?,
+        tcb_level_date_tag: string(&data["tcb_level_date_tag"])?,
+        tcb_eval_ref_num: u32::try_from(data["tcb_eval_ref_num"].as_u64()?).ok()?,
+        fmspc: data["fmspc"].as_str()?.parse().ok()?,
+        pce_id: data["pce_id"].as_str()?.parse().ok()?,
+        tcb_cpusvn: CpuSvn::new(tcb_cpusvn),
+        tcb_pce_isvsvn: PceSvn::new(u16::try_from(data["tcb_pce_isvsvn"].as_u64()?).ok()?),
+        sgx_type: u8::try_from(data["sgx_type"].as_u64()?).ok()?,
+        root_key_id,
+        pck_crl_num,
+    };
+
+    Some(report.with_collateral(level, supplemental))
+}
+
+fn claims_body(claims: &Value) -> Option<Body> {
+    let u64_claim = |name: &str| claims[name].as_u64();
+    let u16_claim = |name: &str| u16::try_from(u64_claim(name)?).ok();
+
+    let mut cpusvn = [0u8; 16];
+    unhex(&claims["sgx_cpusvn"], &mut cpusvn)?;
+
+    let features = Features::from_bits(u64_claim("sgx_attributes_flags")?)?;
+    let xfrm = Xfrm::from_bits(u64_claim("sgx_attributes_xfrm")?)?;
+    let misc = u32::try_from(u64_claim("sgx_miscselect")?).ok()?;
+
+    let mut body = Body::default();
+    body.cpusvn = CpuSvn::new(cpusvn);
+    body.miscselect = MiscSelect::from_bits(misc)?;
+    body.attributes = Attributes::new(features, xfrm);
+    unhex(&claims["sgx_mrenclave"], &mut body.mrenclave)?;
+    unhex(&claims["sgx_mrsigner"], &mut body.mrsigner)?;
+    body.isv_prod_id = ProductId::new(u16_claim("sgx_isvprodid")?);
+    body.isv_svn = SecurityVersion::new(u16_claim("sgx_isvsvn")?);
+    body.config_svn = u16_claim("sgx_config_svn")?;
+    unhex(&claims["sgx_reportdata"], &mut body.reportdata)?;
+    Some(body)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestation_types::verify::FixedTime;
+    use openssl::ec::EcGroup;
+
+    fn issuer() -> (Issuer, KeySet) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        let public = EcKey::from_public_key(&group, key.public_key()).unwrap();
+
+        let mut keys = KeySet::new();
+        keys.insert("verifier", "https://verifier.example", public)
+            .unwrap();
+        (
+            Issuer::new(key, "verifier", "https://verifier.example").unwrap(),
+            keys,
+        )
+    }
+
+    fn report() -> VerifiedReport {
+        let mut body = Body::default();
+        body.mrenclave = [1; 32];
+        body.isv_svn = SecurityVersion::new(2);
+        body.attributes = Attributes::new(Features::MODE64BIT, Xfrm::X87 | Xfrm::SSE);
+        body.reportdata = [3; 64];
+        body.config_svn = 4;
+        VerifiedReport::new(body)
+    }
+
+    fn collateral() -> (TcbLevel, Supplemental) {
+        let level = TcbLevel {
+            status: "SWHardeningNeeded".to_string(),
+            date: "2021-11-10T00:00:00Z".to_string(),
+            advisory_ids: vec!["INTEL-SA-00615".to_string()],
+        };
+        let supplemental = Supplemental {
+            earliest_issue_date: "2022-08-10T12:00:00Z".to_string(),
+            latest_issue_date: "2022-08-11T08:00:00Z".to_string(),
+            earliest_expiration_date: "2022-09-08T08:00:00Z".to_string(),
+            tcb_level_date_tag: "2021-11-10T00:00:00Z".to_string(),
+            tcb_eval_ref_num: 16,
+            fmspc: "00906ed50000".parse().unwrap(),
+            pce_id: "0000".parse().unwrap(),
+            tcb_cpusvn: CpuSvn::new([5; 16]),
+            tcb_pce_isvsvn: PceSvn::new(11),
+            sgx_type: 1,
+            root_key_id: [6; 48],
+            pck_crl_num: None,
+        };
+        (level, supplemental)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (issuer, keys) = issuer();
+        let token = issuer.issue(&report(), None, 100, 200).unwrap();
+
+        let claims = keys.validate(&token, None, &FixedTime(150)).unwrap();
+        assert_eq!(claims.body().mrenclave, [1; 32]);
+        assert_eq!(claims.body().isv_svn, SecurityVersion::new(2));
+        assert_eq!(claims.body().config_svn, 4);
+        assert_eq!(claims.body().attributes, report().body().attributes);
+        assert_eq!(&claims.reportdata()[..], &[3; 64][..]);
+        assert!(claims.tcb_level().is_none());
+        assert!(claims.supplemental().is_none());
+
+        let (level, supplemental) = collateral();
+        let report = report().with_collateral(level.clone(), supplemental.clone());
+        let token = issuer.issue(&report, None, 100, 200).unwrap();
+
+        let claims = keys.validate(&token, None, &FixedTime(150)).unwrap();
+        assert_eq!(claims.tcb_level(), Some(&level));
+        assert_eq!(claims.supplemental(), Some(&supplemental));
+    }
+
+    #[test]
+    fn audience() {
+        let (issuer, keys) = issuer();
+        let token = issuer.issue(&report(), Some("tenant"), 100, 200).unwrap();
+        assert!(keys
+            .validate(&token, Some("tenant"), &FixedTime(150))
+            .is_ok());
+        assert!(keys
+            .validate(&token, Some("other"), &FixedTime(150))
+            .is_err());
+        assert!(keys.validate(&token, None, &FixedTime(150)).is_err());
+
+        // A token for anyone is accepted by everyone.
+        let token = issuer.issue(&report(), None, 100, 200).unwrap();
+        assert!(keys
+            .validate(&token, Some("tenant"), &FixedTime(150))
+            .is_ok());
+    }
+
+    #[test]
+    fn issuer_name() {
+        let (issuer, _) = issuer();
+        let token = issuer.issue(&report(), None, 100, 200).unwrap();
+
+        // The key of the issuer is trusted, but for another issuer.
+        let mut keys = KeySet::new();
+        let key = issuer.key.ec_key().unwrap();
+        let key = EcKey::from_public_key(key.group(), key.public_key()).unwrap();
+        keys.insert("verifier", "https://other.example", key)
+            .unwrap();
+        assert!(keys.validate(&token, None, &FixedTime(150)).is_err());
+    }
+
+    #[test]
+    fn expired() {
+        let (issuer, keys) = issuer();
+        let token = issuer.issue(&report(), None, 100, 200).unwrap();
+        assert!(keys.validate(&token, None, &FixedTime(200)).is_err());
+        assert!(keys.validate(&token, None, &FixedTime(99)).is_err());
+    }
+
+    #[test]
+    fn untrusted() {
+        let (issuer, _) = issuer();
+        let (_, keys) = self::issuer();
+        let token = issuer.issue(&report(), None, 100, 200).unwrap();
+        assert!(keys.validate(&token, None, &FixedTime(150)).is_err());
+    }
+
+    #[test]
+    fn tampered() {
+        let (issuer, keys) = issuer();
+        let token = issuer.issue(&report(), None, 100, 200).unwrap();
+
+        let parts: Vec<&str> = token.split('.').collect();
+        let claims = String::from_utf8(decode(parts[1]).unwrap()).unwrap();
+        let claims = claims.replace("\"sgx_isvsvn\":2", "\"sgx_isvsvn\":3");
+        let token = format!("{}.{}.{}", parts[0], encode(claims.as_bytes()), parts[2]);
+        assert!(keys.validate(&token, None, &FixedTime(150)).is_err());
+    }
+}
@@ -27,7 +27,7 @@ pub struct Key {
 impl Key {
     /// This creates a new public PKey from raw x and y coordinates for the SECP256R1 curve.
     /// The private key is not known or needed.
-    pub fn new_from_xy(xy_coords: &[u8]) -> Result<Self, Box<dyn Error>> {
+    pub fn new_from_xy(xy_coords: &[u8]) -> Result<Self, VerifyError> {
         // TODO: Is it possible to give the Key a reference to this curve without instantiating it in each
         // Key instance? Rust doesn't do runtime-generated global variables.
         let curve = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
@@ -52,7 +52,7 @@ impl Key {
     pub fn new_from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
         let mut ctx = openssl::bn::BigNumContext::new()?;
         let curve = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
-        let pub_ecpoint = openssl::ec::EcPoint::from_bytes(curve.as_ref(), bytes, &mut *ctx)?;
+        let pub_ecpoint = openssl::ec::EcPoint::from_bytes(curve.as_ref(), bytes, &mut ctx)?;
         let pub_eckey = openssl::ec::EcKey::from_public_key(curve.as_ref(), pub_ecpoint.as_ref())?;
         let pub_pkey = openssl::pkey::PKey::from_ec_key(pub_eckey)?;
 
@@ -109,7 +109,7 @@ impl Key {
         let tenant_pubkey_bytes = priv_key.public_key().to_bytes(
             &self.curve,
             openssl::ec::PointConversionForm::UNCOMPRESSED,
-            &mut *new_ctx,
+            &mut new_ctx,
         )?;
 
         Ok(tenant_pubkey_bytes)
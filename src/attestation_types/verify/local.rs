@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::claims::VerifiedReport;
+use super::error::VerifyError;
+use crate::attestation_types::report::Report;
+
+#[cfg(not(feature = "soft-cmac"))]
+use openssl::{pkey::PKey, sign::Signer, symm::Cipher};
+
+/// The source of the Report Key with which a `Report` targeted at the verifying
+/// enclave was MACed. Inside an enclave this is EGETKEY with the Report's KEYID;
+/// outside of one the caller may supply the key itself.
+pub trait ReportKey {
+    /// Returns the Report Key for `report`.
+    fn report_key(&self, report: &Report) -> Result<[u8; 16], VerifyError>;
+}
+
+/// A Report Key supplied by the caller.
+///
+/// With the `zeroize` feature, `verify_report()` zeroes its copy of the key;
+/// the caller may hold its own in `zeroize::Zeroizing`.
+impl ReportKey for [u8; 16] {
+    fn report_key(&self, _report: &Report) -> Result<[u8; 16], VerifyError> {
+        Ok(*self)
+    }
+}
+
+// The MAC of a Report is the AES-128-CMAC of its body under the Report Key.
+#[cfg(feature = "soft-cmac")]
+fn mac(key: &[u8; 16], report: &Report) -> Result<u128, VerifyError> {
+    Ok(report.compute_mac(key))
+}
+
+#[cfg(not(feature = "soft-cmac"))]
+fn mac(key: &[u8; 16], report: &Report) -> Result<u128, VerifyError> {
+    let key = PKey::cmac(&Cipher::aes_128_cbc(), key)?;
+    let mut signer = Signer::new_without_digest(&key)?;
+    signer.update(&report.reportbody.to_vec())?;
+
+    let mut mac = [0u8; 16];
+    mac.copy_from_slice(&signer.sign_to_vec()?);
+    Ok(u128::from_le_bytes(mac))
+}
+
+/// Verifies the MAC of a raw `Report` produced by EREPORT for local attestation
+/// and returns its claims, like `verify_claims_at()` does for a Quote.
+pub fn verify_report(report: &Report, key: &dyn ReportKey) -> Result<VerifiedReport, VerifyError> {
+    // The MACs are compared in constant time, so that the time taken does
+    // not reveal how much of a forged MAC is correct.
+    #[cfg(feature = "zeroize")]
+    let key = zeroize::Zeroizing::new(key.report_key(report)?);
+    #[cfg(not(feature = "zeroize"))]
+    let key = key.report_key(report)?;
+    let mac = mac(&key, report)?.to_le_bytes();
+    if !openssl::memcmp::eq(&mac, &report.mac.to_le_bytes()) {
+        return Err(VerifyError("Report MAC does not match".to_string()));
+    }
+
+    trace_event!("report MAC verified");
+    Ok(VerifiedReport::new(report.reportbody))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KEY: [u8; 16] = [0x42; 16];
+
+    fn report() -> Report {
+        let mut report = Report::default();
+        report.reportbody.reportdata = [7; 64];
+        report.mac = mac(&KEY, &report).unwrap();
+        report
+    }
+
+    #[test]
+    fn verify() {
+        let verified = verify_report(&report(), &KEY).unwrap();
+        assert_eq!(&verified.reportdata()[..], &[7; 64][..]);
+    }
+
+    #[test]
+    fn verify_fail_bad_key() {
+        assert!(verify_report(&report(), &[0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn verify_fail_bad_body() {
+        let mut report = report();
+        report.reportbody.reportdata = [8; 64];
+        assert!(verify_report(&report, &KEY).is_err());
+    }
+}
@@ -1,22 +1,66 @@
 // SPDX-License-Identifier: Apache-2.0
 
-//! Verifies a V3 SGX Quote
+//! Verifies a V3 SGX Quote, or a local Report
 
+mod cache;
 mod cert_chain;
+mod claims;
+mod collateral;
 mod error;
+mod freshness;
+mod handshake;
+#[cfg(feature = "jwt")]
+mod jwt;
 mod key;
+mod local;
+mod qvresult;
+mod roots;
+mod service;
 mod sig;
+mod supplemental;
+mod tcbeval;
+mod time;
 
 #[cfg(test)]
-mod samples;
+pub(crate) mod samples;
 
 use super::quote::Quote;
-use error::VerifyError;
 use key::Key;
 use sig::Signature;
 
+pub use cache::Cache;
+pub use claims::VerifiedReport;
+pub use collateral::{verify_collateral, verify_qe_identity, verify_tcb_info, TCB_SIGNING_CN};
+pub use error::{CollateralError, InvalidSignature, VerifyError};
+pub use freshness::{FreshnessError, Nonce};
+pub use handshake::{
+    report_data, Attester, Challenge, Challenger, HandshakeError, QuoteProvider, QuoteVerifier,
+    Response, Verdict,
+};
+#[cfg(feature = "jwt")]
+pub use jwt::{Issuer, KeySet};
+pub use local::{verify_report, ReportKey};
+pub use qvresult::QvResult;
+pub use roots::{TrustedRoots, INTEL_SGX_ROOT_CA};
+pub use service::{Attestation, AttestationService, Dcap};
+pub use supplemental::Supplemental;
+pub use tcbeval::{
+    check_tcb_evaluation, converge_tcb_levels, match_qe_identity, match_tcb_level,
+    tcb_evaluation_data_number, tcb_info_fmspc, TcbLevel, TcbUpdates, UpdateTrack,
+};
+pub use time::{FixedTime, SystemClock, TimeSource};
+
+use crate::Fmspc;
+
 use openssl::x509::*;
-use std::{borrow::Borrow, convert::TryFrom, error::Error, ops::Deref};
+use std::sync::{Mutex, MutexGuard};
+use std::{borrow::Borrow, convert::TryFrom, error::Error, thread};
+
+// Locks `mutex`, ignoring poisoning: the guarded data stays consistent even
+// if a holder panicked.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
 
 /// The tenant requests attestation of an enclave from the platform's attestation daemon, and
 /// receives a Quote from the daemon. The Quote verifies the enclave's measurement. The tenant
@@ -58,12 +102,217 @@ pub fn get_intel_cert_chain_pem() -> Result<String, Box<dyn Error>> {
 
 /// Verify a quote against a trusted certificate chain and known good measurement. If successful,
 /// it will return the Report's ReportData field as bytes.
+///
+/// The certificate chain must be anchored at the compiled-in Intel SGX Root CA.
 #[allow(dead_code)]
 pub fn verify(
     quote_bytes: &[u8],
     trusted_public_pck_chain: &str,
     good_measurement: &[u8],
 ) -> Result<[u8; 64], Box<dyn Error>> {
+    verify_with_roots(
+        quote_bytes,
+        trusted_public_pck_chain,
+        good_measurement,
+        &TrustedRoots::intel()?,
+    )
+}
+
+/// Like `verify()`, but the certificate chain must be anchored at one of the
+/// given pinned `roots` instead of the compiled-in Intel SGX Root CA.
+#[allow(dead_code)]
+pub fn verify_with_roots(
+    quote_bytes: &[u8],
+    trusted_public_pck_chain: &str,
+    good_measurement: &[u8],
+    roots: &TrustedRoots,
+) -> Result<[u8; 64], Box<dyn Error>> {
+    verify_at(
+        quote_bytes,
+        trusted_public_pck_chain,
+        good_measurement,
+        roots,
+        &SystemClock,
+    )
+}
+
+/// Like `verify_with_roots()`, but certificate validity periods are checked
+/// against the time given by `time` instead of the system clock.
+#[allow(dead_code)]
+pub fn verify_at(
+    quote_bytes: &[u8],
+    trusted_public_pck_chain: &str,
+    good_measurement: &[u8],
+    roots: &TrustedRoots,
+    time: &dyn TimeSource,
+) -> Result<[u8; 64], Box<dyn Error>> {
+    verify_claims_at(
+        quote_bytes,
+        trusted_public_pck_chain,
+        good_measurement,
+        roots,
+        time,
+    )
+    .map(|verified| verified.reportdata())
+}
+
+/// Like `verify_at()`, but returns all claims of the verified enclave Report
+/// rather than only its ReportData.
+#[allow(dead_code)]
+pub fn verify_claims_at(
+    quote_bytes: &[u8],
+    trusted_public_pck_chain: &str,
+    good_measurement: &[u8],
+    roots: &TrustedRoots,
+    time: &dyn TimeSource,
+) -> Result<VerifiedReport, Box<dyn Error>> {
+    let chain = X509::stack_from_pem(trusted_public_pck_chain.as_bytes())?;
+    verify_quote(
+        quote_bytes,
+        &chain,
+        None,
+        good_measurement,
+        roots,
+        time,
+        &Mutex::new(Vec::new()),
+    )
+    .map_err(|e| -> Box<dyn Error> { e })
+}
+
+/// The outcome of verifying one quote of a batch with `verify_all()`
+pub type QuoteResult = Result<VerifiedReport, Box<dyn Error + Send + Sync>>;
+
+/// The signed TCB Info of one FMSPC and the QE Identity of the same TCB
+/// evaluation, as served by the Intel Provisioning Certification Service, see
+/// `verify_tcb_info()` and `verify_qe_identity()`
+#[derive(Copy, Clone, Debug)]
+pub struct TcbCollateral<'a> {
+    /// The TCB Info response body
+    pub body: &'a str,
+
+    /// The PEM certificates of its signer, leaf first
+    pub issuer_chain: &'a str,
+
+    /// The QE Identity response body
+    pub qe_identity: &'a str,
+
+    /// The PEM certificates of the signer of the QE Identity, leaf first
+    pub qe_identity_issuer_chain: &'a str,
+}
+
+// The verified collateral of the platforms of one FMSPC
+struct FmspcCollateral<'a> {
+    fmspc: Fmspc,
+    tcb_info: &'a str,
+    qe_identity: &'a str,
+}
+
+/// Verifies each of `quotes` as `verify_claims_at()` would, returning one
+/// result per quote in the same order. Each quote must also come from a
+/// platform whose FMSPC has a TCB Info in `collateral`, with a TCB level the
+/// platform meets, and its Quoting Enclave must match the QE Identity that
+/// comes with the TCB Info. The combined TCB level of the platform and its QE
+/// and the `Supplemental` data of the collateral are returned with the claims
+/// of the quote.
+///
+/// Quotes are rejected with a `CollateralError` if the collateral is not
+/// valid at `time` (its `issueDate` is later or its `nextUpdate` has passed),
+/// if the platform is below all TCB levels, if the QE does not match or if
+/// either TCB level is `Revoked`.
+///
+/// The tenant's certificate chain is parsed once, the collateral is verified
+/// once for all quotes of its FMSPC, and the PCK certificate chain is only
+/// validated once per distinct PCK leaf certificate, so quotes from the same
+/// platform share that work. The quotes are verified on as many threads as
+/// there are CPUs. An error is returned only if the tenant's certificate chain
+/// cannot be parsed, or if a TCB Info or QE Identity does not verify or they
+/// belong to different TCB evaluations.
+#[allow(dead_code)]
+pub fn verify_all(
+    quotes: &[&[u8]],
+    collateral: &[TcbCollateral<'_>],
+    trusted_public_pck_chain: &str,
+    good_measurement: &[u8],
+    roots: &TrustedRoots,
+    time: &(dyn TimeSource + Sync),
+) -> Result<Vec<QuoteResult>, Box<dyn Error>> {
+    let chain = X509::stack_from_pem(trusted_public_pck_chain.as_bytes())?;
+
+    let mut tcb_infos = Vec::with_capacity(collateral.len());
+    for tcb in collateral {
+        let tcb_info = verify_tcb_info(tcb.body, tcb.issuer_chain, roots, time)?;
+        let qe_identity =
+            verify_qe_identity(tcb.qe_identity, tcb.qe_identity_issuer_chain, roots, time)?;
+        check_tcb_evaluation(&[tcb_info, qe_identity])?;
+        tcb_infos.push(FmspcCollateral {
+            fmspc: tcbeval::tcb_info_fmspc(tcb_info)?,
+            tcb_info,
+            qe_identity,
+        });
+    }
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let per_thread = ((quotes.len() + threads - 1) / threads).max(1);
+    let verified_leaves = Mutex::new(Vec::new());
+
+    let (chain, tcb_infos, verified_leaves) = (&chain, &tcb_infos[..], &verified_leaves);
+    Ok(thread::scope(|scope| {
+        let handles: Vec<_> = quotes
+            .chunks(per_thread)
+            .map(|quotes| {
+                scope.spawn(move || {
+                    quotes
+                        .iter()
+                        .map(|quote_bytes| {
+                            verify_quote(
+                                quote_bytes,
+                                chain,
+                                Some(tcb_infos),
+                                good_measurement,
+                                roots,
+                                time,
+                                verified_leaves,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(results) => results,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    }))
+}
+
+// Verifies one quote against the parsed tenant `chain`. If `tcb_infos` is
+// given, it must hold the verified collateral of the FMSPC of the quote's
+// platform, which must be valid at `time` and in which the TCB levels of the
+// platform and its QE are matched. `verified_leaves` holds the DER encodings
+// of PCK leaf certificates whose chains have already been validated; it is
+// extended when a new one is.
+fn verify_quote(
+    quote_bytes: &[u8],
+    chain: &[X509],
+    tcb_infos: Option<&[FmspcCollateral<'_>]>,
+    good_measurement: &[u8],
+    roots: &TrustedRoots,
+    time: &dyn TimeSource,
+    verified_leaves: &Mutex<Vec<Vec<u8>>>,
+) -> Result<VerifiedReport, Box<dyn Error + Send + Sync>> {
+    // The platform's FMSPC and TCB status are recorded once they are known.
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "verify_quote",
+        fmspc = tracing::field::Empty,
+        tcb_status = tracing::field::Empty
+    )
+    .entered();
+
     // The material (Quote Header || ISV Enclave Report) signed by Quoting Enclave's Attestation Key
     // is retrieved.
     let att_key_signed_material = Quote::raw_header_and_body(quote_bytes)?;
@@ -73,7 +322,14 @@ pub fn verify(
 
     // Parse the Quote's signature section.
     let quote = Quote::try_from(quote_bytes)?;
-    let report = quote.body();
+    let report = match quote.body().sgx() {
+        Some(report) => report,
+        None => {
+            return Err(Box::new(VerifyError(
+                "verification of TDX quotes is not supported".to_string(),
+            )));
+        }
+    };
     let q_sig = quote.sigdata();
     let q_enclave_report_sig = q_sig.report_sig();
     let q_att_key_pub = q_sig.attkey();
@@ -85,39 +341,61 @@ pub fn verify(
     // the PCK corresponding to the Leaf Certificate signs the Attestation Key.
     let certs = q_sig.qe_cert_data_pckchain()?;
     let quote_pck_leaf_cert = &certs.leaf_cert;
-
-    // The PCK chain is reconstructed with the Quote's leaf cert added to end of tenant's chain.
-    let cert_chain = cert_chain::CertChain::new_from_chain(
-        X509::stack_from_pem(trusted_public_pck_chain.deref().as_bytes())?,
-        quote_pck_leaf_cert,
-    );
-    cert_chain.len_ok()?;
-
-    // The PCK certificate chain's issuers and signatures are verified.
-    cert_chain.verify_issuers()?;
-    cert_chain.verify_sigs()?;
+    #[cfg(feature = "tracing")]
+    span.record("fmspc", &tracing::field::display(certs.sgx_extension.fmspc));
+
+    // The PCK chain is reconstructed with the Quote's leaf cert added to end of tenant's chain,
+    // unless the chain of this leaf has already been verified.
+    // The lock is not held while verifying, so concurrent verifications of
+    // the same new leaf may both validate its chain.
+    let leaf_der = quote_pck_leaf_cert.to_der()?;
+    let verified = lock(verified_leaves).contains(&leaf_der);
+    if !verified {
+        let cert_chain = cert_chain::CertChain::new_from_chain(chain.to_vec(), quote_pck_leaf_cert);
+        cert_chain.len_ok()?;
+
+        // The PCK certificate chain's issuers and signatures are verified.
+        cert_chain.verify_issuers()?;
+        cert_chain.verify_sigs(roots, time)?;
+        lock(verified_leaves).push(leaf_der);
+        trace_event!("PCK certificate chain verified");
+    }
 
     // The Attestation Key's signature on the Quote is verified.
     let attestation_key = Key::new_from_xy(&q_att_key_pub.to_vec())?;
     let quote_signature = Signature::try_from(&q_enclave_report_sig.to_vec()[..])?.to_der_vec()?;
-    attestation_key.verify_sig(att_key_signed_material, &quote_signature)?;
+    attestation_key
+        .verify_sig(att_key_signed_material, &quote_signature)
+        .map_err(InvalidSignature)?;
+    trace_event!("attestation key signature verified");
 
     // The PCK's signature on the Attestation Public Key is verified.
     let pc_key = Key::new_from_pubkey(quote_pck_leaf_cert.public_key()?)?;
     let qe_report_signature = Signature::try_from(&q_qe_report_sig.to_vec()[..])?.to_der_vec()?;
     pc_key
         .borrow()
-        .verify_sig(&q_qe_report, &qe_report_signature)?;
+        .verify_sig(&q_qe_report, &qe_report_signature)
+        .map_err(InvalidSignature)?;
+    trace_event!("QE report signature verified");
 
-    // This verifies that the hashed material signed by the PCK is correct.
+    // This verifies that the hashed material signed by the PCK is correct: the QE ReportData is
+    // the hash of the Attestation Key and the QE Authentication Data, followed by zeros.
     let mut unhashed_data = Vec::new();
     unhashed_data.extend(q_att_key_pub.to_vec());
     unhashed_data.extend(q_auth_data.to_vec());
     pc_key
         .borrow()
-        .verify_hash(hashed_reportdata, unhashed_data)?;
+        .verify_hash(hashed_reportdata, unhashed_data)
+        .map_err(InvalidSignature)?;
+    if q_sig.qe_report().reportdata[32..] != [0u8; 32] {
+        return Err(Box::new(InvalidSignature(VerifyError(
+            "QE ReportData is not padded with zeros".to_string(),
+        ))));
+    }
+    trace_event!("attestation key hash verified");
 
     // This verifies that MRENCLAVE from the Report matches the known-good measurement
+    trace_event!(mrenclave = ?report.mrenclave, "checking measurement");
     if &report.mrenclave[..] != good_measurement {
         return Err(Box::new(VerifyError(format!(
             "mrenclave: {:?} did not match known good value: {:?}",
@@ -125,35 +403,347 @@ pub fn verify(
         ))));
     }
 
-    // Return ReportData as bytes
-    let mut reportdata = [0u8; 64];
-    reportdata.copy_from_slice(&report.reportdata[..]);
-    Ok(reportdata)
+    let verified = VerifiedReport::with_qe_auth(*report, q_auth_data);
+    let tcb_infos = match tcb_infos {
+        Some(tcb_infos) => tcb_infos,
+        None => return Ok(verified),
+    };
+
+    // The platform's FMSPC must have collateral that is valid now, which
+    // lists the TCB level of the platform's PCK certificate and the identity
+    // of its QE.
+    let extension = &certs.sgx_extension;
+    let collateral = match tcb_infos.iter().find(|v| v.fmspc == extension.fmspc) {
+        Some(collateral) => collateral,
+        None => {
+            return Err(Box::new(VerifyError(format!(
+                "no TCB Info for FMSPC {}",
+                extension.fmspc
+            ))));
+        }
+    };
+
+    let now = time.now()?;
+    for signed in [collateral.tcb_info, collateral.qe_identity].iter() {
+        let (issued, next_update) = collateral::validity(signed)?;
+        if now < issued {
+            return Err(Box::new(CollateralError::NotYetValid));
+        }
+        if now >= next_update {
+            return Err(Box::new(CollateralError::Expired));
+        }
+    }
+
+    let level = tcbeval::match_tcb_level(
+        collateral.tcb_info,
+        &extension.tcb_components,
+        extension.pcesvn,
+    )?
+    .ok_or(CollateralError::TcbLevelUnsupported)?;
+    let qe_level = tcbeval::match_qe_identity(collateral.qe_identity, q_sig.qe_report())?
+        .ok_or(CollateralError::QeIdentityMismatch)?;
+    if level.status == "Revoked" || qe_level.status == "Revoked" {
+        return Err(Box::new(CollateralError::Revoked));
+    }
+
+    let level = converge_tcb_levels(level, &qe_level);
+    #[cfg(feature = "tracing")]
+    span.record("tcb_status", &level.status.as_str());
+    trace_event!("TCB level matched");
+
+    // The tenant's chain ends at the pinned root that anchored the PCK
+    // certificate chain.
+    let root = chain
+        .last()
+        .ok_or_else(|| VerifyError("no certs found in chain".to_string()))?;
+    let supplemental = Supplemental::new(
+        &[collateral.tcb_info, collateral.qe_identity],
+        &level,
+        extension,
+        root,
+    )?;
+    Ok(verified.with_collateral(level, supplemental))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use crate::attestation_types::quote::pckext::SgxExtension;
     use samples::{SAMPLE_MRENCLAVE, SAMPLE_V3QUOTE};
 
     #[cfg(not(feature = "chain_get"))]
     use samples::SAMPLE_INTEL_CERT_CHAIN;
 
+    // 2022-08-20, while the sample's PCK certificate and the collateral of the
+    // tests are valid.
+    const NOW: FixedTime = FixedTime(1_661_000_000);
+
+    fn verify_now(
+        quote_bytes: &[u8],
+        trusted_public_pck_chain: &str,
+        good_measurement: &[u8],
+    ) -> Result<[u8; 64], Box<dyn Error>> {
+        verify_at(
+            quote_bytes,
+            trusted_public_pck_chain,
+            good_measurement,
+            &TrustedRoots::intel()?,
+            &NOW,
+        )
+    }
+
     #[test]
     fn verify_sample_v3quote() {
         #[cfg(feature = "chain_get")]
-        let cert_chain = get_intel_cert_chain_pem().unwrap();
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
+
+        #[cfg(not(feature = "chain_get"))]
+        let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
+
+        assert!(verify_now(&SAMPLE_V3QUOTE[..], cert_chain, &SAMPLE_MRENCLAVE[..]).is_ok());
+    }
+
+    #[test]
+    fn verify_fail_unpinned_root() {
+        #[cfg(feature = "chain_get")]
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
 
         #[cfg(not(feature = "chain_get"))]
         let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
 
-        assert!(verify(&SAMPLE_V3QUOTE[..], &cert_chain, &SAMPLE_MRENCLAVE[..]).is_ok());
+        // Pin the intermediate instead of the root: the chain must be rejected.
+        let intermediate = X509::stack_from_pem(cert_chain.as_bytes()).unwrap()[0].clone();
+        let roots = TrustedRoots::new(vec![intermediate]);
+
+        assert!(verify_at(
+            &SAMPLE_V3QUOTE[..],
+            cert_chain,
+            &SAMPLE_MRENCLAVE[..],
+            &roots,
+            &NOW
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn verify_fail_not_yet_valid() {
+        #[cfg(feature = "chain_get")]
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
+
+        #[cfg(not(feature = "chain_get"))]
+        let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
+
+        // 2017-07-14, before the Intel SGX Root CA was issued.
+        assert!(verify_at(
+            &SAMPLE_V3QUOTE[..],
+            cert_chain,
+            &SAMPLE_MRENCLAVE[..],
+            &TrustedRoots::intel().unwrap(),
+            &FixedTime(1_500_000_000)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn verify_all_sample_v3quotes() {
+        #[cfg(feature = "chain_get")]
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
+
+        #[cfg(not(feature = "chain_get"))]
+        let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
+
+        let mut bad_quote = SAMPLE_V3QUOTE.to_vec();
+        let _ = bad_quote.splice(436..500, [0u8; 64].iter().cloned());
+
+        // Collateral for the FMSPC of the sample's platform, signed by a test
+        // PKI that is pinned along with the Intel root.
+        let quote = Quote::try_from(&SAMPLE_V3QUOTE[..]).unwrap();
+        let fmspc = quote
+            .sigdata()
+            .qe_cert_data_pckchain()
+            .unwrap()
+            .sgx_extension
+            .fmspc;
+        let pki = samples::pki();
+        let mut roots = TrustedRoots::intel().unwrap();
+        roots.add(pki.roots.roots()[0].clone());
+
+        let quotes: Vec<&[u8]> = vec![&SAMPLE_V3QUOTE[..], &bad_quote[..], &SAMPLE_V3QUOTE[..]];
+        let verify_batch = |body: &str, qe_identity: &str, time: &FixedTime| {
+            let collateral = [TcbCollateral {
+                body,
+                issuer_chain: &pki.chain,
+                qe_identity,
+                qe_identity_issuer_chain: &pki.chain,
+            }];
+            verify_all(
+                &quotes,
+                &collateral,
+                cert_chain,
+                &SAMPLE_MRENCLAVE[..],
+                &roots,
+                time,
+            )
+        };
+        let rejected = |results: Vec<QuoteResult>, error: CollateralError| {
+            assert!(results.iter().all(|r| {
+                r.as_ref()
+                    .err()
+                    .and_then(|e| e.downcast_ref::<CollateralError>())
+                    == Some(&error)
+            }))
+        };
+
+        let body = pki.tcb_info(fmspc, 0, "OutOfDate");
+        let qe = pki.qe_identity(1, "UpToDate", 16);
+        let results = verify_batch(&body, &qe, &NOW).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+
+        let level = results[0].as_ref().unwrap().tcb_level().unwrap();
+        assert_eq!(level.status, "OutOfDate");
+        assert_eq!(level.date, "2020-11-11T00:00:00Z");
+        assert_eq!(level.advisory_ids, ["INTEL-SA-00334"]);
+
+        let supplemental = results[0].as_ref().unwrap().supplemental().unwrap();
+        assert_eq!(supplemental.earliest_issue_date, "2022-08-10T12:00:00Z");
+        assert_eq!(supplemental.latest_issue_date, "2022-08-11T08:00:00Z");
+        assert_eq!(
+            supplemental.earliest_expiration_date,
+            "2022-09-08T08:00:00Z"
+        );
+        assert_eq!(supplemental.tcb_level_date_tag, level.date);
+        assert_eq!(supplemental.tcb_eval_ref_num, 16);
+        assert_eq!(supplemental.fmspc, fmspc);
+
+        // The PCK certificate chain is anchored at the Intel root, unlike the
+        // collateral.
+        let intel = &TrustedRoots::intel().unwrap().roots()[0];
+        assert_eq!(
+            supplemental.root_key_id,
+            supplemental::root_key_id(intel).unwrap()
+        );
+
+        // An up to date platform is only up to date with an up to date QE.
+        let up_to_date = pki.tcb_info(fmspc, 0, "UpToDate");
+        let results = verify_batch(&up_to_date, &qe, &NOW).unwrap();
+        let level = results[0].as_ref().unwrap().tcb_level().unwrap();
+        assert_eq!(level.status, "UpToDate");
+        let results =
+            verify_batch(&up_to_date, &pki.qe_identity(1, "OutOfDate", 16), &NOW).unwrap();
+        let level = results[0].as_ref().unwrap().tcb_level().unwrap();
+        assert_eq!(level.status, "OutOfDate");
+
+        // Collateral is only valid from its issueDate until its nextUpdate.
+        let results = verify_batch(&body, &qe, &FixedTime(1_660_000_000)).unwrap();
+        rejected(results, CollateralError::NotYetValid);
+        let results = verify_batch(&body, &qe, &FixedTime(1_662_700_000)).unwrap();
+        rejected(results, CollateralError::Expired);
+
+        // Quotes of platforms below all TCB levels are rejected.
+        let results = verify_batch(&pki.tcb_info(fmspc, 256, "UpToDate"), &qe, &NOW).unwrap();
+        rejected(results, CollateralError::TcbLevelUnsupported);
+
+        // So are quotes of other QEs, and of revoked platforms or QEs.
+        let results = verify_batch(&body, &pki.qe_identity(2, "UpToDate", 16), &NOW).unwrap();
+        rejected(results, CollateralError::QeIdentityMismatch);
+        let results = verify_batch(&pki.tcb_info(fmspc, 0, "Revoked"), &qe, &NOW).unwrap();
+        rejected(results, CollateralError::Revoked);
+        let results = verify_batch(&body, &pki.qe_identity(1, "Revoked", 16), &NOW).unwrap();
+        rejected(results, CollateralError::Revoked);
+
+        // Quotes of platforms without TCB Info are rejected.
+        let other = pki.tcb_info(Fmspc::new([0xff; 6]), 0, "UpToDate");
+        let results = verify_batch(&other, &qe, &NOW).unwrap();
+        assert!(results.iter().all(|r| r.is_err()));
+
+        // Collateral that does not verify, or of different TCB evaluations,
+        // fails the batch.
+        let tampered = body.replace("tcbLevels", "tcbLevelz");
+        assert!(verify_batch(&tampered, &qe, &NOW).is_err());
+        let tampered = qe.replace("isvsvn", "isvsvm");
+        assert!(verify_batch(&body, &tampered, &NOW).is_err());
+        assert!(verify_batch(&body, &pki.qe_identity(1, "UpToDate", 15), &NOW).is_err());
+
+        assert!(
+            verify_all(&[], &[], cert_chain, &SAMPLE_MRENCLAVE[..], &roots, &NOW)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn quoteref_sample_v3quote() {
+        use crate::attestation_types::quoteref::QuoteRef;
+
+        let quote = Quote::try_from(&SAMPLE_V3QUOTE[..]).unwrap();
+        let view = QuoteRef::try_from(&SAMPLE_V3QUOTE[..]).unwrap();
+        let body = view.sgx_body().unwrap();
+
+        assert_eq!(body.mrenclave(), &SAMPLE_MRENCLAVE[..]);
+        assert_eq!(body.reportdata(), view.reportdata());
+        assert_eq!(
+            view.raw_header_and_body(),
+            Quote::raw_header_and_body(&SAMPLE_V3QUOTE[..]).unwrap()
+        );
+        assert_eq!(view.attkey(), &quote.sigdata().attkey().to_vec()[..]);
+        assert_eq!(
+            view.qe_report_sig(),
+            &quote.sigdata().qe_report_sig().to_vec()[..]
+        );
+        assert_eq!(view.qe_auth(), &quote.sigdata().qe_auth()[..]);
+        assert_eq!(view.qe_cert_data(), &quote.sigdata().qe_cert_data_vec()[..]);
+        assert_eq!(view.header().qe_svn(), quote.header().qe_svn);
+        assert_eq!(
+            view.qe_report().reportdata()[..32],
+            Quote::raw_pck_hash(&SAMPLE_V3QUOTE[..]).unwrap()[..]
+        );
+
+        assert!(QuoteRef::try_from(&SAMPLE_V3QUOTE[..1000]).is_err());
+    }
+
+    #[test]
+    fn pck_chain_sample_v3quote() {
+        use crate::{CpuSvn, Fmspc, PceId, PceSvn};
+
+        let quote = Quote::try_from(&SAMPLE_V3QUOTE[..]).unwrap();
+        let chain = quote.pck_chain().unwrap();
+        let ext = chain.sgx_extension;
+
+        assert_eq!(
+            ext.ppid,
+            [
+                0x40, 0x79, 0x0f, 0xff, 0x83, 0xb1, 0x67, 0x97, 0xd2, 0xbf, 0xf0, 0x62, 0xa0, 0x3c,
+                0xc2, 0x66
+            ]
+        );
+        assert_eq!(
+            ext.tcb_components,
+            [15, 15, 2, 4, 1, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(ext.pcesvn, PceSvn::new(10));
+        assert_eq!(
+            ext.cpusvn,
+            CpuSvn::new([15, 15, 2, 4, 1, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(ext.pceid, PceId::new(0));
+        assert_eq!(ext.fmspc, Fmspc::new([0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00]));
+        assert_eq!(ext.sgx_type, 0);
+
+        // The intermediate and root carry no SGX extension.
+        let der = chain.intermed_cert.to_der().unwrap();
+        assert!(SgxExtension::from_der(&der).is_err());
+        let der = chain.leaf_cert.to_der().unwrap();
+        assert!(SgxExtension::from_der(&der[..der.len() - 1]).is_err());
     }
 
     #[test]
     fn verify_fail_bad_pck_chain() {
-        assert!(verify(
+        assert!(verify_now(
             &SAMPLE_V3QUOTE[..],
             samples::BAD_PCK_CHAIN,
             &SAMPLE_MRENCLAVE[..]
@@ -163,7 +753,7 @@ mod test {
 
     #[test]
     fn verify_fail_backwards_pck_chain() {
-        assert!(verify(
+        assert!(verify_now(
             &SAMPLE_V3QUOTE[..],
             samples::BACKWARDS_PCK_CHAIN,
             &SAMPLE_MRENCLAVE[..]
@@ -173,7 +763,7 @@ mod test {
 
     #[test]
     fn verify_fail_incomplete_pck_chain() {
-        assert!(verify(
+        assert!(verify_now(
             &SAMPLE_V3QUOTE[..],
             samples::INCOMPLETE_PCK_CHAIN,
             &SAMPLE_MRENCLAVE[..]
@@ -184,7 +774,7 @@ mod test {
     #[test]
     fn verify_fail_bad_ak() {
         #[cfg(feature = "chain_get")]
-        let cert_chain = get_intel_cert_chain_pem().unwrap();
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
 
         #[cfg(not(feature = "chain_get"))]
         let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
@@ -193,13 +783,13 @@ mod test {
         let bad_ak = &[0u8; 64];
         let _ = quote.splice(500..564, bad_ak.iter().cloned());
 
-        assert!(verify(&quote, &cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
+        assert!(verify_now(&quote, cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
     }
 
     #[test]
     fn verify_fail_bad_report_sig() {
         #[cfg(feature = "chain_get")]
-        let cert_chain = get_intel_cert_chain_pem().unwrap();
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
 
         #[cfg(not(feature = "chain_get"))]
         let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
@@ -208,13 +798,13 @@ mod test {
         let bad_report_sig = &[0u8; 64];
         let _ = quote.splice(436..500, bad_report_sig.iter().cloned());
 
-        assert!(verify(&quote[..], &cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
+        assert!(verify_now(&quote[..], cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
     }
 
     #[test]
     fn verify_fail_bad_qe_report_sig() {
         #[cfg(feature = "chain_get")]
-        let cert_chain = get_intel_cert_chain_pem().unwrap();
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
 
         #[cfg(not(feature = "chain_get"))]
         let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
@@ -223,13 +813,13 @@ mod test {
         let bad_qe_report_sig = &[0u8; 64];
         let _ = quote.splice(948..1012, bad_qe_report_sig.iter().cloned());
 
-        assert!(verify(&quote[..], &cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
+        assert!(verify_now(&quote[..], cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
     }
 
     #[test]
     fn verify_fail_bad_hashed_material() {
         #[cfg(feature = "chain_get")]
-        let cert_chain = get_intel_cert_chain_pem().unwrap();
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
 
         #[cfg(not(feature = "chain_get"))]
         let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
@@ -238,17 +828,42 @@ mod test {
         let bad_hashed_material = &[0u8; 32];
         let _ = quote.splice(884..916, bad_hashed_material.iter().cloned());
 
-        assert!(verify(&quote[..], &cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
+        assert!(verify_now(&quote[..], cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
+    }
+
+    #[test]
+    fn verify_qe_auth() {
+        #[cfg(feature = "chain_get")]
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
+
+        #[cfg(not(feature = "chain_get"))]
+        let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
+
+        let verified = verify_claims_at(
+            &SAMPLE_V3QUOTE[..],
+            cert_chain,
+            &SAMPLE_MRENCLAVE[..],
+            &TrustedRoots::intel().unwrap(),
+            &NOW,
+        )
+        .unwrap();
+        assert_eq!(verified.qe_auth().len(), 32);
+        assert_eq!(verified.qe_auth(), &SAMPLE_V3QUOTE[1014..1046]);
+
+        // The QE Authentication Data is bound into the QE ReportData.
+        let mut quote = SAMPLE_V3QUOTE.to_vec();
+        quote[1014] ^= 1;
+        assert!(verify_now(&quote[..], cert_chain, &SAMPLE_MRENCLAVE[..]).is_err());
     }
 
     #[test]
     fn verify_fail_bad_measurement() {
         #[cfg(feature = "chain_get")]
-        let cert_chain = get_intel_cert_chain_pem().unwrap();
+        let cert_chain = &get_intel_cert_chain_pem().unwrap();
 
         #[cfg(not(feature = "chain_get"))]
         let cert_chain = SAMPLE_INTEL_CERT_CHAIN;
 
-        assert!(verify(&SAMPLE_V3QUOTE[..], &cert_chain, &[0u8; 32]).is_err());
+        assert!(verify_now(&SAMPLE_V3QUOTE[..], cert_chain, &[0u8; 32]).is_err());
     }
 }
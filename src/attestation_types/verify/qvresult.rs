@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::claims::VerifiedReport;
+use super::error::{CollateralError, InvalidSignature, VerifyError};
+use super::QuoteResult;
+use std::{convert::TryFrom, error::Error, fmt};
+
+/// Quote verification verdicts, with the numeric values of `sgx_ql_qv_result_t`
+/// as returned by the Intel DCAP Quote Verification Library.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u32)]
+pub enum QvResult {
+    /// The quote verified and the platform TCB is up to date
+    Ok = 0x0000,
+
+    /// The quote verified but the platform needs additional configuration
+    ConfigNeeded = 0xA001,
+
+    /// The quote verified but the platform TCB is out of date
+    OutOfDate = 0xA002,
+
+    /// The quote verified but the platform TCB is out of date and needs
+    /// additional configuration
+    OutOfDateConfigNeeded = 0xA003,
+
+    /// A signature in the quote or its collateral is invalid
+    InvalidSignature = 0xA004,
+
+    /// A key or certificate in the chain has been revoked
+    Revoked = 0xA005,
+
+    /// The verdict could not be determined
+    Unspecified = 0xA006,
+
+    /// The quote verified but software mitigations are needed
+    SwHardeningNeeded = 0xA007,
+
+    /// The quote verified but additional configuration and software
+    /// mitigations are needed
+    ConfigAndSwHardeningNeeded = 0xA008,
+}
+
+impl QvResult {
+    /// Returns the numeric `sgx_ql_qv_result_t` value.
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// Returns true if the quote itself was verified, even though the
+    /// platform may need attention (the non-terminal results).
+    pub fn is_verified(self) -> bool {
+        !matches!(
+            self,
+            QvResult::InvalidSignature | QvResult::Revoked | QvResult::Unspecified
+        )
+    }
+
+    /// Returns the verdict for a platform whose TCB level has `status`, as
+    /// given in the TCB Info (e.g. "SWHardeningNeeded").
+    pub fn from_tcb_status(status: &str) -> Result<Self, VerifyError> {
+        match status {
+            "UpToDate" => Ok(QvResult::Ok),
+            "ConfigurationNeeded" => Ok(QvResult::ConfigNeeded),
+            "OutOfDate" => Ok(QvResult::OutOfDate),
+            "OutOfDateConfigurationNeeded" => Ok(QvResult::OutOfDateConfigNeeded),
+            "Revoked" => Ok(QvResult::Revoked),
+            "SWHardeningNeeded" => Ok(QvResult::SwHardeningNeeded),
+            "ConfigurationAndSWHardeningNeeded" => Ok(QvResult::ConfigAndSwHardeningNeeded),
+            _ => Err(VerifyError(format!("Unknown TCB status: {}", status))),
+        }
+    }
+
+    /// Returns the verdict for a verified quote, from the TCB level of its
+    /// platform (see `VerifiedReport::tcb_level()`).
+    ///
+    /// A quote verified without collateral, e.g. with `verify_claims_at()`,
+    /// has no TCB level, so its verdict is `Unspecified`.
+    pub fn from_report(report: &VerifiedReport) -> Self {
+        report
+            .tcb_level()
+            .and_then(|level| Self::from_tcb_status(&level.status).ok())
+            .unwrap_or(QvResult::Unspecified)
+    }
+
+    /// Returns the verdict for a quote that failed to verify with `error`.
+    ///
+    /// Like the QVL, only an `InvalidSignature` or a revoked TCB level (see
+    /// `CollateralError::Revoked`) yield a verdict. For other errors, e.g.
+    /// malformed quotes, untrusted certificates, expired collateral or
+    /// platforms below all TCB levels, the QVL fails with an error status
+    /// instead, and the verdict is `Unspecified`.
+    pub fn from_error(error: &(dyn Error + 'static)) -> Self {
+        if error.is::<InvalidSignature>() {
+            return QvResult::InvalidSignature;
+        }
+
+        match error.downcast_ref::<CollateralError>() {
+            Some(CollateralError::Revoked) => QvResult::Revoked,
+            _ => QvResult::Unspecified,
+        }
+    }
+}
+
+/// The verdict for the outcome of verifying a quote with `verify_all()`, see
+/// `QvResult::from_report()` and `QvResult::from_error()`
+impl From<&QuoteResult> for QvResult {
+    fn from(outcome: &QuoteResult) -> Self {
+        match outcome {
+            Ok(report) => Self::from_report(report),
+            Err(e) => Self::from_error(&**e),
+        }
+    }
+}
+
+impl TryFrom<u32> for QvResult {
+    type Error = VerifyError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x0000 => Ok(QvResult::Ok),
+            0xA001 => Ok(QvResult::ConfigNeeded),
+            0xA002 => Ok(QvResult::OutOfDate),
+            0xA003 => Ok(QvResult::OutOfDateConfigNeeded),
+            0xA004 => Ok(QvResult::InvalidSignature),
+            0xA005 => Ok(QvResult::Revoked),
+            0xA006 => Ok(QvResult::Unspecified),
+            0xA007 => Ok(QvResult::SwHardeningNeeded),
+            0xA008 => Ok(QvResult::ConfigAndSwHardeningNeeded),
+            _ => Err(VerifyError(format!(
+                "Unknown sgx_ql_qv_result_t value: {:#x}",
+                value
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for QvResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QvResult::Ok => write!(f, "SGX_QL_QV_RESULT_OK"),
+            QvResult::ConfigNeeded => write!(f, "SGX_QL_QV_RESULT_CONFIG_NEEDED"),
+            QvResult::OutOfDate => write!(f, "SGX_QL_QV_RESULT_OUT_OF_DATE"),
+            QvResult::OutOfDateConfigNeeded => {
+                write!(f, "SGX_QL_QV_RESULT_OUT_OF_DATE_CONFIG_NEEDED")
+            }
+            QvResult::InvalidSignature => write!(f, "SGX_QL_QV_RESULT_INVALID_SIGNATURE"),
+            QvResult::Revoked => write!(f, "SGX_QL_QV_RESULT_REVOKED"),
+            QvResult::Unspecified => write!(f, "SGX_QL_QV_RESULT_UNSPECIFIED"),
+            QvResult::SwHardeningNeeded => write!(f, "SGX_QL_QV_RESULT_SW_HARDENING_NEEDED"),
+            QvResult::ConfigAndSwHardeningNeeded => {
+                write!(f, "SGX_QL_QV_RESULT_CONFIG_AND_SW_HARDENING_NEEDED")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for code in (0xA001..=0xA008).chain(Some(0)) {
+            assert_eq!(QvResult::try_from(code).unwrap().code(), code);
+        }
+
+        assert!(QvResult::try_from(0xA009).is_err());
+    }
+
+    #[test]
+    fn outcome() {
+        use crate::attestation_types::report::Body;
+        use crate::attestation_types::verify::{Supplemental, TcbLevel};
+        use crate::{CpuSvn, Fmspc, PceId, PceSvn};
+
+        assert_eq!(QvResult::from_tcb_status("UpToDate").unwrap(), QvResult::Ok);
+        assert_eq!(
+            QvResult::from_tcb_status("OutOfDateConfigurationNeeded").unwrap(),
+            QvResult::OutOfDateConfigNeeded
+        );
+        assert!(QvResult::from_tcb_status("Fine").is_err());
+
+        let verified = |status: &str| {
+            let level = TcbLevel {
+                status: status.to_string(),
+                date: "2022-08-10T00:00:00Z".to_string(),
+                advisory_ids: Vec::new(),
+            };
+            let supplemental = Supplemental {
+                earliest_issue_date: "2022-08-10T12:00:00Z".to_string(),
+                latest_issue_date: "2022-08-10T12:00:00Z".to_string(),
+                earliest_expiration_date: "2022-09-09T12:00:00Z".to_string(),
+                tcb_level_date_tag: level.date.clone(),
+                tcb_eval_ref_num: 16,
+                fmspc: Fmspc::new([0; 6]),
+                pce_id: PceId::new(0),
+                tcb_cpusvn: CpuSvn::default(),
+                tcb_pce_isvsvn: PceSvn::new(0),
+                sgx_type: 0,
+                root_key_id: [0; 48],
+                pck_crl_num: None,
+            };
+            let report = VerifiedReport::new(Body::default()).with_collateral(level, supplemental);
+            let outcome: QuoteResult = Ok(report);
+            QvResult::from(&outcome)
+        };
+        assert_eq!(verified("SWHardeningNeeded").code(), 0xA007);
+        assert_eq!(verified("OutOfDate"), QvResult::OutOfDate);
+        assert_eq!(verified("?"), QvResult::Unspecified);
+
+        let uncollateralized: QuoteResult = Ok(VerifiedReport::new(Body::default()));
+        assert_eq!(QvResult::from(&uncollateralized), QvResult::Unspecified);
+
+        let failed: QuoteResult = Err(Box::new(InvalidSignature(VerifyError(
+            "signature verification failed".into(),
+        ))));
+        assert_eq!(QvResult::from(&failed).code(), 0xA004);
+
+        let revoked: QuoteResult = Err(Box::new(CollateralError::Revoked));
+        assert_eq!(QvResult::from(&revoked), QvResult::Revoked);
+
+        for error in [
+            CollateralError::Expired,
+            CollateralError::TcbLevelUnsupported,
+            CollateralError::QeIdentityMismatch,
+        ]
+        .iter()
+        {
+            let rejected: QuoteResult = Err(Box::new(error.clone()));
+            assert_eq!(QvResult::from(&rejected), QvResult::Unspecified);
+        }
+
+        let untrusted: QuoteResult = Err(Box::new(VerifyError("untrusted root".into())));
+        assert_eq!(QvResult::from(&untrusted), QvResult::Unspecified);
+
+        let malformed: Box<dyn Error> = "truncated quote".into();
+        assert_eq!(QvResult::from_error(&*malformed), QvResult::Unspecified);
+    }
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::error::VerifyError;
+use openssl::x509::X509;
+
+/// The Intel SGX Root CA certificate, which anchors the PCK and TCB signing
+/// certificate chains. It is valid until 2033-05-21.
+pub const INTEL_SGX_ROOT_CA: &str = "-----BEGIN CERTIFICATE-----
+MIICjjCCAjSgAwIBAgIUImUM1lqdNInzg7SVUr9QGzknBqwwCgYIKoZIzj0EAwIw
+aDEaMBgGA1UEAwwRSW50ZWwgU0dYIFJvb3QgQ0ExGjAYBgNVBAoMEUludGVsIENv
+cnBvcmF0aW9uMRQwEgYDVQQHDAtTYW50YSBDbGFyYTELMAkGA1UECAwCQ0ExCzAJ
+BgNVBAYTAlVTMB4XDTE4MDUyMTEwNDExMVoXDTMzMDUyMTEwNDExMFowaDEaMBgG
+A1UEAwwRSW50ZWwgU0dYIFJvb3QgQ0ExGjAYBgNVBAoMEUludGVsIENvcnBvcmF0
+aW9uMRQwEgYDVQQHDAtTYW50YSBDbGFyYTELMAkGA1UECAwCQ0ExCzAJBgNVBAYT
+AlVTMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEC6nEwMDIYZOj/iPWsCzaEKi7
+1OiOSLRFhWGjbnBVJfVnkY4u3IjkDYYL0MxO4mqsyYjlBalTVYxFP2sJBK5zlKOB
+uzCBuDAfBgNVHSMEGDAWgBQiZQzWWp00ifODtJVSv1AbOScGrDBSBgNVHR8ESzBJ
+MEegRaBDhkFodHRwczovL2NlcnRpZmljYXRlcy50cnVzdGVkc2VydmljZXMuaW50
+ZWwuY29tL0ludGVsU0dYUm9vdENBLmNybDAdBgNVHQ4EFgQUImUM1lqdNInzg7SV
+Ur9QGzknBqwwDgYDVR0PAQH/BAQDAgEGMBIGA1UdEwEB/wQIMAYBAf8CAQEwCgYI
+KoZIzj0EAwIDSAAwRQIgQQs/08rycdPauCFk8UPQXCMAlsloBe7NwaQGTcdpa0EC
+IQCUt8SGvxKmjpcM/z0WP9Dvo8h2k5du1iWDdBkAn+0iiA==
+-----END CERTIFICATE-----";
+
+/// The set of pinned root certificates to which every verified certificate
+/// chain must anchor. By default, this contains only the Intel SGX Root CA.
+#[derive(Clone)]
+pub struct TrustedRoots(Vec<X509>);
+
+impl TrustedRoots {
+    /// Creates a set containing only the compiled-in Intel SGX Root CA.
+    pub fn intel() -> Result<Self, VerifyError> {
+        Self::from_pem(INTEL_SGX_ROOT_CA.as_bytes())
+    }
+
+    /// Creates a set from one or more concatenated PEM certificates, overriding
+    /// the compiled-in defaults.
+    pub fn from_pem(pem: &[u8]) -> Result<Self, VerifyError> {
+        let roots = X509::stack_from_pem(pem)?;
+        if roots.is_empty() {
+            return Err(VerifyError(
+                "no trusted root certificates given".to_string(),
+            ));
+        }
+        Ok(Self(roots))
+    }
+
+    /// Creates a set from already parsed certificates.
+    pub fn new(roots: Vec<X509>) -> Self {
+        Self(roots)
+    }
+
+    /// Adds another pinned root certificate to the set.
+    pub fn add(&mut self, root: X509) {
+        self.0.push(root);
+    }
+
+    /// Returns the pinned root certificates.
+    pub fn roots(&self) -> &[X509] {
+        &self.0
+    }
+
+    /// Checks whether the certificate is (byte for byte) one of the pinned roots.
+    pub fn contains(&self, cert: &X509) -> Result<bool, VerifyError> {
+        let der = cert.to_der()?;
+        for root in self.0.iter() {
+            if root.to_der()? == der {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
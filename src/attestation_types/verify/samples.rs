@@ -1,5 +1,19 @@
 // SPDX-License-Identifier: Apache-2.0
 
+use super::{TrustedRoots, TCB_SIGNING_CN};
+use crate::Fmspc;
+
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha256;
+use openssl::x509::extension::BasicConstraints;
+use openssl::x509::{X509NameBuilder, X509};
+
 /// A sample MRENCLAVE expected by testing
 #[allow(dead_code)]
 pub const SAMPLE_MRENCLAVE: [u8; 32] = [
@@ -335,3 +349,124 @@ Ur9QGzknBqwwDgYDVR0PAQH/BAQDAgEGMBIGA1UdEwEB/wQIMAYBAf8CAQEwCgYI
 KoZIzj0EAwIDSAAwRQIgQQs/08rycdPauCFk8UPQXCMAlsloBe7NwaQGTcdpa0EC
 IQCUt8SGvxKmjpcM/z0WP9Dvo8h2k5du1iWDdBkAn+0iiA==
 -----END CERTIFICATE-----";
+
+/// Generates a P-256 key
+pub fn key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+/// Issues a certificate for `key`, self-signed unless `issuer` is given.
+pub fn cert(cn: &str, key: &PKey<Private>, issuer: Option<(&X509, &PKey<Private>)>) -> X509 {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", cn).unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    let serial = BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap();
+    builder.set_serial_number(&serial).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder
+        .set_not_before(&Asn1Time::from_unix(1_000_000_000).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::from_unix(2_000_000_000).unwrap())
+        .unwrap();
+    builder.set_pubkey(key).unwrap();
+
+    let (issuer_name, signer) = match issuer {
+        Some((cert, key)) => (cert.subject_name(), key),
+        None => {
+            let ca = BasicConstraints::new().critical().ca().build().unwrap();
+            builder.append_extension(ca).unwrap();
+            (&*name, key)
+        }
+    };
+    builder.set_issuer_name(issuer_name).unwrap();
+    builder.sign(signer, MessageDigest::sha256()).unwrap();
+    builder.build()
+}
+
+/// Returns the hex encoded raw ECDSA signature of `data`.
+pub fn sign(key: &PKey<Private>, data: &str) -> String {
+    let sig = EcdsaSig::sign(&sha256(data.as_bytes()), &key.ec_key().unwrap()).unwrap();
+
+    let mut hex = String::new();
+    for n in [sig.r(), sig.s()].iter() {
+        let bytes = n.to_vec();
+        for b in std::iter::repeat(&0u8).take(32 - bytes.len()).chain(&bytes) {
+            hex.push_str(&format!("{:02x}", b));
+        }
+    }
+
+    hex
+}
+
+/// A test PKI whose root issued a TCB Signing certificate
+pub struct Pki {
+    /// The pinned root
+    pub roots: TrustedRoots,
+
+    /// The PEM chain of the TCB Signing certificate, leaf first
+    pub chain: String,
+
+    /// The key of the TCB Signing certificate
+    pub key: PKey<Private>,
+}
+
+/// Creates a new test PKI
+pub fn pki() -> Pki {
+    let root_key = key();
+    let root = cert("Root", &root_key, None);
+    let key = key();
+    let leaf = cert(TCB_SIGNING_CN, &key, Some((&root, &root_key)));
+
+    let mut chain = String::from_utf8(leaf.to_pem().unwrap()).unwrap();
+    chain.push_str(&String::from_utf8(root.to_pem().unwrap()).unwrap());
+
+    Pki {
+        roots: TrustedRoots::new(vec![root]),
+        chain,
+        key,
+    }
+}
+
+impl Pki {
+    /// Signs `value` as the `field` of signed collateral.
+    pub fn signed(&self, field: &str, value: String) -> String {
+        let signature = sign(&self.key, &value);
+        format!(r#"{{"{}":{},"signature":"{}"}}"#, field, value, signature)
+    }
+
+    /// Returns signed TCB Info for `fmspc`, valid in August 2022, whose
+    /// single TCB level with `status` requires SVNs of at least `min`.
+    pub fn tcb_info(&self, fmspc: Fmspc, min: u16, status: &str) -> String {
+        let components: Vec<_> = (1..=16)
+            .map(|i| format!(r#""sgxtcbcomp{:02}svn":{}"#, i, min))
+            .collect();
+        self.signed(
+            "tcbInfo",
+            format!(
+                r#"{{"version":2,"issueDate":"2022-08-10T12:00:00Z","nextUpdate":"2022-09-09T12:00:00Z","fmspc":"{}","tcbEvaluationDataNumber":16,"tcbLevels":[{{"tcb":{{{},"pcesvn":{}}},"tcbDate":"2020-11-11T00:00:00Z","tcbStatus":"{}","advisoryIDs":["INTEL-SA-00334"]}}]}}"#,
+                fmspc,
+                components.join(","),
+                min,
+                status
+            ),
+        )
+    }
+
+    /// Returns the signed QE Identity of the sample's QE, valid in August
+    /// 2022 and of TCB evaluation `number`, whose single TCB level with
+    /// `status` is at ISVSVN 5.
+    pub fn qe_identity(&self, isvprodid: u16, status: &str, number: u32) -> String {
+        self.signed(
+            "enclaveIdentity",
+            format!(
+                r#"{{"id":"QE","version":2,"issueDate":"2022-08-11T08:00:00Z","nextUpdate":"2022-09-08T08:00:00Z","tcbEvaluationDataNumber":{},"miscselect":"00000000","miscselectMask":"FFFFFFFF","attributes":"11000000000000000000000000000000","attributesMask":"FBFFFFFFFFFFFFFF0000000000000000","mrsigner":"8C4F5775D796503E96137F77C68A829A0056AC8DED70140B081B094490C57BFF","isvprodid":{},"tcbLevels":[{{"tcb":{{"isvsvn":5}},"tcbDate":"2022-08-10T00:00:00Z","tcbStatus":"{}"}}]}}"#,
+                number, isvprodid, status
+            ),
+        )
+    }
+}
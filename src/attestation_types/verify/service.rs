@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::claims::VerifiedReport;
+use super::handshake::QuoteVerifier;
+use super::roots::TrustedRoots;
+use super::time::TimeSource;
+
+use std::error::Error;
+
+/// The result of submitting evidence to an `AttestationService`
+#[derive(Clone)]
+pub enum Attestation {
+    /// The service verified the evidence itself and returns its claims
+    Claims(VerifiedReport),
+
+    /// The service returns a token, e.g. a JWT, attesting to the claims
+    Token(String),
+}
+
+/// A verifier of Quotes, local or remote
+///
+/// Applications written against this trait can switch between verifiers
+/// through configuration.
+pub trait AttestationService {
+    /// Submits the `quote` for verification.
+    fn submit(&self, quote: &[u8]) -> Result<Attestation, Box<dyn Error>>;
+}
+
+/// The local DCAP verifier, see `verify_claims_at()`
+pub struct Dcap {
+    chain: String,
+    measurement: Vec<u8>,
+    roots: TrustedRoots,
+    time: Box<dyn TimeSource>,
+}
+
+impl Dcap {
+    /// Creates a verifier of Quotes of the enclave with the known-good
+    /// `measurement`, against the tenant's PEM certificate `chain` anchored at
+    /// the compiled-in Intel SGX Root CA. Certificate validity periods are
+    /// checked against `time`, e.g. `SystemClock`.
+    pub fn new(
+        chain: String,
+        measurement: Vec<u8>,
+        time: impl TimeSource + 'static,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::with_roots(
+            chain,
+            measurement,
+            TrustedRoots::intel()?,
+            time,
+        ))
+    }
+
+    /// Like `new()`, but the chain must be anchored at one of `roots`.
+    pub fn with_roots(
+        chain: String,
+        measurement: Vec<u8>,
+        roots: TrustedRoots,
+        time: impl TimeSource + 'static,
+    ) -> Self {
+        Self {
+            chain,
+            measurement,
+            roots,
+            time: Box::new(time),
+        }
+    }
+}
+
+impl QuoteVerifier for Dcap {
+    fn verify(&self, quote: &[u8]) -> Result<VerifiedReport, Box<dyn Error>> {
+        super::verify_claims_at(
+            quote,
+            &self.chain,
+            &self.measurement,
+            &self.roots,
+            &*self.time,
+        )
+    }
+}
+
+impl AttestationService for Dcap {
+    fn submit(&self, quote: &[u8]) -> Result<Attestation, Box<dyn Error>> {
+        self.verify(quote).map(Attestation::Claims)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attestation_types::verify::samples::{
+        SAMPLE_INTEL_CERT_CHAIN, SAMPLE_MRENCLAVE, SAMPLE_V3QUOTE,
+    };
+    use crate::attestation_types::verify::FixedTime;
+
+    // 2022-08-20, while the sample's PCK certificate is valid.
+    const NOW: FixedTime = FixedTime(1_661_000_000);
+
+    #[test]
+    fn dcap() {
+        let service: Box<dyn AttestationService> = Box::new(
+            Dcap::new(
+                SAMPLE_INTEL_CERT_CHAIN.to_string(),
+                SAMPLE_MRENCLAVE.to_vec(),
+                NOW,
+            )
+            .unwrap(),
+        );
+
+        match service.submit(&SAMPLE_V3QUOTE[..]).unwrap() {
+            Attestation::Claims(claims) => {
+                assert_eq!(&claims.body().mrenclave[..], &SAMPLE_MRENCLAVE[..])
+            }
+            Attestation::Token(_) => panic!("expected claims"),
+        }
+
+        let service = Dcap::new(SAMPLE_INTEL_CERT_CHAIN.to_string(), vec![0; 32], NOW).unwrap();
+        assert!(service.submit(&SAMPLE_V3QUOTE[..]).is_err());
+
+        // The sample's PCK certificate expires in 2028.
+        let service = Dcap::new(
+            SAMPLE_INTEL_CERT_CHAIN.to_string(),
+            SAMPLE_MRENCLAVE.to_vec(),
+            FixedTime(1_900_000_000),
+        )
+        .unwrap();
+        assert!(service.submit(&SAMPLE_V3QUOTE[..]).is_err());
+    }
+}
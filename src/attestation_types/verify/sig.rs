@@ -3,13 +3,9 @@
 use openssl::{bn::BigNum, ecdsa::EcdsaSig, error::ErrorStack};
 use std::convert::TryFrom;
 
-#[derive(Debug, Clone)]
-/// Error type for the Sig module
-pub struct SigError;
-
 /// This struct creates a Signature from raw r and s values, which can
 /// be converted to DER form with the method below.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default)]
 pub struct Signature {
     r: [u8; 32],
     s: [u8; 32],
@@ -33,15 +29,6 @@ impl PartialEq for Signature {
     }
 }
 
-impl Default for Signature {
-    fn default() -> Self {
-        Signature {
-            r: [0u8; 32],
-            s: [0u8; 32],
-        }
-    }
-}
-
 // turns &[u8] into Signature
 impl TryFrom<&[u8]> for Signature {
     type Error = ErrorStack;
@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::collateral::timestamp;
+use super::error::VerifyError;
+use super::tcbeval::{check_tcb_evaluation, TcbLevel};
+use crate::attestation_types::quote::pckext::SgxExtension;
+use crate::{CpuSvn, Fmspc, PceId, PceSvn};
+
+use openssl::bn::BigNumContext;
+use openssl::ec::PointConversionForm;
+use openssl::sha::sha384;
+use openssl::x509::X509Ref;
+
+/// The supplemental data of a verified Quote, with the fields of the
+/// `sgx_ql_qv_supplemental_t` of Intel's Quote Verification Library that the
+/// collateral and the PCK certificate provide
+///
+/// Dates are kept as the `YYYY-MM-DDThh:mm:ssZ` timestamps of the collateral.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Supplemental {
+    /// The earliest `issueDate` of the collateral
+    pub earliest_issue_date: String,
+
+    /// The latest `issueDate` of the collateral
+    pub latest_issue_date: String,
+
+    /// The earliest `nextUpdate` of the collateral, after which it must be
+    /// fetched again
+    pub earliest_expiration_date: String,
+
+    /// The `tcbDate` of the platform's TCB level
+    pub tcb_level_date_tag: String,
+
+    /// The `tcbEvaluationDataNumber` of the collateral
+    pub tcb_eval_ref_num: u32,
+
+    /// The FMSPC of the platform
+    pub fmspc: Fmspc,
+
+    /// The ID of the platform's Provisioning Certification Enclave
+    pub pce_id: PceId,
+
+    /// The CPUSVN of the platform's PCK certificate
+    pub tcb_cpusvn: CpuSvn,
+
+    /// The PCESVN of the platform's PCK certificate
+    pub tcb_pce_isvsvn: PceSvn,
+
+    /// The SGX type of the platform: 0 (Standard), 1 (Scalable) or 2
+    /// (Scalable with Integrity)
+    pub sgx_type: u8,
+
+    /// The SHA-384 hash of the uncompressed public key of the pinned root CA
+    /// that anchored the PCK certificate chain
+    pub root_key_id: [u8; 48],
+
+    /// The CRL number of the PCK CRL the PCK certificate was checked
+    /// against, or `None` since PCK CRLs are not consulted
+    pub pck_crl_num: Option<u32>,
+}
+
+// Returns the ID of the key of a root CA, as the QVL computes it.
+pub(super) fn root_key_id(root: &X509Ref) -> Result<[u8; 48], VerifyError> {
+    let key = root.public_key()?.ec_key()?;
+    let mut ctx = BigNumContext::new()?;
+    let point =
+        key.public_key()
+            .to_bytes(key.group(), PointConversionForm::UNCOMPRESSED, &mut ctx)?;
+    Ok(sha384(&point))
+}
+
+impl Supplemental {
+    /// Fills the supplemental data from the signed `collateral` of a Quote
+    /// (its TCB Info and QE Identity, as returned by `verify_tcb_info()` and
+    /// `verify_qe_identity()`), the platform's matched TCB `level`, the SGX
+    /// `extension` of its PCK certificate and the pinned `root` at which the
+    /// PCK certificate chain is anchored.
+    ///
+    /// The collateral must belong to a single TCB evaluation, see
+    /// `check_tcb_evaluation()`.
+    pub fn new(
+        collateral: &[&str],
+        level: &TcbLevel,
+        extension: &SgxExtension,
+        root: &X509Ref,
+    ) -> Result<Self, VerifyError> {
+        let tcb_eval_ref_num = check_tcb_evaluation(collateral)?;
+
+        let mut issued = Vec::with_capacity(collateral.len());
+        let mut expires = Vec::with_capacity(collateral.len());
+        for signed in collateral {
+            issued.push(timestamp(signed, "issueDate")?);
+            expires.push(timestamp(signed, "nextUpdate")?);
+        }
+
+        // `check_tcb_evaluation()` rejects empty collateral.
+        Ok(Self {
+            earliest_issue_date: issued.iter().min().unwrap().to_string(),
+            latest_issue_date: issued.iter().max().unwrap().to_string(),
+            earliest_expiration_date: expires.iter().min().unwrap().to_string(),
+            tcb_level_date_tag: level.date.clone(),
+            tcb_eval_ref_num,
+            fmspc: extension.fmspc,
+            pce_id: extension.pceid,
+            tcb_cpusvn: extension.cpusvn,
+            tcb_pce_isvsvn: extension.pcesvn,
+            sgx_type: extension.sgx_type,
+            root_key_id: root_key_id(root)?,
+            pck_crl_num: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::attestation_types::verify::samples::pki;
+
+    const TCB_INFO: &str = r#"{"version":2,"issueDate":"2022-08-10T12:00:00Z","nextUpdate":"2022-09-09T12:00:00Z","tcbEvaluationDataNumber":16}"#;
+    const QE_IDENTITY: &str = r#"{"id":"QE","issueDate":"2022-08-11T08:00:00Z","nextUpdate":"2022-09-08T08:00:00Z","tcbEvaluationDataNumber":16}"#;
+
+    fn extension() -> SgxExtension {
+        SgxExtension {
+            ppid: [1; 16],
+            tcb_components: [2; 16],
+            pcesvn: PceSvn::new(11),
+            cpusvn: CpuSvn::default(),
+            pceid: PceId::new(0),
+            fmspc: Fmspc::new([0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00]),
+            sgx_type: 1,
+        }
+    }
+
+    #[test]
+    fn supplemental() {
+        let level = TcbLevel {
+            status: "UpToDate".to_string(),
+            date: "2021-11-10T00:00:00Z".to_string(),
+            advisory_ids: Vec::new(),
+        };
+
+        let pki = pki();
+        let root = &pki.roots.roots()[0];
+
+        let data = Supplemental::new(&[TCB_INFO, QE_IDENTITY], &level, &extension(), root).unwrap();
+        assert_eq!(data.earliest_issue_date, "2022-08-10T12:00:00Z");
+        assert_eq!(data.latest_issue_date, "2022-08-11T08:00:00Z");
+        assert_eq!(data.earliest_expiration_date, "2022-09-08T08:00:00Z");
+        assert_eq!(data.tcb_level_date_tag, "2021-11-10T00:00:00Z");
+        assert_eq!(data.tcb_eval_ref_num, 16);
+        assert_eq!(data.fmspc, extension().fmspc);
+        assert_eq!(data.tcb_pce_isvsvn, PceSvn::new(11));
+        assert_eq!(data.sgx_type, 1);
+        assert_eq!(data.pck_crl_num, None);
+
+        // The ID is the hash of the root's public key, not of its certificate.
+        let key = pki.roots.roots()[0].public_key().unwrap().ec_key().unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let point = key
+            .public_key()
+            .to_bytes(key.group(), PointConversionForm::UNCOMPRESSED, &mut ctx)
+            .unwrap();
+        assert_eq!(point.len(), 65);
+        assert_eq!(data.root_key_id[..], sha384(&point)[..]);
+        assert_ne!(data.root_key_id[..], sha384(&root.to_der().unwrap())[..]);
+
+        assert!(Supplemental::new(&[], &level, &extension(), root).is_err());
+
+        let undated = r#"{"version":2,"tcbEvaluationDataNumber":16}"#;
+        assert!(Supplemental::new(&[undated], &level, &extension(), root).is_err());
+
+        let local = TCB_INFO.replace("2022-08-10T12:00:00Z", "2022-08-10 12:00:00");
+        assert!(Supplemental::new(&[&local], &level, &extension(), root).is_err());
+    }
+}
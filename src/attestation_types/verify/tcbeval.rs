@@ -0,0 +1,511 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::collateral::{elements, members, unhex};
+use super::error::VerifyError;
+use crate::attestation_types::report::Body;
+use crate::{Fmspc, PceSvn};
+
+fn member<'a>(members: &[(&str, &'a str)], key: &str) -> Result<&'a str, VerifyError> {
+    members
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| *v)
+        .ok_or_else(|| VerifyError(format!("missing {}", key)))
+}
+
+fn string(value: &str) -> Result<&str, VerifyError> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| VerifyError(format!("not a string: {}", value)))
+}
+
+fn svn(value: &str) -> Result<u16, VerifyError> {
+    value
+        .parse()
+        .map_err(|_| VerifyError(format!("invalid SVN: {}", value)))
+}
+
+/// Returns the `tcbEvaluationDataNumber` of a signed collateral value, such
+/// as the JSON returned by `verify_tcb_info()` or `verify_qe_identity()`.
+pub fn tcb_evaluation_data_number(signed: &str) -> Result<u32, VerifyError> {
+    let value = members(signed)?
+        .into_iter()
+        .find(|(k, _)| *k == "tcbEvaluationDataNumber")
+        .map(|(_, v)| v)
+        .ok_or_else(|| VerifyError("missing tcbEvaluationDataNumber".to_string()))?;
+
+    value
+        .parse()
+        .map_err(|_| VerifyError(format!("invalid tcbEvaluationDataNumber: {}", value)))
+}
+
+/// Returns the FMSPC of the platforms a signed `tcbInfo` (as returned by
+/// `verify_tcb_info()`) applies to.
+pub fn tcb_info_fmspc(tcb_info: &str) -> Result<Fmspc, VerifyError> {
+    let value = members(tcb_info)?
+        .into_iter()
+        .find(|(k, _)| *k == "fmspc")
+        .map(|(_, v)| v)
+        .ok_or_else(|| VerifyError("missing fmspc".to_string()))?;
+
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| VerifyError(format!("invalid fmspc: {}", value)))
+}
+
+/// The TCB level of a platform, as matched in its TCB Info
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TcbLevel {
+    /// The `tcbStatus` of the level, e.g. `UpToDate` or `OutOfDate`
+    pub status: String,
+
+    /// The `tcbDate` of the level, e.g. `2021-11-10T00:00:00Z`
+    pub date: String,
+
+    /// The `advisoryIDs` of the level, e.g. `INTEL-SA-00334`, or none if the
+    /// TCB Info lists none
+    pub advisory_ids: Vec<String>,
+}
+
+// Returns the SVNs of the SGX TCB components and the PCESVN of the `tcb` of a
+// TCB level, as laid out by version 2 (`sgxtcbcompNNsvn`) or version 3
+// (`sgxtcbcomponents`) of TCB Info.
+fn level_tcb(tcb: &str) -> Result<([u16; 16], u16), VerifyError> {
+    let tcb = members(tcb)?;
+    let mut components = [0u16; 16];
+
+    match tcb.iter().find(|(k, _)| *k == "sgxtcbcomponents") {
+        Some((_, array)) => {
+            let array = elements(array)?;
+            if array.len() != components.len() {
+                return Err(VerifyError("invalid sgxtcbcomponents".to_string()));
+            }
+
+            for (component, value) in components.iter_mut().zip(array) {
+                *component = svn(member(&members(value)?, "svn")?)?;
+            }
+        }
+
+        None => {
+            for (i, component) in components.iter_mut().enumerate() {
+                *component = svn(member(&tcb, &format!("sgxtcbcomp{:02}svn", i + 1))?)?;
+            }
+        }
+    }
+
+    Ok((components, svn(member(&tcb, "pcesvn")?)?))
+}
+
+// Returns the status, date and advisory IDs of a TCB level of TCB Info or QE
+// Identity.
+fn tcb_level(level: &[(&str, &str)]) -> Result<TcbLevel, VerifyError> {
+    let advisory_ids = match level.iter().find(|(k, _)| *k == "advisoryIDs") {
+        Some((_, ids)) => elements(ids)?
+            .into_iter()
+            .map(|id| string(id).map(str::to_string))
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+
+    Ok(TcbLevel {
+        status: string(member(level, "tcbStatus")?)?.to_string(),
+        date: string(member(level, "tcbDate")?)?.to_string(),
+        advisory_ids,
+    })
+}
+
+/// Returns the TCB level of a platform in a signed `tcbInfo` (as returned by
+/// `verify_tcb_info()`), given the SVNs of its SGX TCB `components` and its
+/// `pcesvn`, e.g. from the SGX extension of its PCK certificate.
+///
+/// The levels of a TCB Info are ordered from the highest TCB down, so the
+/// platform's level is the first one none of whose SVNs exceed the
+/// platform's. `None` is returned for a platform below all levels.
+pub fn match_tcb_level(
+    tcb_info: &str,
+    components: &[u8; 16],
+    pcesvn: PceSvn,
+) -> Result<Option<TcbLevel>, VerifyError> {
+    let levels = member(&members(tcb_info)?, "tcbLevels")?;
+    for level in elements(levels)? {
+        let level = members(level)?;
+        let (tcb, min_pcesvn) = level_tcb(member(&level, "tcb")?)?;
+        let matched = pcesvn.inner() >= min_pcesvn
+            && components
+                .iter()
+                .zip(tcb.iter())
+                .all(|(svn, min)| u16::from(*svn) >= *min);
+        if matched {
+            return tcb_level(&level).map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the TCB level of a Quoting Enclave in a signed `enclaveIdentity`
+/// (as returned by `verify_qe_identity()`), given the body of its Report.
+///
+/// The Report must have the MRSIGNER and ISVPRODID of the QE Identity, and
+/// its MISCSELECT and attributes must have the identity's values under their
+/// masks. Its level is then the first one whose ISVSVN does not exceed the
+/// Report's. `None` is returned for a Report that does not match or is below
+/// all levels.
+pub fn match_qe_identity(
+    qe_identity: &str,
+    report: &Body,
+) -> Result<Option<TcbLevel>, VerifyError> {
+    let identity = members(qe_identity)?;
+    let hex = |key: &str| unhex(string(member(&identity, key)?)?);
+
+    let miscselect = |key: &str| -> Result<u32, VerifyError> {
+        let value = string(member(&identity, key)?)?;
+        u32::from_str_radix(value, 16)
+            .map_err(|_| VerifyError(format!("invalid {}: {}", key, value)))
+    };
+    let misc_ok =
+        (report.miscselect.bits() & miscselect("miscselectMask")?) == miscselect("miscselect")?;
+
+    let attributes = &report.to_vec()[48..64];
+    let (expected, mask) = (hex("attributes")?, hex("attributesMask")?);
+    if expected.len() != attributes.len() || mask.len() != attributes.len() {
+        return Err(VerifyError("invalid QE Identity attributes".to_string()));
+    }
+    let attributes_ok = attributes
+        .iter()
+        .zip(&mask)
+        .map(|(a, m)| a & m)
+        .eq(expected.iter().cloned());
+
+    let isvprodid = svn(member(&identity, "isvprodid")?)?;
+    let matched = misc_ok
+        && attributes_ok
+        && hex("mrsigner")? == report.mrsigner
+        && isvprodid == report.isv_prod_id.inner();
+    if !matched {
+        return Ok(None);
+    }
+
+    let levels = member(&identity, "tcbLevels")?;
+    for level in elements(levels)? {
+        let level = members(level)?;
+        let isvsvn = svn(member(&members(member(&level, "tcb")?)?, "isvsvn")?)?;
+        if report.isv_svn.inner() >= isvsvn {
+            return tcb_level(&level).map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the TCB level of a platform whose Quoting Enclave is at TCB level
+/// `qe`, the way Intel's Quote Verification Library combines them: an out of
+/// date QE makes the platform out of date, the earlier of the dates is kept
+/// and the advisory IDs of both apply.
+pub fn converge_tcb_levels(platform: TcbLevel, qe: &TcbLevel) -> TcbLevel {
+    let mut level = platform;
+    if qe.status == "OutOfDate" {
+        match level.status.as_str() {
+            "UpToDate" | "SWHardeningNeeded" => level.status = "OutOfDate".to_string(),
+            "ConfigurationNeeded" | "ConfigurationAndSWHardeningNeeded" => {
+                level.status = "OutOfDateConfigurationNeeded".to_string()
+            }
+            _ => (),
+        }
+    }
+
+    if qe.date < level.date {
+        level.date = qe.date.clone();
+    }
+
+    for id in &qe.advisory_ids {
+        if !level.advisory_ids.contains(id) {
+            level.advisory_ids.push(id.clone());
+        }
+    }
+
+    level
+}
+
+/// Checks that the signed collateral `pieces` (e.g. TCB Info and QE
+/// Identity) belong to the same TCB evaluation and returns its
+/// `tcbEvaluationDataNumber`.
+///
+/// Collateral from different evaluations must not be combined: a QE Identity
+/// from before a TCB recovery would, for example, accept a QE that the TCB
+/// Info of the recovery no longer trusts.
+pub fn check_tcb_evaluation(pieces: &[&str]) -> Result<u32, VerifyError> {
+    let mut number = None;
+    for piece in pieces {
+        let n = tcb_evaluation_data_number(piece)?;
+        match number {
+            Some(m) if m != n => {
+                return Err(VerifyError(format!(
+                    "collateral of TCB evaluations {} and {} mixed",
+                    m, n
+                )))
+            }
+            _ => number = Some(n),
+        }
+    }
+
+    number.ok_or_else(|| VerifyError("no collateral".to_string()))
+}
+
+/// The TCB update track a verifier follows, i.e. the `update` parameter of
+/// the Intel Provisioning Certification Service
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UpdateTrack {
+    /// Collateral of a TCB recovery is used as soon as it is published
+    Early,
+
+    /// Collateral of a TCB recovery is used once the recovery takes effect
+    Standard,
+}
+
+impl UpdateTrack {
+    /// Returns the value of the `update` parameter for this track
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateTrack::Early => "early",
+            UpdateTrack::Standard => "standard",
+        }
+    }
+}
+
+/// The latest `tcbEvaluationDataNumber` published on each update track,
+/// e.g. from the `tcbevaluationdatanumbers` endpoint of the Provisioning
+/// Certification Service
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TcbUpdates {
+    /// The latest number on the early track
+    pub early: u32,
+
+    /// The latest number on the standard track
+    pub standard: u32,
+}
+
+impl TcbUpdates {
+    /// Returns the latest number on `track`
+    pub fn latest(&self, track: UpdateTrack) -> u32 {
+        match track {
+            UpdateTrack::Early => self.early,
+            UpdateTrack::Standard => self.standard,
+        }
+    }
+
+    /// Returns whether a verifier following `track` with collateral of TCB
+    /// evaluation `number` should fetch newer collateral.
+    pub fn update_available(&self, track: UpdateTrack, number: u32) -> bool {
+        number < self.latest(track)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    const TCB_INFO: &str = r#"{"version":3,"tcbEvaluationDataNumber":16,"tcbLevels":[]}"#;
+    const QE_IDENTITY: &str = r#"{"id":"QE","tcbEvaluationDataNumber":16}"#;
+    const OLD_QE_IDENTITY: &str = r#"{"id":"QE","tcbEvaluationDataNumber":15}"#;
+
+    #[test]
+    fn number() {
+        assert_eq!(tcb_evaluation_data_number(TCB_INFO).unwrap(), 16);
+        assert!(tcb_evaluation_data_number(r#"{"id":"QE"}"#).is_err());
+        assert!(tcb_evaluation_data_number(r#"{"tcbEvaluationDataNumber":"16"}"#).is_err());
+    }
+
+    #[test]
+    fn fmspc() {
+        let tcb_info = r#"{"version":3,"fmspc":"00906ED50000","tcbLevels":[]}"#;
+        assert_eq!(
+            tcb_info_fmspc(tcb_info).unwrap(),
+            Fmspc::new([0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00])
+        );
+        assert!(tcb_info_fmspc(TCB_INFO).is_err());
+        assert!(tcb_info_fmspc(r#"{"fmspc":"00906E"}"#).is_err());
+        assert!(tcb_info_fmspc(r#"{"fmspc":906}"#).is_err());
+    }
+
+    #[test]
+    fn level() {
+        let tcb = |comp: u16, pcesvn: u16| {
+            let comps: Vec<_> = (1..=16)
+                .map(|i| format!(r#""sgxtcbcomp{:02}svn":{}"#, i, comp))
+                .collect();
+            format!(r#"{{{},"pcesvn":{}}}"#, comps.join(","), pcesvn)
+        };
+        let tcb_info = format!(
+            r#"{{"version":2,"tcbLevels":[
+                {{"tcb":{},"tcbDate":"2021-11-10T00:00:00Z","tcbStatus":"UpToDate"}},
+                {{"tcb":{},"tcbDate":"2020-11-11T00:00:00Z","tcbStatus":"OutOfDate",
+                  "advisoryIDs":["INTEL-SA-00334","INTEL-SA-00615"]}}
+            ]}}"#,
+            tcb(5, 11),
+            tcb(2, 10)
+        );
+
+        let level = match_tcb_level(&tcb_info, &[5; 16], PceSvn::new(11))
+            .unwrap()
+            .unwrap();
+        assert_eq!(level.status, "UpToDate");
+        assert_eq!(level.date, "2021-11-10T00:00:00Z");
+        assert!(level.advisory_ids.is_empty());
+
+        // One component or the PCESVN below the top level is enough to fall
+        // to the next one.
+        let mut components = [5; 16];
+        components[15] = 4;
+        let level = match_tcb_level(&tcb_info, &components, PceSvn::new(11))
+            .unwrap()
+            .unwrap();
+        assert_eq!(level.status, "OutOfDate");
+        assert_eq!(level.advisory_ids, ["INTEL-SA-00334", "INTEL-SA-00615"]);
+        let level = match_tcb_level(&tcb_info, &[5; 16], PceSvn::new(10))
+            .unwrap()
+            .unwrap();
+        assert_eq!(level.date, "2020-11-11T00:00:00Z");
+
+        assert_eq!(
+            match_tcb_level(&tcb_info, &[1; 16], PceSvn::new(11)).unwrap(),
+            None
+        );
+        assert_eq!(
+            match_tcb_level(TCB_INFO, &[5; 16], PceSvn::new(11)).unwrap(),
+            None
+        );
+        assert!(match_tcb_level(r#"{"version":2}"#, &[5; 16], PceSvn::new(11)).is_err());
+
+        // Version 3 lists the components in an array.
+        let svns: Vec<_> = (0..16).map(|_| r#"{"svn":3}"#).collect();
+        let tcb_info = format!(
+            r#"{{"id":"SGX","version":3,"tcbLevels":[{{"tcb":{{"sgxtcbcomponents":[{}],"pcesvn":7}},"tcbDate":"2022-08-10T00:00:00Z","tcbStatus":"SWHardeningNeeded","advisoryIDs":["INTEL-SA-00615"]}}]}}"#,
+            svns.join(",")
+        );
+        let level = match_tcb_level(&tcb_info, &[3; 16], PceSvn::new(7))
+            .unwrap()
+            .unwrap();
+        assert_eq!(level.status, "SWHardeningNeeded");
+        assert_eq!(level.advisory_ids, ["INTEL-SA-00615"]);
+        assert_eq!(
+            match_tcb_level(&tcb_info, &[3; 16], PceSvn::new(6)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn qe_identity() {
+        use crate::{ProductId, SecurityVersion};
+
+        let qe_identity = r#"{"id":"QE","version":2,"miscselect":"00000000","miscselectMask":"FFFFFFFF","attributes":"11000000000000000000000000000000","attributesMask":"FBFFFFFFFFFFFFFF0000000000000000","mrsigner":"8C4F5775D796503E96137F77C68A829A0056AC8DED70140B081B094490C57BFF","isvprodid":1,"tcbLevels":[{"tcb":{"isvsvn":6},"tcbDate":"2022-08-10T00:00:00Z","tcbStatus":"UpToDate"},{"tcb":{"isvsvn":5},"tcbDate":"2018-08-15T00:00:00Z","tcbStatus":"OutOfDate","advisoryIDs":["INTEL-SA-00202"]}]}"#;
+
+        // The sample Quote's QE Report.
+        let mut bytes = [0u8; 384];
+        bytes[48] = 0x15;
+        bytes[56] = 0x07;
+        bytes[128..160].copy_from_slice(&[
+            0x8c, 0x4f, 0x57, 0x75, 0xd7, 0x96, 0x50, 0x3e, 0x96, 0x13, 0x7f, 0x77, 0xc6, 0x8a,
+            0x82, 0x9a, 0x00, 0x56, 0xac, 0x8d, 0xed, 0x70, 0x14, 0x0b, 0x08, 0x1b, 0x09, 0x44,
+            0x90, 0xc5, 0x7b, 0xff,
+        ]);
+        bytes[256] = 1;
+        bytes[258] = 5;
+        let mut report = Body::try_from(&bytes).unwrap();
+
+        let level = match_qe_identity(qe_identity, &report).unwrap().unwrap();
+        assert_eq!(level.status, "OutOfDate");
+        assert_eq!(level.advisory_ids, ["INTEL-SA-00202"]);
+
+        report.isv_svn = SecurityVersion::new(6);
+        let level = match_qe_identity(qe_identity, &report).unwrap().unwrap();
+        assert_eq!(level.status, "UpToDate");
+
+        report.isv_svn = SecurityVersion::new(4);
+        assert_eq!(match_qe_identity(qe_identity, &report).unwrap(), None);
+
+        let mut other = report;
+        other.isv_svn = SecurityVersion::new(6);
+        other.isv_prod_id = ProductId::new(2);
+        assert_eq!(match_qe_identity(qe_identity, &other).unwrap(), None);
+
+        let mut other = report;
+        other.isv_svn = SecurityVersion::new(6);
+        other.mrsigner[0] ^= 1;
+        assert_eq!(match_qe_identity(qe_identity, &other).unwrap(), None);
+
+        // Attributes outside of the mask are ignored, others must match.
+        bytes[258] = 6;
+        bytes[48] = 0x11;
+        assert!(
+            match_qe_identity(qe_identity, &Body::try_from(&bytes).unwrap())
+                .unwrap()
+                .is_some()
+        );
+        bytes[48] = 0x13;
+        assert_eq!(
+            match_qe_identity(qe_identity, &Body::try_from(&bytes).unwrap()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn converge() {
+        let level = |status: &str, date: &str, ids: &[&str]| TcbLevel {
+            status: status.to_string(),
+            date: date.to_string(),
+            advisory_ids: ids.iter().map(|id| id.to_string()).collect(),
+        };
+
+        let platform = level(
+            "SWHardeningNeeded",
+            "2022-08-10T00:00:00Z",
+            &["INTEL-SA-00615"],
+        );
+        let qe = level("OutOfDate", "2018-08-15T00:00:00Z", &["INTEL-SA-00202"]);
+        assert_eq!(
+            converge_tcb_levels(platform.clone(), &qe),
+            level(
+                "OutOfDate",
+                "2018-08-15T00:00:00Z",
+                &["INTEL-SA-00615", "INTEL-SA-00202"]
+            )
+        );
+
+        let qe = level("UpToDate", "2022-08-10T00:00:00Z", &[]);
+        assert_eq!(converge_tcb_levels(platform.clone(), &qe), platform);
+
+        let platform = level("ConfigurationNeeded", "2022-08-10T00:00:00Z", &[]);
+        let qe = level("OutOfDate", "2022-08-10T00:00:00Z", &[]);
+        assert_eq!(
+            converge_tcb_levels(platform, &qe).status,
+            "OutOfDateConfigurationNeeded"
+        );
+    }
+
+    #[test]
+    fn mixed() {
+        assert_eq!(check_tcb_evaluation(&[TCB_INFO, QE_IDENTITY]).unwrap(), 16);
+        assert!(check_tcb_evaluation(&[TCB_INFO, OLD_QE_IDENTITY]).is_err());
+        assert!(check_tcb_evaluation(&[]).is_err());
+    }
+
+    #[test]
+    fn update() {
+        let updates = TcbUpdates {
+            early: 17,
+            standard: 16,
+        };
+
+        assert!(updates.update_available(UpdateTrack::Early, 16));
+        assert!(!updates.update_available(UpdateTrack::Standard, 16));
+        assert!(updates.update_available(UpdateTrack::Standard, 15));
+        assert!(!updates.update_available(UpdateTrack::Early, 17));
+        assert_eq!(UpdateTrack::Early.as_str(), "early");
+    }
+}
@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use super::error::VerifyError;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The source of the current time against which certificate validity periods
+/// are checked. Enclaves have no trusted clock, so in-enclave verifiers must
+/// provide their own; tests can use a `FixedTime` to be deterministic.
+pub trait TimeSource {
+    /// Returns the current time in seconds since the Unix epoch.
+    fn now(&self) -> Result<i64, VerifyError>;
+}
+
+/// The operating system's clock.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> Result<i64, VerifyError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| VerifyError("system time is before the Unix epoch".to_string()))?;
+        Ok(now.as_secs() as i64)
+    }
+}
+
+/// A fixed point in time, in seconds since the Unix epoch.
+#[derive(Copy, Clone, Debug)]
+pub struct FixedTime(pub i64);
+
+impl TimeSource for FixedTime {
+    fn now(&self) -> Result<i64, VerifyError> {
+        Ok(self.0)
+    }
+}
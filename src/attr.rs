@@ -35,12 +35,15 @@ bitflags::bitflags! {
 
         /// Enables key separation and sharing
         const KSS = 1 << 7;
+
+        /// Enables threads to opt in to AEX-Notify via `TcsFlags::AEXNOTIFY`
+        const AEXNOTIFY = 1 << 10;
     }
 }
 
 /// Section 38.7.1.
 #[repr(C, packed(4))]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Attributes {
     features: Features,
     xfrm: Xfrm,
@@ -123,6 +126,23 @@ impl core::ops::BitXor for Attributes {
     }
 }
 
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Features {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::from_bits_truncate(u.arbitrary()?))
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Attributes {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::new(
+            u.arbitrary()?,
+            Xfrm::from_bits_truncate(u.arbitrary()?),
+        ))
+    }
+}
+
 #[cfg(test)]
 testaso! {
     struct Attributes: 4, 16 => {}
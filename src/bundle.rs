@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signed Enclave Bundles
+//! A bundle ships a signed enclave as a single file: a header, followed by
+//! the SIGSTRUCT, an optional EINITTOKEN, the enclave (ELF) image and an
+//! optional measurement artifact. All integers are little-endian.
+//!
+//! | Offset | Size | Field                                   |
+//! |--------|------|-----------------------------------------|
+//! | 0      | 8    | Magic (`"SGXBNDL\0"`)                   |
+//! | 8      | 2    | Version                                 |
+//! | 10     | 2    | Reserved                                |
+//! | 12     | 4    | EINITTOKEN length (0 if absent)         |
+//! | 16     | 8    | Image length                            |
+//! | 24     | 8    | Artifact length (0 if absent)           |
+//! | 32     | 1808 | SIGSTRUCT                               |
+
+use crate::Signature;
+
+use core::convert::{TryFrom, TryInto};
+use core::mem::size_of;
+
+/// The magic bytes at the start of every bundle
+pub const MAGIC: [u8; 8] = *b"SGXBNDL\0";
+
+/// The current version of the bundle format
+pub const VERSION: u16 = 1;
+
+/// The length of the header in bytes, not including the SIGSTRUCT
+pub const HEADER_LEN: usize = 32;
+
+/// The bytes are not a bundle this version of the crate can read
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidBundle {
+    /// The bytes do not start with the bundle magic
+    Magic,
+
+    /// The bundle has an unknown format version
+    Version(u16),
+
+    /// The bundle is shorter than its header says
+    Length,
+
+    /// The measurement artifact is not valid UTF-8
+    Artifact,
+}
+
+impl core::fmt::Display for InvalidBundle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidBundle::Magic => write!(f, "not an enclave bundle"),
+            InvalidBundle::Version(v) => write!(f, "unknown bundle version {}", v),
+            InvalidBundle::Length => write!(f, "bundle is truncated"),
+            InvalidBundle::Artifact => write!(f, "bundle artifact is not UTF-8"),
+        }
+    }
+}
+
+/// A signed enclave bundle, borrowing its image, token and artifact
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bundle<'a> {
+    /// The SIGSTRUCT of the enclave
+    pub signature: Signature,
+
+    /// The EINITTOKEN, if the enclave was launched with one
+    pub token: Option<&'a [u8]>,
+
+    /// The enclave image
+    pub image: &'a [u8],
+
+    /// The measurement artifact (JSON), see the `artifact` module
+    pub artifact: Option<&'a str>,
+}
+
+impl<'a> Bundle<'a> {
+    /// Parses a bundle.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, InvalidBundle> {
+        let sig_end = HEADER_LEN + size_of::<Signature>();
+        if bytes.len() < sig_end {
+            return Err(InvalidBundle::Length);
+        }
+
+        if bytes[..8] != MAGIC {
+            return Err(InvalidBundle::Magic);
+        }
+
+        let version = u16::from_le_bytes(bytes[8..10].try_into().unwrap());
+        if version != VERSION {
+            return Err(InvalidBundle::Version(version));
+        }
+
+        let len = |range: core::ops::Range<usize>| {
+            let mut le = [0u8; 8];
+            le[..range.len()].copy_from_slice(&bytes[range]);
+            usize::try_from(u64::from_le_bytes(le)).map_err(|_| InvalidBundle::Length)
+        };
+
+        let token_end = sig_end + len(12..16)?;
+        let image_end = token_end
+            .checked_add(len(16..24)?)
+            .ok_or(InvalidBundle::Length)?;
+        let end = image_end
+            .checked_add(len(24..32)?)
+            .ok_or(InvalidBundle::Length)?;
+        if bytes.len() < end {
+            return Err(InvalidBundle::Length);
+        }
+
+        let signature =
+            Signature::read_from(&bytes[HEADER_LEN..sig_end]).map_err(|_| InvalidBundle::Length)?;
+        let token = Some(&bytes[sig_end..token_end]).filter(|t| !t.is_empty());
+        let artifact = match &bytes[image_end..end] {
+            [] => None,
+            a => Some(core::str::from_utf8(a).map_err(|_| InvalidBundle::Artifact)?),
+        };
+
+        Ok(Self {
+            signature,
+            token,
+            image: &bytes[token_end..image_end],
+            artifact,
+        })
+    }
+
+    /// Returns the bytes of the bundle
+    pub fn to_vec(&self) -> Vec<u8> {
+        let token = self.token.unwrap_or_default();
+        let artifact = self.artifact.unwrap_or_default().as_bytes();
+
+        let mut vec = MAGIC.to_vec();
+        vec.extend_from_slice(&VERSION.to_le_bytes());
+        vec.extend_from_slice(&[0u8; 2]);
+        vec.extend_from_slice(&(token.len() as u32).to_le_bytes());
+        vec.extend_from_slice(&(self.image.len() as u64).to_le_bytes());
+        vec.extend_from_slice(&(artifact.len() as u64).to_le_bytes());
+        vec.extend_from_slice(self.signature.as_bytes());
+        vec.extend_from_slice(token);
+        vec.extend_from_slice(self.image);
+        vec.extend_from_slice(artifact);
+        vec
+    }
+
+    /// Checks that the embedded SIGSTRUCT signs the measurement (MRENCLAVE
+    /// and parameters) recorded in the embedded artifact. Returns `false` if
+    /// there is no artifact or it cannot be parsed.
+    ///
+    /// This only checks that the SIGSTRUCT and the artifact agree with each
+    /// other; the image is not measured, so it may not match either. A loader
+    /// must compare the artifact with its own measurement of the image (see
+    /// `Artifact::diff()`) before trusting the bundle.
+    #[cfg(feature = "artifact")]
+    pub fn check_artifact(&self) -> bool {
+        let artifact = match self.artifact.map(crate::artifact::Artifact::from_json) {
+            Some(Ok(artifact)) => artifact,
+            _ => return false,
+        };
+
+        let measurement = artifact.parameters.measurement(artifact.mrenclave);
+        self.signature.measurement() == measurement
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signature() -> Signature {
+        Signature::read_from(&[0u8; 1808][..]).unwrap()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let bundle = Bundle {
+            signature: signature(),
+            token: None,
+            image: b"\x7fELF",
+            artifact: Some("{}"),
+        };
+
+        let bytes = bundle.to_vec();
+        assert_eq!(bytes.len(), HEADER_LEN + 1808 + 4 + 2);
+        assert_eq!(Bundle::parse(&bytes), Ok(bundle.clone()));
+
+        let bundle = Bundle {
+            token: Some(&[1u8; 304]),
+            artifact: None,
+            ..bundle
+        };
+        assert_eq!(Bundle::parse(&bundle.to_vec()), Ok(bundle));
+    }
+
+    #[test]
+    fn invalid() {
+        let bundle = Bundle {
+            signature: signature(),
+            token: None,
+            image: &[1, 2, 3],
+            artifact: None,
+        };
+        let bytes = bundle.to_vec();
+
+        let err = Bundle::parse(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err, InvalidBundle::Length);
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(Bundle::parse(&bad).unwrap_err(), InvalidBundle::Magic);
+
+        let mut bad = bytes;
+        bad[8] = 2;
+        assert_eq!(Bundle::parse(&bad).unwrap_err(), InvalidBundle::Version(2));
+    }
+
+    #[cfg(feature = "artifact")]
+    #[test]
+    fn check_artifact() {
+        use crate::artifact::Artifact;
+        use core::num::NonZeroU32;
+
+        let signature = signature();
+        let mut artifact = Artifact {
+            tool: String::new(),
+            size: 0x1000,
+            ssa_frame_pages: NonZeroU32::new(1).unwrap(),
+            parameters: signature.measurement().parameters(),
+            pages: Vec::new(),
+            mrenclave: signature.measurement().mrenclave(),
+        };
+
+        let json = artifact.to_json();
+        let bundle = Bundle {
+            signature: signature.clone(),
+            token: None,
+            image: &[],
+            artifact: Some(&json),
+        };
+        assert!(bundle.check_artifact());
+
+        artifact.mrenclave = [1; 32];
+        let json = artifact.to_json();
+        let bundle = Bundle {
+            artifact: Some(&json),
+            ..bundle
+        };
+        assert!(!bundle.check_artifact());
+    }
+}
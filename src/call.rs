@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Host-Enclave Calls
+//! A minimal convention for calls between the host and the enclave: an
+//! ecall enters the enclave to run one of its functions, and an ocall exits
+//! to the host to run one of the host's. The caller fills in a `Call` in
+//! memory both sides can access, passes its address in RDI, and the callee
+//! writes the `CallStatus` and return value back into it.
+//!
+//! Both sides must agree on the numbering of their functions and on the
+//! layout of each argument block; this module only fixes the frame.
+
+use core::convert::TryFrom;
+
+/// The outcome of a call, written by the callee
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CallStatus {
+    /// The function ran; the return value is valid
+    Success = 0,
+
+    /// No function has this index
+    UnknownFunction = 1,
+
+    /// The argument block is not valid for the function, e.g. it has the
+    /// wrong size or lies on the wrong side of the enclave boundary
+    InvalidArguments = 2,
+
+    /// The function ran and failed; the return value holds its error code
+    Failure = 3,
+}
+
+impl TryFrom<u32> for CallStatus {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CallStatus::Success),
+            1 => Ok(CallStatus::UnknownFunction),
+            2 => Ok(CallStatus::InvalidArguments),
+            3 => Ok(CallStatus::Failure),
+            value => Err(value),
+        }
+    }
+}
+
+/// The frame of an ecall or ocall
+///
+/// Addresses and sizes are 64-bit on both sides, so the layout does not
+/// depend on the target of the host.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Call {
+    /// The index of the function to call
+    pub function: u32,
+
+    /// The `CallStatus` of the call, as written by the callee
+    pub status: u32,
+
+    /// The address of the argument block
+    pub args: u64,
+
+    /// The size of the argument block in bytes
+    pub size: u64,
+
+    /// The return value of the function, or its error code on `Failure`
+    pub ret: u64,
+}
+
+impl Call {
+    /// Creates a call of `function` with the argument block `args`.
+    pub fn new(function: u32, args: &[u8]) -> Self {
+        Self {
+            function,
+            status: CallStatus::Success as u32,
+            args: args.as_ptr() as u64,
+            size: args.len() as u64,
+            ret: 0,
+        }
+    }
+
+    /// Returns the status written by the callee, or the raw value if it is
+    /// not a known `CallStatus`.
+    pub fn status(&self) -> Result<CallStatus, u32> {
+        CallStatus::try_from(self.status)
+    }
+
+    /// Records the outcome of the call.
+    pub fn finish(&mut self, status: CallStatus, ret: u64) {
+        self.status = status as u32;
+        self.ret = ret;
+    }
+}
+
+#[cfg(test)]
+testaso! {
+    struct Call: 8, 32 => {
+        function: 0,
+        status: 4,
+        args: 8,
+        size: 16,
+        ret: 24
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn call() {
+        let args = [1u8, 2, 3, 4];
+        let mut call = Call::new(7, &args);
+        assert_eq!(call.args, args.as_ptr() as u64);
+        assert_eq!(call.size, 4);
+        assert_eq!(call.status(), Ok(CallStatus::Success));
+
+        call.finish(CallStatus::Failure, 22);
+        assert_eq!(call.status(), Ok(CallStatus::Failure));
+        assert_eq!(call.ret, 22);
+
+        call.status = 4;
+        assert_eq!(call.status(), Err(4));
+    }
+}
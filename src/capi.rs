@@ -0,0 +1,419 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! C API
+//! `extern "C"` functions for parsing and verifying V3 SGX Quotes from C, C++
+//! or Go services, with `#[repr(C)]` structs and status codes whose layout and
+//! values are stable. A header can be generated with cbindgen, e.g.
+//! `cbindgen --lang c --crate sgx`.
+//!
+//! Pointers passed in must be valid for the given lengths and strings must be
+//! NUL-terminated; none of them is retained after a call returns.
+
+use crate::attestation_types::quote::Quote;
+use crate::attestation_types::report::Body;
+use crate::attestation_types::verify::{
+    verify_all, FixedTime, QuoteResult, QvResult, SystemClock, TcbCollateral, TimeSource,
+    TrustedRoots,
+};
+
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+
+/// The status of a C API call
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SgxStatus {
+    /// The call succeeded
+    Ok = 0,
+
+    /// A pointer is null or a string is not valid UTF-8
+    InvalidArgument = 1,
+
+    /// The Quote cannot be parsed or is not an SGX Quote
+    InvalidQuote = 2,
+
+    /// The tenant's certificate chain or the collateral cannot be used
+    InvalidCollateral = 3,
+
+    /// The call failed unexpectedly, e.g. it panicked
+    Internal = 4,
+}
+
+/// The identity of the enclave of a Quote
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SgxEnclave {
+    /// The MRENCLAVE of the enclave
+    pub mrenclave: [u8; 32],
+
+    /// The MRSIGNER of the enclave
+    pub mrsigner: [u8; 32],
+
+    /// The ISV product ID of the enclave
+    pub isv_prod_id: u16,
+
+    /// The ISV security version of the enclave
+    pub isv_svn: u16,
+
+    /// The ReportData of the enclave's Report
+    pub reportdata: [u8; 64],
+}
+
+impl From<&Body> for SgxEnclave {
+    fn from(body: &Body) -> Self {
+        Self {
+            mrenclave: body.mrenclave,
+            mrsigner: body.mrsigner,
+            isv_prod_id: body.isv_prod_id.inner(),
+            isv_svn: body.isv_svn.inner(),
+            reportdata: body.reportdata,
+        }
+    }
+}
+
+/// The verdict on a Quote, written by `sgx_verify_quote()`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SgxVerdict {
+    /// The `sgx_ql_qv_result_t` value of the verdict, see `QvResult`
+    pub result: u32,
+
+    /// The enclave of the Quote, or zeros unless the Quote was verified (see
+    /// `QvResult::is_verified()`)
+    pub enclave: SgxEnclave,
+}
+
+impl From<&QuoteResult> for SgxVerdict {
+    fn from(outcome: &QuoteResult) -> Self {
+        let result = QvResult::from(outcome);
+        let body = match outcome {
+            Ok(verified) if result.is_verified() => *verified.body(),
+            _ => Body::default(),
+        };
+
+        Self {
+            result: result.code(),
+            enclave: SgxEnclave::from(&body),
+        }
+    }
+}
+
+// Panics must not unwind into the caller.
+fn guard(f: impl FnOnce() -> SgxStatus) -> SgxStatus {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(SgxStatus::Internal)
+}
+
+unsafe fn string<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Parses the SGX Quote of `len` bytes at `quote` and writes the identity of
+/// its enclave to `out`, without verifying the Quote.
+///
+/// # Safety
+///
+/// `quote` must point to `len` readable bytes and `out` to a writable
+/// `SgxEnclave`.
+#[no_mangle]
+pub unsafe extern "C" fn sgx_quote_parse(
+    quote: *const u8,
+    len: usize,
+    out: *mut SgxEnclave,
+) -> SgxStatus {
+    if quote.is_null() || out.is_null() {
+        return SgxStatus::InvalidArgument;
+    }
+
+    let quote = slice::from_raw_parts(quote, len);
+    guard(|| {
+        let quote = match Quote::try_from(quote) {
+            Ok(quote) => quote,
+            Err(_) => return SgxStatus::InvalidQuote,
+        };
+
+        match quote.body().sgx() {
+            Some(body) => {
+                out.write(SgxEnclave::from(body));
+                SgxStatus::Ok
+            }
+            None => SgxStatus::InvalidQuote,
+        }
+    })
+}
+
+/// Verifies the SGX Quote of `len` bytes at `quote` as `verify_all()` does and
+/// writes the verdict to `out`.
+///
+/// `pck_chain` is the tenant's PEM certificate chain, `tcb_info` the signed
+/// TCB Info of the Quote's platform and `qe_identity` the signed QE Identity,
+/// each followed by the PEM certificates of its signer, leaf first (see
+/// `TcbCollateral`). `mrenclave` points to the 32 bytes
+/// of the known-good MRENCLAVE.
+///
+/// Certificates must be anchored at one of the PEM certificates of `roots`,
+/// or at the Intel SGX Root CA if `roots` is null, and be valid at `now`
+/// (a `time_t`, in seconds since the Unix epoch), or at the time of the
+/// system clock if `now` is 0.
+///
+/// A Quote that does not verify is not a failure of the call: its verdict
+/// says why.
+///
+/// # Safety
+///
+/// `quote` must point to `len` readable bytes, `mrenclave` to 32 readable
+/// bytes, the strings must be NUL-terminated, as must `roots` unless it is
+/// null, and `out` must point to a writable `SgxVerdict`.
+#[no_mangle]
+pub unsafe extern "C" fn sgx_verify_quote(
+    quote: *const u8,
+    len: usize,
+    pck_chain: *const c_char,
+    tcb_info: *const c_char,
+    tcb_info_issuer_chain: *const c_char,
+    qe_identity: *const c_char,
+    qe_identity_issuer_chain: *const c_char,
+    mrenclave: *const u8,
+    roots: *const c_char,
+    now: i64,
+    out: *mut SgxVerdict,
+) -> SgxStatus {
+    if quote.is_null() || mrenclave.is_null() || out.is_null() {
+        return SgxStatus::InvalidArgument;
+    }
+
+    let roots = if roots.is_null() {
+        None
+    } else {
+        match string(roots) {
+            Some(roots) => Some(roots),
+            None => return SgxStatus::InvalidArgument,
+        }
+    };
+
+    let (pck_chain, collateral) = match (
+        string(pck_chain),
+        string(tcb_info),
+        string(tcb_info_issuer_chain),
+        string(qe_identity),
+        string(qe_identity_issuer_chain),
+    ) {
+        (
+            Some(pck_chain),
+            Some(body),
+            Some(issuer_chain),
+            Some(qe_identity),
+            Some(qe_identity_issuer_chain),
+        ) => (
+            pck_chain,
+            TcbCollateral {
+                body,
+                issuer_chain,
+                qe_identity,
+                qe_identity_issuer_chain,
+            },
+        ),
+        _ => return SgxStatus::InvalidArgument,
+    };
+
+    let quote = slice::from_raw_parts(quote, len);
+    let mrenclave = slice::from_raw_parts(mrenclave, 32);
+    guard(|| {
+        let roots = match roots {
+            None => match TrustedRoots::intel() {
+                Ok(roots) => roots,
+                Err(_) => return SgxStatus::Internal,
+            },
+            Some(pem) => match TrustedRoots::from_pem(pem.as_bytes()) {
+                Ok(roots) => roots,
+                Err(_) => return SgxStatus::InvalidCollateral,
+            },
+        };
+
+        let time: &dyn TimeSource = match now {
+            0 => &SystemClock,
+            _ => &FixedTime(now),
+        };
+
+        match verify_all(&[quote], &[collateral], pck_chain, mrenclave, &roots, time) {
+            Ok(outcomes) => {
+                out.write(SgxVerdict::from(&outcomes[0]));
+                SgxStatus::Ok
+            }
+            Err(_) => SgxStatus::InvalidCollateral,
+        }
+    })
+}
+
+#[cfg(test)]
+testaso! {
+    struct SgxEnclave: 2, 132 => {
+        mrenclave: 0,
+        mrsigner: 32,
+        isv_prod_id: 64,
+        isv_svn: 66,
+        reportdata: 68
+    }
+
+    struct SgxVerdict: 4, 136 => {
+        result: 0,
+        enclave: 4
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::attestation_types::quote::Quote;
+    use crate::attestation_types::verify::samples::{
+        pki, SAMPLE_INTEL_CERT_CHAIN, SAMPLE_MRENCLAVE, SAMPLE_V3QUOTE,
+    };
+    use crate::attestation_types::verify::INTEL_SGX_ROOT_CA;
+
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::ptr;
+
+    #[test]
+    fn parse() {
+        let mut enclave = MaybeUninit::<SgxEnclave>::uninit();
+        let status = unsafe {
+            sgx_quote_parse(
+                SAMPLE_V3QUOTE.as_ptr(),
+                SAMPLE_V3QUOTE.len(),
+                enclave.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, SgxStatus::Ok);
+        assert_eq!(unsafe { enclave.assume_init() }.mrenclave, SAMPLE_MRENCLAVE);
+
+        let status =
+            unsafe { sgx_quote_parse(SAMPLE_V3QUOTE.as_ptr(), 1000, enclave.as_mut_ptr()) };
+        assert_eq!(status, SgxStatus::InvalidQuote);
+
+        let status = unsafe { sgx_quote_parse(ptr::null(), 0, enclave.as_mut_ptr()) };
+        assert_eq!(status, SgxStatus::InvalidArgument);
+    }
+
+    #[test]
+    fn verify() {
+        let chain = CString::new(SAMPLE_INTEL_CERT_CHAIN).unwrap();
+        let tcb_info = CString::new("{}").unwrap();
+        let mut verdict = MaybeUninit::<SgxVerdict>::uninit();
+
+        // The collateral is not signed.
+        let status = unsafe {
+            sgx_verify_quote(
+                SAMPLE_V3QUOTE.as_ptr(),
+                SAMPLE_V3QUOTE.len(),
+                chain.as_ptr(),
+                tcb_info.as_ptr(),
+                chain.as_ptr(),
+                tcb_info.as_ptr(),
+                chain.as_ptr(),
+                SAMPLE_MRENCLAVE.as_ptr(),
+                ptr::null(),
+                0,
+                verdict.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, SgxStatus::InvalidCollateral);
+
+        let status = unsafe {
+            sgx_verify_quote(
+                SAMPLE_V3QUOTE.as_ptr(),
+                SAMPLE_V3QUOTE.len(),
+                chain.as_ptr(),
+                ptr::null(),
+                chain.as_ptr(),
+                tcb_info.as_ptr(),
+                chain.as_ptr(),
+                SAMPLE_MRENCLAVE.as_ptr(),
+                ptr::null(),
+                0,
+                verdict.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, SgxStatus::InvalidArgument);
+    }
+
+    #[test]
+    fn verify_sample() {
+        // The collateral is signed by a test PKI, pinned along with the Intel
+        // root that anchors the sample's PCK certificate chain.
+        let pki = pki();
+        let fmspc = Quote::try_from(&SAMPLE_V3QUOTE[..])
+            .unwrap()
+            .pck_chain()
+            .unwrap()
+            .sgx_extension
+            .fmspc;
+        let mut roots = format!("{}\n", INTEL_SGX_ROOT_CA);
+        roots.push_str(&String::from_utf8(pki.roots.roots()[0].to_pem().unwrap()).unwrap());
+
+        let chain = CString::new(SAMPLE_INTEL_CERT_CHAIN).unwrap();
+        let tcb_info = CString::new(pki.tcb_info(fmspc, 0, "SWHardeningNeeded")).unwrap();
+        let qe_identity = CString::new(pki.qe_identity(1, "UpToDate", 16)).unwrap();
+        let issuer_chain = CString::new(pki.chain.clone()).unwrap();
+        let roots = CString::new(roots).unwrap();
+
+        let verify = |mrenclave: &[u8; 32], now: i64| {
+            let mut verdict = MaybeUninit::<SgxVerdict>::uninit();
+            let status = unsafe {
+                sgx_verify_quote(
+                    SAMPLE_V3QUOTE.as_ptr(),
+                    SAMPLE_V3QUOTE.len(),
+                    chain.as_ptr(),
+                    tcb_info.as_ptr(),
+                    issuer_chain.as_ptr(),
+                    qe_identity.as_ptr(),
+                    issuer_chain.as_ptr(),
+                    mrenclave.as_ptr(),
+                    roots.as_ptr(),
+                    now,
+                    verdict.as_mut_ptr(),
+                )
+            };
+            assert_eq!(status, SgxStatus::Ok);
+            unsafe { verdict.assume_init() }
+        };
+
+        // 2022-08-20, within the validity of the collateral.
+        let verdict = verify(&SAMPLE_MRENCLAVE, 1_661_000_000);
+        assert_eq!(verdict.result, QvResult::SwHardeningNeeded.code());
+        assert_eq!(verdict.enclave.mrenclave, SAMPLE_MRENCLAVE);
+
+        // After its nextUpdate, or for another enclave, the Quote does not
+        // verify.
+        let verdict = verify(&SAMPLE_MRENCLAVE, 1_662_700_000);
+        assert_eq!(verdict.result, QvResult::Unspecified.code());
+        assert_eq!(verdict.enclave, SgxEnclave::from(&Body::default()));
+        let verdict = verify(&[0; 32], 1_661_000_000);
+        assert_eq!(verdict.result, QvResult::Unspecified.code());
+    }
+
+    #[test]
+    fn verdict() {
+        use crate::attestation_types::verify::{InvalidSignature, VerifyError};
+
+        let failed: QuoteResult = Err(Box::new(InvalidSignature(VerifyError(
+            "bad signature".to_string(),
+        ))));
+        let verdict = SgxVerdict::from(&failed);
+        assert_eq!(verdict.result, QvResult::InvalidSignature.code());
+        assert_eq!(verdict.enclave, SgxEnclave::from(&Body::default()));
+
+        let malformed: QuoteResult = Err("truncated".into());
+        assert_eq!(
+            SgxVerdict::from(&malformed).result,
+            QvResult::Unspecified.code()
+        );
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! CPUSVN (Table 38-21)
+//! The security version number of the processor, as reported by EREPORT and
+//! consumed by EGETKEY.
+
+use core::cmp::Ordering;
+
+/// The security version number of the processor (CPUSVN)
+///
+/// The CPUSVN is a 16-byte vector of independent SVN components. There is
+/// no total order over it: one CPUSVN is at least as recent as another only
+/// if each of its components is greater than or equal to the corresponding
+/// component of the other. `PartialOrd` implements exactly this comparison,
+/// so `a >= b` holds only when every component of `a` is `>=` that of `b`,
+/// while two CPUSVNs that each have a newer component are incomparable.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CpuSvn([u8; 16]);
+
+impl CpuSvn {
+    /// Creates a new CpuSvn based on the value provided.
+    pub const fn new(svn: [u8; 16]) -> Self {
+        Self(svn)
+    }
+
+    /// Returns inner value as a byte array
+    pub const fn inner(&self) -> [u8; 16] {
+        self.0
+    }
+
+    /// Returns true if every component is greater than or equal to the
+    /// corresponding component of `other`.
+    pub fn is_at_least(&self, other: &Self) -> bool {
+        self.0.iter().zip(other.0.iter()).all(|(l, r)| l >= r)
+    }
+}
+
+impl From<[u8; 16]> for CpuSvn {
+    #[inline]
+    fn from(svn: [u8; 16]) -> Self {
+        Self(svn)
+    }
+}
+
+impl From<CpuSvn> for [u8; 16] {
+    #[inline]
+    fn from(svn: CpuSvn) -> Self {
+        svn.0
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for CpuSvn {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut svn = [0u8; 16];
+        u.fill_buffer(&mut svn)?;
+        Ok(Self(svn))
+    }
+}
+
+impl PartialOrd for CpuSvn {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.is_at_least(other), other.is_at_least(self)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Greater),
+            (false, true) => Some(Ordering::Less),
+            (false, false) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CpuSvn;
+
+    #[test]
+    fn componentwise() {
+        let old = CpuSvn::new([1; 16]);
+
+        let mut bytes = [1; 16];
+        bytes[3] = 2;
+        let new = CpuSvn::new(bytes);
+
+        assert!(new >= old);
+        assert!(new > old);
+        assert!(old < new);
+        assert!(old >= old);
+
+        // Newer in one component, older in another: incomparable.
+        let mut bytes = [2; 16];
+        bytes[15] = 0;
+        let mixed = CpuSvn::new(bytes);
+
+        assert_eq!(mixed.partial_cmp(&old), None);
+        assert!(!mixed.is_at_least(&old));
+        assert!(!old.is_at_least(&mixed));
+    }
+}
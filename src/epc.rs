@@ -0,0 +1,682 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! EPC Usage
+//! Enclave pages live in the Enclave Page Cache (EPC), which is a scarce
+//! resource enumerated by CPUID.(EAX=12H, ECX=2H). These types help check
+//! whether an enclave will fit before attempting ECREATE and, with SGX2
+//! (EDMM), keep track of the state of each page while the enclave runs.
+
+use crate::{Class, Permissions, SecInfo, Tcs, TcsFlags};
+use core::convert::TryFrom;
+use core::num::{NonZeroU32, NonZeroUsize};
+use core::ops::Range;
+
+const PAGE: usize = 4096;
+
+/// The planned layout of an enclave, in pages
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Footprint {
+    /// Pages of loaded segments (code and data)
+    pub segment_pages: usize,
+
+    /// Pages of heap
+    pub heap_pages: usize,
+
+    /// Number of threads; each has a TCS, its SSA frames and a stack
+    pub threads: usize,
+
+    /// Pages of stack per thread
+    pub stack_pages: usize,
+
+    /// Number of SSA frames per thread (TCS.NSSA)
+    pub ssa_frames: usize,
+
+    /// Pages per SSA frame (SECS.SSAFRAMESIZE)
+    pub ssa_frame_pages: usize,
+
+    /// Guard pages added (without permissions) around the stacks and heap
+    pub guard_pages: usize,
+}
+
+impl Footprint {
+    /// Returns the number of EPC pages required by the enclave, including
+    /// the SECS, every TCS and SSA frame, and the guard pages.
+    pub fn epc_pages(&self) -> usize {
+        let per_thread = 1 + self.ssa_frames * self.ssa_frame_pages + self.stack_pages;
+
+        1 + self.segment_pages + self.heap_pages + self.threads * per_thread + self.guard_pages
+    }
+
+    /// Returns the smallest valid enclave size (SECS.SIZE) in bytes for this
+    /// layout, which must be a power of two. The SECS page is not mapped into
+    /// the enclave, so it does not count towards the size.
+    pub fn enclave_size(&self) -> Option<NonZeroUsize> {
+        let pages = self.epc_pages() - 1;
+        if pages == 0 {
+            return None;
+        }
+
+        let size = pages.checked_mul(PAGE)?.checked_next_power_of_two()?;
+        NonZeroUsize::new(size)
+    }
+
+    /// Lays out every thread one after another, starting at the page-aligned
+    /// `offset` from the enclave base. Each thread takes a guard page, its
+    /// stack, its TCS and its SSA frames, in that order, so the stack grows
+    /// down towards the guard page; the `threads` guard pages should be
+    /// counted in `guard_pages`.
+    ///
+    /// Returns `None` if there are no SSA frames or too many of them.
+    pub fn threads(&self, offset: u64) -> Option<impl Iterator<Item = Thread>> {
+        let nssa = NonZeroU32::new(u32::try_from(self.ssa_frames).ok()?)?;
+        let page = PAGE as u64;
+        let stack = self.stack_pages as u64 * page;
+        let ssa = self.ssa_frames as u64 * self.ssa_frame_pages as u64 * page;
+        let stride = page + stack + page + ssa;
+
+        Some((0..self.threads as u64).map(move |i| {
+            let start = offset + i * stride + page;
+            Thread {
+                stack: start..start + stack,
+                tcs: start + stack,
+                ssa: start + stack + page,
+                nssa,
+            }
+        }))
+    }
+}
+
+/// The layout of one enclave thread, as offsets from the enclave base; see
+/// `Footprint::threads()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Thread {
+    /// The stack pages; the stack pointer starts at `stack.end`
+    pub stack: Range<u64>,
+
+    /// The TCS page
+    pub tcs: u64,
+
+    /// The first SSA frame (OSSA)
+    pub ssa: u64,
+
+    /// The number of SSA frames (NSSA)
+    pub nssa: NonZeroU32,
+}
+
+impl Thread {
+    /// Creates the TCS of this thread, entering the enclave at `entry`.
+    pub const fn tcs(&self, entry: u64, flags: TcsFlags) -> Tcs {
+        Tcs::new(entry, self.ssa, self.nssa, flags)
+    }
+}
+
+/// An EPC section, as enumerated by CPUID.(EAX=12H, ECX=2H) and the
+/// following subleaves
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EpcSection {
+    /// The physical address of the section
+    pub base: u64,
+
+    /// The size of the section in bytes
+    pub size: u64,
+}
+
+impl EpcSection {
+    /// Decodes the registers of a CPUID.(EAX=12H, ECX>=2H) subleaf; returns
+    /// `None` if the subleaf does not describe an EPC section.
+    pub fn from_cpuid(eax: u32, ebx: u32, ecx: u32, edx: u32) -> Option<Self> {
+        // Bits 3:0 of EAX are the type of the subleaf; 1 is an EPC section.
+        if eax & 0xf != 1 {
+            return None;
+        }
+
+        let join =
+            |low: u32, high: u32| (u64::from(high & 0xfffff) << 32) | u64::from(low & !0xfff);
+        Some(Self {
+            base: join(eax, ebx),
+            size: join(ecx, edx),
+        })
+    }
+
+    /// Enumerates the EPC sections of the processor; returns `None` if SGX is
+    /// not supported.
+    ///
+    /// See `Secs::max_enc_size()` for the availability of `CPUID`.
+    #[cfg(target_arch = "x86_64")]
+    pub fn enumerate() -> Option<impl Iterator<Item = Self>> {
+        use core::arch::x86_64::{__cpuid_count, __get_cpuid_max};
+
+        const LEAF_SGX_SUPPORT: u32 = 0x07;
+        const LEAF_SGX: u32 = 0x12;
+        const SUBLEAF_EPC: u32 = 0x2;
+
+        if unsafe { __get_cpuid_max(0) }.0 < LEAF_SGX {
+            return None;
+        }
+
+        if unsafe { __cpuid_count(LEAF_SGX_SUPPORT, 0) }.ebx & (1 << 2) == 0 {
+            return None;
+        }
+
+        let sections = (SUBLEAF_EPC..)
+            .map(|subleaf| {
+                let res = unsafe { __cpuid_count(LEAF_SGX, subleaf) };
+                Self::from_cpuid(res.eax, res.ebx, res.ecx, res.edx)
+            })
+            .take_while(Option::is_some)
+            .flatten();
+
+        Some(sections)
+    }
+}
+
+/// The EDMM state of an enclave page
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PageState {
+    /// No page is present
+    Unused,
+
+    /// The page was added by EADD, or added by EAUG and accepted
+    Added,
+
+    /// The page was added by EAUG and awaits EACCEPT
+    Pending,
+
+    /// The permissions were restricted by EMODPR; awaits EACCEPT
+    Restricted,
+
+    /// The page was trimmed by EMODT; awaits EACCEPT
+    Trimming,
+
+    /// The trim was accepted; the page awaits EREMOVE
+    Trimmed,
+}
+
+/// The bookkeeping of one enclave page
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Page {
+    /// The type of the page
+    pub class: Class,
+
+    /// The permissions of the page
+    pub perms: Permissions,
+
+    /// The EDMM state of the page
+    pub state: PageState,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            class: Class::Reg,
+            perms: Permissions::empty(),
+            state: PageState::Unused,
+        }
+    }
+}
+
+/// The page does not exist or is not in a state allowing the operation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidTransition;
+
+/// Counts of the EDMM operations recorded by an `EpcMap`, for exporting as
+/// enclave memory metrics
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpcStats {
+    /// Pages added by EADD
+    pub added: usize,
+
+    /// Pages added by EAUG
+    pub augmented: usize,
+
+    /// Pages trimmed by EMODT
+    pub trimmed: usize,
+
+    /// Pages removed by EREMOVE
+    pub removed: usize,
+
+    /// Pages currently in the EPC, in any state
+    pub resident: usize,
+}
+
+/// Tracks the state of each page of an enclave, so that a runtime can check
+/// EAUG, EACCEPT, EMODPR, EMODPE, EMODT and EREMOVE sequences before issuing
+/// them. Pages are indexed by their offset from the enclave base in pages.
+///
+/// The map borrows its storage, so it can be used without an allocator.
+#[derive(Debug)]
+pub struct EpcMap<'a> {
+    pages: &'a mut [Page],
+    stats: EpcStats,
+}
+
+impl<'a> EpcMap<'a> {
+    /// Creates a map of `pages.len()` pages; all pages start `Unused`.
+    pub fn new(pages: &'a mut [Page]) -> Self {
+        for page in pages.iter_mut() {
+            *page = Page::default();
+        }
+
+        Self {
+            pages,
+            stats: EpcStats::default(),
+        }
+    }
+
+    /// Returns the bookkeeping of the page at `index`.
+    pub fn get(&self, index: usize) -> Option<&Page> {
+        self.pages.get(index)
+    }
+
+    /// Returns the counts of the operations recorded so far.
+    pub fn stats(&self) -> EpcStats {
+        self.stats
+    }
+
+    fn transition(
+        &mut self,
+        index: usize,
+        from: PageState,
+        update: impl FnOnce(&mut Page) -> bool,
+    ) -> Result<(), InvalidTransition> {
+        let page = self.pages.get_mut(index).ok_or(InvalidTransition)?;
+        let mut next = *page;
+        if page.state != from || !update(&mut next) {
+            return Err(InvalidTransition);
+        }
+
+        *page = next;
+        Ok(())
+    }
+
+    /// Records an EADD of the page at `index`.
+    pub fn add(&mut self, index: usize, secinfo: SecInfo) -> Result<(), InvalidTransition> {
+        self.transition(index, PageState::Unused, |page| {
+            page.class = secinfo.class;
+            page.perms = secinfo.perms;
+            page.state = PageState::Added;
+            true
+        })?;
+
+        self.stats.added += 1;
+        self.stats.resident += 1;
+        Ok(())
+    }
+
+    /// Records an EAUG of the page at `index`, which adds a pending regular
+    /// page with read and write permissions.
+    pub fn augment(&mut self, index: usize) -> Result<(), InvalidTransition> {
+        self.transition(index, PageState::Unused, |page| {
+            page.class = Class::Reg;
+            page.perms = Permissions::READ | Permissions::WRITE;
+            page.state = PageState::Pending;
+            true
+        })?;
+
+        self.stats.augmented += 1;
+        self.stats.resident += 1;
+        Ok(())
+    }
+
+    /// Records an EACCEPT of the page at `index` with `secinfo`, which must
+    /// match the pending change (see the `SecInfo` constructors).
+    pub fn accept(&mut self, index: usize, secinfo: SecInfo) -> Result<(), InvalidTransition> {
+        let state = self.get(index).ok_or(InvalidTransition)?.state;
+        self.transition(index, state, |page| {
+            let (expected, next) = match page.state {
+                PageState::Pending => (SecInfo::pending(page.perms), PageState::Added),
+                PageState::Restricted => (SecInfo::restricted(page.perms), PageState::Added),
+                PageState::Trimming => (SecInfo::modified(Class::Trim), PageState::Trimmed),
+                _ => return false,
+            };
+
+            page.state = next;
+            secinfo.class == expected.class && secinfo.perms == expected.perms
+        })
+    }
+
+    /// Records an EMODPR of the page at `index`, restricting its permissions
+    /// to `perms`.
+    pub fn restrict(&mut self, index: usize, perms: Permissions) -> Result<(), InvalidTransition> {
+        self.transition(index, PageState::Added, |page| {
+            let valid = page.class == Class::Reg && page.perms.contains(perms);
+            page.perms = perms;
+            page.state = PageState::Restricted;
+            valid
+        })
+    }
+
+    /// Records an EMODPE of the page at `index`, extending its permissions
+    /// with `perms`.
+    pub fn extend(&mut self, index: usize, perms: Permissions) -> Result<(), InvalidTransition> {
+        self.transition(index, PageState::Added, |page| {
+            page.perms |= perms;
+            page.class == Class::Reg
+        })
+    }
+
+    /// Records an EMODT of the page at `index` to the trimmed type.
+    pub fn trim(&mut self, index: usize) -> Result<(), InvalidTransition> {
+        self.transition(index, PageState::Added, |page| {
+            page.class = Class::Trim;
+            page.perms = Permissions::empty();
+            page.state = PageState::Trimming;
+            true
+        })?;
+
+        self.stats.trimmed += 1;
+        Ok(())
+    }
+
+    /// Records a step of a `PermissionPlan` on the page at `index`.
+    pub fn apply(&mut self, index: usize, step: PermissionStep) -> Result<(), InvalidTransition> {
+        match step {
+            PermissionStep::Restrict(perms) => self.restrict(index, perms),
+            PermissionStep::Accept(perms) => self.accept(index, SecInfo::restricted(perms)),
+            PermissionStep::Extend(perms) => self.extend(index, perms),
+        }
+    }
+
+    /// Records an EREMOVE of the trimmed page at `index`.
+    pub fn remove(&mut self, index: usize) -> Result<(), InvalidTransition> {
+        self.transition(index, PageState::Trimmed, |page| {
+            *page = Page::default();
+            true
+        })?;
+
+        self.stats.removed += 1;
+        self.stats.resident -= 1;
+        Ok(())
+    }
+}
+
+/// One operation of a `PermissionPlan`, to perform on every page of its range
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PermissionStep {
+    /// The host restricts the permissions to these with EMODPR
+    Restrict(Permissions),
+
+    /// The enclave accepts the restriction to these permissions with EACCEPT
+    Accept(Permissions),
+
+    /// The enclave extends the permissions with these with EMODPE
+    Extend(Permissions),
+}
+
+impl PermissionStep {
+    /// Returns the SecInfo to pass to the instruction of this step.
+    pub fn secinfo(&self) -> SecInfo {
+        match *self {
+            PermissionStep::Restrict(perms) => SecInfo::reg(perms),
+            PermissionStep::Accept(perms) => SecInfo::restricted(perms),
+            PermissionStep::Extend(perms) => SecInfo::extended(perms),
+        }
+    }
+}
+
+/// The ordered operations changing the permissions of a range of regular
+/// pages from one `SecInfo` to another
+///
+/// Permissions that are dropped are restricted first (EMODPR by the host,
+/// then EACCEPT by the enclave), so that the pages never have more
+/// permissions than both the current and the desired ones; permissions that
+/// are added are then extended (EMODPE by the enclave). Iterating the plan
+/// yields these steps in order.
+#[derive(Clone, Debug)]
+pub struct PermissionPlan {
+    pages: Range<usize>,
+    steps: [Option<PermissionStep>; 3],
+    next: usize,
+}
+
+impl PermissionPlan {
+    /// Plans the change of the pages at `pages` from `current` to `desired`.
+    ///
+    /// Both must be settled regular pages (no PENDING, MODIFIED or
+    /// RESTRICTED bits) with valid permissions, i.e. no write permission
+    /// without read permission.
+    pub fn new(
+        pages: Range<usize>,
+        current: SecInfo,
+        desired: SecInfo,
+    ) -> Result<Self, InvalidTransition> {
+        let rwx = Permissions::READ | Permissions::WRITE | Permissions::EXECUTE;
+        for secinfo in [current, desired].iter() {
+            if secinfo.class != Class::Reg
+                || !rwx.contains(secinfo.perms)
+                || (secinfo.perms.contains(Permissions::WRITE)
+                    && !secinfo.perms.contains(Permissions::READ))
+            {
+                return Err(InvalidTransition);
+            }
+        }
+
+        let kept = current.perms & desired.perms;
+        let mut steps = [None; 3];
+        if kept != current.perms {
+            steps[0] = Some(PermissionStep::Restrict(kept));
+            steps[1] = Some(PermissionStep::Accept(kept));
+        }
+        if kept != desired.perms {
+            steps[2] = Some(PermissionStep::Extend(desired.perms - kept));
+        }
+
+        Ok(Self {
+            pages,
+            steps,
+            next: 0,
+        })
+    }
+
+    /// Returns the indices of the pages to change
+    pub fn pages(&self) -> Range<usize> {
+        self.pages.clone()
+    }
+}
+
+impl Iterator for PermissionPlan {
+    type Item = PermissionStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(step) = self.steps.get(self.next) {
+            self.next += 1;
+            if step.is_some() {
+                return *step;
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn footprint() {
+        let footprint = Footprint {
+            segment_pages: 10,
+            heap_pages: 16,
+            threads: 2,
+            stack_pages: 4,
+            ssa_frames: 2,
+            ssa_frame_pages: 1,
+            guard_pages: 3,
+        };
+
+        // SECS + segments + heap + 2 * (TCS + SSA + stack) + guards
+        assert_eq!(footprint.epc_pages(), 1 + 10 + 16 + 2 * (1 + 2 + 4) + 3);
+        assert_eq!(footprint.enclave_size().unwrap().get(), 64 * PAGE);
+
+        assert_eq!(Footprint::default().epc_pages(), 1);
+        assert_eq!(Footprint::default().enclave_size(), None);
+    }
+
+    #[test]
+    fn threads() {
+        let footprint = Footprint {
+            threads: 2,
+            stack_pages: 4,
+            ssa_frames: 2,
+            ssa_frame_pages: 1,
+            ..Default::default()
+        };
+
+        let page = PAGE as u64;
+        let nssa = NonZeroU32::new(2).unwrap();
+        let threads: Vec<Thread> = footprint.threads(0x10000).unwrap().collect();
+        assert_eq!(
+            threads,
+            [
+                Thread {
+                    stack: 0x10000 + page..0x10000 + page * 5,
+                    tcs: 0x10000 + page * 5,
+                    ssa: 0x10000 + page * 6,
+                    nssa,
+                },
+                Thread {
+                    stack: 0x10000 + page * 9..0x10000 + page * 13,
+                    tcs: 0x10000 + page * 13,
+                    ssa: 0x10000 + page * 14,
+                    nssa,
+                },
+            ]
+        );
+
+        let tcs = threads[1].tcs(0x1000, TcsFlags::empty());
+        let one = NonZeroU32::new(1).unwrap();
+        let secs = crate::Secs::new(
+            core::ptr::null(),
+            0x20000,
+            one,
+            crate::Parameters::default(),
+        );
+        assert_eq!(tcs.validate(&secs), Ok(()));
+
+        let footprint = Footprint {
+            threads: 1,
+            ..Default::default()
+        };
+        assert!(footprint.threads(0).is_none());
+    }
+
+    #[test]
+    fn section() {
+        let section = EpcSection::from_cpuid(0x7000_0001, 0x1, 0x0780_0001, 0x0).unwrap();
+        assert_eq!(
+            section,
+            EpcSection {
+                base: 0x1_7000_0000,
+                size: 0x0780_0000,
+            }
+        );
+
+        assert_eq!(EpcSection::from_cpuid(0, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn epc_map() {
+        let rw = Permissions::READ | Permissions::WRITE;
+        let mut pages = [Page::default(); 2];
+        let mut map = EpcMap::new(&mut pages);
+
+        // EAUG, then EACCEPT with matching SECINFO only
+        map.augment(0).unwrap();
+        assert_eq!(map.augment(0), Err(InvalidTransition));
+        assert_eq!(map.accept(0, SecInfo::reg(rw)), Err(InvalidTransition));
+        map.accept(0, SecInfo::pending(rw)).unwrap();
+        assert_eq!(map.get(0).unwrap().state, PageState::Added);
+
+        // EMODPR can only restrict
+        assert_eq!(
+            map.restrict(0, Permissions::EXECUTE),
+            Err(InvalidTransition)
+        );
+        map.restrict(0, Permissions::READ).unwrap();
+        map.accept(0, SecInfo::restricted(Permissions::READ))
+            .unwrap();
+        map.extend(0, Permissions::WRITE).unwrap();
+        assert_eq!(map.get(0).unwrap().perms, rw);
+
+        // Trim, accept and remove
+        assert_eq!(map.remove(0), Err(InvalidTransition));
+        map.trim(0).unwrap();
+        map.accept(0, SecInfo::modified(Class::Trim)).unwrap();
+        map.remove(0).unwrap();
+        assert_eq!(map.get(0), Some(&Page::default()));
+
+        map.add(1, SecInfo::tcs()).unwrap();
+        assert_eq!(map.extend(1, rw), Err(InvalidTransition));
+        assert_eq!(map.augment(2), Err(InvalidTransition));
+
+        assert_eq!(
+            map.stats(),
+            EpcStats {
+                added: 1,
+                augmented: 1,
+                trimmed: 1,
+                removed: 1,
+                resident: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn permission_plan() {
+        let r = Permissions::READ;
+        let rw = Permissions::READ | Permissions::WRITE;
+        let rx = Permissions::READ | Permissions::EXECUTE;
+
+        // RW -> RX restricts to R, then extends with X
+        let plan = PermissionPlan::new(1..3, SecInfo::reg(rw), SecInfo::reg(rx)).unwrap();
+        assert_eq!(plan.pages(), 1..3);
+
+        let steps: Vec<_> = plan.clone().collect();
+        assert_eq!(
+            steps,
+            [
+                PermissionStep::Restrict(r),
+                PermissionStep::Accept(r),
+                PermissionStep::Extend(Permissions::EXECUTE),
+            ]
+        );
+        assert_eq!(steps[1].secinfo().perms, r | Permissions::RESTRICTED);
+
+        let mut pages = [Page::default(); 3];
+        let mut map = EpcMap::new(&mut pages);
+        for index in plan.pages() {
+            map.add(index, SecInfo::reg(rw)).unwrap();
+            for step in plan.clone() {
+                map.apply(index, step).unwrap();
+            }
+
+            assert_eq!(map.get(index).unwrap().perms, rx);
+            assert_eq!(map.get(index).unwrap().state, PageState::Added);
+        }
+
+        // Only restrict, only extend, or nothing at all
+        let restrict: Vec<_> = PermissionPlan::new(0..1, SecInfo::reg(rw), SecInfo::reg(r))
+            .unwrap()
+            .collect();
+        assert_eq!(
+            restrict,
+            [PermissionStep::Restrict(r), PermissionStep::Accept(r)]
+        );
+
+        let extend: Vec<_> = PermissionPlan::new(0..1, SecInfo::reg(r), SecInfo::reg(rw))
+            .unwrap()
+            .collect();
+        assert_eq!(extend, [PermissionStep::Extend(Permissions::WRITE)]);
+
+        let plan = PermissionPlan::new(0..1, SecInfo::reg(rx), SecInfo::reg(rx));
+        assert_eq!(plan.unwrap().count(), 0);
+
+        // Illegal transitions are rejected up front
+        let w = SecInfo::reg(Permissions::WRITE);
+        let pending = SecInfo::pending(rw);
+        assert!(PermissionPlan::new(0..1, SecInfo::reg(r), w).is_err());
+        assert!(PermissionPlan::new(0..1, pending, SecInfo::reg(r)).is_err());
+        assert!(PermissionPlan::new(0..1, SecInfo::tcs(), SecInfo::reg(r)).is_err());
+    }
+}
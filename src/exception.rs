@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Exceptions (Section 38.9.1.1)
+//! When an exception causes an AEX, the processor records its vector and
+//! type in the EXITINFO field of the GPRSGX region, if the vector is set in
+//! SECS.MISCSELECT or the exception is one SGX always reports. A runner that
+//! emulates a path on the host sees the same faults as signals instead; the
+//! mapping between the two lets it report them to the enclave as if they had
+//! happened inside.
+
+/// An exception reported in EXITINFO
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exception {
+    /// Divide error (#DE)
+    DivideError = 0,
+
+    /// Debug exception (#DB)
+    Debug = 1,
+
+    /// Breakpoint (#BP)
+    Breakpoint = 3,
+
+    /// BOUND range exceeded (#BR)
+    BoundRange = 5,
+
+    /// Invalid opcode (#UD)
+    InvalidOpcode = 6,
+
+    /// General protection (#GP)
+    GeneralProtection = 13,
+
+    /// Page fault (#PF)
+    PageFault = 14,
+
+    /// x87 floating-point error (#MF)
+    FloatingPoint = 16,
+
+    /// Alignment check (#AC)
+    AlignmentCheck = 17,
+
+    /// SIMD floating-point exception (#XM)
+    SimdFloatingPoint = 19,
+
+    /// Control protection (#CP)
+    ControlProtection = 21,
+}
+
+impl Exception {
+    /// Returns the exception with the vector `vector`, if SGX reports it.
+    pub fn from_vector(vector: u8) -> Option<Self> {
+        Some(match vector {
+            0 => Exception::DivideError,
+            1 => Exception::Debug,
+            3 => Exception::Breakpoint,
+            5 => Exception::BoundRange,
+            6 => Exception::InvalidOpcode,
+            13 => Exception::GeneralProtection,
+            14 => Exception::PageFault,
+            16 => Exception::FloatingPoint,
+            17 => Exception::AlignmentCheck,
+            19 => Exception::SimdFloatingPoint,
+            21 => Exception::ControlProtection,
+            _ => return None,
+        })
+    }
+
+    /// Returns the type of the exception as reported in EXITINFO.
+    pub fn exit_type(&self) -> ExitType {
+        match self {
+            Exception::Breakpoint => ExitType::Software,
+            _ => ExitType::Hardware,
+        }
+    }
+
+    /// Returns the signal and `si_code` Linux delivers for this exception.
+    pub fn signal(&self) -> (Signal, i32) {
+        match self {
+            Exception::DivideError => (Signal::Fpe, FPE_INTDIV),
+            Exception::Debug => (Signal::Trap, TRAP_TRACE),
+            Exception::Breakpoint => (Signal::Trap, TRAP_BRKPT),
+            Exception::BoundRange => (Signal::Segv, SI_KERNEL),
+            Exception::InvalidOpcode => (Signal::Ill, ILL_ILLOPN),
+            Exception::GeneralProtection => (Signal::Segv, SI_KERNEL),
+            Exception::PageFault => (Signal::Segv, SEGV_ACCERR),
+            Exception::FloatingPoint => (Signal::Fpe, FPE_FLTINV),
+            Exception::AlignmentCheck => (Signal::Bus, BUS_ADRALN),
+            Exception::SimdFloatingPoint => (Signal::Fpe, FPE_FLTINV),
+            Exception::ControlProtection => (Signal::Segv, SEGV_CPERR),
+        }
+    }
+
+    /// Returns the exception that a host fault delivered as `signal` with
+    /// `si_code` `code` stands for, if any.
+    ///
+    /// The mapping is not exact: a SIGSEGV from the kernel may also be a
+    /// #BR, and floating-point errors are reported as #XM, since x86-64 code
+    /// uses SSE for floating point.
+    pub fn from_signal(signal: Signal, code: i32) -> Option<Self> {
+        Some(match (signal, code) {
+            (Signal::Fpe, FPE_INTDIV) | (Signal::Fpe, FPE_INTOVF) => Exception::DivideError,
+            (Signal::Fpe, _) => Exception::SimdFloatingPoint,
+            (Signal::Trap, TRAP_BRKPT) => Exception::Breakpoint,
+            (Signal::Trap, _) => Exception::Debug,
+            (Signal::Ill, _) => Exception::InvalidOpcode,
+            (Signal::Segv, SEGV_MAPERR) | (Signal::Segv, SEGV_ACCERR) => Exception::PageFault,
+            (Signal::Segv, SEGV_CPERR) => Exception::ControlProtection,
+            (Signal::Segv, SI_KERNEL) => Exception::GeneralProtection,
+            (Signal::Bus, BUS_ADRALN) => Exception::AlignmentCheck,
+            (Signal::Bus, _) => Exception::PageFault,
+            _ => return None,
+        })
+    }
+}
+
+// The si_code values of the signals, from the Linux uapi siginfo.h
+const SI_KERNEL: i32 = 0x80;
+const ILL_ILLOPN: i32 = 2;
+const FPE_INTDIV: i32 = 1;
+const FPE_INTOVF: i32 = 2;
+const FPE_FLTINV: i32 = 7;
+const SEGV_MAPERR: i32 = 1;
+const SEGV_ACCERR: i32 = 2;
+const SEGV_CPERR: i32 = 10;
+const BUS_ADRALN: i32 = 1;
+const TRAP_BRKPT: i32 = 1;
+const TRAP_TRACE: i32 = 2;
+
+/// A signal delivered for a fault, with its Linux x86-64 number
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Signal {
+    /// SIGILL
+    Ill = 4,
+
+    /// SIGTRAP
+    Trap = 5,
+
+    /// SIGBUS
+    Bus = 7,
+
+    /// SIGFPE
+    Fpe = 8,
+
+    /// SIGSEGV
+    Segv = 11,
+}
+
+impl Signal {
+    /// Returns the signal with the number `signo`, if it is one of the fault
+    /// signals.
+    pub fn from_number(signo: i32) -> Option<Self> {
+        Some(match signo {
+            4 => Signal::Ill,
+            5 => Signal::Trap,
+            7 => Signal::Bus,
+            8 => Signal::Fpe,
+            11 => Signal::Segv,
+            _ => return None,
+        })
+    }
+}
+
+/// The type of an exit in EXITINFO
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExitType {
+    /// A hardware exception
+    Hardware = 3,
+
+    /// A software exception (INT3 or INTO)
+    Software = 6,
+}
+
+/// The EXITINFO field of the GPRSGX region (Section 38.9.1.1)
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExitInfo(u32);
+
+impl ExitInfo {
+    const VALID: u32 = 1 << 31;
+
+    /// Creates a valid EXITINFO reporting `exception`.
+    pub fn new(exception: Exception) -> Self {
+        let exit_type = exception.exit_type() as u32;
+        Self(Self::VALID | (exit_type << 8) | exception as u32)
+    }
+
+    /// Creates an EXITINFO from its raw value.
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the raw value
+    pub const fn raw(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if the exit was caused by a reported exception
+    pub const fn is_valid(&self) -> bool {
+        self.0 & Self::VALID != 0
+    }
+
+    /// Returns the vector of the exception, whether or not it is valid
+    pub const fn vector(&self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Returns the reported exception, if EXITINFO is valid and reports a
+    /// known exception.
+    pub fn exception(&self) -> Option<Exception> {
+        if !self.is_valid() {
+            return None;
+        }
+
+        Exception::from_vector(self.vector())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ALL: [Exception; 11] = [
+        Exception::DivideError,
+        Exception::Debug,
+        Exception::Breakpoint,
+        Exception::BoundRange,
+        Exception::InvalidOpcode,
+        Exception::GeneralProtection,
+        Exception::PageFault,
+        Exception::FloatingPoint,
+        Exception::AlignmentCheck,
+        Exception::SimdFloatingPoint,
+        Exception::ControlProtection,
+    ];
+
+    #[test]
+    fn exit_info() {
+        let info = ExitInfo::new(Exception::PageFault);
+        assert_eq!(info.raw(), 0x8000_030e);
+        assert_eq!(info.exception(), Some(Exception::PageFault));
+
+        let info = ExitInfo::new(Exception::Breakpoint);
+        assert_eq!(info.raw(), 0x8000_0603);
+
+        assert_eq!(ExitInfo::from_raw(0x030e).exception(), None);
+        assert_eq!(ExitInfo::from_raw(0x8000_0302).exception(), None);
+
+        for exception in ALL.iter() {
+            let info = ExitInfo::new(*exception);
+            assert_eq!(info.exception(), Some(*exception));
+        }
+    }
+
+    #[test]
+    fn signals() {
+        for exception in ALL.iter() {
+            let (signal, code) = exception.signal();
+            assert_eq!(Signal::from_number(signal as i32), Some(signal));
+
+            let back = Exception::from_signal(signal, code).unwrap();
+            match exception {
+                Exception::BoundRange => assert_eq!(back, Exception::GeneralProtection),
+                Exception::FloatingPoint => assert_eq!(back, Exception::SimdFloatingPoint),
+                _ => assert_eq!(back, *exception),
+            }
+        }
+
+        assert_eq!(
+            Exception::from_signal(Signal::Segv, SEGV_MAPERR),
+            Some(Exception::PageFault)
+        );
+        assert_eq!(Exception::from_signal(Signal::Segv, 3), None);
+        assert_eq!(Signal::from_number(9), None);
+    }
+}
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Flag Iteration
+//! Enumerates the bits set in `Features`, `Xfrm` and `MiscSelect`, including
+//! the bits this crate has no name for, e.g. when dumping the `Attributes`
+//! of an enclave built for a newer processor.
+
+use crate::{Features, MiscSelect, Xfrm};
+
+use core::convert::TryFrom;
+use core::fmt;
+
+/// A bit set in a flags value, see `FlagBits::iter_bits()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bit<T> {
+    /// A named bit, as a value with only that bit set
+    Named(T),
+
+    /// The bit with this index has no name
+    Unknown(u32),
+}
+
+/// Formatted as the name of the bit, or as `unknown bit N`
+impl<T: fmt::Debug> fmt::Display for Bit<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bit::Named(flag) => write!(f, "{:?}", flag),
+            Bit::Unknown(index) => write!(f, "unknown bit {}", index),
+        }
+    }
+}
+
+/// An iterator over the bits set in a flags value, from the lowest to the
+/// highest
+#[derive(Clone)]
+pub struct BitIter<T> {
+    bits: u64,
+    named: fn(u64) -> Option<T>,
+}
+
+impl<T> Iterator for BitIter<T> {
+    type Item = Bit<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bits == 0 {
+            return None;
+        }
+
+        let index = self.bits.trailing_zeros();
+        let bit = 1 << index;
+        self.bits &= !bit;
+
+        match (self.named)(bit) {
+            Some(flag) => Some(Bit::Named(flag)),
+            None => Some(Bit::Unknown(index)),
+        }
+    }
+}
+
+/// Flags whose set bits can be enumerated
+///
+/// For `Attributes`, enumerate its `features()` and `xfrm()` separately.
+pub trait FlagBits: Sized {
+    /// Returns an iterator over the bits set, including unknown bits.
+    fn iter_bits(&self) -> BitIter<Self>;
+}
+
+macro_rules! flag_bits {
+    ($($name:ident)+) => {
+        $(
+            impl FlagBits for $name {
+                fn iter_bits(&self) -> BitIter<Self> {
+                    BitIter {
+                        bits: u64::from(self.bits()),
+                        named: |bit| Self::from_bits(TryFrom::try_from(bit).ok()?),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+flag_bits! { Features Xfrm MiscSelect }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn features() {
+        let features = Features::INIT | Features::MODE64BIT | Features::AEXNOTIFY;
+        let features = unsafe { Features::from_bits_unchecked(features.bits() | 1 << 63) };
+
+        let bits: Vec<_> = features.iter_bits().collect();
+        assert_eq!(
+            bits,
+            [
+                Bit::Named(Features::INIT),
+                Bit::Named(Features::MODE64BIT),
+                Bit::Named(Features::AEXNOTIFY),
+                Bit::Unknown(63),
+            ]
+        );
+
+        assert_eq!(format!("{}", bits[1]), "MODE64BIT");
+        assert_eq!(format!("{}", bits[3]), "unknown bit 63");
+    }
+
+    #[test]
+    fn misc() {
+        let misc = unsafe { MiscSelect::from_bits_unchecked(0b101) };
+        let bits: Vec<_> = misc.iter_bits().collect();
+        assert_eq!(bits, [Bit::Named(MiscSelect::EXINFO), Bit::Unknown(2)]);
+
+        assert_eq!(MiscSelect::empty().iter_bits().count(), 0);
+    }
+
+    #[test]
+    fn xfrm() {
+        let xfrm = Xfrm::from_bits_truncate(0b11);
+        assert_eq!(xfrm.iter_bits().count(), 2);
+        assert!(xfrm.iter_bits().all(|bit| match bit {
+            Bit::Named(flag) => xfrm.contains(flag),
+            Bit::Unknown(_) => false,
+        }));
+    }
+}
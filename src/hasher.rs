@@ -4,20 +4,83 @@
 //! Section references in further documentation refer to this document.
 //! https://www.intel.com/content/dam/www/public/emea/xe/en/documents/manuals/64-ia-32-architectures-software-developer-vol-3d-part-4-manual.pdf
 
-#![cfg(feature = "crypto")]
 #![allow(clippy::unreadable_literal)]
 
-use crate::{Measurement, Parameters, SecInfo};
+use crate::{InvalidRange, Measurement, Parameters, SecInfo, Secs};
 
-use openssl::sha;
-
-use std::num::NonZeroU32;
+use core::num::NonZeroU32;
 
 const PAGE: usize = 4096;
 
-/// Input length is not a multiple of the page size
+/// The pages cannot be loaded at the requested offset
 #[derive(Copy, Clone, Debug, PartialEq)]
-pub struct InvalidSize;
+pub enum InvalidLoad {
+    /// Input length is not a multiple of the page size
+    Size,
+
+    /// The offset is not aligned to the page size
+    Offset,
+
+    /// The pages extend beyond the enclave size
+    Bounds,
+}
+
+/// The error of `Hasher::load()` before it checked offsets
+#[deprecated(note = "use `InvalidLoad`")]
+pub type InvalidSize = InvalidLoad;
+
+/// A SHA-256 implementation used by the `Hasher`
+///
+/// With the `crypto` feature, this is implemented for OpenSSL's `Sha256`.
+/// With the `sha2` feature, it is implemented for the pure Rust `sha2::Sha256`,
+/// which detects and uses the SHA extensions (SHA-NI) at runtime on x86_64.
+/// Other implementations allow measuring enclaves without OpenSSL, e.g. on
+/// `no_std` targets.
+pub trait Digest {
+    /// Hashes more data
+    fn update(&mut self, data: &[u8]);
+
+    /// Hashes each of `data` in turn, as if by calling `update()` for each
+    ///
+    /// The `Hasher` passes whole 64-byte SHA-256 blocks here, so that
+    /// implementations can hash them without buffering; override this if
+    /// the implementation can hash several slices in one call.
+    #[inline]
+    fn update_vectored(&mut self, data: &[&[u8]]) {
+        for data in data {
+            self.update(data);
+        }
+    }
+
+    /// Produces the hash of all of the data
+    fn finish(self) -> [u8; 32];
+}
+
+#[cfg(feature = "crypto")]
+impl Digest for openssl::sha::Sha256 {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        openssl::sha::Sha256::update(self, data)
+    }
+
+    #[inline]
+    fn finish(self) -> [u8; 32] {
+        openssl::sha::Sha256::finish(self)
+    }
+}
+
+#[cfg(feature = "sha2")]
+impl Digest for sha2::Sha256 {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(self, data)
+    }
+
+    #[inline]
+    fn finish(self) -> [u8; 32] {
+        sha2::Digest::finalize(self).into()
+    }
+}
 
 /// This struct creates and updates the MRENCLAVE value associated
 /// with an enclave's Signature (or SIGSTRUCT). This value is updated with
@@ -25,70 +88,271 @@ pub struct InvalidSize;
 /// summarized at https://github.com/enarx/enarx/wiki/SGX-Measurement. The leaf
 /// functions are mimicked to obtain these values, but are not actually called here;
 /// to use them, refer to the [iocuddle-sgx](../../iocuddle-sgx) library.
-pub struct Hasher(sha::Sha256, Parameters);
+///
+/// With the `crypto` feature, the digest defaults to OpenSSL's `Sha256`, so
+/// that a plain `Hasher` still names the type it did before it was generic.
+#[cfg(feature = "crypto")]
+pub struct Hasher<D: Digest = openssl::sha::Sha256>(D, Parameters, u64);
 
-impl Hasher {
-    /// Mimics call to SGX_IOC_ENCLAVE_CREATE (ECREATE).
-    pub fn new(size: usize, ssa_frame_pages: NonZeroU32, parameters: Parameters) -> Self {
-        let size = size as u64;
+/// This struct creates and updates the MRENCLAVE value associated
+/// with an enclave's Signature (or SIGSTRUCT). This value is updated with
+/// each ECREATE, EADD, or EEXTEND operation as documented in 41.3 and as
+/// summarized at https://github.com/enarx/enarx/wiki/SGX-Measurement. The leaf
+/// functions are mimicked to obtain these values, but are not actually called here;
+/// to use them, refer to the [iocuddle-sgx](../../iocuddle-sgx) library.
+#[cfg(not(feature = "crypto"))]
+pub struct Hasher<D: Digest>(D, Parameters, u64);
+
+#[cfg(feature = "crypto")]
+impl Hasher<openssl::sha::Sha256> {
+    /// Mimics call to SGX_IOC_ENCLAVE_CREATE (ECREATE), hashing with OpenSSL.
+    pub fn new(size: u64, ssa_frame_pages: NonZeroU32, parameters: Parameters) -> Self {
+        Self::with_digest(
+            openssl::sha::Sha256::new(),
+            size,
+            ssa_frame_pages,
+            parameters,
+        )
+    }
+
+    /// Like `new()`, but checks the enclave size with `Secs::check_size()`
+    /// and the parameters with `Parameters::validate()`.
+    pub fn try_new(
+        size: u64,
+        ssa_frame_pages: NonZeroU32,
+        parameters: Parameters,
+    ) -> Result<Self, InvalidRange> {
+        Self::try_with_digest(
+            openssl::sha::Sha256::new(),
+            size,
+            ssa_frame_pages,
+            parameters,
+        )
+    }
+
+    /// Like `new()`, but with the `usize` enclave size it took before sizes
+    /// and offsets became `u64`.
+    #[deprecated(note = "use `new()` with a `u64` size")]
+    pub fn new_usize(size: usize, ssa_frame_pages: NonZeroU32, parameters: Parameters) -> Self {
+        Self::new(size as u64, ssa_frame_pages, parameters)
+    }
+}
+
+impl<D: Digest> Hasher<D> {
+    /// Mimics call to SGX_IOC_ENCLAVE_CREATE (ECREATE), hashing with the
+    /// given fresh `digest`.
+    pub fn with_digest(
+        mut digest: D,
+        size: u64,
+        ssa_frame_pages: NonZeroU32,
+        parameters: Parameters,
+    ) -> Self {
+        trace_event!(size, ssa_frame_pages = ssa_frame_pages.get(), "ECREATE");
 
         // This value documented in 41.3.
         const ECREATE: u64 = 0x0045544145524345;
 
-        let mut sha256 = sha::Sha256::new();
-        sha256.update(&ECREATE.to_le_bytes());
-        sha256.update(&ssa_frame_pages.get().to_le_bytes());
-        sha256.update(&size.to_le_bytes());
-        sha256.update(&[0u8; 44]); // Reserved
+        let mut block = [0u8; 64]; // The rest is reserved
+        block[..8].copy_from_slice(&ECREATE.to_le_bytes());
+        block[8..12].copy_from_slice(&ssa_frame_pages.get().to_le_bytes());
+        block[12..20].copy_from_slice(&size.to_le_bytes());
+        digest.update(&block);
+
+        Self(digest, parameters, size)
+    }
+
+    /// Like `with_digest()`, but checks the enclave size with
+    /// `Secs::check_size()` and the parameters with `Parameters::validate()`.
+    pub fn try_with_digest(
+        digest: D,
+        size: u64,
+        ssa_frame_pages: NonZeroU32,
+        parameters: Parameters,
+    ) -> Result<Self, InvalidRange> {
+        Secs::check_size(size)?;
+        parameters.validate()?;
+        Ok(Self::with_digest(digest, size, ssa_frame_pages, parameters))
+    }
+
+    fn check(&self, offset: u64, len: usize) -> Result<(), InvalidLoad> {
+        if len % PAGE != 0 {
+            return Err(InvalidLoad::Size);
+        }
+
+        if offset % PAGE as u64 != 0 {
+            return Err(InvalidLoad::Offset);
+        }
+
+        match offset.checked_add(len as u64) {
+            Some(end) if end <= self.2 => Ok(()),
+            _ => Err(InvalidLoad::Bounds),
+        }
+    }
+
+    fn page(&mut self, page: &[u8], offset: u64, secinfo: &SecInfo, measure: bool) {
+        // These values documented in 41.3.
+        const EEXTEND: u64 = 0x00444E4554584545;
+        const EADD: u64 = 0x0000000044444145;
+
+        // Each instruction is hashed as one 64-byte block of its name and
+        // offset, followed by any data; 256-byte segments are whole blocks.
+        fn block(name: u64, offset: u64) -> [u8; 64] {
+            let mut block = [0u8; 64];
+            block[..8].copy_from_slice(&name.to_le_bytes());
+            block[8..16].copy_from_slice(&offset.to_le_bytes());
+            block
+        }
+
+        // Hash for the EADD instruction.
+        let mut eadd = block(EADD, offset);
+        eadd[16..].copy_from_slice(unsafe {
+            core::slice::from_raw_parts(secinfo as *const _ as *const u8, 48)
+        });
+        self.0.update(&eadd);
 
-        Self(sha256, parameters)
+        // Hash for the EEXTEND instruction.
+        if measure {
+            let mut off = offset;
+            for segment in page.chunks(256) {
+                self.0.update_vectored(&[&block(EEXTEND, off), segment]);
+                off += segment.len() as u64;
+            }
+        }
     }
 
     /// Hashes pages as if they were loaded via EADD/EEXTEND
+    ///
+    /// The `offset` must be page-aligned and the pages must fit within the
+    /// enclave size given when the `Hasher` was created.
     pub fn load(
         &mut self,
         pages: &[u8],
-        mut offset: usize,
+        mut offset: u64,
         secinfo: SecInfo,
         measure: bool,
-    ) -> Result<(), InvalidSize> {
-        // These values documented in 41.3.
-        const EEXTEND: u64 = 0x00444E4554584545;
-        const EADD: u64 = 0x0000000044444145;
-
-        if pages.len() % PAGE != 0 {
-            return Err(InvalidSize);
-        }
+    ) -> Result<(), InvalidLoad> {
+        self.check(offset, pages.len())?;
 
         // For each page in the input...
         for page in pages.chunks(PAGE) {
-            // Hash for the EADD instruction.
-            self.0.update(&EADD.to_le_bytes());
-            self.0.update(&(offset as u64).to_le_bytes());
-            self.0.update(unsafe {
-                std::slice::from_raw_parts(&secinfo as *const _ as *const u8, 48)
-            });
-
-            // Hash for the EEXTEND instruction.
-            if measure {
-                let mut off = offset;
-                for segment in page.chunks(256) {
-                    self.0.update(&EEXTEND.to_le_bytes());
-                    self.0.update(&(off as u64).to_le_bytes());
-                    self.0.update(&[0u8; 48]);
-                    self.0.update(segment);
-                    off += segment.len();
-                }
-            }
+            self.page(page, offset, &secinfo, measure);
+            offset += page.len() as u64;
+        }
+
+        Ok(())
+    }
 
-            offset += page.len();
+    /// Like `load()`, but with the `usize` offset it took before sizes and
+    /// offsets became `u64`.
+    #[deprecated(note = "use `load()` with a `u64` offset")]
+    pub fn load_usize(
+        &mut self,
+        pages: &[u8],
+        offset: usize,
+        secinfo: SecInfo,
+        measure: bool,
+    ) -> Result<(), InvalidLoad> {
+        self.load(pages, offset as u64, secinfo, measure)
+    }
+
+    /// Copies pages into `dest` and hashes the copies as if they were loaded
+    /// via EADD/EEXTEND, in one pass over the pages.
+    ///
+    /// Hashing the copy rather than `pages` guarantees that the measurement
+    /// matches what was loaded, even if `pages` is modified meanwhile.
+    /// `dest` must be as long as `pages`; on error, nothing is copied.
+    pub fn load_into(
+        &mut self,
+        pages: &[u8],
+        dest: &mut [u8],
+        mut offset: u64,
+        secinfo: SecInfo,
+        measure: bool,
+    ) -> Result<(), InvalidLoad> {
+        if dest.len() != pages.len() {
+            return Err(InvalidLoad::Size);
+        }
+
+        self.check(offset, pages.len())?;
+
+        for (page, copy) in pages.chunks(PAGE).zip(dest.chunks_mut(PAGE)) {
+            copy.copy_from_slice(page);
+            self.page(copy, offset, &secinfo, measure);
+            offset += page.len() as u64;
         }
 
         Ok(())
     }
 
+    /// Hashes a plan of individual pages, each given as its offset, contents,
+    /// `SecInfo` and whether it is measured, in the order they are loaded.
+    ///
+    /// Every page is validated as in `load` before any is hashed, so on error
+    /// the `Hasher` is left unchanged.
+    pub fn load_pages(
+        &mut self,
+        pages: &[(u64, &[u8; PAGE], SecInfo, bool)],
+    ) -> Result<(), InvalidLoad> {
+        for (offset, page, ..) in pages {
+            self.check(*offset, page.len())?;
+        }
+
+        for (offset, page, secinfo, measure) in pages {
+            self.page(&page[..], *offset, secinfo, *measure);
+        }
+
+        Ok(())
+    }
+
+    /// Hashes pages read from `reader` until EOF as if they were loaded via
+    /// EADD/EEXTEND starting at `offset`, returning the number of bytes hashed.
+    ///
+    /// The input is read in page-aligned windows, so large images can be
+    /// measured without holding them in memory. The total length must be a
+    /// multiple of the page size.
+    #[cfg(feature = "std")]
+    pub fn load_from(
+        &mut self,
+        mut reader: impl std::io::Read,
+        offset: u64,
+        secinfo: SecInfo,
+        measure: bool,
+    ) -> std::io::Result<u64> {
+        const WINDOW: usize = 256 * PAGE;
+
+        let mut window = vec![0u8; WINDOW];
+        let mut total = 0;
+
+        loop {
+            // Fill the window, tolerating short reads.
+            let mut len = 0;
+            while len < WINDOW {
+                match reader.read(&mut window[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if len == 0 {
+                return Ok(total);
+            }
+
+            self.load(&window[..len], offset + total, secinfo, measure)
+                .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+            total += len as u64;
+
+            if len < WINDOW {
+                return Ok(total);
+            }
+        }
+    }
+
     /// Produces MRENCLAVE value by hashing with SHA256.
     pub fn finish(self) -> Measurement {
-        self.1.measurement(self.0.finish())
+        let mrenclave = self.0.finish();
+        trace_event!(?mrenclave, "measurement finished");
+        self.1.measurement(mrenclave)
     }
 }
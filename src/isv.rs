@@ -3,14 +3,21 @@
 //! ISV_PRODID and ISVSVN in SIGSTRUCT (Table 38-19)
 //! Definitions for Independent Software Vendor Product ID and Security Version Number.
 
+use core::fmt;
+use core::num::ParseIntError;
+use core::str::FromStr;
+
 /// ISV assigned Product ID.
 #[repr(transparent)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct ProductId(u16);
 
 /// ISV assigned SVN (security version number).
+///
+/// SVNs are ordered, so a minimum can be enforced with e.g.
+/// `report.isv_svn >= min_svn`.
 #[repr(transparent)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct SecurityVersion(u16);
 
 impl ProductId {
@@ -36,3 +43,87 @@ impl SecurityVersion {
         self.0
     }
 }
+
+/// Implements conversions, formatting and parsing as a decimal number, and
+/// serde support as a `u16`.
+macro_rules! u16_newtype {
+    ($($name:ident)+) => {
+        $(
+            impl From<u16> for $name {
+                #[inline]
+                fn from(value: u16) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl From<$name> for u16 {
+                #[inline]
+                fn from(value: $name) -> Self {
+                    value.0
+                }
+            }
+
+            impl fmt::Display for $name {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "{}", self.0)
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = ParseIntError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Ok(Self(s.parse()?))
+                }
+            }
+
+            #[cfg(feature = "serde")]
+            impl serde::Serialize for $name {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_u16(self.0)
+                }
+            }
+
+            #[cfg(feature = "serde")]
+            impl<'de> serde::Deserialize<'de> for $name {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    <u16 as serde::Deserialize>::deserialize(deserializer).map(Self)
+                }
+            }
+        )+
+    };
+}
+
+u16_newtype! { ProductId SecurityVersion }
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for ProductId {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for SecurityVersion {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let svn = SecurityVersion::from(3);
+        assert_eq!(u16::from(svn), 3);
+        assert!(svn >= SecurityVersion::new(2));
+        assert!(svn < SecurityVersion::new(4));
+
+        assert_eq!(format!("{}", ProductId::new(42)), "42");
+        assert_eq!("42".parse(), Ok(ProductId::new(42)));
+        assert!("65536".parse::<ProductId>().is_err());
+        assert!("-1".parse::<SecurityVersion>().is_err());
+    }
+}
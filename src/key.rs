@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Key Request (Section 38.18)
+//! The KEYREQUEST structure is the input of the EGETKEY instruction; its KEYNAME
+//! field selects which key is derived and its KEYPOLICY field which identity of
+//! the enclave the key is bound to.
+
+use crate::{Attributes, CpuSvn, Features, MiscSelect, SecurityVersion, Xfrm};
+
+use core::convert::TryFrom;
+
+/// The `KeyName` of an EGETKEY request
+///
+/// Table 38-22
+#[repr(u16)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum KeyName {
+    /// The EINIT token key, used by the launch enclave
+    EinitToken = 0,
+    /// The provisioning key
+    Provision = 1,
+    /// The provisioning seal key
+    ProvisionSeal = 2,
+    /// The report key, used to verify Reports targeted at the enclave
+    Report = 3,
+    /// The seal key
+    Seal = 4,
+}
+
+/// The enclave's attributes do not allow it to request the key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyNotAllowed(pub KeyName);
+
+impl core::fmt::Display for KeyNotAllowed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?} key not allowed", self.0)
+    }
+}
+
+impl TryFrom<u16> for KeyName {
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(KeyName::EinitToken),
+            1 => Ok(KeyName::Provision),
+            2 => Ok(KeyName::ProvisionSeal),
+            3 => Ok(KeyName::Report),
+            4 => Ok(KeyName::Seal),
+            value => Err(value),
+        }
+    }
+}
+
+impl KeyName {
+    /// Returns the attribute an enclave must have to request this key, if any
+    pub fn required_feature(self) -> Option<Features> {
+        match self {
+            KeyName::EinitToken => Some(Features::EINIT_KEY),
+            KeyName::Provision | KeyName::ProvisionSeal => Some(Features::PROVISIONING_KEY),
+            KeyName::Report | KeyName::Seal => None,
+        }
+    }
+
+    /// Checks that an enclave with `attributes` may request this key, as
+    /// EGETKEY does: only the launch enclave may request the EINIT token key
+    /// and only provisioning enclaves may request the provisioning keys.
+    pub fn check(self, attributes: &Attributes) -> Result<(), KeyNotAllowed> {
+        match self.required_feature() {
+            Some(feature) if !attributes.features().contains(feature) => Err(KeyNotAllowed(self)),
+            _ => Ok(()),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// The identity an EGETKEY key is bound to
+    ///
+    /// Table 38-23
+    #[derive(Default)]
+    pub struct KeyPolicy: u16 {
+        /// Derive the key from the enclave's MRENCLAVE
+        const MRENCLAVE = 1 << 0;
+        /// Derive the key from the enclave's MRSIGNER
+        const MRSIGNER = 1 << 1;
+        /// Do not derive the key from the enclave's ISV product ID
+        const NOISVPRODID = 1 << 2;
+        /// Derive the key from the enclave's CONFIGID
+        const CONFIGID = 1 << 3;
+        /// Derive the key from the enclave's ISV family ID
+        const ISVFAMILYID = 1 << 4;
+        /// Derive the key from the enclave's ISV extended product ID
+        const ISVEXTPRODID = 1 << 5;
+    }
+}
+
+/// The input of EGETKEY
+///
+/// Section 38.18
+#[derive(Copy, Clone)]
+#[repr(C, align(512))]
+pub struct KeyRequest {
+    /// The key to derive
+    pub keyname: KeyName,
+
+    /// The identity of the enclave the key is bound to
+    pub keypolicy: KeyPolicy,
+
+    /// The ISV security version to derive the key for
+    pub isvsvn: SecurityVersion,
+
+    reserved0: u16,
+
+    /// The CPU security version to derive the key for
+    pub cpusvn: CpuSvn,
+
+    /// The enclave attributes to derive the key from
+    pub attributemask: Attributes,
+
+    /// Value for key wear-out protection
+    pub keyid: [u8; 32],
+
+    /// The MISCSELECT bits to derive the key from
+    pub miscmask: MiscSelect,
+
+    /// The CONFIGSVN to derive the key for
+    pub configsvn: u16,
+
+    reserved1: [u8; 434],
+}
+
+impl KeyRequest {
+    /// Creates a request for the `keyname` key bound to `keypolicy`, for the
+    /// given security versions. No attribute or MISCSELECT bits are included
+    /// in the derivation and the key ID is zero.
+    pub const fn new(
+        keyname: KeyName,
+        keypolicy: KeyPolicy,
+        isvsvn: SecurityVersion,
+        cpusvn: CpuSvn,
+    ) -> Self {
+        Self {
+            keyname,
+            keypolicy,
+            isvsvn,
+            reserved0: 0,
+            cpusvn,
+            attributemask: Attributes::new(Features::empty(), Xfrm::empty()),
+            keyid: [0; 32],
+            miscmask: MiscSelect::empty(),
+            configsvn: 0,
+            reserved1: [0; 434],
+        }
+    }
+}
+
+/// Zeroes the key ID and resets the rest of the request but its key name.
+///
+/// `KeyRequest` is `Copy`, so it cannot zero itself on drop; callers must
+/// zeroize each copy, or hold it in `zeroize::Zeroizing`.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for KeyRequest {
+    fn zeroize(&mut self) {
+        use zeroize::Zeroize;
+
+        *self = Self::new(
+            self.keyname,
+            KeyPolicy::empty(),
+            SecurityVersion::default(),
+            CpuSvn::default(),
+        );
+        self.keyid.zeroize();
+    }
+}
+
+impl core::fmt::Debug for KeyRequest {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KeyRequest")
+            .field("keyname", &self.keyname)
+            .field("keypolicy", &self.keypolicy)
+            .field("isvsvn", &self.isvsvn)
+            .field("cpusvn", &self.cpusvn)
+            .field("attributemask", &self.attributemask)
+            .field("keyid", &self.keyid)
+            .field("miscmask", &self.miscmask)
+            .field("configsvn", &self.configsvn)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+testaso! {
+    struct KeyRequest: 512, 512 => {
+        keyname: 0,
+        keypolicy: 2,
+        isvsvn: 4,
+        reserved0: 6,
+        cpusvn: 8,
+        attributemask: 24,
+        keyid: 40,
+        miscmask: 72,
+        configsvn: 76,
+        reserved1: 78
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values() {
+        for value in 0..5 {
+            assert_eq!(KeyName::try_from(value).unwrap() as u16, value);
+        }
+
+        assert_eq!(KeyName::try_from(5), Err(5));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize() {
+        use zeroize::Zeroize;
+
+        let mut request = KeyRequest::new(
+            KeyName::Seal,
+            KeyPolicy::MRSIGNER,
+            SecurityVersion::new(3),
+            CpuSvn::default(),
+        );
+        request.keyid = [0xaa; 32];
+        request.configsvn = 1;
+        request.zeroize();
+
+        assert_eq!(request.keyname, KeyName::Seal);
+        assert_eq!(request.keypolicy, KeyPolicy::empty());
+        assert_eq!(request.isvsvn, SecurityVersion::default());
+        assert_eq!(request.keyid, [0; 32]);
+        assert_eq!(request.configsvn, 0);
+    }
+
+    #[test]
+    fn check() {
+        let attributes = Attributes::new(Features::MODE64BIT, Xfrm::empty());
+        assert_eq!(KeyName::Report.check(&attributes), Ok(()));
+        assert_eq!(KeyName::Seal.check(&attributes), Ok(()));
+        assert_eq!(
+            KeyName::EinitToken.check(&attributes),
+            Err(KeyNotAllowed(KeyName::EinitToken))
+        );
+        assert_eq!(
+            KeyName::Provision.check(&attributes),
+            Err(KeyNotAllowed(KeyName::Provision))
+        );
+
+        let attributes = Attributes::new(Features::PROVISIONING_KEY, Xfrm::empty());
+        assert_eq!(KeyName::Provision.check(&attributes), Ok(()));
+        assert_eq!(KeyName::ProvisionSeal.check(&attributes), Ok(()));
+        assert!(KeyName::EinitToken.check(&attributes).is_err());
+    }
+}
@@ -73,27 +73,81 @@ macro_rules! testaso {
     };
 }
 
-//pub mod attestation_types;
+/// This macro emits a debug-level `tracing` event when the `tracing` feature
+/// is enabled and expands to nothing otherwise. It takes the same arguments
+/// as `tracing::debug!`.
+#[allow(unused_macros)]
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+#[cfg(all(feature = "crypto", unix))]
+pub mod agent;
+#[cfg(feature = "artifact")]
+pub mod artifact;
+pub mod attestation_types;
+#[cfg(feature = "std")]
+pub mod bundle;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod seal;
+#[cfg(feature = "soft-cmac")]
+pub mod sim;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub mod sysfs;
 
 mod attr;
+mod call;
+mod cpusvn;
+mod epc;
+mod exception;
+mod flags;
+mod hasher;
 mod isv;
+mod key;
 mod misc;
 mod page;
+mod paging;
+mod pck;
+mod pkcs1;
+mod reloc;
 mod secs;
 mod sig;
-
-#[cfg(feature = "crypto")]
-mod hasher;
+mod ssa;
+mod tcs;
 
 pub use attr::{Attributes, Features, Xfrm};
+pub use call::{Call, CallStatus};
+pub use cpusvn::CpuSvn;
+pub use epc::{
+    EpcMap, EpcSection, EpcStats, Footprint, InvalidTransition, Page, PageState, PermissionPlan,
+    PermissionStep, Thread,
+};
+pub use exception::{Exception, ExitInfo, ExitType, Signal};
+pub use flags::{Bit, BitIter, FlagBits};
+#[allow(deprecated)]
+pub use hasher::InvalidSize;
+pub use hasher::{Digest, Hasher, InvalidLoad};
 pub use isv::{ProductId, SecurityVersion};
+pub use key::{KeyName, KeyNotAllowed, KeyPolicy, KeyRequest};
 pub use misc::MiscSelect;
-pub use page::{Class, Permissions, SecInfo};
-pub use secs::Secs;
-pub use sig::{Author, Masked, Measurement, Parameters, Signature};
-
+pub use page::{Class, InvalidSecInfo, Permissions, SecInfo};
+pub use paging::{Pcmd, VersionArray};
+pub use pck::{Fmspc, InvalidHex, PceId, PceSvn};
+pub use pkcs1::InvalidSignature;
+pub use reloc::{relocate, InvalidRelocation};
+pub use secs::{InvalidRange, Secs};
 #[cfg(feature = "crypto")]
-pub use hasher::{Hasher, InvalidSize};
+pub use sig::{AsyncSigner, Blocking, Signer, SignerFuture};
+pub use sig::{
+    Author, Finding, InvalidDate, InvalidMask, InvalidParameters, Masked, Measurement, Mismatch,
+    Parameters, RsaNumber, Signature, SwDefined, Vendor,
+};
+pub use ssa::{Cet, Fxsave, Gpr, SsaLayout, Xsave};
+pub use tcs::{InvalidTcs, Tcs, TcsFlags};
 
 /// SGX ENCLU Leaf Instructions
 #[allow(missing_docs)]
@@ -112,6 +166,7 @@ pub mod leaf {
 mod crypto {
     use super::*;
 
+    use std::cell::Cell;
     use std::fs::File;
     use std::io::Read;
     use std::num::NonZeroU32;
@@ -133,6 +188,7 @@ mod crypto {
 
     const DATA: [u8; PAGE] = [123u8; PAGE];
     const PAGE: usize = 4096;
+    const OFFSET: u64 = PAGE as u64;
 
     fn load(path: &str) -> Vec<u8> {
         let mut file = File::open(path).unwrap();
@@ -149,7 +205,7 @@ mod crypto {
         rsa::Rsa::private_key_from_pem(&pem).unwrap()
     }
 
-    fn hash(input: &[(&[u8], SecInfo)]) -> Result<[u8; 32], InvalidSize> {
+    fn hash(input: &[(&[u8], SecInfo)]) -> Result<[u8; 32], InvalidLoad> {
         // Add the lengths of all the enclave segments to produce enclave size.
         let size = input.iter().fold(0, |c, x| c + x.0.len());
 
@@ -157,22 +213,207 @@ mod crypto {
         //   enclave size: the next power of two beyond our segments
         //      ssa pages: 1
         let ssa_pages = NonZeroU32::new(1).unwrap();
-        let mut hasher = Hasher::new(size.next_power_of_two(), ssa_pages, Parameters::default());
+        let size = size.next_power_of_two() as u64;
+        // The digest defaults to OpenSSL's, as before `Hasher` was generic.
+        let mut hasher: Hasher = Hasher::new(size, ssa_pages, Parameters::default());
 
         let mut off = 0;
         for i in input {
             hasher.load(i.0, off, i.1, true)?;
-            off += i.0.len();
+            off += i.0.len() as u64;
         }
 
         // Use default signature parameters
         Ok(hasher.finish().mrenclave())
     }
 
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn sha2() {
+        let mut hasher = Hasher::with_digest(
+            sha2::Sha256::default(),
+            OFFSET * 2,
+            NonZeroU32::new(1).unwrap(),
+            Parameters::default(),
+        );
+        hasher.load(&DATA, 0, SecInfo::tcs(), true).unwrap();
+        hasher
+            .load(&DATA, OFFSET, SecInfo::reg(Permissions::READ), true)
+            .unwrap();
+
+        let question = hash(&[
+            (&DATA, SecInfo::tcs()),
+            (&DATA, SecInfo::reg(Permissions::READ)),
+        ]);
+        assert_eq!(question, Ok(hasher.finish().mrenclave()));
+    }
+
+    #[test]
+    fn blocks() {
+        // A digest checking that it is only given whole SHA-256 blocks
+        struct Blocks<'a>(openssl::sha::Sha256, &'a Cell<usize>);
+
+        impl Digest for Blocks<'_> {
+            fn update(&mut self, data: &[u8]) {
+                assert_eq!(data.len() % 64, 0);
+                self.0.update(data);
+                self.1.set(self.1.get() + 1);
+            }
+
+            fn finish(self) -> [u8; 32] {
+                self.0.finish()
+            }
+        }
+
+        let calls = Cell::new(0);
+        let digest = Blocks(openssl::sha::Sha256::new(), &calls);
+        let ssa_pages = NonZeroU32::new(1).unwrap();
+        let mut hasher = Hasher::with_digest(digest, OFFSET, ssa_pages, Parameters::default());
+        hasher.load(&DATA, 0, SecInfo::tcs(), true).unwrap();
+
+        // ECREATE, EADD and 16 EEXTENDs of a header and a segment each
+        assert_eq!(calls.get(), 1 + 1 + 16 * 2);
+
+        let question = hash(&[(&DATA, SecInfo::tcs())]);
+        assert_eq!(question, Ok(hasher.finish().mrenclave()));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn usize_shims() {
+        let ssa_pages = NonZeroU32::new(1).unwrap();
+        let mut hasher = Hasher::new_usize(PAGE * 2, ssa_pages, Parameters::default());
+        hasher
+            .load_usize(&DATA, PAGE, SecInfo::tcs(), true)
+            .unwrap();
+
+        let mut expected = Hasher::new(OFFSET * 2, ssa_pages, Parameters::default());
+        expected.load(&DATA, OFFSET, SecInfo::tcs(), true).unwrap();
+        assert_eq!(hasher.finish().mrenclave(), expected.finish().mrenclave());
+
+        let mut hasher = Hasher::new_usize(PAGE, ssa_pages, Parameters::default());
+        let question: Result<(), InvalidSize> =
+            hasher.load_usize(&DATA, PAGE, SecInfo::tcs(), true);
+        assert_eq!(question, Err(InvalidLoad::Bounds));
+    }
+
+    #[test]
+    fn load_pages() {
+        const CODE: [u8; PAGE] = [0x90u8; PAGE];
+        let rx = SecInfo::reg(Permissions::READ | Permissions::EXECUTE);
+        let rw = SecInfo::reg(Permissions::READ | Permissions::WRITE);
+        let ssa_pages = NonZeroU32::new(1).unwrap();
+
+        let mut hasher = Hasher::new(OFFSET * 4, ssa_pages, Parameters::default());
+        hasher
+            .load_pages(&[
+                (0, &DATA, SecInfo::tcs(), true),
+                (OFFSET, &CODE, rx, true),
+                (OFFSET * 2, &DATA, rw, false),
+            ])
+            .unwrap();
+
+        let mut expected = Hasher::new(OFFSET * 4, ssa_pages, Parameters::default());
+        expected.load(&DATA, 0, SecInfo::tcs(), true).unwrap();
+        expected.load(&CODE, OFFSET, rx, true).unwrap();
+        expected.load(&DATA, OFFSET * 2, rw, false).unwrap();
+        assert_eq!(hasher.finish().mrenclave(), expected.finish().mrenclave());
+
+        let mut hasher = Hasher::new(OFFSET, ssa_pages, Parameters::default());
+        let question = hasher.load_pages(&[(0, &DATA, rw, true), (OFFSET, &DATA, rw, true)]);
+        assert_eq!(question, Err(InvalidLoad::Bounds));
+    }
+
+    #[test]
+    fn load_into() {
+        let ssa_pages = NonZeroU32::new(1).unwrap();
+        let rw = SecInfo::reg(Permissions::READ | Permissions::WRITE);
+
+        let mut dest = vec![0u8; PAGE * 2];
+        let mut hasher = Hasher::new(OFFSET * 2, ssa_pages, Parameters::default());
+        hasher
+            .load_into(&DATA, &mut dest[..PAGE], 0, rw, true)
+            .unwrap();
+        assert_eq!(&dest[..PAGE], &DATA[..]);
+        assert_eq!(&dest[PAGE..], &[0u8; PAGE][..]);
+
+        let mut expected = Hasher::new(OFFSET * 2, ssa_pages, Parameters::default());
+        expected.load(&DATA, 0, rw, true).unwrap();
+        assert_eq!(hasher.finish().mrenclave(), expected.finish().mrenclave());
+
+        let mut hasher = Hasher::new(OFFSET, ssa_pages, Parameters::default());
+        let result = hasher.load_into(&DATA, &mut dest, 0, rw, true);
+        assert_eq!(result, Err(InvalidLoad::Size));
+        let result = hasher.load_into(&DATA, &mut dest[..PAGE], OFFSET, rw, true);
+        assert_eq!(result, Err(InvalidLoad::Bounds));
+    }
+
+    #[test]
+    fn load_from() {
+        const LONG: [u8; PAGE * 2] = [123u8; PAGE * 2];
+
+        let mut hasher = Hasher::new(
+            OFFSET * 4,
+            NonZeroU32::new(1).unwrap(),
+            Parameters::default(),
+        );
+        hasher.load(&DATA, 0, SecInfo::tcs(), true).unwrap();
+        let len = hasher
+            .load_from(&LONG[..], OFFSET, SecInfo::reg(Permissions::READ), true)
+            .unwrap();
+        assert_eq!(len, LONG.len() as u64);
+
+        let question = hash(&[
+            (&DATA, SecInfo::tcs()),
+            (&LONG, SecInfo::reg(Permissions::READ)),
+        ]);
+        assert_eq!(question, Ok(hasher.finish().mrenclave()));
+
+        let mut hasher = Hasher::new(OFFSET, NonZeroU32::new(1).unwrap(), Parameters::default());
+        assert!(hasher
+            .load_from(&[1u8, 2, 3, 4][..], 0, SecInfo::tcs(), true)
+            .is_err());
+    }
+
     #[test]
     fn badsize() {
         let question = hash(&[(&[1u8, 2, 3, 4], SecInfo::tcs())]);
-        assert_eq!(question, Err(InvalidSize));
+        assert_eq!(question, Err(InvalidLoad::Size));
+    }
+
+    #[test]
+    fn badrange() {
+        let ssa_pages = NonZeroU32::new(1).unwrap();
+        let params = Parameters {
+            attr: Attributes::new(Features::MODE64BIT, Xfrm::X87 | Xfrm::SSE).into(),
+            ..Default::default()
+        };
+        assert!(Hasher::try_new(OFFSET * 2, ssa_pages, params).is_ok());
+
+        let question = Hasher::try_new(OFFSET * 3, ssa_pages, params);
+        assert_eq!(question.err(), Some(InvalidRange::Size));
+
+        let question = Hasher::try_new(OFFSET * 2, ssa_pages, Parameters::default());
+        assert_eq!(
+            question.err(),
+            Some(InvalidRange::Parameters(InvalidParameters::XfrmLegacy))
+        );
+    }
+
+    #[test]
+    fn badoffset() {
+        let ssa_pages = NonZeroU32::new(1).unwrap();
+        let mut hasher = Hasher::new(OFFSET * 2, ssa_pages, Parameters::default());
+        let question = hasher.load(&DATA, 1, SecInfo::tcs(), true);
+        assert_eq!(question, Err(InvalidLoad::Offset));
+
+        let question = hasher.load(&DATA, OFFSET * 2, SecInfo::tcs(), true);
+        assert_eq!(question, Err(InvalidLoad::Bounds));
+
+        let question = hasher.load(&DATA, u64::MAX & !(OFFSET - 1), SecInfo::tcs(), true);
+        assert_eq!(question, Err(InvalidLoad::Bounds));
+
+        assert_eq!(hasher.load(&DATA, OFFSET, SecInfo::tcs(), true), Ok(()));
     }
 
     #[test]
@@ -285,4 +526,83 @@ mod crypto {
             "failed to produce correct signature"
         );
     }
+
+    #[test]
+    fn signer() {
+        struct Broken(rsa::Rsa<pkey::Private>);
+
+        impl Signer for Broken {
+            fn modulus(&self) -> std::io::Result<Vec<u8>> {
+                Signer::modulus(&self.0)
+            }
+
+            fn sign(&self, message: &[u8]) -> std::io::Result<Vec<u8>> {
+                let mut signature = Signer::sign(&self.0, message)?;
+                signature[0] ^= 1;
+                Ok(signature)
+            }
+        }
+
+        let sig = Signature::read_from(File::open("tests/encl.ss").unwrap()).unwrap();
+        let key = loadkey("tests/encl.pem");
+
+        let measurement = sig.measurement();
+        let signed = measurement.sign_with(sig.author(), &key).unwrap();
+        assert_eq!(sig, signed);
+
+        let err = measurement
+            .sign_with(sig.author(), &Broken(key))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn verify() {
+        let sig = Signature::read_from(File::open("tests/encl.ss").unwrap()).unwrap();
+        assert_eq!(sig.verify(openssl::sha::Sha256::new()), Ok(()));
+
+        #[cfg(feature = "sha2")]
+        assert_eq!(sig.verify(sha2::Sha256::default()), Ok(()));
+
+        let mut bytes = sig.as_bytes().to_vec();
+        bytes[900] ^= 1; // measurement
+        let bad = Signature::read_from(&bytes[..]).unwrap();
+        let err = bad.verify(openssl::sha::Sha256::new());
+        assert_eq!(err, Err(InvalidSignature::Mismatch));
+
+        let mut bytes = sig.as_bytes().to_vec();
+        bytes[1040] ^= 1; // q1
+        let bad = Signature::read_from(&bytes[..]).unwrap();
+        assert_eq!(
+            bad.verify(openssl::sha::Sha256::new()),
+            Err(InvalidSignature::Q1)
+        );
+
+        let mut bytes = sig.as_bytes().to_vec();
+        bytes[1424] ^= 1; // q2
+        let bad = Signature::read_from(&bytes[..]).unwrap();
+        assert_eq!(
+            bad.verify(openssl::sha::Sha256::new()),
+            Err(InvalidSignature::Q2)
+        );
+
+        let mut bytes = sig.as_bytes().to_vec();
+        bytes[512] = 65; // exponent
+        let bad = Signature::read_from(&bytes[..]).unwrap();
+        let err = bad.verify(openssl::sha::Sha256::new());
+        assert_eq!(err, Err(InvalidSignature::Exponent));
+    }
+
+    #[test]
+    fn async_signer() {
+        let sig = Signature::read_from(File::open("tests/encl.ss").unwrap()).unwrap();
+        let key = Blocking(loadkey("tests/encl.pem"));
+
+        let measurement = sig.measurement();
+        let signed = sig::block_on(measurement.sign_async(sig.author(), &key)).unwrap();
+        assert_eq!(sig, signed);
+
+        let signed = measurement.sign_with(sig.author(), &key).unwrap();
+        assert_eq!(sig, signed);
+    }
 }
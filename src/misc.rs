@@ -12,3 +12,10 @@ bitflags::bitflags! {
         const EXINFO = 1 << 0;
     }
 }
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for MiscSelect {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::from_bits_truncate(u.arbitrary()?))
+    }
+}
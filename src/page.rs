@@ -4,6 +4,8 @@
 //! These structs specify metadata about en enclave page.
 
 use bitflags::bitflags;
+use core::fmt;
+use core::str::FromStr;
 
 bitflags! {
     /// The `Permissions` of a page
@@ -23,7 +25,7 @@ bitflags! {
         /// The page is in the MODIFIED state
         const MODIFIED = 1 << 4;
 
-        /// A permission restriction operation on the page is in progress
+        /// A permission restriction operation on the page is in progress (PR)
         const RESTRICTED = 1 << 5;
     }
 }
@@ -94,6 +96,299 @@ impl SecInfo {
             reserved: [0; 31],
         }
     }
+
+    /// Creates a SecInfo for EACCEPT or EACCEPTCOPY of a regular page added
+    /// by EAUG, which is in the PENDING state.
+    pub const fn pending(perms: Permissions) -> Self {
+        Self {
+            perms: Permissions::from_bits_truncate(perms.bits() | Permissions::PENDING.bits()),
+            class: Class::Reg,
+            reserved: [0; 31],
+        }
+    }
+
+    /// Creates a SecInfo for EACCEPT of a regular page whose permissions
+    /// were restricted to `perms` by EMODPR.
+    pub const fn restricted(perms: Permissions) -> Self {
+        Self {
+            perms: Permissions::from_bits_truncate(perms.bits() | Permissions::RESTRICTED.bits()),
+            class: Class::Reg,
+            reserved: [0; 31],
+        }
+    }
+
+    /// Creates a SecInfo for EACCEPT of a page whose type was changed to
+    /// `class` (`Class::Tcs` or `Class::Trim`) by EMODT, which is in the
+    /// MODIFIED state.
+    pub const fn modified(class: Class) -> Self {
+        Self {
+            perms: Permissions::MODIFIED,
+            class,
+            reserved: [0; 31],
+        }
+    }
+
+    /// Creates a SecInfo for EMODPE, extending the permissions of a regular
+    /// page with `perms`.
+    pub const fn extended(perms: Permissions) -> Self {
+        Self::reg(perms)
+    }
+}
+
+/// The string is not a valid `Permissions`, `Class` or `SecInfo`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidSecInfo;
+
+impl fmt::Display for InvalidSecInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid page permissions or type")
+    }
+}
+
+// The characters of the permissions, in the order they are formatted
+const PERMISSIONS: [(Permissions, char); 6] = [
+    (Permissions::READ, 'r'),
+    (Permissions::WRITE, 'w'),
+    (Permissions::EXECUTE, 'x'),
+    (Permissions::PENDING, 'P'),
+    (Permissions::MODIFIED, 'M'),
+    (Permissions::RESTRICTED, 'R'),
+];
+
+/// Formatted like `ls`, e.g. `r-x`, followed by `P`, `M` and `R` if the
+/// page is PENDING, MODIFIED or has a permission restriction in progress
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (perm, c)) in PERMISSIONS.iter().enumerate() {
+            match (self.contains(*perm), i < 3) {
+                (true, _) => write!(f, "{}", c)?,
+                (false, true) => write!(f, "-")?,
+                (false, false) => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Permissions {
+    type Err = InvalidSecInfo;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let mut perms = Permissions::empty();
+
+        for (perm, c) in PERMISSIONS[..3].iter() {
+            match chars.next() {
+                Some(x) if x == *c => perms |= *perm,
+                Some('-') => (),
+                _ => return Err(InvalidSecInfo),
+            }
+        }
+
+        // The states may follow in order, each at most once
+        let mut states = PERMISSIONS[3..].iter();
+        for x in chars {
+            match states.find(|(_, c)| *c == x) {
+                Some((perm, _)) => perms |= *perm,
+                None => return Err(InvalidSecInfo),
+            }
+        }
+
+        Ok(perms)
+    }
+}
+
+/// Formatted in lowercase, e.g. `reg`
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Class::Secs => "secs",
+            Class::Tcs => "tcs",
+            Class::Reg => "reg",
+            Class::Va => "va",
+            Class::Trim => "trim",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Class {
+    type Err = InvalidSecInfo;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "secs" => Ok(Class::Secs),
+            "tcs" => Ok(Class::Tcs),
+            "reg" => Ok(Class::Reg),
+            "va" => Ok(Class::Va),
+            "trim" => Ok(Class::Trim),
+            _ => Err(InvalidSecInfo),
+        }
+    }
+}
+
+/// Formatted as the class and the permissions, e.g. `reg:r-x` or `tcs:---`
+impl fmt::Display for SecInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.class, self.perms)
+    }
+}
+
+impl FromStr for SecInfo {
+    type Err = InvalidSecInfo;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let class = parts.next().ok_or(InvalidSecInfo)?.parse()?;
+        let perms = parts.next().ok_or(InvalidSecInfo)?.parse()?;
+
+        Ok(Self {
+            perms,
+            class,
+            reserved: [0; 31],
+        })
+    }
+}
+
+/// Implements serde support using the string representation.
+#[cfg(feature = "serde")]
+macro_rules! str_serde {
+    ($($name:ident)+) => {
+        $(
+            impl serde::Serialize for $name {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for $name {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    struct Visitor;
+
+                    impl<'de> serde::de::Visitor<'de> for Visitor {
+                        type Value = $name;
+
+                        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                            write!(f, "a {} string", stringify!($name))
+                        }
+
+                        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                            v.parse()
+                                .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+                        }
+                    }
+
+                    deserializer.deserialize_str(Visitor)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "serde")]
+str_serde! { Permissions Class SecInfo }
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Permissions {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::from_bits_truncate(u.arbitrary()?))
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Class {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(*u.choose(&[Class::Secs, Class::Tcs, Class::Reg, Class::Va, Class::Trim])?)
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for SecInfo {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            perms: u.arbitrary()?,
+            class: u.arbitrary()?,
+            reserved: [0; 31],
+        })
+    }
+}
+
+#[cfg(test)]
+mod edmm {
+    use super::*;
+
+    #[test]
+    fn secinfo() {
+        let rw = Permissions::READ | Permissions::WRITE;
+
+        let pending = SecInfo::pending(rw);
+        assert_eq!(pending.perms, rw | Permissions::PENDING);
+        assert_eq!(pending.class, Class::Reg);
+
+        let restricted = SecInfo::restricted(Permissions::READ);
+        assert_eq!(
+            restricted.perms,
+            Permissions::READ | Permissions::RESTRICTED
+        );
+
+        let modified = SecInfo::modified(Class::Trim);
+        assert_eq!(modified.perms, Permissions::MODIFIED);
+        assert_eq!(modified.class, Class::Trim);
+    }
+}
+
+#[cfg(test)]
+mod string {
+    use super::*;
+
+    #[test]
+    fn permissions() {
+        let rx = Permissions::READ | Permissions::EXECUTE;
+        assert_eq!(format!("{}", rx), "r-x");
+        assert_eq!(format!("{}", Permissions::empty()), "---");
+        assert_eq!(
+            format!(
+                "{}",
+                SecInfo::pending(Permissions::READ | Permissions::WRITE).perms
+            ),
+            "rw-P"
+        );
+
+        assert_eq!("r-x".parse(), Ok(rx));
+        assert_eq!(
+            "r--R".parse(),
+            Ok(Permissions::READ | Permissions::RESTRICTED)
+        );
+        assert_eq!(
+            "---PM".parse(),
+            Ok(Permissions::PENDING | Permissions::MODIFIED)
+        );
+
+        for bad in ["", "rx", "xwr", "r-x-", "r-xRP", "r-xPP", "rwxq"].iter() {
+            assert_eq!(bad.parse::<Permissions>(), Err(InvalidSecInfo), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn secinfo() {
+        let secinfo = SecInfo::reg(Permissions::READ | Permissions::EXECUTE);
+        assert_eq!(format!("{}", secinfo), "reg:r-x");
+        assert_eq!(format!("{}", SecInfo::tcs()), "tcs:---");
+
+        let parsed: SecInfo = "reg:r-x".parse().unwrap();
+        assert_eq!(parsed.class, secinfo.class);
+        assert_eq!(parsed.perms, secinfo.perms);
+
+        let parsed: SecInfo = "trim:---M".parse().unwrap();
+        assert_eq!(parsed.class, Class::Trim);
+        assert_eq!(parsed.perms, Permissions::MODIFIED);
+
+        assert!("reg".parse::<SecInfo>().is_err());
+        assert!("page:r-x".parse::<SecInfo>().is_err());
+        assert!("reg:r-x:".parse::<SecInfo>().is_err());
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! EPC Paging (Sections 38.19 and 38.20)
+//! EWB evicts an EPC page, encrypting it and recording its version in a slot
+//! of a Version Array page and its metadata in a PCMD. ELDB/ELDU load the page
+//! back, checking both.
+
+use crate::SecInfo;
+
+/// A Version Array (VA) page
+///
+/// Each of its 512 slots holds the version of an evicted page, or 0 if free.
+///
+/// Section 38.19
+#[derive(Copy, Clone)]
+#[repr(C, align(4096))]
+pub struct VersionArray {
+    slots: [u64; Self::SLOTS],
+}
+
+impl Default for VersionArray {
+    fn default() -> Self {
+        Self {
+            slots: [0; Self::SLOTS],
+        }
+    }
+}
+
+impl core::fmt::Debug for VersionArray {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map()
+            .entries(self.slots.iter().enumerate().filter(|(_, v)| **v != 0))
+            .finish()
+    }
+}
+
+impl VersionArray {
+    /// The number of slots in a VA page
+    pub const SLOTS: usize = 512;
+
+    /// Returns the version in `slot`, if it exists.
+    pub fn get(&self, slot: usize) -> Option<u64> {
+        self.slots.get(slot).copied()
+    }
+
+    /// Sets the version in `slot`, if it exists, as EWB does.
+    pub fn set(&mut self, slot: usize, version: u64) -> Option<()> {
+        *self.slots.get_mut(slot)? = version;
+        Some(())
+    }
+
+    /// Returns the first free slot, if any.
+    pub fn free_slot(&self) -> Option<usize> {
+        self.slots.iter().position(|v| *v == 0)
+    }
+
+    /// Returns the offset of `slot` within the page, as passed to EWB and
+    /// ELDB/ELDU.
+    pub fn offset(slot: usize) -> Option<usize> {
+        if slot < Self::SLOTS {
+            Some(slot * core::mem::size_of::<u64>())
+        } else {
+            None
+        }
+    }
+}
+
+/// Paging Crypto MetaData (PCMD) of an evicted page
+///
+/// Section 38.20
+#[derive(Copy, Clone, Debug)]
+#[repr(C, align(128))]
+pub struct Pcmd {
+    /// The security information of the evicted page
+    pub secinfo: SecInfo,
+
+    /// The ID of the enclave the page belongs to
+    pub enclaveid: u64,
+
+    reserved: [u8; 40],
+
+    /// The MAC over the page, its metadata and its version
+    pub mac: [u8; 16],
+}
+
+impl Pcmd {
+    /// Creates a PCMD as written by EWB.
+    pub const fn new(secinfo: SecInfo, enclaveid: u64, mac: [u8; 16]) -> Self {
+        Self {
+            secinfo,
+            enclaveid,
+            reserved: [0; 40],
+            mac,
+        }
+    }
+}
+
+#[cfg(test)]
+testaso! {
+    struct VersionArray: 4096, 4096 => {
+        slots: 0
+    }
+
+    struct Pcmd: 128, 128 => {
+        secinfo: 0,
+        enclaveid: 64,
+        reserved: 72,
+        mac: 112
+    }
+}
+
+#[cfg(test)]
+mod va {
+    use super::VersionArray;
+
+    #[test]
+    fn slots() {
+        let mut va = VersionArray::default();
+        assert_eq!(va.free_slot(), Some(0));
+
+        va.set(0, 7).unwrap();
+        assert_eq!(va.get(0), Some(7));
+        assert_eq!(va.free_slot(), Some(1));
+
+        assert_eq!(va.set(VersionArray::SLOTS, 1), None);
+        assert_eq!(va.get(VersionArray::SLOTS), None);
+        assert_eq!(VersionArray::offset(511), Some(4088));
+        assert_eq!(VersionArray::offset(512), None);
+    }
+}
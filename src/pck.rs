@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! PCK Certificate Identifiers
+//! The FMSPC, PCEID and PCESVN values carried in the SGX extension of PCK
+//! certificates (Intel SGX PCK Certificate and CRL Profile Specification)
+//! and used to look up platform collateral.
+//!
+//! All of these types are formatted and parsed as hex strings, in the same
+//! byte order used by the Intel Provisioning Certification Service.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// The string is not a valid hex encoding of the expected length
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InvalidHex;
+
+fn encode(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for b in bytes.iter() {
+        write!(f, "{:02x}", b)?;
+    }
+
+    Ok(())
+}
+
+fn decode(s: &str, out: &mut [u8]) -> Result<(), InvalidHex> {
+    let s = s.as_bytes();
+    if s.len() != out.len() * 2 {
+        return Err(InvalidHex);
+    }
+
+    for (o, pair) in out.iter_mut().zip(s.chunks(2)) {
+        let hi = (pair[0] as char).to_digit(16).ok_or(InvalidHex)?;
+        let lo = (pair[1] as char).to_digit(16).ok_or(InvalidHex)?;
+        *o = (hi << 4 | lo) as u8;
+    }
+
+    Ok(())
+}
+
+/// Family-Model-Stepping-Platform-CustomSKU (FMSPC) of a platform.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fmspc([u8; 6]);
+
+/// ID of the Provisioning Certification Enclave (PCEID).
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PceId(u16);
+
+/// Security version number of the Provisioning Certification Enclave (PCESVN).
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PceSvn(u16);
+
+impl Fmspc {
+    /// Creates a new Fmspc based on the value provided.
+    pub const fn new(fmspc: [u8; 6]) -> Self {
+        Self(fmspc)
+    }
+
+    /// Returns inner value as a byte array
+    pub const fn inner(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl PceId {
+    /// Creates a new PceId based on the value provided.
+    pub const fn new(pce_id: u16) -> Self {
+        Self(pce_id)
+    }
+
+    /// Returns inner value as u16
+    pub const fn inner(&self) -> u16 {
+        self.0
+    }
+}
+
+impl PceSvn {
+    /// Creates a new PceSvn based on the value provided.
+    pub const fn new(svn: u16) -> Self {
+        Self(svn)
+    }
+
+    /// Returns inner value as u16
+    pub const fn inner(&self) -> u16 {
+        self.0
+    }
+}
+
+impl fmt::Display for Fmspc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        encode(f, &self.0)
+    }
+}
+
+impl FromStr for Fmspc {
+    type Err = InvalidHex;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fmspc = [0u8; 6];
+        decode(s, &mut fmspc)?;
+        Ok(Self(fmspc))
+    }
+}
+
+/// Formatted as the little-endian bytes in hex, e.g. `"0000"`
+impl fmt::Display for PceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        encode(f, &self.0.to_le_bytes())
+    }
+}
+
+impl FromStr for PceId {
+    type Err = InvalidHex;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut le = [0u8; 2];
+        decode(s, &mut le)?;
+        Ok(Self(u16::from_le_bytes(le)))
+    }
+}
+
+/// Formatted as the little-endian bytes in hex, e.g. `"0a00"` for 10
+impl fmt::Display for PceSvn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        encode(f, &self.0.to_le_bytes())
+    }
+}
+
+impl FromStr for PceSvn {
+    type Err = InvalidHex;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut le = [0u8; 2];
+        decode(s, &mut le)?;
+        Ok(Self(u16::from_le_bytes(le)))
+    }
+}
+
+/// Implements serde support using the hex string representation.
+#[cfg(feature = "serde")]
+macro_rules! hex_serde {
+    ($($name:ident)+) => {
+        $(
+            impl serde::Serialize for $name {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for $name {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    struct Visitor;
+
+                    impl<'de> serde::de::Visitor<'de> for Visitor {
+                        type Value = $name;
+
+                        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                            write!(f, "a hex encoded {}", stringify!($name))
+                        }
+
+                        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                            v.parse()
+                                .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+                        }
+                    }
+
+                    deserializer.deserialize_str(Visitor)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "serde")]
+hex_serde! { Fmspc PceId PceSvn }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fmspc() {
+        let fmspc: Fmspc = "00906ea10000".parse().unwrap();
+        assert_eq!(fmspc.inner(), [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00]);
+        assert_eq!(fmspc, "00906EA10000".parse().unwrap());
+        assert_eq!(format!("{}", fmspc), "00906ea10000");
+
+        assert_eq!("00906ea100".parse::<Fmspc>(), Err(InvalidHex));
+        assert_eq!("00906ea1000g".parse::<Fmspc>(), Err(InvalidHex));
+    }
+
+    #[test]
+    fn pce() {
+        let svn: PceSvn = "0a00".parse().unwrap();
+        assert_eq!(svn, PceSvn::new(10));
+        assert_eq!(format!("{}", svn), "0a00");
+
+        let id: PceId = "0000".parse().unwrap();
+        assert_eq!(id, PceId::new(0));
+        assert_eq!(format!("{}", PceId::new(0x1234)), "3412");
+
+        assert_eq!("0a0".parse::<PceSvn>(), Err(InvalidHex));
+        assert_eq!("+a00".parse::<PceSvn>(), Err(InvalidHex));
+    }
+}
@@ -0,0 +1,282 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! RSA Signature Verification (Section 40.4)
+//! SIGSTRUCT carries Q1 and Q2 so that EINIT can compute `signature^3 mod
+//! modulus` with multiplications and subtractions only. Verifying the same
+//! way needs no big number library, so SIGSTRUCTs can be verified without
+//! OpenSSL.
+
+use crate::RsaNumber;
+
+const LIMBS: usize = RsaNumber::SIZE / 4;
+
+/// The DER encoding of the SHA-256 `DigestInfo` prefix (RFC 8017, 9.2)
+const DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// The SIGSTRUCT signature is invalid, see `Signature::verify()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidSignature {
+    /// The public exponent is not 3
+    Exponent,
+
+    /// The signature is not less than the modulus
+    Range,
+
+    /// Q1 is not `floor(signature^2 / modulus)`
+    Q1,
+
+    /// Q2 is not `floor((signature^3 - q1 * signature * modulus) / modulus)`
+    Q2,
+
+    /// The signed message is not a PKCS#1 v1.5 encoding: it does not start
+    /// with `00 01`, followed by `ff` bytes up to a `00` separator right
+    /// before the `DigestInfo`
+    Padding,
+
+    /// The `DigestInfo` is not exactly the DER encoding for SHA-256
+    DigestInfo,
+
+    /// The signature does not sign the SIGSTRUCT contents
+    Mismatch,
+}
+
+impl core::fmt::Display for InvalidSignature {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidSignature::Exponent => write!(f, "public exponent is not 3"),
+            InvalidSignature::Range => write!(f, "signature is not less than the modulus"),
+            InvalidSignature::Q1 => write!(f, "Q1 is incorrect"),
+            InvalidSignature::Q2 => write!(f, "Q2 is incorrect"),
+            InvalidSignature::Padding => write!(f, "invalid PKCS#1 v1.5 padding"),
+            InvalidSignature::DigestInfo => write!(f, "invalid PKCS#1 v1.5 DigestInfo"),
+            InvalidSignature::Mismatch => write!(f, "signature does not match"),
+        }
+    }
+}
+
+/// Little-endian 32-bit limbs of a number
+fn limbs(number: &RsaNumber) -> [u32; LIMBS] {
+    let bytes = number.to_le_bytes();
+    let mut limbs = [0u32; LIMBS];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(4)) {
+        *limb = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+
+    limbs
+}
+
+/// Returns `a * b`
+fn mul(a: &[u32; LIMBS], b: &[u32; LIMBS]) -> [u32; LIMBS * 2] {
+    let mut out = [0u32; LIMBS * 2];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let t = u64::from(x) * u64::from(y) + u64::from(out[i + j]) + carry;
+            out[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        out[i + LIMBS] = carry as u32;
+    }
+
+    out
+}
+
+/// Returns `a - b`, or `None` if `b > a`
+fn sub(a: &[u32; LIMBS * 2], b: &[u32; LIMBS * 2]) -> Option<[u32; LIMBS * 2]> {
+    let mut out = [0u32; LIMBS * 2];
+    let mut borrow = 0u64;
+    for (o, (&x, &y)) in out.iter_mut().zip(a.iter().zip(b.iter())) {
+        let t = u64::from(x).wrapping_sub(u64::from(y)).wrapping_sub(borrow);
+        *o = t as u32;
+        borrow = t >> 63;
+    }
+
+    match borrow {
+        0 => Some(out),
+        _ => None,
+    }
+}
+
+/// Returns `a < b`
+fn less(a: &[u32], b: &[u32; LIMBS]) -> bool {
+    if a.iter().skip(LIMBS).any(|&x| x != 0) {
+        return false;
+    }
+
+    for (x, y) in a[..LIMBS].iter().rev().zip(b.iter().rev()) {
+        if x != y {
+            return x < y;
+        }
+    }
+
+    false
+}
+
+/// Returns the little-endian limbs of `a mod m`, checking that `quotient`
+/// is `floor(a / m)`.
+fn reduce(a: &[u32; LIMBS * 2], quotient: &[u32; LIMBS], m: &[u32; LIMBS]) -> Option<[u32; LIMBS]> {
+    let r = sub(a, &mul(quotient, m))?;
+    if !less(&r, m) {
+        return None;
+    }
+
+    let mut out = [0u32; LIMBS];
+    out.copy_from_slice(&r[..LIMBS]);
+    Some(out)
+}
+
+/// The PKCS#1 v1.5 encoding (EMSA-PKCS1-v1_5) of a SHA-256 `hash`, as
+/// big-endian bytes of the length of the modulus
+fn encode(hash: &[u8; 32]) -> [u8; RsaNumber::SIZE] {
+    let mut em = [0xffu8; RsaNumber::SIZE];
+    let t = RsaNumber::SIZE - DIGEST_INFO.len() - hash.len();
+    em[0] = 0x00;
+    em[1] = 0x01;
+    em[t - 1] = 0x00;
+    em[t..t + DIGEST_INFO.len()].copy_from_slice(&DIGEST_INFO);
+    em[t + DIGEST_INFO.len()..].copy_from_slice(hash);
+    em
+}
+
+/// Decodes a PKCS#1 v1.5 encoded message (EMSA-PKCS1-v1_5), returning the
+/// SHA-256 hash it contains.
+///
+/// EINIT compares the whole encoding, so every byte is checked here as well:
+/// any other padding length, a `DigestInfo` without the NULL parameters or
+/// trailing data is rejected, even where some RSA libraries accept it.
+fn decode(em: &[u8; RsaNumber::SIZE]) -> Result<&[u8], InvalidSignature> {
+    let t = RsaNumber::SIZE - DIGEST_INFO.len() - 32;
+    let (padding, rest) = em.split_at(t);
+    let (info, hash) = rest.split_at(DIGEST_INFO.len());
+
+    match padding {
+        [0x00, 0x01, ff @ .., 0x00] if ff.iter().all(|&b| b == 0xff) => (),
+        _ => return Err(InvalidSignature::Padding),
+    }
+
+    if info != DIGEST_INFO {
+        return Err(InvalidSignature::DigestInfo);
+    }
+
+    Ok(hash)
+}
+
+/// Verifies that `signature` is the RSA signature (with exponent 3) of the
+/// SHA-256 `hash` under `modulus`, using `q1` and `q2` like EINIT.
+pub(crate) fn verify(
+    modulus: &RsaNumber,
+    signature: &RsaNumber,
+    q1: &RsaNumber,
+    q2: &RsaNumber,
+    hash: &[u8; 32],
+) -> Result<(), InvalidSignature> {
+    let n = limbs(modulus);
+    let s = limbs(signature);
+
+    if !less(&s, &n) {
+        return Err(InvalidSignature::Range);
+    }
+
+    // signature^2 mod modulus
+    let r1 = reduce(&mul(&s, &s), &limbs(q1), &n).ok_or(InvalidSignature::Q1)?;
+
+    // signature^3 mod modulus
+    let r2 = reduce(&mul(&r1, &s), &limbs(q2), &n).ok_or(InvalidSignature::Q2)?;
+
+    let mut em = [0u8; RsaNumber::SIZE];
+    for (chunk, limb) in em.rchunks_mut(4).zip(r2.iter()) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+
+    if decode(&em)? != hash {
+        return Err(InvalidSignature::Mismatch);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::convert::TryFrom;
+
+    fn number(value: u64) -> RsaNumber {
+        let mut le = [0u8; RsaNumber::SIZE];
+        le[..8].copy_from_slice(&value.to_le_bytes());
+        RsaNumber::try_from(&le[..]).unwrap()
+    }
+
+    #[test]
+    fn reduce() {
+        // 1000^2 = 3 * 333331 + 7
+        let n = limbs(&number(333331));
+        let s = limbs(&number(1000));
+        let square = mul(&s, &s);
+        assert_eq!(
+            super::reduce(&square, &limbs(&number(3)), &n).unwrap()[0],
+            7
+        );
+        assert_eq!(super::reduce(&square, &limbs(&number(2)), &n), None);
+        assert_eq!(super::reduce(&square, &limbs(&number(4)), &n), None);
+    }
+
+    #[test]
+    fn encoding() {
+        let em = encode(&[0xaa; 32]);
+        assert_eq!(em[..3], [0x00, 0x01, 0xff]);
+        assert_eq!(em[RsaNumber::SIZE - 52..RsaNumber::SIZE - 51], [0x00]);
+        assert_eq!(em[RsaNumber::SIZE - 51..RsaNumber::SIZE - 32], DIGEST_INFO);
+        assert_eq!(em[RsaNumber::SIZE - 32..], [0xaa; 32]);
+        assert_eq!(decode(&em), Ok(&[0xaa; 32][..]));
+    }
+
+    #[test]
+    fn malformed() {
+        let em = encode(&[0xaa; 32]);
+        let t = RsaNumber::SIZE - DIGEST_INFO.len() - 32;
+
+        let check = |index: usize, value: u8, err: InvalidSignature| {
+            let mut bad = em;
+            bad[index] = value;
+            assert_eq!(decode(&bad), Err(err), "byte {}", index);
+        };
+
+        // Block type 0 and 2, or no leading zero
+        check(0, 0x01, InvalidSignature::Padding);
+        check(1, 0x00, InvalidSignature::Padding);
+        check(1, 0x02, InvalidSignature::Padding);
+
+        // Padding bytes other than 0xff
+        check(2, 0x00, InvalidSignature::Padding);
+        check(t - 2, 0xfe, InvalidSignature::Padding);
+
+        // Missing separator
+        check(t - 1, 0xff, InvalidSignature::Padding);
+
+        // A different hash algorithm (SHA-384) and a wrong length
+        check(t + 14, 0x02, InvalidSignature::DigestInfo);
+        check(t + 18, 0x30, InvalidSignature::DigestInfo);
+
+        // DigestInfo without the NULL parameters, i.e. one more padding byte
+        let mut bad = [0xffu8; RsaNumber::SIZE];
+        bad[..2].copy_from_slice(&[0x00, 0x01]);
+        bad[t + 1] = 0x00;
+        bad[t + 2..t + 6].copy_from_slice(&[0x30, 0x2f, 0x30, 0x0b]);
+        bad[t + 6..t + 17].copy_from_slice(&DIGEST_INFO[4..15]);
+        bad[t + 17..t + 19].copy_from_slice(&[0x04, 0x20]);
+        bad[t + 19..].copy_from_slice(&[0xaa; 32]);
+        assert_eq!(decode(&bad), Err(InvalidSignature::Padding));
+
+        // Trailing data after the hash, i.e. one less padding byte
+        let mut bad = [0xffu8; RsaNumber::SIZE];
+        bad[..2].copy_from_slice(&[0x00, 0x01]);
+        bad[t - 2] = 0x00;
+        bad[t - 1..t + 18].copy_from_slice(&DIGEST_INFO);
+        bad[t + 18..RsaNumber::SIZE - 1].copy_from_slice(&[0xaa; 32]);
+        bad[RsaNumber::SIZE - 1] = 0x55;
+        assert_eq!(decode(&bad), Err(InvalidSignature::Padding));
+    }
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Relocations
+//! Static-PIE enclaves are linked at address zero and carry RELA relocations
+//! that must be applied against the enclave base. Because the pages are
+//! measured after they are loaded, the relocations have to be applied to the
+//! image before hashing it, or the measurement never matches what a correct
+//! loader maps.
+
+/// The size of an `Elf64_Rela` entry
+const RELA: usize = 24;
+
+/// No relocation
+const R_X86_64_NONE: u32 = 0;
+
+/// Adjust by the base address: `B + A`
+const R_X86_64_RELATIVE: u32 = 8;
+
+/// A relocation that cannot be applied, see `relocate()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidRelocation {
+    /// The relocation table is not a whole number of `Elf64_Rela` entries
+    Length,
+
+    /// The relocation type is not supported
+    Type(u32),
+
+    /// The relocation targets bytes outside of the image
+    Offset(u64),
+}
+
+impl core::fmt::Display for InvalidRelocation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidRelocation::Length => write!(f, "truncated relocation table"),
+            InvalidRelocation::Type(t) => write!(f, "unsupported relocation type {}", t),
+            InvalidRelocation::Offset(o) => write!(f, "relocation at {:#x} is out of bounds", o),
+        }
+    }
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    let mut le = [0u8; 8];
+    le.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(le)
+}
+
+/// Applies the `Elf64_Rela` entries in `rela` (the contents of `.rela.dyn`)
+/// to `image`, which holds the enclave memory starting at offset zero, as if
+/// the enclave were loaded at `base`.
+///
+/// Only `R_X86_64_RELATIVE` (and `R_X86_64_NONE`) relocations are supported,
+/// which is all a static-PIE enclave needs. The image is not modified unless
+/// every relocation is valid.
+pub fn relocate(image: &mut [u8], rela: &[u8], base: u64) -> Result<(), InvalidRelocation> {
+    if rela.len() % RELA != 0 {
+        return Err(InvalidRelocation::Length);
+    }
+
+    for entry in rela.chunks(RELA) {
+        let offset = u64_at(entry, 0);
+        match u64_at(entry, 8) as u32 {
+            R_X86_64_NONE => (),
+            R_X86_64_RELATIVE => match offset.checked_add(8) {
+                Some(end) if end <= image.len() as u64 => (),
+                _ => return Err(InvalidRelocation::Offset(offset)),
+            },
+            t => return Err(InvalidRelocation::Type(t)),
+        }
+    }
+
+    for entry in rela.chunks(RELA) {
+        if u64_at(entry, 8) as u32 == R_X86_64_RELATIVE {
+            let offset = u64_at(entry, 0) as usize;
+            let value = base.wrapping_add(u64_at(entry, 16));
+            image[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(offset: u64, kind: u32, addend: u64) -> [u8; RELA] {
+        let mut entry = [0u8; RELA];
+        entry[..8].copy_from_slice(&offset.to_le_bytes());
+        entry[8..16].copy_from_slice(&u64::from(kind).to_le_bytes());
+        entry[16..].copy_from_slice(&addend.to_le_bytes());
+        entry
+    }
+
+    #[test]
+    fn relative() {
+        let mut rela = [0u8; RELA * 3];
+        rela[..RELA].copy_from_slice(&entry(0x10, R_X86_64_RELATIVE, 0x1234));
+        rela[RELA..RELA * 2].copy_from_slice(&entry(0, R_X86_64_NONE, 0));
+        rela[RELA * 2..].copy_from_slice(&entry(0x18, R_X86_64_RELATIVE, 0));
+
+        let mut image = [0xffu8; 0x20];
+        relocate(&mut image, &rela, 0x7000_0000).unwrap();
+        assert_eq!(u64_at(&image, 0x10), 0x7000_1234);
+        assert_eq!(u64_at(&image, 0x18), 0x7000_0000);
+        assert_eq!(image[..0x10], [0xffu8; 0x10]);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut image = [0u8; 0x20];
+
+        let rela = entry(0x10, R_X86_64_RELATIVE, 0);
+        assert_eq!(
+            relocate(&mut image, &rela[..RELA - 1], 0),
+            Err(InvalidRelocation::Length)
+        );
+
+        let rela = entry(0x19, R_X86_64_RELATIVE, 0);
+        assert_eq!(
+            relocate(&mut image, &rela, 0),
+            Err(InvalidRelocation::Offset(0x19))
+        );
+
+        // R_X86_64_64 needs a symbol table
+        let rela = entry(0x10, 1, 0);
+        assert_eq!(
+            relocate(&mut image, &rela, 0),
+            Err(InvalidRelocation::Type(1))
+        );
+        assert_eq!(image, [0u8; 0x20]);
+    }
+}
@@ -0,0 +1,450 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sealed Data Format
+//! A sealed blob is a versioned header, followed by additional authenticated
+//! data (AAD) and the encrypted payload. The header records the EGETKEY
+//! request of the seal key, so the key can be derived again when unsealing,
+//! even after the enclave or the platform has been upgraded.
+//!
+//! The payload is encrypted with AES-128-GCM under the seal key, using the
+//! header's nonce and authenticating the header bytes followed by the AAD.
+//! This module only defines the format; the encryption is up to the caller.
+
+use crate::{
+    Attributes, CpuSvn, Features, KeyName, KeyPolicy, KeyRequest, MiscSelect, SecurityVersion, Xfrm,
+};
+
+use core::convert::{TryFrom, TryInto};
+
+/// The current version of the sealed data format
+pub const VERSION: u16 = 1;
+
+/// The length of the header in bytes
+pub const HEADER_LEN: usize = 96;
+
+/// The bytes are not a sealed blob this version of the crate can read
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidSealed {
+    /// The blob is shorter than its header says
+    Length,
+
+    /// The blob has an unknown format version
+    Version(u16),
+
+    /// The recorded key request has unknown bits set
+    KeyRequest,
+}
+
+impl core::fmt::Display for InvalidSealed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidSealed::Length => write!(f, "sealed data is truncated"),
+            InvalidSealed::Version(v) => write!(f, "unknown sealed data version {}", v),
+            InvalidSealed::KeyRequest => write!(f, "invalid key request in sealed data"),
+        }
+    }
+}
+
+/// The header of a sealed blob
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The format version
+    pub version: u16,
+
+    /// The identity of the enclave the seal key is bound to
+    pub keypolicy: KeyPolicy,
+
+    /// The ISV security version the seal key was derived for
+    pub isvsvn: SecurityVersion,
+
+    /// The CONFIGSVN the seal key was derived for
+    pub configsvn: u16,
+
+    /// The CPU security version the seal key was derived for
+    pub cpusvn: CpuSvn,
+
+    /// The enclave attributes the seal key was derived from
+    pub attributemask: Attributes,
+
+    /// The MISCSELECT bits the seal key was derived from
+    pub miscmask: MiscSelect,
+
+    /// The key ID of the seal key
+    pub keyid: [u8; 32],
+
+    /// The AES-GCM nonce
+    pub nonce: [u8; 12],
+
+    /// The length of the AAD in bytes
+    pub aad_len: u32,
+
+    /// The length of the encrypted payload, including the tag, in bytes
+    pub payload_len: u32,
+}
+
+impl Header {
+    /// Creates a header recording the seal key `request`.
+    pub fn new(request: &KeyRequest, nonce: [u8; 12], aad_len: u32, payload_len: u32) -> Self {
+        Self {
+            version: VERSION,
+            keypolicy: request.keypolicy,
+            isvsvn: request.isvsvn,
+            configsvn: request.configsvn,
+            cpusvn: request.cpusvn,
+            attributemask: request.attributemask,
+            miscmask: request.miscmask,
+            keyid: request.keyid,
+            nonce,
+            aad_len,
+            payload_len,
+        }
+    }
+
+    /// Returns the EGETKEY request of the seal key.
+    pub fn key_request(&self) -> KeyRequest {
+        let mut request = KeyRequest::new(KeyName::Seal, self.keypolicy, self.isvsvn, self.cpusvn);
+        request.attributemask = self.attributemask;
+        request.miscmask = self.miscmask;
+        request.keyid = self.keyid;
+        request.configsvn = self.configsvn;
+        request
+    }
+
+    /// Returns whether the seal key was derived for older security versions
+    /// than the enclave's `isvsvn` and the platform's `cpusvn`, so the data
+    /// should be resealed. Note that a CPUSVN incomparable to `cpusvn` is
+    /// also outdated, since EGETKEY cannot derive keys for it.
+    pub fn is_outdated(&self, isvsvn: SecurityVersion, cpusvn: CpuSvn) -> bool {
+        self.isvsvn < isvsvn || cpusvn != self.cpusvn
+    }
+
+    /// Returns whether EGETKEY can derive the seal key for an enclave with
+    /// `isvsvn` on a platform with `cpusvn`, i.e. whether neither recorded
+    /// security version is newer.
+    pub fn can_unseal(&self, isvsvn: SecurityVersion, cpusvn: CpuSvn) -> bool {
+        self.isvsvn <= isvsvn && cpusvn.is_at_least(&self.cpusvn)
+    }
+
+    /// Returns the header for resealing the same data with the seal key of
+    /// the given security versions and a fresh `nonce`.
+    pub fn upgraded(&self, isvsvn: SecurityVersion, cpusvn: CpuSvn, nonce: [u8; 12]) -> Self {
+        Self {
+            version: VERSION,
+            isvsvn,
+            cpusvn,
+            nonce,
+            ..*self
+        }
+    }
+
+    /// Returns the header bytes, all integers in little endian.
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0..2].copy_from_slice(&self.version.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.keypolicy.bits().to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.isvsvn.inner().to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.configsvn.to_le_bytes());
+        bytes[8..24].copy_from_slice(&self.cpusvn.inner());
+        bytes[24..32].copy_from_slice(&self.attributemask.features().bits().to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.attributemask.xfrm().bits().to_le_bytes());
+        bytes[40..44].copy_from_slice(&self.miscmask.bits().to_le_bytes());
+        bytes[44..76].copy_from_slice(&self.keyid);
+        bytes[76..88].copy_from_slice(&self.nonce);
+        bytes[88..92].copy_from_slice(&self.aad_len.to_le_bytes());
+        bytes[92..96].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes
+    }
+
+    /// Parses the header at the start of `bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, InvalidSealed> {
+        if bytes.len() < HEADER_LEN {
+            return Err(InvalidSealed::Length);
+        }
+
+        let u16_at = |i: usize| u16::from_le_bytes(bytes[i..i + 2].try_into().unwrap());
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+
+        let version = u16_at(0);
+        if version != VERSION {
+            return Err(InvalidSealed::Version(version));
+        }
+
+        let keypolicy = KeyPolicy::from_bits(u16_at(2)).ok_or(InvalidSealed::KeyRequest)?;
+        let features = Features::from_bits(u64_at(24)).ok_or(InvalidSealed::KeyRequest)?;
+        let xfrm = Xfrm::from_bits(u64_at(32)).ok_or(InvalidSealed::KeyRequest)?;
+        let miscmask = MiscSelect::from_bits(u32_at(40)).ok_or(InvalidSealed::KeyRequest)?;
+
+        Ok(Self {
+            version,
+            keypolicy,
+            isvsvn: SecurityVersion::new(u16_at(4)),
+            configsvn: u16_at(6),
+            cpusvn: CpuSvn::new(bytes[8..24].try_into().unwrap()),
+            attributemask: Attributes::new(features, xfrm),
+            miscmask,
+            keyid: bytes[44..76].try_into().unwrap(),
+            nonce: bytes[76..88].try_into().unwrap(),
+            aad_len: u32_at(88),
+            payload_len: u32_at(92),
+        })
+    }
+}
+
+/// A sealed blob, borrowing its AAD and encrypted payload
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sealed<'a> {
+    /// The header
+    pub header: Header,
+
+    /// The additional authenticated data, stored in the clear
+    pub aad: &'a [u8],
+
+    /// The encrypted payload, including the tag
+    pub payload: &'a [u8],
+}
+
+impl<'a> Sealed<'a> {
+    /// Parses a sealed blob.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, InvalidSealed> {
+        let header = Header::parse(bytes)?;
+        let len = |len: u32| usize::try_from(len).map_err(|_| InvalidSealed::Length);
+        let aad_end = HEADER_LEN
+            .checked_add(len(header.aad_len)?)
+            .ok_or(InvalidSealed::Length)?;
+        let end = aad_end
+            .checked_add(len(header.payload_len)?)
+            .ok_or(InvalidSealed::Length)?;
+        if bytes.len() < end {
+            return Err(InvalidSealed::Length);
+        }
+
+        Ok(Self {
+            header,
+            aad: &bytes[HEADER_LEN..aad_end],
+            payload: &bytes[aad_end..end],
+        })
+    }
+
+    /// Returns the bytes of the sealed blob
+    #[cfg(feature = "std")]
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut vec = self.header.to_bytes().to_vec();
+        vec.extend_from_slice(self.aad);
+        vec.extend_from_slice(self.payload);
+        vec
+    }
+}
+
+/// The encryption of sealed blobs, used by `reseal()`
+///
+/// The seal key is derived with `Header::key_request()`, e.g. with EGETKEY.
+#[cfg(feature = "std")]
+pub trait Sealer {
+    /// The error of a failed encryption or decryption
+    type Error;
+
+    /// Decrypts and authenticates the `payload` sealed with `header` and `aad`.
+    fn unseal(
+        &mut self,
+        header: &Header,
+        aad: &[u8],
+        payload: &[u8],
+    ) -> Result<Vec<u8>, Self::Error>;
+
+    /// Encrypts the `plaintext`, authenticating `header` and `aad`.
+    fn seal(
+        &mut self,
+        header: &Header,
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// A sealed blob that cannot be resealed, see `reseal()`
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResealError<E> {
+    /// The blob cannot be read
+    Invalid(InvalidSealed),
+
+    /// The blob was sealed for newer security versions than the current ones
+    Newer,
+
+    /// The blob could not be decrypted or encrypted again
+    Sealer(E),
+}
+
+/// Migrates a sealed blob to the seal key of the enclave's current `isvsvn`
+/// and the platform's current `cpusvn`, e.g. after a TCB recovery.
+///
+/// Returns `None` if the blob is not outdated. Otherwise the payload is
+/// unsealed with the key of the recorded security versions and sealed again
+/// with the key of the current ones under `nonce`, keeping the AAD and the
+/// rest of the key policy. The sealed payload must have the same length as
+/// before.
+///
+/// With the `zeroize` feature, the unsealed payload is zeroed before it is
+/// freed.
+#[cfg(feature = "std")]
+pub fn reseal<S: Sealer>(
+    bytes: &[u8],
+    isvsvn: SecurityVersion,
+    cpusvn: CpuSvn,
+    nonce: [u8; 12],
+    sealer: &mut S,
+) -> Result<Option<Vec<u8>>, ResealError<S::Error>> {
+    let sealed = Sealed::parse(bytes).map_err(ResealError::Invalid)?;
+    let old = sealed.header;
+
+    if !old.is_outdated(isvsvn, cpusvn) {
+        return Ok(None);
+    }
+
+    if !old.can_unseal(isvsvn, cpusvn) {
+        return Err(ResealError::Newer);
+    }
+
+    #[cfg(feature = "zeroize")]
+    let plaintext = zeroize::Zeroizing::new(
+        sealer
+            .unseal(&old, sealed.aad, sealed.payload)
+            .map_err(ResealError::Sealer)?,
+    );
+    #[cfg(not(feature = "zeroize"))]
+    let plaintext = sealer
+        .unseal(&old, sealed.aad, sealed.payload)
+        .map_err(ResealError::Sealer)?;
+
+    // AES-GCM preserves the payload length, so the header is unchanged by sealing.
+    let new = old.upgraded(isvsvn, cpusvn, nonce);
+    let payload = sealer
+        .seal(&new, sealed.aad, &plaintext)
+        .map_err(ResealError::Sealer)?;
+    if payload.len() != new.payload_len as usize {
+        return Err(ResealError::Invalid(InvalidSealed::Length));
+    }
+
+    trace_event!(isvsvn = isvsvn.inner(), "resealed");
+    Ok(Some(
+        Sealed {
+            header: new,
+            aad: sealed.aad,
+            payload: &payload,
+        }
+        .to_vec(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn header() -> Header {
+        let mut request = KeyRequest::new(
+            KeyName::Seal,
+            KeyPolicy::MRSIGNER,
+            SecurityVersion::new(2),
+            CpuSvn::new([3; 16]),
+        );
+        request.attributemask = Attributes::new(Features::DEBUG, Xfrm::X87);
+        request.miscmask = MiscSelect::EXINFO;
+        request.keyid = [4; 32];
+
+        Header::new(&request, [5; 12], 3, 16)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let header = header();
+        assert_eq!(Header::parse(&header.to_bytes()), Ok(header));
+
+        let request = header.key_request();
+        assert_eq!(request.keyname, KeyName::Seal);
+        assert_eq!(request.keypolicy, KeyPolicy::MRSIGNER);
+        assert_eq!(request.keyid, [4; 32]);
+    }
+
+    #[test]
+    fn sealed() {
+        let mut bytes = header().to_bytes().to_vec();
+        bytes.extend_from_slice(b"aad");
+        bytes.extend_from_slice(&[6; 16]);
+
+        let sealed = Sealed::parse(&bytes).unwrap();
+        assert_eq!(sealed.aad, b"aad");
+        assert_eq!(sealed.payload, &[6; 16]);
+        assert_eq!(
+            Sealed::parse(&bytes[..bytes.len() - 1]),
+            Err(InvalidSealed::Length)
+        );
+
+        // Lengths that overflow the address space are truncated blobs too.
+        bytes[88..96].copy_from_slice(&[0xff; 8]);
+        assert_eq!(Sealed::parse(&bytes), Err(InvalidSealed::Length));
+    }
+
+    #[test]
+    fn version() {
+        let mut bytes = header().to_bytes();
+        bytes[0] = 2;
+        assert_eq!(Header::parse(&bytes), Err(InvalidSealed::Version(2)));
+    }
+
+    // Stands in for AES-GCM: XORs with the ISVSVN and appends it as the tag.
+    #[cfg(feature = "std")]
+    struct Xor;
+
+    #[cfg(feature = "std")]
+    impl Sealer for Xor {
+        type Error = ();
+
+        fn unseal(&mut self, header: &Header, _: &[u8], payload: &[u8]) -> Result<Vec<u8>, ()> {
+            let key = header.isvsvn.inner() as u8;
+            let (data, tag) = payload.split_at(payload.len() - 1);
+            if tag != [key] {
+                return Err(());
+            }
+
+            Ok(data.iter().map(|b| b ^ key).collect())
+        }
+
+        fn seal(&mut self, header: &Header, _: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, ()> {
+            let key = header.isvsvn.inner() as u8;
+            let mut payload: Vec<u8> = plaintext.iter().map(|b| b ^ key).collect();
+            payload.push(key);
+            Ok(payload)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reseal() {
+        let mut header = header();
+        header.payload_len = 4;
+        let payload = Xor.seal(&header, b"aad", b"abc").unwrap();
+        let blob = Sealed {
+            header,
+            aad: b"aad",
+            payload: &payload,
+        }
+        .to_vec();
+
+        let cpusvn = CpuSvn::new([3; 16]);
+        let current = super::reseal(&blob, SecurityVersion::new(2), cpusvn, [0; 12], &mut Xor);
+        assert_eq!(current, Ok(None));
+
+        let older = super::reseal(&blob, SecurityVersion::new(1), cpusvn, [0; 12], &mut Xor);
+        assert_eq!(older, Err(ResealError::Newer));
+
+        let newer = super::reseal(&blob, SecurityVersion::new(3), cpusvn, [7; 12], &mut Xor);
+        let newer = newer.unwrap().unwrap();
+        let sealed = Sealed::parse(&newer).unwrap();
+        assert_eq!(sealed.header.isvsvn, SecurityVersion::new(3));
+        assert_eq!(sealed.header.nonce, [7; 12]);
+        assert_eq!(sealed.aad, b"aad");
+
+        let plaintext = Xor.unseal(&sealed.header, sealed.aad, sealed.payload);
+        assert_eq!(plaintext, Ok(b"abc".to_vec()));
+    }
+}
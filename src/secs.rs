@@ -7,8 +7,49 @@
 //! page created for any enclave. It is moved from a temporary buffer to an EPC
 //! by the means of ENCLS(ECREATE) leaf.
 
-use super::{attr, isv, misc::MiscSelect, sig::Parameters};
-use core::num::{NonZeroU32, NonZeroUsize};
+use super::{attr, isv, misc::MiscSelect, sig::InvalidParameters, sig::Parameters};
+use core::num::NonZeroU32;
+
+#[cfg(target_arch = "x86_64")]
+use core::num::NonZeroUsize;
+
+/// The maximum ELRANGE end (exclusive) in the lower canonical half of a
+/// 48-bit linear address space
+const CANONICAL: u64 = 1 << 47;
+
+/// An enclave range or parameters that ECREATE would reject, see
+/// `Secs::check_range()` and `Parameters::validate()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidRange {
+    /// The size is not a power of two of at least one page
+    Size,
+
+    /// The base address is not naturally aligned to the size
+    Base,
+
+    /// The range is not within the canonical lower half of the address space
+    Canonical,
+
+    /// The parameters are not valid for the enclave
+    Parameters(InvalidParameters),
+}
+
+impl From<InvalidParameters> for InvalidRange {
+    fn from(value: InvalidParameters) -> Self {
+        InvalidRange::Parameters(value)
+    }
+}
+
+impl core::fmt::Display for InvalidRange {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidRange::Size => write!(f, "enclave size is not a power of two"),
+            InvalidRange::Base => write!(f, "enclave base is not aligned to the size"),
+            InvalidRange::Canonical => write!(f, "enclave range is not canonical"),
+            InvalidRange::Parameters(e) => write!(f, "invalid enclave parameters: {}", e),
+        }
+    }
+}
 
 /// Section 38.7
 #[derive(Copy, Clone, Debug)]
@@ -56,6 +97,63 @@ impl Secs {
         }
     }
 
+    /// Creates a new SECS struct, checking the range with `check_range()`
+    /// and the parameters with `Parameters::validate()`.
+    pub fn try_new(
+        addr: *const (),
+        size: usize,
+        ssa_frame_pages: NonZeroU32,
+        parameters: Parameters,
+    ) -> Result<Self, InvalidRange> {
+        Self::check_range(addr as u64, size as u64)?;
+        parameters.validate()?;
+        Ok(Self::new(addr, size, ssa_frame_pages, parameters))
+    }
+
+    /// Checks that the enclave size is a power of two of at least one page.
+    pub fn check_size(size: u64) -> Result<(), InvalidRange> {
+        if size < 4096 || !size.is_power_of_two() {
+            return Err(InvalidRange::Size);
+        }
+
+        Ok(())
+    }
+
+    /// Checks the enclave range (ELRANGE) with the same constraints as
+    /// ECREATE: the size must be valid (see `check_size()`), the base must
+    /// be naturally aligned to the size and the whole range must be within
+    /// the canonical lower half of the address space.
+    pub fn check_range(base: u64, size: u64) -> Result<(), InvalidRange> {
+        Self::check_size(size)?;
+
+        if base & (size - 1) != 0 {
+            return Err(InvalidRange::Base);
+        }
+
+        match base.checked_add(size) {
+            Some(end) if end <= CANONICAL => Ok(()),
+            _ => Err(InvalidRange::Canonical),
+        }
+    }
+
+    /// Returns the size of the enclave in bytes.
+    #[inline]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of pages per SSA frame.
+    #[inline]
+    pub const fn ssa_frame_pages(&self) -> NonZeroU32 {
+        self.ssaframesize
+    }
+
+    /// Returns the attributes of the enclave.
+    #[inline]
+    pub const fn attributes(&self) -> attr::Attributes {
+        self.attributes
+    }
+
     /// # Usage
     /// Returns the maximum enclave size for 64bit in bytes.
     /// CPUID.(EAX=12H, ECX=0H) enumerates Intel SGX capability;
@@ -76,6 +174,7 @@ impl Secs {
     ///
     /// https://github.com/rust-lang/rust/issues/60123
     ///
+    #[cfg(target_arch = "x86_64")]
     pub fn max_enc_size() -> Option<NonZeroUsize> {
         use core::arch::x86_64::{__cpuid_count, __get_cpuid_max};
 
@@ -106,6 +205,49 @@ impl Secs {
     }
 }
 
+#[cfg(test)]
+mod range {
+    use super::*;
+
+    #[test]
+    fn check_range() {
+        assert_eq!(Secs::check_range(0x10000, 0x10000), Ok(()));
+        assert_eq!(Secs::check_range(0, 0), Err(InvalidRange::Size));
+        assert_eq!(Secs::check_range(0, 0x800), Err(InvalidRange::Size));
+        assert_eq!(Secs::check_range(0, 0x3000), Err(InvalidRange::Size));
+        assert_eq!(Secs::check_range(0x8000, 0x10000), Err(InvalidRange::Base));
+        assert_eq!(
+            Secs::check_range(CANONICAL, 0x10000),
+            Err(InvalidRange::Canonical)
+        );
+        assert_eq!(Secs::check_range(CANONICAL - 0x10000, 0x10000), Ok(()));
+        assert_eq!(Secs::check_range(0, 1 << 63), Err(InvalidRange::Canonical));
+    }
+
+    #[test]
+    fn try_new() {
+        let one = NonZeroU32::new(1).unwrap();
+        let addr = 0x20000 as *const ();
+        let xfrm = attr::Xfrm::X87 | attr::Xfrm::SSE;
+        let mut params = Parameters {
+            attr: attr::Attributes::new(attr::Features::MODE64BIT, xfrm).into(),
+            ..Default::default()
+        };
+        let secs = Secs::try_new(addr, 0x20000, one, params).unwrap();
+        assert_eq!(secs.size(), 0x20000);
+
+        let err = Secs::try_new(addr, 0x40000, one, params).unwrap_err();
+        assert_eq!(err, InvalidRange::Base);
+
+        params.attr = attr::Attributes::new(attr::Features::EINIT_KEY, xfrm).into();
+        let err = Secs::try_new(addr, 0x20000, one, params).unwrap_err();
+        assert_eq!(err, InvalidRange::Parameters(InvalidParameters::EinitKey));
+
+        params.launch_enclave = true;
+        assert!(Secs::try_new(addr, 0x20000, one, params).is_ok());
+    }
+}
+
 #[cfg(test)]
 testaso! {
     struct Secs: 4096, 4096 => {
@@ -5,8 +5,12 @@
 //! contains information about the enclave. SIGSTRUCT is processed by the EINIT
 //! leaf function to verify that the enclave was properly built.
 
-use crate::{Attributes, MiscSelect, ProductId, SecurityVersion};
+use crate::pkcs1::{self, InvalidSignature};
+use crate::{
+    Attributes, Digest, Features, KeyPolicy, MiscSelect, ProductId, SecurityVersion, Xfrm,
+};
 
+use core::convert::TryFrom;
 use core::fmt::Debug;
 use core::ops::{BitAnd, BitOr, Not};
 
@@ -14,7 +18,7 @@ use core::ops::{BitAnd, BitOr, Not};
 use openssl::{bn, pkey, rsa};
 
 #[cfg(feature = "crypto")]
-use core::convert::{TryFrom, TryInto};
+use core::convert::TryInto;
 
 /// Succinctly describes a masked type, e.g. masked Attributes or masked MiscSelect.
 /// A mask is applied to Attributes and MiscSelect structs in a Signature (SIGSTRUCT)
@@ -30,6 +34,35 @@ pub struct Masked<T: BitAnd<Output = T>> {
     pub mask: T,
 }
 
+/// The data of a `Masked` has bits set outside of its mask
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidMask;
+
+impl<T> Masked<T>
+where
+    T: BitAnd<Output = T>,
+    T: Not<Output = T>,
+    T: PartialEq,
+    T: Default,
+    T: Copy,
+{
+    /// Creates a masked value, checking that `data` has no bits set outside
+    /// of `mask`: such bits are ignored, so setting them is likely a bug.
+    pub fn new(data: T, mask: T) -> Result<Self, InvalidMask> {
+        if data & !mask != T::default() {
+            return Err(InvalidMask);
+        }
+
+        Ok(Self { data, mask })
+    }
+
+    /// Returns whether `value` matches the data in every bit of the mask;
+    /// bits outside of the mask may have any value.
+    pub fn matches(&self, value: &T) -> bool {
+        self.mask & self.data == self.mask & *value
+    }
+}
+
 impl<T> Default for Masked<T>
 where
     T: BitAnd<Output = T>,
@@ -69,6 +102,74 @@ where
     }
 }
 
+/// The vendor of an enclave, as recorded in `SIGSTRUCT.VENDOR`
+///
+/// EINIT rejects any value other than these two.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Vendor {
+    /// Any vendor other than Intel
+    Other = 0x0000,
+
+    /// Intel
+    Intel = 0x8086,
+}
+
+impl Default for Vendor {
+    fn default() -> Self {
+        Vendor::Other
+    }
+}
+
+impl TryFrom<u32> for Vendor {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x0000 => Ok(Vendor::Other),
+            0x8086 => Ok(Vendor::Intel),
+            value => Err(value),
+        }
+    }
+}
+
+/// Software-defined value in `SIGSTRUCT.SWDEFINED`
+///
+/// The architecture does not interpret this value; it is reported to the
+/// enclave and is available for the author's own use.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SwDefined(u32);
+
+impl SwDefined {
+    /// Creates a new SwDefined based on value provided.
+    pub const fn new(value: u32) -> Self {
+        Self(value)
+    }
+
+    /// Returns inner value as u32
+    pub const fn inner(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The date is not representable as YYYYMMDD or is not a Unix timestamp
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidDate;
+
+/// Encodes the decimal digits of `value` as BCD
+const fn bcd(mut value: u32) -> u32 {
+    let mut out = 0;
+    let mut shift = 0;
+    while value > 0 {
+        out |= (value % 10) << shift;
+        value /= 10;
+        shift += 4;
+    }
+
+    out
+}
+
 /// The `Author` of an enclave
 ///
 /// This structure encompasses the first block of fields from `SIGSTRUCT`
@@ -79,30 +180,123 @@ where
 pub struct Author {
     /// Constant byte string.
     header1: u128,
-    /// Vendor.
-    pub vendor: u32,
+    /// Vendor, see `Author::vendor()`.
+    vendor: u32,
     /// YYYYMMDD in BCD.
     pub date: u32,
     /// Constant byte string.
     header2: u128,
     /// Software-defined value.
-    pub swdefined: u32,
+    pub swdefined: SwDefined,
     reserved: [u32; 21],
 }
 
 impl Author {
-    #[allow(clippy::unreadable_literal)]
+    /// The architectural value of `SIGSTRUCT.HEADER`
+    pub const HEADER1: [u8; 16] =
+        *b"\x06\x00\x00\x00\xE1\x00\x00\x00\x00\x00\x01\x00\x00\x00\x00\x00";
+
+    /// The architectural value of `SIGSTRUCT.HEADER2`
+    pub const HEADER2: [u8; 16] =
+        *b"\x01\x01\x00\x00\x60\x00\x00\x00\x60\x00\x00\x00\x01\x00\x00\x00";
+
     /// Creates a new Author from a date and software defined value.
-    pub const fn new(date: u32, swdefined: u32) -> Self {
+    ///
+    /// The vendor is `Vendor::Other`; use `Author::with_vendor()` to change it.
+    pub const fn new(date: u32, swdefined: SwDefined) -> Self {
         Self {
-            header1: u128::from_be(0x06000000E10000000000010000000000),
-            vendor: 0u32,
+            header1: u128::from_ne_bytes(Self::HEADER1),
+            vendor: Vendor::Other as u32,
             date,
-            header2: u128::from_be(0x01010000600000006000000001000000),
+            header2: u128::from_ne_bytes(Self::HEADER2),
             swdefined,
             reserved: [0; 21],
         }
     }
+
+    /// Creates a new Author dated `secs` seconds after the Unix epoch (in UTC),
+    /// encoding the date as YYYYMMDD in BCD.
+    pub fn from_unix_time(secs: u64, swdefined: SwDefined) -> Result<Self, InvalidDate> {
+        // Convert days since the epoch to a civil date, counting eras of 400
+        // years from 0000-03-01 so that leap days fall at the end of a year.
+        let days = secs / 86400 + 719468;
+        let era = days / 146097;
+        let doe = days % 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = era * 400 + yoe + if month <= 2 { 1 } else { 0 };
+
+        if year > 9999 {
+            return Err(InvalidDate);
+        }
+
+        let date = bcd(year as u32) << 16 | bcd(month as u32) << 8 | bcd(day as u32);
+        Ok(Self::new(date, swdefined))
+    }
+
+    /// Creates a new Author dated today (in UTC) according to the system
+    /// clock. Fails if the clock is before the Unix epoch or past the year
+    /// 9999.
+    #[cfg(feature = "std")]
+    pub fn today(swdefined: SwDefined) -> Result<Self, InvalidDate> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| InvalidDate)?;
+
+        Self::from_unix_time(now.as_secs(), swdefined)
+    }
+
+    /// Creates a new Author dated by the `SOURCE_DATE_EPOCH` environment
+    /// variable, so that independent builders produce byte-identical
+    /// SIGSTRUCTs. Returns `Ok(None)` if the variable is not set.
+    #[cfg(feature = "std")]
+    pub fn from_source_date_epoch(swdefined: SwDefined) -> Result<Option<Self>, InvalidDate> {
+        let epoch = match std::env::var_os("SOURCE_DATE_EPOCH") {
+            Some(epoch) => epoch,
+            None => return Ok(None),
+        };
+
+        let secs = epoch
+            .to_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or(InvalidDate)?;
+        Self::from_unix_time(secs, swdefined).map(Some)
+    }
+
+    /// Returns the `Author` with its vendor set to `vendor`.
+    pub const fn with_vendor(self, vendor: Vendor) -> Self {
+        Self {
+            vendor: vendor as u32,
+            ..self
+        }
+    }
+
+    /// Returns the vendor, or the raw value if it is reserved (e.g. when
+    /// the `Author` was read from an untrusted `SIGSTRUCT`).
+    pub fn vendor(&self) -> Result<Vendor, u32> {
+        Vendor::try_from(self.vendor)
+    }
+
+    /// Returns true if `HEADER` and `HEADER2` hold their architectural
+    /// values (`Author::HEADER1` and `Author::HEADER2`), which EINIT
+    /// requires of every `SIGSTRUCT`.
+    pub fn is_valid_header(&self) -> bool {
+        self.header1.to_ne_bytes() == Self::HEADER1 && self.header2.to_ne_bytes() == Self::HEADER2
+    }
+
+    /// Returns the canonical bytes of the `Author`, as hashed for the signature
+    pub fn as_bytes(&self) -> &[u8] {
+        // # Safety
+        //
+        // This is safe because `Author` is `repr(C)` and all of its fields are
+        // plain integers.
+        unsafe {
+            core::slice::from_raw_parts(self as *const _ as *const u8, core::mem::size_of_val(self))
+        }
+    }
 }
 
 /// Enclave parameters
@@ -119,9 +313,125 @@ pub struct Parameters {
 
     /// ISV-defined security version number
     pub isv_svn: SecurityVersion,
+
+    /// The enclave is a launch enclave, so it may have EINIT_KEY; this is not
+    /// part of the SIGSTRUCT
+    pub launch_enclave: bool,
 }
 
 impl Parameters {
+    /// Requires the AEXNOTIFY attribute, so that threads of the enclave can
+    /// opt in to AEX-Notify.
+    pub fn enable_aex_notify(&mut self) {
+        let aexnotify = Attributes::new(Features::AEXNOTIFY, Xfrm::empty());
+        self.attr.data = self.attr.data | aexnotify;
+        self.attr.mask = self.attr.mask | aexnotify;
+    }
+
+    /// Checks that the attributes the enclave will have (`attr.data` under
+    /// `attr.mask`) can be loaded: the XFRM must be a valid XCR0 value and no
+    /// attribute reserved to the architecture is set. EINIT_KEY is only
+    /// accepted if `launch_enclave` is set.
+    pub fn validate(&self) -> Result<(), InvalidParameters> {
+        let attr = self.attr.data & self.attr.mask;
+        let features = attr.features();
+        let xfrm = attr.xfrm();
+
+        let legacy = Xfrm::X87 | Xfrm::SSE;
+        let avx512 = Xfrm::OPMASK | Xfrm::ZMM_HI256 | Xfrm::HI16_ZMM;
+        let mpx = Xfrm::BNDREG | Xfrm::BNDCSR;
+
+        if features.contains(Features::INIT) {
+            return Err(InvalidParameters::Init);
+        }
+
+        if !xfrm.contains(legacy) {
+            return Err(InvalidParameters::XfrmLegacy);
+        }
+
+        if xfrm.intersects(avx512) && !xfrm.contains(avx512 | Xfrm::AVX) {
+            return Err(InvalidParameters::XfrmAvx512);
+        }
+
+        if xfrm.intersects(mpx) && !xfrm.contains(mpx) {
+            return Err(InvalidParameters::XfrmMpx);
+        }
+
+        if features.contains(Features::EINIT_KEY) && !self.launch_enclave {
+            return Err(InvalidParameters::EinitKey);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the enclave may request keys with `policy`: deriving keys
+    /// from the CONFIGID, ISV family ID or ISV extended product ID requires
+    /// the KSS attribute.
+    pub fn validate_key_policy(&self, policy: KeyPolicy) -> Result<(), InvalidParameters> {
+        let kss = KeyPolicy::CONFIGID | KeyPolicy::ISVFAMILYID | KeyPolicy::ISVEXTPRODID;
+        let features = (self.attr.data & self.attr.mask).features();
+
+        if policy.intersects(kss) && !features.contains(Features::KSS) {
+            return Err(InvalidParameters::Kss);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the potential weaknesses of these parameters as a signing
+    /// policy, e.g. for CI pipelines to reject unsafe signing configurations.
+    pub fn security_findings(&self) -> impl Iterator<Item = Finding> + '_ {
+        Finding::ALL
+            .iter()
+            .copied()
+            .filter(move |finding| finding.applies(self))
+    }
+
+    /// Checks the Report `body` against these parameters, listing every field
+    /// that does not match. MISCSELECT and attributes must match under their
+    /// masks, the product ID must be equal and the security version must be
+    /// at least `isv_svn`.
+    #[cfg(feature = "std")]
+    pub fn check(
+        &self,
+        body: &crate::attestation_types::report::Body,
+    ) -> Result<(), Vec<Mismatch>> {
+        let mut mismatches = Vec::new();
+
+        let misc = (self.misc.data ^ body.miscselect) & self.misc.mask;
+        if !misc.is_empty() {
+            mismatches.push(Mismatch::MiscSelect(misc));
+        }
+
+        let attr = (self.attr.data ^ body.attributes) & self.attr.mask;
+        if !attr.features().is_empty() {
+            mismatches.push(Mismatch::Features(attr.features()));
+        }
+        if !attr.xfrm().is_empty() {
+            mismatches.push(Mismatch::Xfrm(attr.xfrm()));
+        }
+
+        if body.isv_prod_id != self.isv_prod_id {
+            mismatches.push(Mismatch::ProductId {
+                expected: self.isv_prod_id,
+                actual: body.isv_prod_id,
+            });
+        }
+
+        if body.isv_svn < self.isv_svn {
+            mismatches.push(Mismatch::SecurityVersion {
+                expected: self.isv_svn,
+                actual: body.isv_svn,
+            });
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+
     /// Combines the parameters and a hash of the enclave to produce a `Measurement`
     pub const fn measurement(&self, mrenclave: [u8; 32]) -> Measurement {
         Measurement {
@@ -136,6 +446,125 @@ impl Parameters {
     }
 }
 
+/// A field of a Report that does not satisfy the expected `Parameters`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// These MISCSELECT bits differ under the mask
+    MiscSelect(MiscSelect),
+
+    /// These attribute feature bits differ under the mask
+    Features(Features),
+
+    /// These attribute XFRM bits differ under the mask
+    Xfrm(Xfrm),
+
+    /// The ISV product ID differs
+    ProductId {
+        /// The expected product ID
+        expected: ProductId,
+        /// The product ID of the Report
+        actual: ProductId,
+    },
+
+    /// The ISV security version is below the expected minimum
+    SecurityVersion {
+        /// The minimum expected security version
+        expected: SecurityVersion,
+        /// The security version of the Report
+        actual: SecurityVersion,
+    },
+}
+
+/// An architecturally invalid or dangerous combination of `Parameters`,
+/// see `Parameters::validate()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidParameters {
+    /// INIT is set; it MUST be cleared when loading the enclave
+    Init,
+
+    /// XFRM[1:0] (x87 and SSE state) are not both set
+    XfrmLegacy,
+
+    /// XFRM enables only part of the AVX-512 state, or AVX-512 without AVX
+    XfrmAvx512,
+
+    /// XFRM enables only one of the MPX states (BNDREG and BNDCSR)
+    XfrmMpx,
+
+    /// EINIT_KEY is set; only the launch enclave may use the EINIT token key
+    EinitKey,
+
+    /// The key policy uses the KSS identities, but KSS is not set
+    Kss,
+}
+
+impl core::fmt::Display for InvalidParameters {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidParameters::Init => write!(f, "INIT must be cleared"),
+            InvalidParameters::XfrmLegacy => write!(f, "XFRM x87 and SSE must be set"),
+            InvalidParameters::XfrmAvx512 => write!(f, "XFRM AVX-512 state is incomplete"),
+            InvalidParameters::XfrmMpx => write!(f, "XFRM MPX state is incomplete"),
+            InvalidParameters::EinitKey => write!(f, "EINIT_KEY is reserved for launch enclaves"),
+            InvalidParameters::Kss => write!(f, "key policy requires KSS"),
+        }
+    }
+}
+
+/// A potential weakness of a signing policy, see `Parameters::security_findings()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Finding {
+    /// The DEBUG attribute is not masked, so a debug enclave would pass
+    DebugNotMasked,
+
+    /// The DEBUG attribute is required, so only a debug enclave would pass
+    DebugRequired,
+
+    /// The PROVISIONING_KEY attribute is allowed
+    ProvisioningKeyAllowed,
+
+    /// The EINIT_KEY attribute is allowed
+    EinitKeyAllowed,
+}
+
+impl Finding {
+    const ALL: [Finding; 4] = [
+        Finding::DebugNotMasked,
+        Finding::DebugRequired,
+        Finding::ProvisioningKeyAllowed,
+        Finding::EinitKeyAllowed,
+    ];
+
+    fn applies(self, params: &Parameters) -> bool {
+        let data = params.attr.data.features();
+        let mask = params.attr.mask.features();
+
+        // A feature is allowed unless it is masked and cleared.
+        let allowed = |f: Features| !mask.contains(f) || data.contains(f);
+
+        match self {
+            Finding::DebugNotMasked => !mask.contains(Features::DEBUG),
+            Finding::DebugRequired => (data & mask).contains(Features::DEBUG),
+            Finding::ProvisioningKeyAllowed => allowed(Features::PROVISIONING_KEY),
+            Finding::EinitKeyAllowed => allowed(Features::EINIT_KEY),
+        }
+    }
+}
+
+impl core::fmt::Display for Finding {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Finding::DebugNotMasked => write!(
+                f,
+                "DEBUG bit not masked; a debug enclave would pass this policy"
+            ),
+            Finding::DebugRequired => write!(f, "DEBUG bit required; the enclave is debuggable"),
+            Finding::ProvisioningKeyAllowed => write!(f, "PROVISIONING_KEY allowed"),
+            Finding::EinitKeyAllowed => write!(f, "EINIT_KEY allowed"),
+        }
+    }
+}
+
 /// The enclave Measurement
 ///
 /// This structure encompasses the second block of fields from `SIGSTRUCT`
@@ -159,6 +588,37 @@ impl Measurement {
         self.mrenclave
     }
 
+    /// Returns the canonical bytes of the `Measurement`, as hashed for the signature
+    pub fn as_bytes(&self) -> &[u8] {
+        // # Safety
+        //
+        // This is safe because `Measurement` is `repr(C)` without padding and
+        // all of its fields are plain integers or bit flags.
+        unsafe {
+            core::slice::from_raw_parts(self as *const _ as *const u8, core::mem::size_of_val(self))
+        }
+    }
+
+    /// Get the masked enclave attributes
+    pub fn attributes(&self) -> Masked<Attributes> {
+        self.attr
+    }
+
+    /// Get the masked MISCSELECT
+    pub fn misc_select(&self) -> Masked<MiscSelect> {
+        self.misc
+    }
+
+    /// Get the ISV-defined product identifier
+    pub fn isv_prod_id(&self) -> ProductId {
+        self.isv_prod_id
+    }
+
+    /// Get the ISV-defined security version number
+    pub fn isv_svn(&self) -> SecurityVersion {
+        self.isv_svn
+    }
+
     /// Get the enclave parameters
     pub fn parameters(&self) -> Parameters {
         Parameters {
@@ -166,70 +626,253 @@ impl Measurement {
             isv_svn: self.isv_svn,
             misc: self.misc,
             attr: self.attr,
+            launch_enclave: false,
         }
     }
 
     /// Signs a measurement using the specified key on behalf of an author
     #[cfg(feature = "crypto")]
     pub fn sign(self, author: Author, key: rsa::Rsa<pkey::Private>) -> std::io::Result<Signature> {
-        use openssl::{hash, sign};
-        const EXPONENT: u32 = 3;
-
         if key.e() != &*bn::BigNum::from_u32(EXPONENT)? {
+            trace_event!("signing key exponent is not 3");
             return Err(std::io::ErrorKind::InvalidInput.into());
         }
 
-        let a = unsafe {
-            core::slice::from_raw_parts(
-                &author as *const _ as *const u8,
-                core::mem::size_of_val(&author),
-            )
-        };
+        self.sign_with(author, &key)
+    }
 
-        let c = unsafe {
-            core::slice::from_raw_parts(
-                &self as *const _ as *const u8,
-                core::mem::size_of_val(&self),
-            )
-        };
+    /// Signs a measurement on behalf of an author with a `Signer` backend,
+    /// e.g. a key held in an HSM or a cloud KMS.
+    ///
+    /// The signature is verified against the signer's modulus, so a backend
+    /// using the wrong key or padding is detected before Q1 and Q2 are
+    /// computed.
+    #[cfg(feature = "crypto")]
+    pub fn sign_with(self, author: Author, signer: &impl Signer) -> std::io::Result<Signature> {
+        trace_event!(mrenclave = ?self.mrenclave, "signing measurement");
+        let message = self.message(&author);
+        let modulus = Signer::modulus(signer)?;
+        let signature = Signer::sign(signer, &message)?;
+        self.assemble(author, &message, &modulus, &signature)
+    }
+
+    /// Like `sign_with()`, but with an `AsyncSigner` backend, so that
+    /// waiting on a remote HSM or KMS does not block the executor.
+    #[cfg(feature = "crypto")]
+    pub async fn sign_async(
+        self,
+        author: Author,
+        signer: &impl AsyncSigner,
+    ) -> std::io::Result<Signature> {
+        trace_event!(mrenclave = ?self.mrenclave, "signing measurement");
+        let message = self.message(&author);
+        let modulus = AsyncSigner::modulus(signer).await?;
+        let signature = AsyncSigner::sign(signer, &message).await?;
+        self.assemble(author, &message, &modulus, &signature)
+    }
 
-        // Generates signature on Signature author and contents
-        let rsa_key = pkey::PKey::from_rsa(key.clone())?;
-        let md = hash::MessageDigest::sha256();
-        let mut signer = sign::Signer::new(md, &rsa_key)?;
-        signer.update(a)?;
-        signer.update(c)?;
-        let signature = signer.sign_to_vec()?;
+    /// Returns the signed part of the SIGSTRUCT.
+    #[cfg(feature = "crypto")]
+    fn message(&self, author: &Author) -> Vec<u8> {
+        let mut message = author.as_bytes().to_vec();
+        message.extend_from_slice(self.as_bytes());
+        message
+    }
+
+    /// Verifies the `signature` of `message` and builds the SIGSTRUCT.
+    #[cfg(feature = "crypto")]
+    fn assemble(
+        self,
+        author: Author,
+        message: &[u8],
+        modulus: &[u8],
+        signature: &[u8],
+    ) -> std::io::Result<Signature> {
+        use openssl::{hash, sign};
+
+        // Verify the signature with the public key
+        let n = bn::BigNum::from_slice(modulus)?;
+        let e = bn::BigNum::from_u32(EXPONENT)?;
+        let public = rsa::Rsa::from_public_components(n, e)?;
+        let public = pkey::PKey::from_rsa(public)?;
+        let mut verifier = sign::Verifier::new(hash::MessageDigest::sha256(), &public)?;
+        verifier.update(message)?;
+        if !verifier.verify(signature)? {
+            trace_event!("signer produced an invalid signature");
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
 
         // Generates q1, q2 values for RSA signature verification
-        let s = bn::BigNum::from_slice(&signature)?;
-        let m = key.n();
+        let s = bn::BigNum::from_slice(signature)?;
+        let m = bn::BigNum::from_slice(modulus)?;
 
         let mut ctx = bn::BigNumContext::new()?;
         let mut q1 = bn::BigNum::new()?;
         let mut qr = bn::BigNum::new()?;
 
-        q1.div_rem(&mut qr, &(&s * &s), m, &mut ctx)?;
-        let q2 = &(&s * &qr) / m;
+        q1.div_rem(&mut qr, &(&s * &s), &m, &mut ctx)?;
+        let q2 = &(&s * &qr) / &m;
 
-        Ok(Signature {
+        let sig = Signature {
             author,
-            modulus: m.try_into()?,
+            modulus: (&*m).try_into()?,
             exponent: EXPONENT,
             signature: s.try_into()?,
             measurement: self,
             reserved: [0; 12],
             q1: q1.try_into()?,
             q2: q2.try_into()?,
-        })
+        };
+
+        // Check the exact encoding like EINIT does, not only like OpenSSL
+        if sig.verify(openssl::sha::Sha256::new()).is_err() {
+            trace_event!("signer produced a signature EINIT would reject");
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+
+        Ok(sig)
     }
 }
 
+/// The public exponent of SIGSTRUCT signing keys
+#[cfg(feature = "crypto")]
+const EXPONENT: u32 = 3;
+
+/// A backend producing SIGSTRUCT signatures, e.g. an HSM or a cloud KMS
+///
+/// The key must be an RSA-3072 key with public exponent 3. Backends report
+/// their own failures as `std::io::Error`s.
+#[cfg(feature = "crypto")]
+pub trait Signer {
+    /// Returns the big-endian modulus of the public key.
+    fn modulus(&self) -> std::io::Result<Vec<u8>>;
+
+    /// Signs `message` with RSASSA-PKCS1-v1_5 and SHA-256, returning the
+    /// big-endian signature.
+    fn sign(&self, message: &[u8]) -> std::io::Result<Vec<u8>>;
+}
+
+#[cfg(feature = "crypto")]
+impl Signer for rsa::Rsa<pkey::Private> {
+    fn modulus(&self) -> std::io::Result<Vec<u8>> {
+        Ok(self.n().to_vec())
+    }
+
+    fn sign(&self, message: &[u8]) -> std::io::Result<Vec<u8>> {
+        use openssl::{hash, sign};
+
+        let key = pkey::PKey::from_rsa(self.clone())?;
+        let mut signer = sign::Signer::new(hash::MessageDigest::sha256(), &key)?;
+        signer.update(message)?;
+        Ok(signer.sign_to_vec()?)
+    }
+}
+
+/// A boxed, sendable future, as returned by `AsyncSigner`
+#[cfg(feature = "crypto")]
+pub type SignerFuture<'a, T> =
+    core::pin::Pin<Box<dyn core::future::Future<Output = std::io::Result<T>> + Send + 'a>>;
+
+/// The asynchronous variant of `Signer`, for backends reached over the
+/// network; see `Measurement::sign_async()`
+///
+/// Every `Signer` is an `AsyncSigner` that completes immediately, and
+/// `Blocking` adapts an `AsyncSigner` back to a `Signer`.
+#[cfg(feature = "crypto")]
+pub trait AsyncSigner {
+    /// Returns the big-endian modulus of the public key.
+    fn modulus(&self) -> SignerFuture<'_, Vec<u8>>;
+
+    /// Signs `message` with RSASSA-PKCS1-v1_5 and SHA-256, returning the
+    /// big-endian signature.
+    fn sign<'a>(&'a self, message: &'a [u8]) -> SignerFuture<'a, Vec<u8>>;
+}
+
+#[cfg(feature = "crypto")]
+impl<S: Signer + Sync> AsyncSigner for S {
+    fn modulus(&self) -> SignerFuture<'_, Vec<u8>> {
+        Box::pin(async move { Signer::modulus(self) })
+    }
+
+    fn sign<'a>(&'a self, message: &'a [u8]) -> SignerFuture<'a, Vec<u8>> {
+        Box::pin(async move { Signer::sign(self, message) })
+    }
+}
+
+/// Adapts an `AsyncSigner` to a `Signer` by blocking the current thread
+/// until each operation completes
+#[cfg(feature = "crypto")]
+#[derive(Clone, Debug)]
+pub struct Blocking<A>(pub A);
+
+/// Polls `future` to completion on the current thread.
+#[cfg(feature = "crypto")]
+pub(crate) fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = Box::pin(future);
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl<A: AsyncSigner> Signer for Blocking<A> {
+    fn modulus(&self) -> std::io::Result<Vec<u8>> {
+        block_on(self.0.modulus())
+    }
+
+    fn sign(&self, message: &[u8]) -> std::io::Result<Vec<u8>> {
+        block_on(self.0.sign(message))
+    }
+}
+
+/// A 3072-bit number of a SIGSTRUCT, e.g. the RSA modulus or signature
+///
+/// SIGSTRUCT stores these in little-endian byte order.
 #[derive(Clone)]
-struct RsaNumber([u8; Self::SIZE]);
+pub struct RsaNumber([u8; Self::SIZE]);
 
 impl RsaNumber {
-    const SIZE: usize = 384;
+    /// The length of the number in bytes
+    pub const SIZE: usize = 384;
+
+    /// Returns the number as little-endian bytes, as stored in SIGSTRUCT
+    pub fn to_le_bytes(&self) -> [u8; Self::SIZE] {
+        self.0
+    }
+
+    /// Returns the number as big-endian bytes
+    pub fn to_be_bytes(&self) -> [u8; Self::SIZE] {
+        let mut be = self.0;
+        be.reverse();
+        be
+    }
+}
+
+/// Converts from exactly `RsaNumber::SIZE` little-endian bytes
+impl TryFrom<&[u8]> for RsaNumber {
+    type Error = core::array::TryFromSliceError;
+
+    #[inline]
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Ok(Self(<[u8; Self::SIZE]>::try_from(value)?))
+    }
 }
 
 impl core::fmt::Debug for RsaNumber {
@@ -313,6 +956,134 @@ impl Signature {
         self.measurement
     }
 
+    /// Get the modulus of the author's public key
+    pub fn modulus(&self) -> &RsaNumber {
+        &self.modulus
+    }
+
+    /// Get the exponent of the author's public key
+    pub fn exponent(&self) -> u32 {
+        self.exponent
+    }
+
+    /// Get the RSA signature over the author and measurement
+    pub fn signature(&self) -> &RsaNumber {
+        &self.signature
+    }
+
+    /// Get Q1, which is `floor(signature^2 / modulus)`
+    pub fn q1(&self) -> &RsaNumber {
+        &self.q1
+    }
+
+    /// Get Q2, which is `floor((signature^3 - q1 * signature * modulus) / modulus)`
+    pub fn q2(&self) -> &RsaNumber {
+        &self.q2
+    }
+
+    /// Verifies the RSA signature of the SIGSTRUCT like EINIT does, using Q1
+    /// and Q2 instead of a big number library, so that OpenSSL is not needed.
+    /// `digest` is a fresh SHA-256 implementation, e.g. `sha2::Sha256`.
+    pub fn verify(&self, mut digest: impl Digest) -> Result<(), InvalidSignature> {
+        if self.exponent != 3 {
+            return Err(InvalidSignature::Exponent);
+        }
+
+        digest.update(self.author.as_bytes());
+        digest.update(self.measurement.as_bytes());
+        let hash = digest.finish();
+
+        pkcs1::verify(&self.modulus, &self.signature, &self.q1, &self.q2, &hash)
+    }
+
+    /// Checks whether `other` signs the same enclave with the same key
+    ///
+    /// The measurement (including its parameters), the public key and the
+    /// remaining `Author` fields must match, but the signing date and the
+    /// signature itself (along with Q1 and Q2) may differ. This detects an
+    /// enclave that was merely re-signed on a different day.
+    pub fn equivalent_to(&self, other: &Self) -> bool {
+        let mut author = other.author;
+        author.date = self.author.date;
+
+        self.author == author
+            && self.measurement == other.measurement
+            && self.modulus == other.modulus
+            && self.exponent == other.exponent
+    }
+
+    /// Writes a human-readable description of the `Signature` to `w`, like
+    /// `sgx_sign dump` does. MRSIGNER is only included with the `crypto`
+    /// feature, since it is the SHA-256 hash of the modulus.
+    pub fn dump(&self, mut w: impl core::fmt::Write) -> core::fmt::Result {
+        fn hex(w: &mut impl core::fmt::Write, bytes: &[u8]) -> core::fmt::Result {
+            for b in bytes.iter() {
+                write!(w, "{:02x}", b)?;
+            }
+
+            writeln!(w)
+        }
+
+        let author = self.author;
+        let params = self.measurement.parameters();
+        let date = author.date;
+
+        writeln!(
+            w,
+            "date: {:04x}-{:02x}-{:02x}",
+            date >> 16,
+            (date >> 8) & 0xff,
+            date & 0xff
+        )?;
+        writeln!(w, "vendor: {:#06x}", author.vendor)?;
+        writeln!(w, "swdefined: {:#010x}", author.swdefined.inner())?;
+
+        write!(w, "mrenclave: ")?;
+        hex(&mut w, &self.measurement.mrenclave)?;
+
+        #[cfg(feature = "crypto")]
+        {
+            write!(w, "mrsigner: ")?;
+            hex(&mut w, &openssl::sha::sha256(&self.modulus.0))?;
+        }
+
+        writeln!(w, "isv_prod_id: {}", params.isv_prod_id.inner())?;
+        writeln!(w, "isv_svn: {}", params.isv_svn.inner())?;
+        writeln!(
+            w,
+            "miscselect: {:#010x} (mask {:#010x})",
+            params.misc.data.bits(),
+            params.misc.mask.bits()
+        )?;
+        writeln!(
+            w,
+            "attributes.flags: {:#018x} (mask {:#018x})",
+            params.attr.data.features().bits(),
+            params.attr.mask.features().bits()
+        )?;
+        writeln!(
+            w,
+            "attributes.xfrm: {:#018x} (mask {:#018x})",
+            params.attr.data.xfrm().bits(),
+            params.attr.mask.xfrm().bits()
+        )?;
+        writeln!(w, "exponent: {}", self.exponent)?;
+
+        write!(w, "modulus: ")?;
+        hex(&mut w, &self.modulus.to_be_bytes())
+    }
+
+    /// Returns the bytes of the `Signature`, in the SIGSTRUCT layout
+    pub fn as_bytes(&self) -> &[u8] {
+        // # Safety
+        //
+        // This is safe because `Signature` is `repr(C)` without padding and
+        // all of its fields are plain integers or byte arrays.
+        unsafe {
+            core::slice::from_raw_parts(self as *const _ as *const u8, core::mem::size_of_val(self))
+        }
+    }
+
     /// Read a `Signature` from a file
     #[cfg(feature = "std")]
     pub fn read_from(mut reader: impl std::io::Read) -> std::io::Result<Self> {
@@ -330,6 +1101,99 @@ impl Signature {
     }
 }
 
+// An unsigned `Signature`, for the simulated launch tests.
+#[cfg(all(test, feature = "sim"))]
+impl Signature {
+    pub(crate) fn unsigned(author: Author, measurement: Measurement, modulus: RsaNumber) -> Self {
+        Self {
+            author,
+            modulus,
+            exponent: 3,
+            signature: RsaNumber([0; RsaNumber::SIZE]),
+            measurement,
+            reserved: [0; 12],
+            q1: RsaNumber([0; RsaNumber::SIZE]),
+            q2: RsaNumber([0; RsaNumber::SIZE]),
+        }
+    }
+}
+
+// Arbitrary values are structurally valid: constant and reserved fields keep
+// their fixed values, masked data has no bits set outside of its mask and the
+// exponent is always 3, but the signature itself is not expected to verify.
+#[cfg(feature = "fuzzing")]
+impl<'a, T> arbitrary::Arbitrary<'a> for Masked<T>
+where
+    T: arbitrary::Arbitrary<'a>,
+    T: BitAnd<Output = T>,
+    T: Copy,
+{
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let data: T = u.arbitrary()?;
+        let mask: T = u.arbitrary()?;
+        Ok(Self {
+            data: data & mask,
+            mask,
+        })
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Author {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let author = Self::new(u.arbitrary()?, SwDefined::new(u.arbitrary()?));
+        Ok(author.with_vendor(*u.choose(&[Vendor::Other, Vendor::Intel])?))
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Parameters {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            misc: u.arbitrary()?,
+            attr: u.arbitrary()?,
+            isv_prod_id: u.arbitrary()?,
+            isv_svn: u.arbitrary()?,
+            launch_enclave: u.arbitrary()?,
+        })
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Measurement {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let parameters: Parameters = u.arbitrary()?;
+        let mut mrenclave = [0u8; 32];
+        u.fill_buffer(&mut mrenclave)?;
+        Ok(parameters.measurement(mrenclave))
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for RsaNumber {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut number = [0u8; Self::SIZE];
+        u.fill_buffer(&mut number)?;
+        Ok(Self(number))
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for Signature {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            author: u.arbitrary()?,
+            modulus: u.arbitrary()?,
+            exponent: 3,
+            signature: u.arbitrary()?,
+            measurement: u.arbitrary()?,
+            reserved: [0; 12],
+            q1: u.arbitrary()?,
+            q2: u.arbitrary()?,
+        })
+    }
+}
+
 #[cfg(test)]
 testaso! {
     struct Author: 8, 128 => {
@@ -365,11 +1229,12 @@ testaso! {
 
 #[cfg(test)]
 mod author {
-    use super::Author;
+    use super::{Author, InvalidDate, SwDefined, Vendor};
+    use core::convert::TryFrom;
 
     #[test]
     fn author_instantiation() {
-        let author = Author::new(20000330, 0u32);
+        let author = Author::new(20000330, SwDefined::new(0));
         assert_eq!(
             author.header1,
             u128::from_be(0x06000000E10000000000010000000000)
@@ -379,7 +1244,380 @@ mod author {
             author.header2,
             u128::from_be(0x01010000600000006000000001000000)
         );
-        assert_eq!(author.swdefined, 0u32);
+        assert_eq!(author.swdefined, SwDefined::new(0));
         assert_eq!(author.reserved, [0; 21]);
+        assert!(author.is_valid_header());
+    }
+
+    #[test]
+    fn author_header() {
+        let author = Author::new(20000330, SwDefined::new(0)).with_vendor(Vendor::Intel);
+        assert!(author.is_valid_header());
+        assert_eq!(&author.as_bytes()[..16], &Author::HEADER1);
+        assert_eq!(&author.as_bytes()[24..40], &Author::HEADER2);
+
+        let bad = Author {
+            header2: 0,
+            ..author
+        };
+        assert!(!bad.is_valid_header());
+        assert_eq!(bad.vendor(), Ok(Vendor::Intel));
+    }
+
+    #[test]
+    fn author_as_bytes() {
+        let author = Author::new(20000330, SwDefined::new(0));
+        let bytes = author.as_bytes();
+        assert_eq!(&bytes[..16], &author.header1.to_ne_bytes());
+        assert_eq!(&bytes[20..24], &20000330u32.to_le_bytes());
+    }
+
+    #[test]
+    fn author_vendor() {
+        let author = Author::new(20000330, SwDefined::new(0));
+        assert_eq!(author.vendor(), Ok(Vendor::Other));
+
+        let author = author.with_vendor(Vendor::Intel);
+        assert_eq!(author.vendor(), Ok(Vendor::Intel));
+        assert_eq!(&author.as_bytes()[16..20], &0x8086u32.to_le_bytes());
+
+        assert_eq!(Vendor::try_from(0x1234), Err(0x1234));
+    }
+
+    #[test]
+    fn author_from_unix_time() {
+        let swdefined = SwDefined::new(0);
+        let date = |secs| Author::from_unix_time(secs, swdefined).map(|a| a.date);
+
+        assert_eq!(date(0), Ok(0x19700101));
+        assert_eq!(date(954374400), Ok(0x20000330));
+        assert_eq!(date(951782400 + 86399), Ok(0x20000229));
+        assert_eq!(date(4102444799), Ok(0x20991231));
+        assert_eq!(date(253402300800), Err(InvalidDate));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn author_today() {
+        let author = Author::today(SwDefined::new(7)).unwrap();
+        let date = author.date;
+        assert!(date >= 0x20240101, "date: {:x}", date);
+        assert!((1..=0x12).contains(&(date >> 8 & 0xff)));
+        assert!((1..=0x31).contains(&(date & 0xff)));
+        assert_eq!(author.swdefined, SwDefined::new(7));
+    }
+}
+
+#[cfg(test)]
+mod rsa_number {
+    use super::RsaNumber;
+    use core::convert::TryFrom;
+
+    #[test]
+    fn bytes() {
+        let mut le = [0u8; RsaNumber::SIZE];
+        le[0] = 1;
+        let number = RsaNumber::try_from(&le[..]).unwrap();
+        assert_eq!(number.to_le_bytes()[..], le[..]);
+        assert_eq!(number.to_be_bytes()[RsaNumber::SIZE - 1], 1);
+        assert!(RsaNumber::try_from(&le[1..]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod dump {
+    use super::*;
+
+    #[test]
+    fn signature() {
+        let sig = Signature {
+            author: Author::new(0x20000330, SwDefined::new(0)),
+            modulus: RsaNumber([0xab; RsaNumber::SIZE]),
+            exponent: 3,
+            signature: RsaNumber([0; RsaNumber::SIZE]),
+            measurement: Parameters::default().measurement([0x11; 32]),
+            reserved: [0; 12],
+            q1: RsaNumber([0; RsaNumber::SIZE]),
+            q2: RsaNumber([0; RsaNumber::SIZE]),
+        };
+
+        let mut out = String::new();
+        sig.dump(&mut out).unwrap();
+
+        assert!(out.contains("date: 2000-03-30\n"));
+        assert!(out.contains(&format!("mrenclave: {}\n", "11".repeat(32))));
+        assert!(out.contains("exponent: 3\n"));
+        assert!(out.contains(&format!("modulus: {}\n", "ab".repeat(RsaNumber::SIZE))));
+    }
+}
+
+#[cfg(test)]
+mod masked {
+    use super::*;
+
+    #[test]
+    fn new() {
+        let masked = Masked::new(MiscSelect::EXINFO, MiscSelect::EXINFO).unwrap();
+        assert!(masked.matches(&MiscSelect::EXINFO));
+        assert!(!masked.matches(&MiscSelect::empty()));
+
+        let masked = Masked::new(MiscSelect::empty(), MiscSelect::empty()).unwrap();
+        assert!(masked.matches(&MiscSelect::EXINFO));
+        assert!(masked.matches(&MiscSelect::empty()));
+
+        assert_eq!(
+            Masked::new(MiscSelect::EXINFO, MiscSelect::empty()),
+            Err(InvalidMask)
+        );
+    }
+}
+
+#[cfg(test)]
+mod measurement {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let params = Parameters {
+            misc: Masked {
+                data: MiscSelect::EXINFO,
+                mask: MiscSelect::EXINFO,
+            },
+            attr: Masked {
+                data: Attributes::new(Features::MODE64BIT, Xfrm::X87 | Xfrm::SSE),
+                mask: Attributes::new(Features::DEBUG | Features::MODE64BIT, Xfrm::all()),
+            },
+            isv_prod_id: ProductId::new(1),
+            isv_svn: SecurityVersion::new(2),
+            launch_enclave: false,
+        };
+
+        let measurement = params.measurement([0; 32]);
+        assert_eq!(measurement.attributes(), params.attr);
+        assert_eq!(measurement.misc_select(), params.misc);
+        assert_eq!(measurement.isv_prod_id(), ProductId::new(1));
+        assert_eq!(measurement.isv_svn(), SecurityVersion::new(2));
+    }
+}
+
+#[cfg(test)]
+mod equivalent {
+    use super::*;
+
+    fn signature(date: u32, signature: u8) -> Signature {
+        Signature {
+            author: Author::new(date, SwDefined::new(0)),
+            modulus: RsaNumber([0xab; RsaNumber::SIZE]),
+            exponent: 3,
+            signature: RsaNumber([signature; RsaNumber::SIZE]),
+            measurement: Parameters::default().measurement([0x11; 32]),
+            reserved: [0; 12],
+            q1: RsaNumber([signature; RsaNumber::SIZE]),
+            q2: RsaNumber([signature; RsaNumber::SIZE]),
+        }
+    }
+
+    #[test]
+    fn resigned() {
+        let sig = signature(0x20000330, 1);
+        assert!(sig.equivalent_to(&sig));
+        assert!(sig.equivalent_to(&signature(0x20210101, 2)));
+    }
+
+    #[test]
+    fn different() {
+        let sig = signature(0x20000330, 1);
+
+        let mut other = sig.clone();
+        other.measurement = Parameters::default().measurement([0x22; 32]);
+        assert!(!sig.equivalent_to(&other));
+
+        let mut other = sig.clone();
+        other.modulus = RsaNumber([0xcd; RsaNumber::SIZE]);
+        assert!(!sig.equivalent_to(&other));
+
+        let mut other = sig.clone();
+        other.exponent = 65537;
+        assert!(!sig.equivalent_to(&other));
+
+        let mut other = sig.clone();
+        other.author = other.author.with_vendor(Vendor::Intel);
+        assert!(!sig.equivalent_to(&other));
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod check {
+    use super::*;
+    use crate::attestation_types::report::Body;
+
+    #[test]
+    fn mismatches() {
+        let params = Parameters {
+            misc: Masked {
+                data: MiscSelect::EXINFO,
+                mask: MiscSelect::EXINFO,
+            },
+            attr: Masked {
+                data: Attributes::new(Features::MODE64BIT, Xfrm::empty()),
+                mask: Attributes::new(Features::DEBUG | Features::MODE64BIT, Xfrm::empty()),
+            },
+            isv_prod_id: ProductId::new(1),
+            isv_svn: SecurityVersion::new(2),
+            launch_enclave: false,
+        };
+
+        let mut body = Body::default();
+        body.miscselect = MiscSelect::EXINFO;
+        body.attributes = Attributes::new(Features::MODE64BIT | Features::INIT, Xfrm::empty());
+        body.isv_prod_id = ProductId::new(1);
+        body.isv_svn = SecurityVersion::new(3);
+        assert_eq!(params.check(&body), Ok(()));
+
+        body.attributes = Attributes::new(Features::DEBUG | Features::MODE64BIT, Xfrm::empty());
+        body.isv_prod_id = ProductId::new(2);
+        body.isv_svn = SecurityVersion::new(1);
+        assert_eq!(
+            params.check(&body),
+            Err(vec![
+                Mismatch::Features(Features::DEBUG),
+                Mismatch::ProductId {
+                    expected: ProductId::new(1),
+                    actual: ProductId::new(2),
+                },
+                Mismatch::SecurityVersion {
+                    expected: SecurityVersion::new(2),
+                    actual: SecurityVersion::new(1),
+                },
+            ])
+        );
+    }
+}
+
+#[cfg(test)]
+mod findings {
+    use super::*;
+
+    #[test]
+    fn findings() {
+        assert_eq!(Parameters::default().security_findings().count(), 0);
+
+        let params = Parameters {
+            attr: Masked {
+                data: Attributes::new(Features::PROVISIONING_KEY, Xfrm::empty()),
+                mask: Attributes::new(
+                    Features::PROVISIONING_KEY | Features::EINIT_KEY,
+                    Xfrm::empty(),
+                ),
+            },
+            ..Default::default()
+        };
+
+        let mut findings = params.security_findings();
+        assert_eq!(findings.next(), Some(Finding::DebugNotMasked));
+        assert_eq!(findings.next(), Some(Finding::ProvisioningKeyAllowed));
+        assert_eq!(findings.next(), None);
+    }
+}
+
+#[cfg(test)]
+mod validate {
+    use super::*;
+
+    fn params(features: Features, xfrm: Xfrm) -> Parameters {
+        Parameters {
+            attr: Attributes::new(features, xfrm).into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let legacy = Xfrm::X87 | Xfrm::SSE;
+        let avx512 = Xfrm::OPMASK | Xfrm::ZMM_HI256 | Xfrm::HI16_ZMM;
+
+        assert_eq!(params(Features::MODE64BIT, legacy).validate(), Ok(()));
+        assert_eq!(
+            params(Features::MODE64BIT, legacy | Xfrm::AVX | avx512).validate(),
+            Ok(())
+        );
+
+        assert_eq!(
+            params(Features::INIT, legacy).validate(),
+            Err(InvalidParameters::Init)
+        );
+        assert_eq!(
+            params(Features::MODE64BIT, Xfrm::X87).validate(),
+            Err(InvalidParameters::XfrmLegacy)
+        );
+        assert_eq!(
+            params(Features::MODE64BIT, legacy | avx512).validate(),
+            Err(InvalidParameters::XfrmAvx512)
+        );
+        assert_eq!(
+            params(Features::MODE64BIT, legacy | Xfrm::BNDREG).validate(),
+            Err(InvalidParameters::XfrmMpx)
+        );
+        assert_eq!(
+            params(Features::EINIT_KEY, legacy).validate(),
+            Err(InvalidParameters::EinitKey)
+        );
+
+        let mut launch = params(Features::EINIT_KEY, legacy);
+        launch.launch_enclave = true;
+        assert_eq!(launch.validate(), Ok(()));
+
+        // Bits outside the mask are not enforced, so they are acceptable.
+        let mut masked = params(Features::EINIT_KEY, legacy);
+        masked.attr.mask = Attributes::new(Features::empty(), legacy);
+        assert_eq!(masked.validate(), Ok(()));
+    }
+
+    #[test]
+    fn key_policy() {
+        let legacy = Xfrm::X87 | Xfrm::SSE;
+        let plain = params(Features::MODE64BIT, legacy);
+        let kss = params(Features::MODE64BIT | Features::KSS, legacy);
+
+        assert_eq!(plain.validate_key_policy(KeyPolicy::MRSIGNER), Ok(()));
+        for policy in [
+            KeyPolicy::CONFIGID,
+            KeyPolicy::ISVFAMILYID,
+            KeyPolicy::ISVEXTPRODID,
+        ]
+        .iter()
+        {
+            let policy = KeyPolicy::MRSIGNER | *policy;
+            assert_eq!(
+                plain.validate_key_policy(policy),
+                Err(InvalidParameters::Kss)
+            );
+            assert_eq!(kss.validate_key_policy(policy), Ok(()));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "sha2"))]
+mod verify {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn sha2() {
+        let sig = Signature::read_from(File::open("tests/encl.ss").unwrap()).unwrap();
+        assert_eq!(sig.verify(sha2::Sha256::default()), Ok(()));
+
+        let mut bytes = sig.as_bytes().to_vec();
+        bytes[900] ^= 1; // measurement
+        let bad = Signature::read_from(&bytes[..]).unwrap();
+        assert_eq!(
+            bad.verify(sha2::Sha256::default()),
+            Err(InvalidSignature::Mismatch)
+        );
+
+        let mut bytes = sig.as_bytes().to_vec();
+        bytes[600] ^= 1; // signature
+        let bad = Signature::read_from(&bytes[..]).unwrap();
+        assert!(bad.verify(sha2::Sha256::default()).is_err());
     }
 }
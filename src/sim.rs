@@ -0,0 +1,367 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Simulation of SGX key derivation and, with the `sim` feature, of enclave
+//! launch and local attestation
+//!
+//! THE KEYS DERIVED HERE ARE NOT SECRET. They are derived from a fixed root
+//! key compiled into this crate, so that code using EGETKEY (e.g. sealing or
+//! Report MAC verification) can be tested on machines without SGX. They must
+//! never protect real data.
+
+use crate::attestation_types::report::Body;
+use crate::{KeyName, KeyPolicy, KeyRequest};
+
+#[cfg(feature = "sim")]
+use crate::attestation_types::{report::Report, ti::TargetInfo};
+#[cfg(feature = "sim")]
+use crate::{
+    Attributes, CpuSvn, Digest, Features, InvalidParameters, Measurement, SecurityVersion,
+    Signature,
+};
+
+use cmac::{Cmac, Mac, NewMac};
+
+/// The public root key from which all simulated keys are derived
+pub const ROOT_KEY: [u8; 16] = *b"sgx-sim-root-key";
+
+/// A `KeyRequest` that EGETKEY would reject
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidKeyRequest {
+    /// The enclave's attributes do not allow it to request the key
+    NotAllowed(KeyName),
+
+    /// The requested ISVSVN is newer than the enclave's
+    IsvSvn,
+
+    /// The requested CPUSVN is not at most the platform's
+    CpuSvn,
+}
+
+impl core::fmt::Display for InvalidKeyRequest {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidKeyRequest::NotAllowed(name) => write!(f, "{:?} key not allowed", name),
+            InvalidKeyRequest::IsvSvn => write!(f, "ISVSVN is newer than the enclave's"),
+            InvalidKeyRequest::CpuSvn => write!(f, "CPUSVN is newer than the platform's"),
+        }
+    }
+}
+
+/// Simulates EGETKEY for the enclave described by `enclave`, whose CPUSVN is
+/// that of the simulated platform.
+///
+/// Like EGETKEY, the key is derived from the request's key name, key ID,
+/// security versions and masks, and from the enclave identities selected by
+/// the key policy. The Report key only depends on the enclave's MRENCLAVE and
+/// attributes, the platform's CPUSVN and the key ID, so that it matches the
+/// key a simulated EREPORT targeting the enclave would use.
+///
+/// With the `zeroize` feature, the intermediate copies of the key are zeroed
+/// and the returned key can be held in `zeroize::Zeroizing`.
+pub fn egetkey(request: &KeyRequest, enclave: &Body) -> Result<[u8; 16], InvalidKeyRequest> {
+    request
+        .keyname
+        .check(&enclave.attributes)
+        .map_err(|e| InvalidKeyRequest::NotAllowed(e.0))?;
+
+    let mut cmac = Cmac::<aes::Aes128>::new(&ROOT_KEY.into());
+    cmac.update(&(request.keyname as u16).to_le_bytes());
+
+    if request.keyname == KeyName::Report {
+        cmac.update(&enclave.mrenclave);
+        cmac.update(&enclave.attributes.features().bits().to_le_bytes());
+        cmac.update(&enclave.attributes.xfrm().bits().to_le_bytes());
+        cmac.update(&enclave.cpusvn.inner());
+    } else {
+        if request.isvsvn > enclave.isv_svn {
+            return Err(InvalidKeyRequest::IsvSvn);
+        }
+
+        if !enclave.cpusvn.is_at_least(&request.cpusvn) {
+            return Err(InvalidKeyRequest::CpuSvn);
+        }
+
+        let policy = request.keypolicy;
+        let attributes = enclave.attributes & request.attributemask;
+        let misc = enclave.miscselect & request.miscmask;
+
+        cmac.update(&policy.bits().to_le_bytes());
+        cmac.update(&request.isvsvn.inner().to_le_bytes());
+        cmac.update(&request.cpusvn.inner());
+        cmac.update(&attributes.features().bits().to_le_bytes());
+        cmac.update(&attributes.xfrm().bits().to_le_bytes());
+        cmac.update(&misc.bits().to_le_bytes());
+        cmac.update(&request.configsvn.to_le_bytes());
+
+        if policy.contains(KeyPolicy::MRENCLAVE) {
+            cmac.update(&enclave.mrenclave);
+        }
+
+        if policy.contains(KeyPolicy::MRSIGNER) {
+            cmac.update(&enclave.mrsigner);
+        }
+
+        if !policy.contains(KeyPolicy::NOISVPRODID) {
+            cmac.update(&enclave.isv_prod_id.inner().to_le_bytes());
+        }
+    }
+
+    cmac.update(&request.keyid);
+
+    #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+    let mut mac = cmac.finalize().into_bytes();
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&mac);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(mac.as_mut_slice());
+
+    Ok(key)
+}
+
+/// A `Signature` that EINIT would reject, see `Enclave::launch()`
+#[cfg(feature = "sim")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LaunchError {
+    /// The signed MRENCLAVE is not the one of the loaded enclave
+    Measurement,
+
+    /// The signed parameters are invalid
+    Parameters(InvalidParameters),
+}
+
+#[cfg(feature = "sim")]
+impl core::fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LaunchError::Measurement => write!(f, "MRENCLAVE does not match the signature"),
+            LaunchError::Parameters(e) => write!(f, "invalid parameters: {}", e),
+        }
+    }
+}
+
+/// A simulated, initialized enclave
+///
+/// Its identity is what EREPORT would report for it. Note that the RSA
+/// signature of the `Signature` is not verified at launch.
+#[cfg(feature = "sim")]
+#[derive(Clone, Copy)]
+pub struct Enclave {
+    identity: Body,
+}
+
+#[cfg(feature = "sim")]
+impl Enclave {
+    /// Simulates EINIT of the enclave measured as `measurement` (e.g. with the
+    /// `Hasher`) on a platform with the given `cpusvn`.
+    ///
+    /// As with EINIT, the signed MRENCLAVE must match the measured one. The
+    /// enclave gets the signed MISCSELECT and attributes, with INIT set, and
+    /// MRSIGNER is the hash of the signer's modulus.
+    pub fn launch(
+        measurement: &Measurement,
+        signature: &Signature,
+        cpusvn: CpuSvn,
+    ) -> Result<Self, LaunchError> {
+        let signed = signature.measurement();
+        if signed.mrenclave() != measurement.mrenclave() {
+            return Err(LaunchError::Measurement);
+        }
+
+        let params = signed.parameters();
+        params.validate().map_err(LaunchError::Parameters)?;
+        let attr = params.attr.data & params.attr.mask;
+
+        let mut mrsigner = sha2::Sha256::default();
+        Digest::update(&mut mrsigner, &signature.modulus().to_le_bytes());
+
+        let mut identity = Body::default();
+        identity.cpusvn = cpusvn;
+        identity.miscselect = params.misc.data & params.misc.mask;
+        identity.attributes = Attributes::new(attr.features() | Features::INIT, attr.xfrm());
+        identity.mrenclave = measurement.mrenclave();
+        identity.mrsigner = Digest::finish(mrsigner);
+        identity.isv_prod_id = params.isv_prod_id;
+        identity.isv_svn = params.isv_svn;
+
+        trace_event!(mrenclave = ?identity.mrenclave, "simulated EINIT");
+        Ok(Self { identity })
+    }
+
+    /// Returns the identity of the enclave, as reported by EREPORT
+    pub fn identity(&self) -> &Body {
+        &self.identity
+    }
+
+    /// Returns the Target Info with which other enclaves target their Reports
+    /// at this enclave
+    pub fn target_info(&self) -> TargetInfo {
+        let mut target = TargetInfo::default();
+        target.mrenclave = self.identity.mrenclave;
+        target.attributes = self.identity.attributes;
+        target.misc = self.identity.miscselect;
+        target
+    }
+
+    /// Simulates EREPORT: produces a Report of this enclave with `reportdata`,
+    /// MACed with the Report key of the `target` enclave.
+    pub fn ereport(&self, target: &TargetInfo, reportdata: [u8; 64]) -> Report {
+        let mut body = self.identity;
+        body.reportdata = reportdata;
+
+        let mut report = Report::default();
+        report.reportbody = body;
+
+        let mut identity = Body::default();
+        identity.cpusvn = self.identity.cpusvn;
+        identity.attributes = target.attributes;
+        identity.mrenclave = target.mrenclave;
+        #[cfg(feature = "zeroize")]
+        let key = zeroize::Zeroizing::new(report_key(&identity, &report.keyid));
+        #[cfg(not(feature = "zeroize"))]
+        let key = report_key(&identity, &report.keyid);
+        report.mac = report.compute_mac(&key);
+        report
+    }
+
+    /// Simulates EGETKEY in this enclave
+    pub fn egetkey(&self, request: &KeyRequest) -> Result<[u8; 16], InvalidKeyRequest> {
+        egetkey(request, &self.identity)
+    }
+
+    /// Returns the Report key with which `report` was MACed, if it targets
+    /// this enclave
+    pub fn report_key(&self, report: &Report) -> [u8; 16] {
+        report_key(&self.identity, &report.keyid)
+    }
+}
+
+#[cfg(feature = "sim")]
+fn report_key(enclave: &Body, keyid: &[u8; 32]) -> [u8; 16] {
+    let mut request = KeyRequest::new(
+        KeyName::Report,
+        KeyPolicy::empty(),
+        SecurityVersion::default(),
+        CpuSvn::default(),
+    );
+    request.keyid = *keyid;
+
+    // The Report key is available to every enclave.
+    let key = egetkey(&request, enclave).unwrap();
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut request);
+
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CpuSvn, SecurityVersion};
+
+    fn enclave() -> Body {
+        let mut enclave = Body::default();
+        enclave.mrenclave = [1; 32];
+        enclave.mrsigner = [2; 32];
+        enclave.isv_svn = SecurityVersion::new(2);
+        enclave.cpusvn = CpuSvn::new([3; 16]);
+        enclave
+    }
+
+    fn seal(policy: KeyPolicy, isvsvn: u16) -> KeyRequest {
+        KeyRequest::new(
+            KeyName::Seal,
+            policy,
+            SecurityVersion::new(isvsvn),
+            CpuSvn::new([3; 16]),
+        )
+    }
+
+    #[test]
+    fn deterministic() {
+        let request = seal(KeyPolicy::MRSIGNER, 2);
+        assert_eq!(egetkey(&request, &enclave()), egetkey(&request, &enclave()));
+    }
+
+    #[test]
+    fn policy() {
+        let mut other = enclave();
+        other.mrenclave = [4; 32];
+
+        let request = seal(KeyPolicy::MRSIGNER, 2);
+        assert_eq!(egetkey(&request, &enclave()), egetkey(&request, &other));
+
+        let request = seal(KeyPolicy::MRENCLAVE, 2);
+        assert_ne!(egetkey(&request, &enclave()), egetkey(&request, &other));
+    }
+
+    #[test]
+    fn versions() {
+        let old = seal(KeyPolicy::MRSIGNER, 1);
+        let new = seal(KeyPolicy::MRSIGNER, 2);
+        assert_ne!(egetkey(&old, &enclave()), egetkey(&new, &enclave()));
+
+        let newer = seal(KeyPolicy::MRSIGNER, 3);
+        assert_eq!(egetkey(&newer, &enclave()), Err(InvalidKeyRequest::IsvSvn));
+
+        let mut request = seal(KeyPolicy::MRSIGNER, 2);
+        request.cpusvn = CpuSvn::new([4; 16]);
+        assert_eq!(
+            egetkey(&request, &enclave()),
+            Err(InvalidKeyRequest::CpuSvn)
+        );
+    }
+
+    #[test]
+    fn not_allowed() {
+        let request = KeyRequest::new(
+            KeyName::Provision,
+            KeyPolicy::MRSIGNER,
+            SecurityVersion::new(2),
+            CpuSvn::new([3; 16]),
+        );
+
+        assert_eq!(
+            egetkey(&request, &enclave()),
+            Err(InvalidKeyRequest::NotAllowed(KeyName::Provision))
+        );
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn local_attestation() {
+        use crate::{Author, Parameters, RsaNumber, SwDefined, Xfrm};
+        use core::convert::TryFrom;
+
+        let mut params = Parameters::default();
+        params.attr.data = Attributes::new(Features::MODE64BIT, Xfrm::X87 | Xfrm::SSE);
+
+        let measurement = params.measurement([5; 32]);
+        let signature = Signature::unsigned(
+            Author::new(0x20000330, SwDefined::new(0)),
+            measurement,
+            RsaNumber::try_from(&[0xab; RsaNumber::SIZE][..]).unwrap(),
+        );
+
+        let cpusvn = CpuSvn::new([3; 16]);
+        let source = Enclave::launch(&measurement, &signature, cpusvn).unwrap();
+        assert!(source
+            .identity()
+            .attributes
+            .features()
+            .contains(Features::INIT));
+
+        let other = params.measurement([6; 32]);
+        let target = Enclave::launch(&other, &signature, cpusvn);
+        assert_eq!(target.err(), Some(LaunchError::Measurement));
+
+        let report = source.ereport(&source.target_info(), [7; 64]);
+        assert!(report.verify_mac(&source.report_key(&report)));
+
+        let mut other = source.target_info();
+        other.mrenclave = [6; 32];
+        let report = source.ereport(&other, [7; 64]);
+        assert!(!report.verify_mac(&source.report_key(&report)));
+    }
+}
@@ -0,0 +1,437 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! SSA (Section 38.9)
+//! When an asynchronous exit (AEX) occurs, the processor saves the state of
+//! the enclave thread in the current State Save Area (SSA) frame. The GPRSGX
+//! region is at the end of the frame, with the XSAVE region at its start.
+//! The MISC region and, for CET enclaves, the CET state save area are stored
+//! immediately below the GPRSGX region. The layout of the XSAVE region is
+//! determined by the enclave's XFRM.
+
+use crate::{Attributes, ExitInfo, Features, MiscSelect, Xfrm};
+use core::convert::TryInto;
+use core::mem::size_of;
+use core::num::NonZeroU32;
+
+const PAGE: usize = 4096;
+
+// The size of the EXINFO structure in the MISC region (Section 38.9.2)
+const EXINFO_SIZE: usize = 16;
+
+// The sizes of the legacy region and header of the XSAVE region (Section 13.4)
+const XSAVE_LEGACY_SIZE: usize = 512;
+const XSAVE_HEADER_SIZE: usize = 64;
+
+// The value of the x87 FPU control word in its initial state
+const FCW_INIT: u16 = 0x037F;
+
+/// Section 38.9.1
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Gpr {
+    rax: u64,
+    rcx: u64,
+    rdx: u64,
+    rbx: u64,
+    rsp: u64,
+    rbp: u64,
+    rsi: u64,
+    rdi: u64,
+    r8: u64,
+    r9: u64,
+    r10: u64,
+    r11: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+    rflags: u64,
+    rip: u64,
+    ursp: u64,
+    urbp: u64,
+    exitinfo: u32,
+    reserved: [u8; 3],
+    aexnotify: u8,
+    fsbase: u64,
+    gsbase: u64,
+}
+
+// Generates a getter and a setter for each register of `Gpr`.
+macro_rules! registers {
+    ($($reg:ident, $set:ident: $doc:literal;)+) => {
+        $(
+            #[doc = $doc]
+            #[inline]
+            pub const fn $reg(&self) -> u64 {
+                self.$reg
+            }
+
+            #[doc = $doc]
+            #[inline]
+            pub fn $set(&mut self, value: u64) {
+                self.$reg = value;
+            }
+        )+
+    };
+}
+
+impl Gpr {
+    registers! {
+        rax, set_rax: "The RAX register; the return value";
+        rcx, set_rcx: "The RCX register; the fourth argument";
+        rdx, set_rdx: "The RDX register; the third argument";
+        rbx, set_rbx: "The RBX register";
+        rsp, set_rsp: "The RSP register; the stack pointer";
+        rbp, set_rbp: "The RBP register; the frame pointer";
+        rsi, set_rsi: "The RSI register; the second argument";
+        rdi, set_rdi: "The RDI register; the first argument";
+        r8, set_r8: "The R8 register; the fifth argument";
+        r9, set_r9: "The R9 register; the sixth argument";
+        r10, set_r10: "The R10 register";
+        r11, set_r11: "The R11 register";
+        r12, set_r12: "The R12 register";
+        r13, set_r13: "The R13 register";
+        r14, set_r14: "The R14 register";
+        r15, set_r15: "The R15 register";
+        rflags, set_rflags: "The RFLAGS register";
+        rip, set_rip: "The RIP register; the instruction pointer";
+    }
+
+    /// Advances RIP past the faulting instruction of `len` bytes, so that
+    /// the thread resumes after it.
+    #[inline]
+    pub fn skip_instruction(&mut self, len: u64) {
+        self.rip = self.rip.wrapping_add(len);
+    }
+
+    /// Returns the EXITINFO field, reporting the exception that caused the
+    /// AEX, if any.
+    pub const fn exit_info(&self) -> ExitInfo {
+        ExitInfo::from_raw(self.exitinfo)
+    }
+
+    /// Sets the EXITINFO field, e.g. to report an exception emulated on the
+    /// host.
+    pub fn set_exit_info(&mut self, info: ExitInfo) {
+        self.exitinfo = info.raw();
+    }
+
+    /// Returns true if AEX-Notify is enabled for this SSA frame, i.e. if an
+    /// AEX will deliver a notification when the thread resumes.
+    pub const fn aex_notify(&self) -> bool {
+        self.aexnotify & 1 != 0
+    }
+
+    /// Enables or disables AEX-Notify for this SSA frame.
+    pub fn set_aex_notify(&mut self, enabled: bool) {
+        self.aexnotify = (self.aexnotify & !1) | enabled as u8;
+    }
+}
+
+/// The legacy region of the XSAVE area: the x87 and SSE state (Section 10.5.1)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fxsave {
+    /// The x87 FPU control word
+    pub fcw: u16,
+
+    /// The x87 FPU status word
+    pub fsw: u16,
+
+    /// The abridged x87 FPU tag word
+    pub ftw: u8,
+
+    /// The x87 FPU opcode
+    pub fop: u16,
+
+    /// The x87 FPU instruction pointer
+    pub fip: u64,
+
+    /// The x87 FPU data pointer
+    pub fdp: u64,
+
+    /// The MXCSR register
+    pub mxcsr: u32,
+
+    /// The mask of the supported MXCSR bits
+    pub mxcsr_mask: u32,
+
+    /// The x87 registers ST0-ST7 (or MMX registers MM0-MM7), 80 bits each
+    pub st: [[u8; 10]; 8],
+
+    /// The XMM registers XMM0-XMM15
+    pub xmm: [u128; 16],
+}
+
+/// The XSAVE region at the start of an SSA frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Xsave {
+    /// The x87 and SSE state
+    pub legacy: Fxsave,
+
+    /// The state components that are not in their initial state (XSTATE_BV)
+    pub xstate_bv: Xfrm,
+
+    /// The compaction mask (XCOMP_BV); zero for the standard format
+    pub xcomp_bv: u64,
+}
+
+impl Xsave {
+    /// Parses the legacy region and the header of the XSAVE region of an SSA
+    /// `frame` for an enclave with the given XFRM. Components that are in
+    /// their initial state are reported with their initial values.
+    ///
+    /// Returns None if the frame is too short or XSTATE_BV has bits outside
+    /// of XFRM.
+    pub fn parse(frame: &[u8], xfrm: Xfrm) -> Option<Self> {
+        let bytes = frame.get(..XSAVE_LEGACY_SIZE + XSAVE_HEADER_SIZE)?;
+        let u16_at = |i: usize| u16::from_le_bytes(bytes[i..i + 2].try_into().unwrap());
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+
+        let xstate_bv = Xfrm::from_bits(u64_at(XSAVE_LEGACY_SIZE))?;
+        if !xfrm.contains(xstate_bv) {
+            return None;
+        }
+
+        let mut legacy = Fxsave {
+            fcw: FCW_INIT,
+            mxcsr: u32_at(24),
+            mxcsr_mask: u32_at(28),
+            ..Default::default()
+        };
+
+        if xstate_bv.contains(Xfrm::X87) {
+            legacy.fcw = u16_at(0);
+            legacy.fsw = u16_at(2);
+            legacy.ftw = bytes[4];
+            legacy.fop = u16_at(6);
+            legacy.fip = u64_at(8);
+            legacy.fdp = u64_at(16);
+
+            for (i, st) in legacy.st.iter_mut().enumerate() {
+                let start = 32 + i * 16;
+                st.copy_from_slice(&bytes[start..start + 10]);
+            }
+        }
+
+        if xstate_bv.contains(Xfrm::SSE) {
+            for (i, xmm) in legacy.xmm.iter_mut().enumerate() {
+                let start = 160 + i * 16;
+                *xmm = u128::from_le_bytes(bytes[start..start + 16].try_into().unwrap());
+            }
+        }
+
+        Some(Self {
+            legacy,
+            xstate_bv,
+            xcomp_bv: u64_at(XSAVE_LEGACY_SIZE + 8),
+        })
+    }
+}
+
+/// The CET state save area (Section 38.9.3)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Cet {
+    /// The user mode CET control register (IA32_U_CET)
+    pub u_cet: u64,
+
+    /// The user mode shadow stack pointer (IA32_PL3_SSP)
+    pub ssp: u64,
+}
+
+/// The layout of an SSA frame, which depends on the XSAVE size for the
+/// enclave's XFRM and on which of MISCSELECT and the CET attribute are enabled
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SsaLayout {
+    size: usize,
+    misc_size: usize,
+    cet: bool,
+}
+
+impl SsaLayout {
+    /// Creates the layout of an SSA frame from the size of the XSAVE region,
+    /// as enumerated by CPUID.(EAX=0DH, ECX=0H):EBX for the enclave's XFRM.
+    pub fn new(xsave_size: usize, miscselect: MiscSelect, attributes: Attributes) -> Self {
+        let misc_size = if miscselect.contains(MiscSelect::EXINFO) {
+            EXINFO_SIZE
+        } else {
+            0
+        };
+
+        let cet = attributes.features().contains(Features::CET);
+        let used =
+            xsave_size + misc_size + if cet { size_of::<Cet>() } else { 0 } + size_of::<Gpr>();
+
+        Self {
+            size: (used + PAGE - 1) & !(PAGE - 1),
+            misc_size,
+            cet,
+        }
+    }
+
+    /// The number of pages per SSA frame (SECS.SSAFRAMESIZE)
+    pub fn frame_pages(&self) -> NonZeroU32 {
+        NonZeroU32::new((self.size / PAGE) as u32).unwrap()
+    }
+
+    /// The offset of the GPRSGX region within the frame
+    pub fn gpr_offset(&self) -> usize {
+        self.size - size_of::<Gpr>()
+    }
+
+    /// The offset of the MISC region within the frame
+    pub fn misc_offset(&self) -> usize {
+        self.gpr_offset() - self.misc_size
+    }
+
+    /// The offset of the CET state save area within the frame, if enabled
+    pub fn cet_offset(&self) -> Option<usize> {
+        if self.cet {
+            Some(self.misc_offset() - size_of::<Cet>())
+        } else {
+            None
+        }
+    }
+
+    /// Reads the CET state save area from an SSA `frame`, if enabled and
+    /// the frame is large enough.
+    pub fn cet(&self, frame: &[u8]) -> Option<Cet> {
+        let offset = self.cet_offset()?;
+        let bytes = frame.get(offset..offset + size_of::<Cet>())?;
+
+        Some(Cet {
+            u_cet: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            ssp: u64::from_le_bytes(bytes[8..].try_into().unwrap()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod gpr {
+    use super::*;
+
+    #[test]
+    fn registers() {
+        let mut gpr = Gpr::default();
+        gpr.set_rdi(1);
+        gpr.set_rsp(0x8000);
+        gpr.set_rip(0x1000);
+        gpr.skip_instruction(2);
+
+        assert_eq!(gpr.rdi(), 1);
+        assert_eq!(gpr.rsp(), 0x8000);
+        assert_eq!(gpr.rip(), 0x1002);
+        assert_eq!(gpr.rax(), 0);
+
+        assert!(!gpr.exit_info().is_valid());
+        gpr.set_exit_info(crate::ExitInfo::new(crate::Exception::InvalidOpcode));
+        assert_eq!(
+            gpr.exit_info().exception(),
+            Some(crate::Exception::InvalidOpcode)
+        );
+    }
+}
+
+#[cfg(test)]
+mod xsave {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let xfrm = Xfrm::X87 | Xfrm::SSE;
+        let mut frame = [0u8; 4096];
+        frame[0..2].copy_from_slice(&0x027Fu16.to_le_bytes());
+        frame[24..28].copy_from_slice(&0x1F80u32.to_le_bytes());
+        frame[32] = 0xAA;
+        frame[160 + 16] = 0x55;
+
+        // Both components in their initial state
+        let xsave = Xsave::parse(&frame, xfrm).unwrap();
+        assert_eq!(xsave.legacy.fcw, FCW_INIT);
+        assert_eq!(xsave.legacy.mxcsr, 0x1F80);
+        assert_eq!(xsave.legacy.st[0][0], 0);
+        assert_eq!(xsave.legacy.xmm[1], 0);
+
+        frame[512..520].copy_from_slice(&xfrm.bits().to_le_bytes());
+        let xsave = Xsave::parse(&frame, xfrm).unwrap();
+        assert_eq!(xsave.xstate_bv, xfrm);
+        assert_eq!(xsave.legacy.fcw, 0x027F);
+        assert_eq!(xsave.legacy.st[0][0], 0xAA);
+        assert_eq!(xsave.legacy.xmm[1], 0x55);
+
+        assert_eq!(Xsave::parse(&frame, Xfrm::X87), None);
+        assert_eq!(Xsave::parse(&frame[..512], xfrm), None);
+    }
+}
+
+#[cfg(test)]
+mod layout {
+    use super::*;
+    use crate::Xfrm;
+
+    #[test]
+    fn layout() {
+        let attr = Attributes::new(Features::MODE64BIT, Xfrm::X87 | Xfrm::SSE);
+        let layout = SsaLayout::new(512 + 64, MiscSelect::empty(), attr);
+        assert_eq!(layout.frame_pages().get(), 1);
+        assert_eq!(layout.gpr_offset(), PAGE - 184);
+        assert_eq!(layout.misc_offset(), PAGE - 184);
+        assert_eq!(layout.cet_offset(), None);
+
+        let attr = Attributes::new(Features::MODE64BIT | Features::CET, Xfrm::X87 | Xfrm::SSE);
+        let layout = SsaLayout::new(PAGE - 184 - 16, MiscSelect::EXINFO, attr);
+        assert_eq!(layout.frame_pages().get(), 2);
+        assert_eq!(layout.misc_offset(), 2 * PAGE - 184 - 16);
+        assert_eq!(layout.cet_offset(), Some(2 * PAGE - 184 - 32));
+
+        let mut frame = [0u8; 2 * PAGE];
+        let offset = layout.cet_offset().unwrap();
+        frame[offset..offset + 8].copy_from_slice(&1u64.to_le_bytes());
+        frame[offset + 8..offset + 16].copy_from_slice(&0x7000u64.to_le_bytes());
+        assert_eq!(
+            layout.cet(&frame),
+            Some(Cet {
+                u_cet: 1,
+                ssp: 0x7000
+            })
+        );
+        assert_eq!(layout.cet(&frame[..PAGE]), None);
+    }
+}
+
+#[cfg(test)]
+testaso! {
+    struct Cet: 8, 16 => {
+        u_cet: 0,
+        ssp: 8
+    }
+
+    struct Gpr: 8, 184 => {
+        rax: 0,
+        rcx: 8,
+        rdx: 16,
+        rbx: 24,
+        rsp: 32,
+        rbp: 40,
+        rsi: 48,
+        rdi: 56,
+        r8: 64,
+        r9: 72,
+        r10: 80,
+        r11: 88,
+        r12: 96,
+        r13: 104,
+        r14: 112,
+        r15: 120,
+        rflags: 128,
+        rip: 136,
+        ursp: 144,
+        urbp: 152,
+        exitinfo: 160,
+        reserved: 164,
+        aexnotify: 167,
+        fsbase: 168,
+        gsbase: 176
+    }
+}
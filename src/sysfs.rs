@@ -0,0 +1,338 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Linux SGX Platform
+//! The in-kernel SGX driver exposes its devices under `/dev` and the EPC
+//! capacity of each NUMA node in
+//! `/sys/devices/system/node/nodeN/x86/sgx_total_bytes`. Unlike CPUID, these
+//! are visible in containers and VMs where CPUID may be masked, as long as
+//! the files are mounted.
+
+use crate::{EpcSection, Footprint};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const PAGE: u64 = 4096;
+
+/// The EPC capacity of a NUMA node
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EpcNode {
+    /// The number of the NUMA node
+    pub node: u32,
+
+    /// The size of the EPC sections of the node in bytes
+    pub total_bytes: u64,
+}
+
+/// The SGX support of the running Linux kernel
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Platform {
+    /// `/dev/sgx_enclave` is present, i.e. the in-kernel driver is loaded
+    pub enclave: bool,
+
+    /// `/dev/sgx_provision` is present, for enclaves with the PROVISIONKEY
+    /// attribute
+    pub provision: bool,
+
+    /// `/dev/sgx_vepc` is present, for giving EPC to guests
+    pub vepc: bool,
+
+    /// The NUMA nodes with EPC, in order of their number
+    pub nodes: Vec<EpcNode>,
+}
+
+impl Platform {
+    /// Reads the SGX support of the running kernel.
+    pub fn probe() -> io::Result<Self> {
+        Self::probe_at(Path::new("/"))
+    }
+
+    /// Like `probe()`, but with `/dev` and `/sys` under `root`, e.g. for a
+    /// container whose host filesystems are mounted elsewhere.
+    pub fn probe_at(root: &Path) -> io::Result<Self> {
+        let dev = root.join("dev");
+        let mut platform = Self {
+            enclave: dev.join("sgx_enclave").exists(),
+            provision: dev.join("sgx_provision").exists(),
+            vepc: dev.join("sgx_vepc").exists(),
+            nodes: Vec::new(),
+        };
+
+        let nodes = match fs::read_dir(root.join("sys/devices/system/node")) {
+            Ok(nodes) => nodes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(platform),
+            Err(e) => return Err(e),
+        };
+
+        for entry in nodes {
+            let entry = entry?;
+            let node = match entry.file_name().to_str() {
+                Some(name) if name.starts_with("node") => match name[4..].parse() {
+                    Ok(node) => node,
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+
+            let total = match fs::read_to_string(entry.path().join("x86/sgx_total_bytes")) {
+                Ok(total) => total,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+
+            let total_bytes = total
+                .trim()
+                .parse()
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+
+            if total_bytes > 0 {
+                platform.nodes.push(EpcNode { node, total_bytes });
+            }
+        }
+
+        platform.nodes.sort_by_key(|n| n.node);
+        Ok(platform)
+    }
+
+    /// Returns the EPC capacity of all nodes in bytes
+    pub fn epc_bytes(&self) -> u64 {
+        self.nodes.iter().map(|n| n.total_bytes).sum()
+    }
+
+    /// Assigns the EPC `sections` (e.g. from `EpcSection::enumerate()`) to
+    /// the NUMA nodes, returning each section with the number of its node.
+    ///
+    /// sysfs only reports the capacity of each node, so this relies on the
+    /// nodes owning consecutive sections in the order of their physical
+    /// addresses, as on multi-socket servers. Returns `None` if the sections
+    /// cannot be split between the nodes that way.
+    pub fn sections(
+        &self,
+        sections: impl IntoIterator<Item = EpcSection>,
+    ) -> Option<Vec<(EpcSection, u32)>> {
+        let mut sections: Vec<_> = sections.into_iter().collect();
+        sections.sort_by_key(|s| s.base);
+
+        let mut assigned = Vec::with_capacity(sections.len());
+        let mut sections = sections.into_iter();
+        for node in &self.nodes {
+            let mut remaining = node.total_bytes;
+            while remaining > 0 {
+                let section = sections.next()?;
+                remaining = remaining.checked_sub(section.size)?;
+                assigned.push((section, node.node));
+            }
+        }
+
+        match sections.next() {
+            None => Some(assigned),
+            Some(_) => None,
+        }
+    }
+}
+
+/// The EPC budget of the misc cgroup (cgroup v2) of the calling process
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpcCgroup {
+    /// The tightest `sgx_epc` limit of the cgroup and its ancestors in bytes,
+    /// or `None` if unlimited
+    pub limit: Option<u64>,
+
+    /// The EPC charged to the cgroup in bytes
+    pub current: u64,
+}
+
+impl EpcCgroup {
+    /// Reads the EPC budget of the cgroup of the calling process; returns
+    /// `None` if the process is not in a cgroup v2 hierarchy or the misc
+    /// controller does not account EPC.
+    pub fn probe() -> io::Result<Option<Self>> {
+        Self::probe_at(Path::new("/"))
+    }
+
+    /// Like `probe()`, but with `/proc` and `/sys` under `root`.
+    pub fn probe_at(root: &Path) -> io::Result<Option<Self>> {
+        let cgroups = fs::read_to_string(root.join("proc/self/cgroup"))?;
+        let path = match cgroups.lines().find_map(|l| l.strip_prefix("0::")) {
+            Some(path) => path.trim_start_matches('/'),
+            None => return Ok(None),
+        };
+
+        let mut dir = root.join("sys/fs/cgroup").join(path);
+        let current = match Self::read(&dir.join("misc.current"))? {
+            Some(Some(current)) => current,
+            _ => return Ok(None),
+        };
+
+        // A cgroup is bound by the limits of all of its ancestors.
+        let mut limit = None;
+        loop {
+            if let Some(Some(max)) = Self::read(&dir.join("misc.max"))? {
+                limit = Some(limit.map_or(max, |l: u64| l.min(max)));
+            }
+
+            if path.is_empty() || dir.ends_with("sys/fs/cgroup") || !dir.pop() {
+                break;
+            }
+        }
+
+        Ok(Some(Self { limit, current }))
+    }
+
+    // Reads the `sgx_epc` entry of a misc controller file: `None` if absent,
+    // `Some(None)` if "max".
+    fn read(path: &Path) -> io::Result<Option<Option<u64>>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let value = match contents
+            .lines()
+            .find_map(|l| l.strip_prefix("sgx_epc "))
+            .map(str::trim)
+        {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        match value {
+            "max" => Ok(Some(None)),
+            value => value
+                .parse()
+                .map(|v| Some(Some(v)))
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidData)),
+        }
+    }
+
+    /// Returns the EPC still available to the cgroup in bytes, or `None` if
+    /// unlimited
+    pub fn available(&self) -> Option<u64> {
+        self.limit.map(|l| l.saturating_sub(self.current))
+    }
+
+    /// Returns true if an enclave with this `footprint` fits in the budget,
+    /// so that loading it fails up front rather than with ENOMEM midway.
+    pub fn fits(&self, footprint: &Footprint) -> bool {
+        let bytes = footprint.epc_pages() as u64 * PAGE;
+        self.available().map_or(true, |a| bytes <= a)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    fn root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("sgx-sysfs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("dev")).unwrap();
+        root
+    }
+
+    fn node(root: &Path, name: &str, total: &str) {
+        let dir = root.join("sys/devices/system/node").join(name).join("x86");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("sgx_total_bytes"), total).unwrap();
+    }
+
+    #[test]
+    fn probe() {
+        let root = root("probe");
+        fs::write(root.join("dev/sgx_enclave"), "").unwrap();
+        node(&root, "node1", "67108864\n");
+        node(&root, "node0", "134217728\n");
+        node(&root, "node2", "0\n");
+        fs::create_dir_all(root.join("sys/devices/system/node/possible")).unwrap();
+
+        let platform = Platform::probe_at(&root).unwrap();
+        assert!(platform.enclave);
+        assert!(!platform.provision);
+        assert_eq!(
+            platform.nodes,
+            [
+                EpcNode {
+                    node: 0,
+                    total_bytes: 128 << 20
+                },
+                EpcNode {
+                    node: 1,
+                    total_bytes: 64 << 20
+                },
+            ]
+        );
+        assert_eq!(platform.epc_bytes(), 192 << 20);
+
+        let section = |base, size| EpcSection { base, size };
+        let low = section(0x1000_0000, 64 << 20);
+        let high = section(0x20_0000_0000, 64 << 20);
+        let sections = [high, section(0x1400_0000, 64 << 20), low];
+        assert_eq!(
+            platform.sections(sections.iter().copied()).unwrap(),
+            [(low, 0), (section(0x1400_0000, 64 << 20), 0), (high, 1)]
+        );
+        assert_eq!(platform.sections(vec![low, high]), None);
+        assert_eq!(platform.sections(vec![section(0, 256 << 20)]), None);
+
+        node(&root, "node3", "lots");
+        assert!(Platform::probe_at(&root).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn cgroup() {
+        let root = root("cgroup");
+        let parent = root.join("sys/fs/cgroup/system.slice");
+        let child = parent.join("sgx.service");
+        fs::create_dir_all(&child).unwrap();
+        fs::create_dir_all(root.join("proc/self")).unwrap();
+        fs::write(
+            root.join("proc/self/cgroup"),
+            "0::/system.slice/sgx.service\n",
+        )
+        .unwrap();
+        fs::write(parent.join("misc.max"), "sgx_epc 1048576\n").unwrap();
+        fs::write(child.join("misc.max"), "sgx_epc max\n").unwrap();
+        fs::write(child.join("misc.current"), "sgx_epc 262144\n").unwrap();
+
+        let cgroup = EpcCgroup::probe_at(&root).unwrap().unwrap();
+        assert_eq!(
+            cgroup,
+            EpcCgroup {
+                limit: Some(1 << 20),
+                current: 256 << 10,
+            }
+        );
+        assert_eq!(cgroup.available(), Some(768 << 10));
+
+        let mut footprint = Footprint {
+            heap_pages: 191,
+            ..Default::default()
+        };
+        assert!(cgroup.fits(&footprint));
+        footprint.heap_pages += 1;
+        assert!(!cgroup.fits(&footprint));
+
+        fs::write(parent.join("misc.max"), "sgx_epc max\n").unwrap();
+        let cgroup = EpcCgroup::probe_at(&root).unwrap().unwrap();
+        assert_eq!(cgroup.available(), None);
+        assert!(cgroup.fits(&footprint));
+
+        fs::write(root.join("proc/self/cgroup"), "1:cpu:/\n").unwrap();
+        assert_eq!(EpcCgroup::probe_at(&root).unwrap(), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn absent() {
+        let root = root("absent");
+        assert_eq!(Platform::probe_at(&root).unwrap(), Platform::default());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
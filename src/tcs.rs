@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! TCS (Section 38.8)
+//! The Thread Control Structure (TCS) is a special enclave page that holds
+//! the state of one enclave thread. Every EENTER names a TCS, and its fields
+//! are offsets relative to the enclave base address.
+
+use crate::{Features, Secs};
+use core::num::NonZeroU32;
+
+const PAGE: u64 = 4096;
+
+bitflags::bitflags! {
+    /// Section 38.8.1
+    #[derive(Default)]
+    pub struct TcsFlags: u64 {
+        /// Allows debugging features (single-stepping, breakpoints) while
+        /// executing in the enclave on this TCS, if the enclave is a debug enclave
+        const DBGOPTIN = 1 << 0;
+
+        /// Enables AEX-Notify for this thread; requires the AEXNOTIFY attribute
+        const AEXNOTIFY = 1 << 1;
+    }
+}
+
+/// A TCS field that would make EENTER fail, see `Tcs::validate()`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidTcs {
+    /// OSSA is not page aligned
+    SsaAlignment,
+
+    /// The NSSA SSA frames at OSSA do not fit in the enclave
+    SsaRange,
+
+    /// CSSA is not less than NSSA
+    Cssa,
+
+    /// OENTRY is not within the enclave
+    Entry,
+
+    /// OFSBASE is not page aligned or not within the enclave
+    FsBase,
+
+    /// OGSBASE is not page aligned or not within the enclave
+    GsBase,
+
+    /// The AEXNOTIFY flag is set but the enclave lacks the AEXNOTIFY attribute
+    AexNotify,
+}
+
+impl core::fmt::Display for InvalidTcs {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidTcs::SsaAlignment => write!(f, "OSSA is not page aligned"),
+            InvalidTcs::SsaRange => write!(f, "SSA frames exceed the enclave"),
+            InvalidTcs::Cssa => write!(f, "CSSA is not less than NSSA"),
+            InvalidTcs::Entry => write!(f, "OENTRY is outside the enclave"),
+            InvalidTcs::FsBase => write!(f, "OFSBASE is misaligned or outside the enclave"),
+            InvalidTcs::GsBase => write!(f, "OGSBASE is misaligned or outside the enclave"),
+            InvalidTcs::AexNotify => write!(f, "AEXNOTIFY requires the AEXNOTIFY attribute"),
+        }
+    }
+}
+
+/// Section 38.8
+#[derive(Copy, Clone, Debug)]
+#[repr(C, align(4096))]
+pub struct Tcs {
+    state: u64,
+    flags: TcsFlags,
+    ossa: u64,
+    cssa: u32,
+    nssa: NonZeroU32,
+    oentry: u64,
+    aep: u64,
+    ofsbase: u64,
+    ogsbase: u64,
+    fslimit: u32,
+    gslimit: u32,
+    reserved: [u8; 4024],
+}
+
+impl Tcs {
+    /// Creates a new TCS from the offsets of the entry point and of the first
+    /// of `nssa` SSA frames.
+    pub const fn new(entry: u64, ssa: u64, nssa: NonZeroU32, flags: TcsFlags) -> Self {
+        Self {
+            state: 0,
+            flags,
+            ossa: ssa,
+            cssa: 0,
+            nssa,
+            oentry: entry,
+            aep: 0,
+            ofsbase: 0,
+            ogsbase: 0,
+            fslimit: u32::MAX,
+            gslimit: u32::MAX,
+            reserved: [0; 4024],
+        }
+    }
+
+    /// Returns the flags of the TCS.
+    pub const fn flags(&self) -> TcsFlags {
+        self.flags
+    }
+
+    /// Sets the offsets of the FS and GS segment bases.
+    pub fn set_segments(&mut self, fsbase: u64, gsbase: u64) {
+        self.ofsbase = fsbase;
+        self.ogsbase = gsbase;
+    }
+
+    /// Checks the fields of the TCS against the enclave's SECS, with the
+    /// same constraints as EADD and EENTER. NSSA is nonzero by construction.
+    pub fn validate(&self, secs: &Secs) -> Result<(), InvalidTcs> {
+        let size = secs.size();
+        let within = |offset: u64| offset < size;
+        let aligned = |offset: u64| offset % PAGE == 0;
+
+        if !aligned(self.ossa) {
+            return Err(InvalidTcs::SsaAlignment);
+        }
+
+        let ssa_size = u64::from(self.nssa.get())
+            .checked_mul(u64::from(secs.ssa_frame_pages().get()) * PAGE)
+            .and_then(|len| len.checked_add(self.ossa));
+        match ssa_size {
+            Some(end) if end <= size => (),
+            _ => return Err(InvalidTcs::SsaRange),
+        }
+
+        if self.cssa >= self.nssa.get() {
+            return Err(InvalidTcs::Cssa);
+        }
+
+        if !within(self.oentry) {
+            return Err(InvalidTcs::Entry);
+        }
+
+        if !aligned(self.ofsbase) || !within(self.ofsbase) {
+            return Err(InvalidTcs::FsBase);
+        }
+
+        if !aligned(self.ogsbase) || !within(self.ogsbase) {
+            return Err(InvalidTcs::GsBase);
+        }
+
+        if self.flags.contains(TcsFlags::AEXNOTIFY)
+            && !secs.attributes().features().contains(Features::AEXNOTIFY)
+        {
+            return Err(InvalidTcs::AexNotify);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod validate {
+    use super::*;
+    use crate::Parameters;
+
+    #[test]
+    fn validate() {
+        let one = NonZeroU32::new(1).unwrap();
+        let two = NonZeroU32::new(2).unwrap();
+        let secs = Secs::new(core::ptr::null(), 0x10000, one, Parameters::default());
+
+        let tcs = Tcs::new(0x1000, 0x2000, two, TcsFlags::empty());
+        assert_eq!(tcs.validate(&secs), Ok(()));
+
+        let tcs = Tcs::new(0x1000, 0x2010, two, TcsFlags::empty());
+        assert_eq!(tcs.validate(&secs), Err(InvalidTcs::SsaAlignment));
+
+        let tcs = Tcs::new(0x1000, 0xF000, two, TcsFlags::empty());
+        assert_eq!(tcs.validate(&secs), Err(InvalidTcs::SsaRange));
+
+        let tcs = Tcs::new(0x10000, 0x2000, two, TcsFlags::empty());
+        assert_eq!(tcs.validate(&secs), Err(InvalidTcs::Entry));
+
+        let mut tcs = Tcs::new(0x1000, 0x2000, two, TcsFlags::empty());
+        tcs.set_segments(0x4001, 0);
+        assert_eq!(tcs.validate(&secs), Err(InvalidTcs::FsBase));
+        tcs.set_segments(0, 0x10000);
+        assert_eq!(tcs.validate(&secs), Err(InvalidTcs::GsBase));
+
+        let tcs = Tcs::new(0x1000, 0x2000, two, TcsFlags::AEXNOTIFY);
+        assert_eq!(tcs.validate(&secs), Err(InvalidTcs::AexNotify));
+
+        let mut params = Parameters::default();
+        params.enable_aex_notify();
+        let secs = Secs::new(core::ptr::null(), 0x10000, one, params);
+        assert_eq!(tcs.validate(&secs), Ok(()));
+    }
+}
+
+#[cfg(test)]
+testaso! {
+    struct Tcs: 4096, 4096 => {
+        state: 0,
+        flags: 8,
+        ossa: 16,
+        cssa: 24,
+        nssa: 28,
+        oentry: 32,
+        aep: 40,
+        ofsbase: 48,
+        ogsbase: 56,
+        fslimit: 64,
+        gslimit: 68,
+        reserved: 72
+    }
+}